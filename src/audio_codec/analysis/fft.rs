@@ -0,0 +1,385 @@
+//! Radix-2 FFT of complex and real signals, and the windows used before it.
+
+use super::super::{Result, dsp::bessel_i0, error::Error};
+use std::{f64::consts::PI, ops};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+impl Complex {
+    #[inline(always)]
+    pub const fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    #[inline(always)]
+    pub fn from_polar(magnitude: f32, phase: f32) -> Self {
+        Self::new(magnitude * phase.cos(), magnitude * phase.sin())
+    }
+
+    #[inline(always)]
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    #[inline(always)]
+    pub fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    #[inline(always)]
+    pub fn abs(self) -> f32 {
+        self.norm_sqr().sqrt()
+    }
+
+    #[inline(always)]
+    pub fn arg(self) -> f32 {
+        self.im.atan2(self.re)
+    }
+}
+impl ops::Add for Complex {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+impl ops::Sub for Complex {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+impl ops::Mul for Complex {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+impl ops::Mul<f32> for Complex {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.re * rhs, self.im * rhs)
+    }
+}
+impl ops::AddAssign for Complex {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// In-place FFT of a power of two size.
+#[derive(Debug, Clone)]
+pub struct Fft {
+    size: usize,
+    /// `e^(-2πik/size)` for the first half.
+    twiddles: Vec<Complex>,
+    reversed: Vec<u32>,
+}
+impl Fft {
+    pub fn new(size: usize) -> Result<Self> {
+        if !size.is_power_of_two() {
+            return Err(Error::Custom(format!(
+                "FFT size {size} is not a power of two!"
+            )));
+        }
+
+        let bits = size.trailing_zeros();
+        Ok(Self {
+            size,
+            twiddles: (0..size / 2)
+                .map(|k| {
+                    let phase = -2.0 * PI * k as f64 / size as f64;
+                    Complex::new(phase.cos() as f32, phase.sin() as f32)
+                })
+                .collect(),
+            reversed: (0..size as u32)
+                .map(|i| i.reverse_bits().checked_shr(32 - bits).unwrap_or(0))
+                .collect(),
+        })
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Not normalized.
+    pub fn forward(&self, data: &mut [Complex]) {
+        self.transform(data, false);
+    }
+
+    /// Divides by the size, so it undoes [`Fft::forward`].
+    pub fn inverse(&self, data: &mut [Complex]) {
+        self.transform(data, true);
+
+        let scale = 1.0 / self.size as f32;
+        for value in data.iter_mut() {
+            *value = *value * scale;
+        }
+    }
+}
+impl Fft {
+    fn transform(&self, data: &mut [Complex], inverse: bool) {
+        assert_eq!(data.len(), self.size, "FFT data must have the FFT size!");
+
+        for i in 0..self.size {
+            let j = self.reversed[i] as usize;
+            if j > i {
+                data.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= self.size {
+            let half = len / 2;
+            let step = self.size / len;
+
+            for block in data.chunks_exact_mut(len) {
+                let (low, high) = block.split_at_mut(half);
+                for k in 0..half {
+                    let twiddle = self.twiddles[k * step];
+                    let twiddle = if inverse { twiddle.conj() } else { twiddle };
+
+                    let a = low[k];
+                    let b = high[k] * twiddle;
+                    low[k] = a + b;
+                    high[k] = a - b;
+                }
+            }
+
+            len <<= 1;
+        }
+    }
+}
+
+/// FFT of real signals, through a complex one of half the size.
+#[derive(Debug, Clone)]
+pub struct RealFft {
+    size: usize,
+    fft: Fft,
+    /// `e^(-2πik/size)` for the bins.
+    twiddles: Vec<Complex>,
+    scratch: Vec<Complex>,
+}
+impl RealFft {
+    /// `size` is a power of two, at least 2.
+    pub fn new(size: usize) -> Result<Self> {
+        if size < 2 {
+            return Err(Error::Custom(format!("Real FFT size {size} is under 2!")));
+        }
+        let fft = Fft::new(size / 2)?;
+
+        Ok(Self {
+            size,
+            fft,
+            twiddles: (0..=size / 2)
+                .map(|k| {
+                    let phase = -2.0 * PI * k as f64 / size as f64;
+                    Complex::new(phase.cos() as f32, phase.sin() as f32)
+                })
+                .collect(),
+            scratch: vec![Complex::default(); size / 2],
+        })
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// `size / 2 + 1`, from 0 Hz to the Nyquist frequency.
+    #[inline(always)]
+    pub fn bins(&self) -> usize {
+        self.size / 2 + 1
+    }
+
+    /// Writes the bins of the `input` to the `output`, not normalized.
+    pub fn forward(&mut self, input: &[f32], output: &mut Vec<Complex>) {
+        assert_eq!(input.len(), self.size, "FFT input must have the FFT size!");
+        let half = self.size / 2;
+
+        for (value, pair) in self.scratch.iter_mut().zip(input.chunks_exact(2)) {
+            *value = Complex::new(pair[0], pair[1]);
+        }
+        self.fft.forward(&mut self.scratch);
+
+        output.clear();
+        output.extend((0..=half).map(|k| {
+            let z = self.scratch[k % half];
+            let mirror = self.scratch[(half - k) % half].conj();
+
+            let even = (z + mirror) * 0.5;
+            let odd = (z - mirror) * Complex::new(0.0, -0.5);
+            even + odd * self.twiddles[k]
+        }));
+    }
+
+    /// Writes the signal of the `bins` to the `output`, undoing [`RealFft::forward`].
+    pub fn inverse(&mut self, bins: &[Complex], output: &mut Vec<f32>) {
+        assert_eq!(
+            bins.len(),
+            self.bins(),
+            "FFT input must have size / 2 + 1 bins!"
+        );
+        let half = self.size / 2;
+
+        for k in 0..half {
+            let x = bins[k];
+            let mirror = bins[half - k].conj();
+
+            let even = (x + mirror) * 0.5;
+            let odd = (x - mirror) * 0.5 * self.twiddles[k].conj();
+            self.scratch[k] = even + odd * Complex::new(0.0, 1.0);
+        }
+        self.fft.inverse(&mut self.scratch);
+
+        output.clear();
+        output.extend(self.scratch.iter().flat_map(|z| [z.re, z.im]));
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Window {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    BlackmanHarris,
+    /// With the given beta.
+    Kaiser(f64),
+}
+impl Window {
+    /// Periodic coefficients, so hops of a quarter or a half of the size add up evenly.
+    pub fn coefficients(&self, size: usize) -> Vec<f32> {
+        let cosines = |a: &[f64]| -> Vec<f32> {
+            (0..size)
+                .map(|i| {
+                    let x = 2.0 * PI * i as f64 / size as f64;
+                    a.iter()
+                        .enumerate()
+                        .map(|(k, a)| {
+                            a * (k as f64 * x).cos() * if k % 2 == 0 { 1.0 } else { -1.0 }
+                        })
+                        .sum::<f64>() as f32
+                })
+                .collect()
+        };
+
+        match self {
+            Self::Rectangular => vec![1.0; size],
+            Self::Hann => cosines(&[0.5, 0.5]),
+            Self::Hamming => cosines(&[0.54, 0.46]),
+            Self::Blackman => cosines(&[0.42, 0.5, 0.08]),
+            Self::BlackmanHarris => cosines(&[0.35875, 0.48829, 0.14128, 0.01168]),
+            Self::Kaiser(beta) => {
+                let denominator = bessel_i0(*beta);
+                (0..size)
+                    .map(|i| {
+                        let x = 2.0 * i as f64 / size as f64 - 1.0;
+                        (bessel_i0(beta * (1.0 - x * x).max(0.0).sqrt()) / denominator) as f32
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 64;
+
+    fn assert_near(value: Complex, expected: Complex) {
+        assert!(
+            (value - expected).abs() < 1e-4,
+            "{value:?} is not {expected:?}"
+        );
+    }
+
+    #[test]
+    fn complex_sine() {
+        // `e^(2πi 5n/size + phase)` is all in bin 5.
+        let fft = Fft::new(SIZE).unwrap();
+        let mut data: Vec<Complex> = (0..SIZE)
+            .map(|n| {
+                Complex::from_polar(1.0, (2.0 * PI * 5.0 * n as f64 / SIZE as f64) as f32 + 0.3)
+            })
+            .collect();
+        let input = data.clone();
+
+        fft.forward(&mut data);
+        for (k, bin) in data.iter().enumerate() {
+            let expected = if k == 5 {
+                Complex::from_polar(SIZE as f32, 0.3)
+            } else {
+                Complex::default()
+            };
+            assert_near(*bin, expected);
+        }
+
+        fft.inverse(&mut data);
+        for (value, expected) in data.into_iter().zip(input) {
+            assert_near(value, expected);
+        }
+    }
+
+    #[test]
+    fn real_sine() {
+        // `cos(2π 7n/size + phase)` has half its amplitude in bin 7 and half in the mirrored one.
+        let mut fft = RealFft::new(SIZE).unwrap();
+        let input: Vec<f32> = (0..SIZE)
+            .map(|n| (0.5 * (2.0 * PI * 7.0 * n as f64 / SIZE as f64 - 1.0).cos()) as f32)
+            .collect();
+
+        let mut bins = Vec::new();
+        fft.forward(&input, &mut bins);
+        assert_eq!(bins.len(), fft.bins());
+        for (k, bin) in bins.iter().enumerate() {
+            let expected = if k == 7 {
+                Complex::from_polar(0.5 * SIZE as f32 / 2.0, -1.0)
+            } else {
+                Complex::default()
+            };
+            assert_near(*bin, expected);
+        }
+
+        let mut output = Vec::new();
+        fft.inverse(&bins, &mut output);
+        for (value, expected) in output.into_iter().zip(input) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn real_matches_complex() {
+        let input: Vec<f32> = (0..SIZE)
+            .map(|n| ((n * 37 % 11) as f32 - 5.0) / 5.0)
+            .collect();
+
+        let mut bins = Vec::new();
+        RealFft::new(SIZE).unwrap().forward(&input, &mut bins);
+
+        let mut data: Vec<Complex> = input.iter().map(|x| Complex::new(*x, 0.0)).collect();
+        Fft::new(SIZE).unwrap().forward(&mut data);
+
+        for (bin, expected) in bins.into_iter().zip(data) {
+            assert_near(bin, expected);
+        }
+    }
+}
@@ -0,0 +1,575 @@
+//! Loudness as in ITU-R BS.1770-4 and EBU R128 (Tech 3341 and 3342).
+//!
+//! Channels are weighted by their speaker, the surrounds get +1.5 dB and the LFE is ignored.
+//! The true peak uses a windowed sinc interpolation up to at least 192 kHz.
+
+use super::super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::{LgDecoder, read_chunk},
+    dsp::{ChannelLayout, Speaker, bessel_i0, db_to_gain},
+    error::Error,
+};
+use std::collections::VecDeque;
+
+/// Length of the gating sub-blocks, the 400 ms and 3 s windows move by it.
+const SUB_BLOCK_SECONDS: f64 = 0.1;
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+const ABSOLUTE_GATE: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+
+/// True peak oversampling aims for this rate.
+const TRUE_PEAK_RATE: u32 = 192_000;
+const TRUE_PEAK_MAX_FACTOR: usize = 8;
+/// Samples on each side of the interpolated position.
+const TRUE_PEAK_HALF_TAPS: usize = 12;
+const TRUE_PEAK_BETA: f64 = 8.0;
+
+/// Everything measured, in LUFS, LU, dBTP and dBFS.
+/// Values are `-inf` when there was not enough audio, or everything was below the gates.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub integrated: f64,
+    pub range: f64,
+    pub max_momentary: f64,
+    pub max_short_term: f64,
+    pub true_peak: f64,
+    pub sample_peak: f64,
+}
+
+/// Measures interleaved samples as they come.
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<KWeighting>,
+
+    sub_block_frames: usize,
+    /// Weighted sum of the squares of the current sub-block.
+    sub_block_sum: f64,
+    sub_block_len: usize,
+    /// Mean square of the last sub-blocks, enough for the short-term window.
+    sub_blocks: VecDeque<f64>,
+    sub_block_count: usize,
+
+    /// Mean square of every 400 ms and 3 s window, for the gating.
+    momentary_blocks: Vec<f64>,
+    short_term_blocks: Vec<f64>,
+
+    true_peak: Vec<TruePeak>,
+    sample_peak: f64,
+}
+impl LoudnessMeter {
+    /// Uses the usual speakers for the channels, see [`ChannelLayout::default_for`].
+    pub fn new(info: AudioInfo) -> Result<Self> {
+        Self::with_layout(info.sample_rate, &ChannelLayout::default_for(info.channels))
+    }
+
+    pub fn with_layout(sample_rate: u32, layout: &ChannelLayout) -> Result<Self> {
+        if sample_rate == 0 || layout.channels() == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Loudness needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        let channels = layout.channels();
+
+        Ok(Self {
+            channels,
+            weights: layout.speakers.iter().map(|s| channel_weight(*s)).collect(),
+            filters: vec![KWeighting::new(sample_rate); channels],
+            sub_block_frames: ((sample_rate as f64 * SUB_BLOCK_SECONDS).round() as usize).max(1),
+            sub_block_sum: 0.0,
+            sub_block_len: 0,
+            sub_blocks: VecDeque::with_capacity(SHORT_TERM_SUB_BLOCKS),
+            sub_block_count: 0,
+            momentary_blocks: Vec::new(),
+            short_term_blocks: Vec::new(),
+            true_peak: vec![TruePeak::new(sample_rate); channels],
+            sample_peak: 0.0,
+        })
+    }
+
+    /// Measures interleaved samples, an incomplete frame at the end is ignored.
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            let mut sum = 0.0;
+            for (channel, sample) in frame.iter().enumerate() {
+                let sample = *sample as f64;
+                self.sample_peak = self.sample_peak.max(sample.abs());
+                self.true_peak[channel].push(sample);
+
+                if self.weights[channel] != 0.0 {
+                    let filtered = self.filters[channel].process(sample);
+                    sum += self.weights[channel] * filtered * filtered;
+                }
+            }
+
+            self.sub_block_sum += sum;
+            self.sub_block_len += 1;
+            if self.sub_block_len == self.sub_block_frames {
+                self.end_sub_block();
+            }
+        }
+    }
+
+    /// Last 400 ms, in LUFS.
+    pub fn momentary(&self) -> f64 {
+        loudness(self.window(MOMENTARY_SUB_BLOCKS))
+    }
+
+    /// Last 3 s, in LUFS.
+    pub fn short_term(&self) -> f64 {
+        loudness(self.window(SHORT_TERM_SUB_BLOCKS))
+    }
+
+    /// Gated loudness of everything so far, in LUFS.
+    pub fn integrated(&self) -> f64 {
+        let Some(threshold) = relative_threshold(&self.momentary_blocks, INTEGRATED_RELATIVE_GATE)
+        else {
+            return f64::NEG_INFINITY;
+        };
+
+        let (sum, count) = gated(&self.momentary_blocks, threshold.max(ABSOLUTE_GATE))
+            .fold((0.0, 0), |(sum, count), block| (sum + block, count + 1));
+
+        loudness(sum / count as f64)
+    }
+
+    /// Spread of the short-term loudness, in LU, as in EBU Tech 3342.
+    pub fn loudness_range(&self) -> f64 {
+        let Some(threshold) = relative_threshold(&self.short_term_blocks, RANGE_RELATIVE_GATE)
+        else {
+            return f64::NEG_INFINITY;
+        };
+
+        let mut values: Vec<f64> = gated(&self.short_term_blocks, threshold.max(ABSOLUTE_GATE))
+            .map(loudness)
+            .collect();
+        values.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+
+        percentile(RANGE_HIGH_PERCENTILE) - percentile(RANGE_LOW_PERCENTILE)
+    }
+
+    /// Highest interpolated peak of any channel, in dBTP.
+    pub fn true_peak(&self) -> f64 {
+        let peak = self
+            .true_peak
+            .iter()
+            .fold(self.sample_peak, |peak, channel| peak.max(channel.peak));
+
+        20.0 * peak.log10()
+    }
+
+    /// Highest sample of any channel, in dBFS.
+    pub fn sample_peak(&self) -> f64 {
+        20.0 * self.sample_peak.log10()
+    }
+
+    pub fn result(&self) -> Loudness {
+        let max = |blocks: &[f64]| loudness(blocks.iter().copied().fold(0.0, f64::max));
+
+        Loudness {
+            integrated: self.integrated(),
+            range: self.loudness_range(),
+            max_momentary: max(&self.momentary_blocks),
+            max_short_term: max(&self.short_term_blocks),
+            true_peak: self.true_peak(),
+            sample_peak: self.sample_peak(),
+        }
+    }
+}
+impl LoudnessMeter {
+    fn end_sub_block(&mut self) {
+        if self.sub_blocks.len() == SHORT_TERM_SUB_BLOCKS {
+            self.sub_blocks.pop_front();
+        }
+        self.sub_blocks
+            .push_back(self.sub_block_sum / self.sub_block_frames as f64);
+        self.sub_block_count += 1;
+        self.sub_block_sum = 0.0;
+        self.sub_block_len = 0;
+
+        if self.sub_block_count >= MOMENTARY_SUB_BLOCKS {
+            self.momentary_blocks
+                .push(self.window(MOMENTARY_SUB_BLOCKS));
+        }
+        if self.sub_block_count >= SHORT_TERM_SUB_BLOCKS {
+            self.short_term_blocks
+                .push(self.window(SHORT_TERM_SUB_BLOCKS));
+        }
+    }
+
+    /// Mean square of the last sub-blocks, the missing ones at the start count as silence.
+    fn window(&self, sub_blocks: usize) -> f64 {
+        self.sub_blocks.iter().rev().take(sub_blocks).sum::<f64>() / sub_blocks as f64
+    }
+}
+
+/// Measures the whole buffer with the usual speakers for its channels.
+pub fn measure(buffer: &AudioBuffer) -> Result<Loudness> {
+    let mut meter = LoudnessMeter::new(buffer.info)?;
+    meter.process(&buffer.samples);
+
+    Ok(meter.result())
+}
+
+/// Measures every remaining sample of the decoder with the usual speakers for its channels.
+pub fn measure_decoder(decoder: &mut impl LgDecoder) -> Result<Loudness> {
+    let mut meter = LoudnessMeter::new(decoder.info())?;
+
+    let mut chunk = Vec::new();
+    while read_chunk(decoder, &mut chunk) {
+        meter.process(&chunk);
+    }
+
+    Ok(meter.result())
+}
+
+/// BS.1770 weights by position, surrounds are +1.5 dB and the LFE is left out.
+fn channel_weight(speaker: Speaker) -> f64 {
+    match speaker {
+        Speaker::LowFrequency => 0.0,
+        Speaker::BackLeft | Speaker::BackRight | Speaker::SideLeft | Speaker::SideRight => 1.41,
+        _ => 1.0,
+    }
+}
+
+#[inline(always)]
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Blocks above `threshold`, in LUFS.
+fn gated(blocks: &[f64], threshold: f64) -> impl Iterator<Item = f64> + '_ {
+    blocks
+        .iter()
+        .copied()
+        .filter(move |block| loudness(*block) > threshold)
+}
+
+/// The relative gate, `None` if no block is above the absolute gate.
+fn relative_threshold(blocks: &[f64], gate: f64) -> Option<f64> {
+    let (sum, count) =
+        gated(blocks, ABSOLUTE_GATE).fold((0.0, 0), |(sum, count), block| (sum + block, count + 1));
+
+    (count > 0).then(|| loudness(sum / count as f64) + gate)
+}
+
+// ------------------------- FILTERS --------------------------
+
+/// Direct form II transposed biquad.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+impl Biquad {
+    #[inline(always)]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+
+        y
+    }
+}
+
+/// The BS.1770 pre-filter and RLB high-pass, from their analog prototypes so any rate works.
+#[derive(Debug, Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (std::f64::consts::PI * f0 / rate).tan();
+        let vh = db_to_gain(gain);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            state: [0.0; 2],
+        };
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (std::f64::consts::PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            state: [0.0; 2],
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, x: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(x))
+    }
+}
+
+/// Peak of a channel between its samples.
+#[derive(Debug, Clone)]
+pub(crate) struct TruePeak {
+    /// Weights of every interpolated position between two samples.
+    phases: Vec<Vec<f64>>,
+    history: VecDeque<f64>,
+    pub(crate) peak: f64,
+}
+impl TruePeak {
+    /// Samples between a push and the interval it returns.
+    pub(crate) const LATENCY: usize = TRUE_PEAK_HALF_TAPS;
+
+    /// Oversamples enough to reach about 192 kHz.
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        let factor = (TRUE_PEAK_RATE as usize)
+            .div_ceil(sample_rate.max(1) as usize)
+            .clamp(1, TRUE_PEAK_MAX_FACTOR);
+        let taps = TRUE_PEAK_HALF_TAPS * 2;
+
+        let phases = (1..factor)
+            .map(|phase| {
+                let fraction = phase as f64 / factor as f64;
+
+                (0..taps)
+                    .map(|tap| {
+                        // From the oldest sample, the position is between the two middle ones.
+                        let x = tap as f64 - (TRUE_PEAK_HALF_TAPS - 1) as f64 - fraction;
+                        let t = x / TRUE_PEAK_HALF_TAPS as f64;
+                        let sinc = (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x);
+                        let window = bessel_i0(TRUE_PEAK_BETA * (1.0 - t * t).max(0.0).sqrt())
+                            / bessel_i0(TRUE_PEAK_BETA);
+
+                        sinc * window
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            phases,
+            history: VecDeque::from(vec![0.0; taps]),
+            peak: 0.0,
+        }
+    }
+
+    /// Returns the peak from the sample pushed [`TruePeak::LATENCY`] samples ago up to the next one.
+    pub(crate) fn push(&mut self, sample: f64) -> f64 {
+        self.history.pop_front();
+        self.history.push_back(sample);
+
+        let mut peak = self.history[TRUE_PEAK_HALF_TAPS - 1].abs();
+        for weights in &self.phases {
+            let value: f64 = self.history.iter().zip(weights).map(|(x, w)| x * w).sum();
+            peak = peak.max(value.abs());
+        }
+
+        self.peak = self.peak.max(peak);
+        peak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Sine signals of EBU Tech 3341 and 3342, at 48 kHz. A stereo sine of -23 dBFS is -23 LUFS.
+
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    /// Plays `(seconds, dBFS)` segments of a 1 kHz sine on every channel, with the gains in dB.
+    fn play(meter: &mut LoudnessMeter, gains: &[f64], segments: &[(f64, f64)]) {
+        let mut phase = 0usize;
+        for (seconds, level) in segments {
+            let frames = (seconds * RATE as f64).round() as usize;
+            let amplitudes: Vec<f64> = gains.iter().map(|gain| db_to_gain(level + gain)).collect();
+
+            let mut samples = Vec::with_capacity(frames * gains.len());
+            for _ in 0..frames {
+                let sine = (std::f64::consts::TAU * 1000.0 * phase as f64 / RATE as f64).sin();
+                phase += 1;
+                samples.extend(amplitudes.iter().map(|amplitude| (sine * amplitude) as f32));
+            }
+
+            meter.process(&samples);
+        }
+    }
+
+    fn stereo(segments: &[(f64, f64)]) -> LoudnessMeter {
+        let mut meter = LoudnessMeter::with_layout(RATE, &ChannelLayout::stereo()).unwrap();
+        play(&mut meter, &[0.0, 0.0], segments);
+
+        meter
+    }
+
+    fn assert_near(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{value} is not {expected} ± {tolerance}"
+        );
+    }
+
+    #[test]
+    fn tech_3341_constant_sines() {
+        for level in [-23.0, -33.0] {
+            let meter = stereo(&[(20.0, level)]);
+
+            assert_near(meter.momentary(), level, 0.1);
+            assert_near(meter.short_term(), level, 0.1);
+            assert_near(meter.integrated(), level, 0.1);
+        }
+    }
+
+    #[test]
+    fn tech_3341_gating() {
+        let cases: [&[(f64, f64)]; 3] = [
+            &[(10.0, -36.0), (60.0, -23.0), (10.0, -36.0)],
+            &[
+                (10.0, -72.0),
+                (10.0, -36.0),
+                (60.0, -23.0),
+                (10.0, -36.0),
+                (10.0, -72.0),
+            ],
+            &[(20.0, -26.0), (20.1, -20.0), (20.0, -26.0)],
+        ];
+
+        for segments in cases {
+            assert_near(stereo(segments).integrated(), -23.0, 0.1);
+        }
+    }
+
+    #[test]
+    fn tech_3341_surround() {
+        // L and R at -28 dBFS, C at -24 dBFS, Ls and Rs at -30 dBFS.
+        let mut meter = LoudnessMeter::with_layout(
+            RATE,
+            &ChannelLayout::new(vec![
+                Speaker::FrontLeft,
+                Speaker::FrontRight,
+                Speaker::FrontCenter,
+                Speaker::BackLeft,
+                Speaker::BackRight,
+            ]),
+        )
+        .unwrap();
+        play(
+            &mut meter,
+            &[-28.0, -28.0, -24.0, -30.0, -30.0],
+            &[(20.0, 0.0)],
+        );
+
+        assert_near(meter.integrated(), -23.0, 0.1);
+    }
+
+    #[test]
+    fn tech_3341_short_term() {
+        let mut meter = LoudnessMeter::with_layout(RATE, &ChannelLayout::stereo()).unwrap();
+        for i in 0..20 {
+            play(&mut meter, &[0.0, 0.0], &[(1.34, -20.0), (1.66, -30.0)]);
+            if i > 0 {
+                assert_near(meter.short_term(), -23.0, 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn tech_3342_range() {
+        let cases: [(&[(f64, f64)], f64); 4] = [
+            (&[(20.0, -20.0), (20.0, -30.0)], 10.0),
+            (&[(20.0, -20.0), (20.0, -15.0)], 5.0),
+            (&[(20.0, -40.0), (20.0, -20.0)], 20.0),
+            (
+                &[
+                    (20.0, -50.0),
+                    (20.0, -35.0),
+                    (20.0, -20.0),
+                    (20.0, -35.0),
+                    (20.0, -50.0),
+                ],
+                15.0,
+            ),
+        ];
+
+        for (segments, range) in cases {
+            assert_near(stereo(segments).loudness_range(), range, 1.0);
+        }
+    }
+
+    #[test]
+    fn tech_3341_true_peak() {
+        // Stereo sines of known peak, sampled on or between their peaks, like the true peak cases
+        // of Tech 3341: `(sample rate, frequency, phase in degrees, peak in dBFS, sample peak)`.
+        let cases = [
+            (48_000, 12_000.0, 0.0f64, -6.0, -6.0),
+            (48_000, 12_000.0, 45.0, -6.0, -9.01),
+            (48_000, 8_000.0, 30.0, -6.0, -6.0),
+            (48_000, 8_000.0, 0.0, -6.0, -7.25),
+            (48_000, 12_000.0, 45.0, 3.01, 0.0),
+            (96_000, 24_000.0, 45.0, 0.0, -3.01),
+            (44_100, 11_025.0, 45.0, 0.0, -3.01),
+        ];
+
+        for (rate, frequency, phase, peak, sample_peak) in cases {
+            let mut meter = LoudnessMeter::with_layout(rate, &ChannelLayout::stereo()).unwrap();
+
+            // Faded in over 10 ms, a sine starting at once rings over its peak.
+            let amplitude = db_to_gain(peak);
+            let fade = rate as f64 / 100.0;
+            let samples: Vec<f32> = (0..rate as usize)
+                .flat_map(|i| {
+                    let angle = std::f64::consts::TAU * frequency * i as f64 / rate as f64;
+                    let gain = (i as f64 / fade).min(1.0);
+                    let value = (gain * amplitude * (angle + phase.to_radians()).sin()) as f32;
+                    [value, value]
+                })
+                .collect();
+            meter.process(&samples);
+
+            assert_near(meter.sample_peak(), sample_peak, 0.01);
+            // The tolerance of Tech 3341 is +0.2 / -0.4 dB.
+            assert_near(meter.true_peak(), peak - 0.1, 0.3);
+        }
+    }
+
+    #[test]
+    fn absolute_gate_under_relative_gate() {
+        // Both relative thresholds fall under -70 LUFS, the quiet parts must still be gated.
+        assert_near(
+            stereo(&[(10.0, -65.0), (10.0, -72.0)]).integrated(),
+            -65.0,
+            0.1,
+        );
+        // Only the 3 s windows fading into the quiet part spread it, it would be 15 LU otherwise.
+        assert!(stereo(&[(20.0, -60.0), (20.0, -75.0)]).loudness_range() < 2.0);
+    }
+
+    #[test]
+    fn silence() {
+        let result = stereo(&[(5.0, f64::NEG_INFINITY)]).result();
+
+        assert_eq!(result.integrated, f64::NEG_INFINITY);
+        assert_eq!(result.range, f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod fft;
+pub mod loudness;
+pub mod silence;
+pub mod spectrogram;
+pub mod waveform;
+
+pub use fft::{Complex, Fft, RealFft, Window};
+pub use loudness::{Loudness, LoudnessMeter};
+pub use silence::{
+    SilenceDetector, SilenceParams, SilenceRegion, detect_silence, detect_silence_decoder,
+};
+#[cfg(feature = "spectrogram_png")]
+pub use spectrogram::{Colormap, FrequencyScale, SpectrogramStyle};
+pub use spectrogram::{Spectrogram, SpectrogramBuilder, Stft, StftParams};
+#[cfg(feature = "waveform_png")]
+pub use waveform::WaveformStyle;
+pub use waveform::{LgWaveformEncoder, Waveform, WaveformBuilder, WaveformLevel, WaveformPoint};
@@ -0,0 +1,187 @@
+//! Silent regions, where the samples stay under a threshold for a minimum duration.
+
+use super::super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::{LgDecoder, read_chunk},
+    dsp::db_to_gain,
+    error::Error,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceParams {
+    /// Samples under it are silent, in dBFS.
+    pub threshold: f64,
+    /// Shorter silences are ignored, in seconds.
+    pub min_duration: f64,
+    /// A frame is only silent when every channel is, otherwise every channel has its own regions.
+    pub linked: bool,
+}
+impl Default for SilenceParams {
+    fn default() -> Self {
+        Self {
+            threshold: -60.0,
+            min_duration: 0.1,
+            linked: true,
+        }
+    }
+}
+impl SilenceParams {
+    /// Threshold as a sample value.
+    #[inline(always)]
+    pub fn threshold_amplitude(&self) -> f32 {
+        db_to_gain(self.threshold) as f32
+    }
+
+    #[inline(always)]
+    pub fn min_frames(&self, sample_rate: u32) -> usize {
+        (self.min_duration.max(0.0) * sample_rate as f64).round() as usize
+    }
+}
+
+/// Silent frames from `start` up to `end`, not included.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SilenceRegion {
+    /// `None` when the channels are linked.
+    pub channel: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+}
+impl SilenceRegion {
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline(always)]
+    pub fn duration(&self) -> f64 {
+        self.end_seconds - self.start_seconds
+    }
+}
+
+/// Finds the silent regions of interleaved samples as they come.
+#[derive(Debug, Clone)]
+pub struct SilenceDetector {
+    channels: usize,
+    sample_rate: u32,
+    params: SilenceParams,
+    threshold: f32,
+    min_frames: usize,
+
+    /// Start of the current silence, of every channel or a single one when linked.
+    runs: Vec<Option<usize>>,
+    frame: usize,
+    regions: Vec<SilenceRegion>,
+}
+impl SilenceDetector {
+    pub fn new(info: AudioInfo, params: SilenceParams) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Silence detection needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        let trackers = if params.linked {
+            1
+        } else {
+            info.channels as usize
+        };
+
+        Ok(Self {
+            channels: info.channels as usize,
+            sample_rate: info.sample_rate,
+            params,
+            threshold: params.threshold_amplitude(),
+            min_frames: params.min_frames(info.sample_rate),
+            runs: vec![None; trackers],
+            frame: 0,
+            regions: Vec::new(),
+        })
+    }
+
+    /// Checks interleaved samples, an incomplete frame at the end is ignored.
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for tracker in 0..self.runs.len() {
+                let silent = if self.params.linked {
+                    frame.iter().all(|sample| sample.abs() < self.threshold)
+                } else {
+                    frame[tracker].abs() < self.threshold
+                };
+
+                if silent {
+                    self.runs[tracker].get_or_insert(self.frame);
+                } else if let Some(start) = self.runs[tracker].take() {
+                    self.close(tracker, start, self.frame);
+                }
+            }
+
+            self.frame += 1;
+        }
+    }
+
+    /// Regions that already ended.
+    #[inline(always)]
+    pub fn regions(&self) -> &[SilenceRegion] {
+        &self.regions
+    }
+
+    /// Frames checked so far.
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.frame
+    }
+
+    /// Ends the silences still going, returning every region ordered by start.
+    pub fn finish(mut self) -> Vec<SilenceRegion> {
+        for tracker in 0..self.runs.len() {
+            if let Some(start) = self.runs[tracker].take() {
+                self.close(tracker, start, self.frame);
+            }
+        }
+
+        self.regions
+            .sort_by_key(|region| (region.start, region.channel));
+        self.regions
+    }
+}
+impl SilenceDetector {
+    fn close(&mut self, tracker: usize, start: usize, end: usize) {
+        if end - start < self.min_frames.max(1) {
+            return;
+        }
+
+        let rate = self.sample_rate as f64;
+        self.regions.push(SilenceRegion {
+            channel: (!self.params.linked).then_some(tracker),
+            start,
+            end,
+            start_seconds: start as f64 / rate,
+            end_seconds: end as f64 / rate,
+        });
+    }
+}
+
+pub fn detect_silence(buffer: &AudioBuffer, params: SilenceParams) -> Result<Vec<SilenceRegion>> {
+    let mut detector = SilenceDetector::new(buffer.info, params)?;
+    detector.process(&buffer.samples);
+
+    Ok(detector.finish())
+}
+
+/// Checks every remaining sample of the decoder, a chunk at a time.
+pub fn detect_silence_decoder(
+    decoder: &mut impl LgDecoder,
+    params: SilenceParams,
+) -> Result<Vec<SilenceRegion>> {
+    let mut detector = SilenceDetector::new(decoder.info(), params)?;
+
+    let mut chunk = Vec::new();
+    while read_chunk(decoder, &mut chunk) {
+        detector.process(&chunk);
+    }
+
+    Ok(detector.finish())
+}
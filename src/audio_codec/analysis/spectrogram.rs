@@ -0,0 +1,487 @@
+//! Short-time Fourier transform and spectrograms, in dBFS so a full scale sine reads 0 dB.
+
+use super::{
+    super::{
+        AudioInfo, Result,
+        buffer::AudioBuffer,
+        decoder::{LgDecoder, read_chunk},
+        error::Error,
+    },
+    fft::{Complex, RealFft, Window},
+};
+
+/// Lowest level kept, in dB.
+const MIN_DB: f32 = -200.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StftParams {
+    /// Samples of every frame, a power of two.
+    pub size: usize,
+    /// Samples between frames, the overlap is `size - hop`.
+    pub hop: usize,
+    pub window: Window,
+}
+impl Default for StftParams {
+    fn default() -> Self {
+        Self {
+            size: 2048,
+            hop: 512,
+            window: Window::Hann,
+        }
+    }
+}
+
+/// Transforms a single signal as it comes, a windowed frame every hop.
+#[derive(Debug, Clone)]
+pub struct Stft {
+    params: StftParams,
+    fft: RealFft,
+    window: Vec<f32>,
+
+    /// Samples from the start of the next frame.
+    input: Vec<f32>,
+    windowed: Vec<f32>,
+    received: usize,
+    /// Position of the next frame, and end of the last one.
+    start: usize,
+    covered: usize,
+}
+impl Stft {
+    pub fn new(params: StftParams) -> Result<Self> {
+        if params.hop == 0 || params.hop > params.size {
+            return Err(Error::Custom(format!(
+                "STFT hop {} must be in 1..={}!",
+                params.hop, params.size
+            )));
+        }
+
+        Ok(Self {
+            fft: RealFft::new(params.size)?,
+            window: params.window.coefficients(params.size),
+            params,
+            input: Vec::with_capacity(params.size),
+            windowed: vec![0.0; params.size],
+            received: 0,
+            start: 0,
+            covered: 0,
+        })
+    }
+
+    #[inline(always)]
+    pub fn params(&self) -> StftParams {
+        self.params
+    }
+
+    #[inline(always)]
+    pub fn window(&self) -> &[f32] {
+        &self.window
+    }
+
+    /// Appends the bins of every frame completed by the `input`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<Vec<Complex>>) {
+        for sample in input {
+            self.input.push(*sample);
+            self.received += 1;
+
+            if self.input.len() == self.params.size {
+                output.push(self.frame());
+            }
+        }
+    }
+
+    /// Appends the frames, padded with silence, needed to cover every sample.
+    pub fn flush(&mut self, output: &mut Vec<Vec<Complex>>) {
+        while self.covered < self.received {
+            self.input.resize(self.params.size, 0.0);
+            output.push(self.frame());
+        }
+
+        self.input.clear();
+        self.received = 0;
+        self.start = 0;
+        self.covered = 0;
+    }
+}
+impl Stft {
+    fn frame(&mut self) -> Vec<Complex> {
+        for ((windowed, sample), coefficient) in
+            self.windowed.iter_mut().zip(&self.input).zip(&self.window)
+        {
+            *windowed = sample * coefficient;
+        }
+
+        let mut bins = Vec::with_capacity(self.fft.bins());
+        self.fft.forward(&self.windowed, &mut bins);
+
+        self.covered = self.start + self.params.size;
+        self.start += self.params.hop;
+        self.input.drain(..self.params.hop);
+
+        bins
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Spectrogram {
+    pub sample_rate: u32,
+    pub params: Option<StftParams>,
+    /// Level of every bin in dBFS, a frame at a time.
+    pub frames: Vec<Vec<f32>>,
+}
+impl Spectrogram {
+    /// Of a single channel, or of all of them mixed when `channel` is `None`.
+    pub fn from_buffer(
+        buffer: &AudioBuffer,
+        params: StftParams,
+        channel: Option<usize>,
+    ) -> Result<Self> {
+        let mut builder = SpectrogramBuilder::new(buffer.info, params, channel)?;
+        builder.process(&buffer.samples);
+
+        Ok(builder.finish())
+    }
+
+    /// Reads every remaining sample of the decoder, a chunk at a time.
+    pub fn from_decoder(
+        decoder: &mut impl LgDecoder,
+        params: StftParams,
+        channel: Option<usize>,
+    ) -> Result<Self> {
+        let mut builder = SpectrogramBuilder::new(decoder.info(), params, channel)?;
+
+        let mut chunk = Vec::new();
+        while read_chunk(decoder, &mut chunk) {
+            builder.process(&chunk);
+        }
+
+        Ok(builder.finish())
+    }
+
+    #[inline(always)]
+    pub fn bins(&self) -> usize {
+        self.params.map_or(0, |params| params.size / 2 + 1)
+    }
+
+    /// Center frequency of the bin, in Hz.
+    #[inline(always)]
+    pub fn bin_frequency(&self, bin: usize) -> f64 {
+        self.params.map_or(0.0, |params| {
+            bin as f64 * self.sample_rate as f64 / params.size as f64
+        })
+    }
+
+    /// Start of the frame, in seconds.
+    #[inline(always)]
+    pub fn frame_time(&self, frame: usize) -> f64 {
+        self.params.map_or(0.0, |params| {
+            (frame * params.hop) as f64 / self.sample_rate as f64
+        })
+    }
+
+    /// Average power of every bin over all the frames, in dBFS.
+    pub fn average(&self) -> Vec<f32> {
+        let mut power = vec![0.0f64; self.bins()];
+        for frame in &self.frames {
+            for (power, level) in power.iter_mut().zip(frame) {
+                *power += 10f64.powf(*level as f64 / 10.0);
+            }
+        }
+
+        let frames = self.frames.len().max(1) as f64;
+        power
+            .iter()
+            .map(|power| ((10.0 * (power / frames).log10()) as f32).max(MIN_DB))
+            .collect()
+    }
+
+    /// Highest frequency of the average within `range` dB of its loudest bin, in Hz.
+    /// Upsampled files fall sharply above their original Nyquist frequency.
+    pub fn bandwidth(&self, range: f32) -> f64 {
+        let average = self.average();
+        let loudest = average.iter().copied().fold(MIN_DB, f32::max);
+
+        average
+            .iter()
+            .rposition(|level| *level >= loudest - range)
+            .map_or(0.0, |bin| self.bin_frequency(bin))
+    }
+
+    /// Level of the average at the `frequency`, like of a hum, in dBFS.
+    pub fn level_at(&self, frequency: f64) -> f32 {
+        let Some(params) = self.params else {
+            return MIN_DB;
+        };
+
+        let bin = (frequency * params.size as f64 / self.sample_rate as f64).round() as usize;
+        self.average().get(bin).copied().unwrap_or(MIN_DB)
+    }
+}
+
+/// Builds a [`Spectrogram`] from interleaved samples as they come.
+#[derive(Debug, Clone)]
+pub struct SpectrogramBuilder {
+    channels: usize,
+    channel: Option<usize>,
+    sample_rate: u32,
+    stft: Stft,
+    /// Scale of the bins so a full scale sine reads 1, the first and last bins are not mirrored.
+    scale: f32,
+
+    frame: Vec<f32>,
+    mono: Vec<f32>,
+    spectra: Vec<Vec<Complex>>,
+    frames: Vec<Vec<f32>>,
+}
+impl SpectrogramBuilder {
+    pub fn new(info: AudioInfo, params: StftParams, channel: Option<usize>) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Spectrogram needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+        if channel.is_some_and(|channel| channel >= info.channels as usize) {
+            return Err(Error::Custom(format!(
+                "Channel {channel:?} is not in {} channels!",
+                info.channels
+            )));
+        }
+
+        let stft = Stft::new(params)?;
+        let window_sum: f32 = stft.window().iter().sum();
+
+        Ok(Self {
+            channels: info.channels as usize,
+            channel,
+            sample_rate: info.sample_rate,
+            scale: 2.0 / window_sum.max(f32::MIN_POSITIVE),
+            stft,
+            frame: Vec::with_capacity(info.channels as usize),
+            mono: Vec::new(),
+            spectra: Vec::new(),
+            frames: Vec::new(),
+        })
+    }
+
+    /// Adds interleaved samples, they can stop in the middle of a frame.
+    pub fn process(&mut self, samples: &[f32]) {
+        self.mono.clear();
+        for sample in samples {
+            self.frame.push(*sample);
+            if self.frame.len() < self.channels {
+                continue;
+            }
+
+            self.mono.push(match self.channel {
+                Some(channel) => self.frame[channel],
+                None => self.frame.iter().sum::<f32>() / self.channels as f32,
+            });
+            self.frame.clear();
+        }
+
+        self.stft.process(&self.mono, &mut self.spectra);
+        self.convert();
+    }
+
+    /// Frames done so far.
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Transforms the last samples, padded with silence.
+    pub fn finish(mut self) -> Spectrogram {
+        self.stft.flush(&mut self.spectra);
+        self.convert();
+
+        Spectrogram {
+            sample_rate: self.sample_rate,
+            params: Some(self.stft.params()),
+            frames: self.frames,
+        }
+    }
+}
+impl SpectrogramBuilder {
+    fn convert(&mut self) {
+        for spectrum in self.spectra.drain(..) {
+            let last = spectrum.len() - 1;
+            self.frames.push(
+                spectrum
+                    .iter()
+                    .enumerate()
+                    .map(|(bin, value)| {
+                        let scale = if bin == 0 || bin == last {
+                            self.scale / 2.0
+                        } else {
+                            self.scale
+                        };
+
+                        (20.0 * (value.abs() * scale).log10()).max(MIN_DB)
+                    })
+                    .collect(),
+            );
+        }
+    }
+}
+
+// ------------------------- RENDER --------------------------
+
+#[cfg(feature = "spectrogram_png")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FrequencyScale {
+    #[default]
+    Linear,
+    /// Starting at the given frequency, in Hz.
+    Log(f64),
+}
+
+#[cfg(feature = "spectrogram_png")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Colormap {
+    Grayscale,
+    #[default]
+    Magma,
+    Viridis,
+}
+#[cfg(feature = "spectrogram_png")]
+impl Colormap {
+    /// Color of `t` in `0.0..=1.0`.
+    pub fn color(&self, t: f32) -> [u8; 4] {
+        const MAGMA: [[f32; 3]; 5] = [
+            [0.0, 0.0, 4.0],
+            [81.0, 18.0, 124.0],
+            [183.0, 55.0, 121.0],
+            [252.0, 137.0, 97.0],
+            [252.0, 253.0, 191.0],
+        ];
+        const VIRIDIS: [[f32; 3]; 5] = [
+            [68.0, 1.0, 84.0],
+            [59.0, 82.0, 139.0],
+            [33.0, 145.0, 140.0],
+            [94.0, 201.0, 98.0],
+            [253.0, 231.0, 37.0],
+        ];
+
+        let t = t.clamp(0.0, 1.0);
+        let stops = match self {
+            Self::Grayscale => {
+                let value = (t * 255.0).round() as u8;
+                return [value, value, value, 255];
+            }
+            Self::Magma => &MAGMA,
+            Self::Viridis => &VIRIDIS,
+        };
+
+        let position = t * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+
+        let mut color = [255; 4];
+        for (i, value) in color.iter_mut().take(3).enumerate() {
+            let start = stops[index][i];
+            *value = (start + (stops[index + 1][i] - start) * fraction).round() as u8;
+        }
+        color
+    }
+}
+
+#[cfg(feature = "spectrogram_png")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrogramStyle {
+    /// A column every frame when `None`.
+    pub width: Option<u32>,
+    pub height: u32,
+    pub scale: FrequencyScale,
+    /// Levels from `min_db` up to `max_db` go through the whole colormap.
+    pub min_db: f32,
+    pub max_db: f32,
+    pub colormap: Colormap,
+}
+#[cfg(feature = "spectrogram_png")]
+impl Default for SpectrogramStyle {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: 512,
+            scale: FrequencyScale::Linear,
+            min_db: -120.0,
+            max_db: 0.0,
+            colormap: Colormap::Magma,
+        }
+    }
+}
+
+#[cfg(feature = "spectrogram_png")]
+impl Spectrogram {
+    /// Time goes right and frequency up, every pixel shows the loudest bin it covers.
+    pub fn render(&self, style: &SpectrogramStyle) -> image::RgbaImage {
+        let width = style.width.unwrap_or(self.frames.len() as u32);
+        let mut image = image::RgbaImage::from_pixel(
+            width,
+            style.height,
+            image::Rgba(style.colormap.color(0.0)),
+        );
+        if width == 0 || style.height == 0 || self.frames.is_empty() {
+            return image;
+        }
+
+        let bins = self.bins();
+        let nyquist = self.sample_rate as f64 / 2.0;
+        let bin_of = |fraction: f64| -> f64 {
+            let frequency = match style.scale {
+                FrequencyScale::Linear => fraction * nyquist,
+                FrequencyScale::Log(min) => {
+                    let min = min.clamp(f64::MIN_POSITIVE, nyquist);
+                    min * (nyquist / min).powf(fraction)
+                }
+            };
+            frequency / nyquist * (bins - 1) as f64
+        };
+        // Bins of every row, from the bottom.
+        let rows: Vec<(usize, usize)> = (0..style.height)
+            .map(|y| {
+                let low = bin_of(y as f64 / style.height as f64);
+                let high = bin_of((y + 1) as f64 / style.height as f64);
+                let first = low.round() as usize;
+                (
+                    first.min(bins - 1),
+                    (high.round() as usize).clamp(first + 1, bins),
+                )
+            })
+            .collect();
+
+        let frames_per_column = self.frames.len() as f64 / width as f64;
+        let range = (style.max_db - style.min_db).max(f32::EPSILON);
+        let mut column = vec![MIN_DB; bins];
+
+        for x in 0..width {
+            let first = (x as f64 * frames_per_column) as usize;
+            let last = (((x + 1) as f64 * frames_per_column).ceil() as usize)
+                .clamp(first + 1, self.frames.len());
+
+            column.fill(MIN_DB);
+            for frame in &self.frames[first.min(self.frames.len() - 1)..last] {
+                for (level, frame_level) in column.iter_mut().zip(frame) {
+                    *level = level.max(*frame_level);
+                }
+            }
+
+            for (y, (first, last)) in rows.iter().enumerate() {
+                let level = column[*first..*last].iter().copied().fold(MIN_DB, f32::max);
+                let color = style.colormap.color((level - style.min_db) / range);
+                image.put_pixel(x, style.height - 1 - y as u32, image::Rgba(color));
+            }
+        }
+
+        image
+    }
+
+    pub fn save_png(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        style: &SpectrogramStyle,
+    ) -> Result<()> {
+        self.render(style)
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+}
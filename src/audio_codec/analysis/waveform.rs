@@ -0,0 +1,515 @@
+//! Waveform overviews: min, max and RMS of every channel over blocks of frames, at several
+//! resolutions, each one with blocks twice as long as the previous.
+//!
+//! Binary layout, little-endian:
+//! `LGWF`, version `u16`, channels `u16`, sample_rate `u32`, frames `u64`, levels `u16`, then
+//! every level with frames_per_point `u32`, points `u32` and the points, every one with the
+//! min `i16`, max `i16` and RMS `u16` of every channel.
+
+use super::super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::{LgDecoder, read_chunk},
+    encoder::LgEncoder,
+    error::Error,
+    sample::{Sample, SampleType, stored_sample},
+};
+use crate::{reader::LgReader, writer::LgWriter};
+use std::{fs, io, path};
+
+const WAVEFORM_MAGIC: &[u8; 4] = b"LGWF";
+const WAVEFORM_VERSION: u16 = 1;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WaveformPoint {
+    pub min: f32,
+    pub max: f32,
+    pub rms: f32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WaveformLevel {
+    pub frames_per_point: u32,
+    /// Interleaved like the samples, a point of every channel at a time.
+    pub points: Vec<WaveformPoint>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Waveform {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub frames: u64,
+    /// From the finest to the coarsest.
+    pub levels: Vec<WaveformLevel>,
+}
+impl Waveform {
+    /// `levels` resolutions, the finest with `frames_per_point` frames in every point.
+    pub fn from_buffer(buffer: &AudioBuffer, frames_per_point: u32, levels: usize) -> Result<Self> {
+        let mut builder = WaveformBuilder::new(buffer.info, frames_per_point, levels)?;
+        builder.process(&buffer.samples);
+
+        Ok(builder.waveform())
+    }
+
+    /// Reads every remaining sample of the decoder, a chunk at a time.
+    pub fn from_decoder(
+        decoder: &mut impl LgDecoder,
+        frames_per_point: u32,
+        levels: usize,
+    ) -> Result<Self> {
+        let mut builder = WaveformBuilder::new(decoder.info(), frames_per_point, levels)?;
+
+        let mut chunk = Vec::new();
+        while read_chunk(decoder, &mut chunk) {
+            builder.process(&chunk);
+        }
+
+        Ok(builder.waveform())
+    }
+
+    /// The coarsest level with at most `frames_per_point`, or the finest.
+    pub fn level_for(&self, frames_per_point: f64) -> Option<&WaveformLevel> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|level| level.frames_per_point as f64 <= frames_per_point)
+            .or(self.levels.first())
+    }
+
+    pub fn write<W: io::Write + io::Seek>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(WAVEFORM_MAGIC)?;
+        writer.write_le_u16(WAVEFORM_VERSION)?;
+        writer.write_le_u16(self.channels)?;
+        writer.write_le_u32(self.sample_rate)?;
+        writer.write_le_u64(self.frames)?;
+        let level_count = u16::try_from(self.levels.len()).map_err(|_| {
+            Error::Custom("Waveforms can't have more than u16::MAX levels!".to_string())
+        })?;
+        writer.write_le_u16(level_count)?;
+
+        for level in &self.levels {
+            let points = u32::try_from(level.points.len() / self.channels.max(1) as usize)
+                .map_err(|_| {
+                    Error::Custom(
+                        "Waveform levels can't have more than u32::MAX points!".to_string(),
+                    )
+                })?;
+            writer.write_le_u32(level.frames_per_point)?;
+            writer.write_le_u32(points)?;
+
+            for point in &level.points {
+                writer.write_le_i16(quantize(point.min))?;
+                writer.write_le_i16(quantize(point.max))?;
+                writer.write_le_u16((point.rms.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: io::Read>(reader: &mut R) -> Result<Self> {
+        if &reader.read_next_bytes::<4>()? != WAVEFORM_MAGIC {
+            return Err(Error::WrongHeader);
+        }
+        let version = reader.read_le_u16()?;
+        if version != WAVEFORM_VERSION {
+            return Err(Error::Custom(format!(
+                "Waveform version {version} is not supported!"
+            )));
+        }
+
+        let channels = reader.read_le_u16()?;
+        let sample_rate = reader.read_le_u32()?;
+        let frames = reader.read_le_u64()?;
+        let level_count = reader.read_le_u16()?;
+
+        let mut levels = Vec::with_capacity(level_count as usize);
+        for _ in 0..level_count {
+            let frames_per_point = reader.read_le_u32()?;
+            let points = reader.read_le_u32()? as usize * channels as usize;
+
+            let mut level = WaveformLevel {
+                frames_per_point,
+                points: Vec::with_capacity(points.min(1 << 20)),
+            };
+            for _ in 0..points {
+                level.points.push(WaveformPoint {
+                    min: reader.read_le_i16()? as f32 / i16::MAX as f32,
+                    max: reader.read_le_i16()? as f32 / i16::MAX as f32,
+                    rms: reader.read_le_u16()? as f32 / u16::MAX as f32,
+                });
+            }
+
+            levels.push(level);
+        }
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            frames,
+            levels,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<path::Path>) -> Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        self.write(&mut writer)?;
+        io::Write::flush(&mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<path::Path>) -> Result<Self> {
+        Self::read(&mut io::BufReader::new(fs::File::open(path)?))
+    }
+}
+
+#[inline(always)]
+fn quantize(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+// ------------------------- BUILDER --------------------------
+
+/// Block of frames being summarized.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    min: f32,
+    max: f32,
+    square_sum: f64,
+    frames: u32,
+}
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            min: f32::MAX,
+            max: f32::MIN,
+            square_sum: 0.0,
+            frames: 0,
+        }
+    }
+}
+impl Accumulator {
+    #[inline(always)]
+    fn push(&mut self, sample: f32) {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.square_sum += sample as f64 * sample as f64;
+        self.frames += 1;
+    }
+
+    #[inline(always)]
+    fn merge(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            square_sum: self.square_sum + other.square_sum,
+            frames: self.frames + other.frames,
+        }
+    }
+
+    fn point(&self) -> WaveformPoint {
+        if self.frames == 0 {
+            return WaveformPoint::default();
+        }
+
+        WaveformPoint {
+            min: self.min,
+            max: self.max,
+            rms: (self.square_sum / self.frames as f64).sqrt() as f32,
+        }
+    }
+}
+
+/// Builds a [`Waveform`] as the samples come, it can be taken at any time.
+#[derive(Debug, Clone)]
+pub struct WaveformBuilder {
+    channels: usize,
+    sample_rate: u32,
+    frames_per_point: u32,
+    frames: u64,
+
+    /// Current point of every channel.
+    current: Vec<Accumulator>,
+    channel: usize,
+    /// Finished points of every level, interleaved.
+    levels: Vec<Vec<Accumulator>>,
+}
+impl WaveformBuilder {
+    pub fn new(info: AudioInfo, frames_per_point: u32, levels: usize) -> Result<Self> {
+        if info.channels == 0 || frames_per_point == 0 || levels == 0 {
+            return Err(Error::Custom(
+                "Waveform needs channels, frames_per_point and levels > 0!".to_string(),
+            ));
+        }
+        // The coarsest level has `frames_per_point << (levels - 1)` frames in every point.
+        if levels > u32::BITS as usize || frames_per_point.leading_zeros() < levels as u32 - 1 {
+            return Err(Error::Custom(format!(
+                "Waveform coarsest level overflows u32 with {levels} levels!"
+            )));
+        }
+
+        Ok(Self {
+            channels: info.channels as usize,
+            sample_rate: info.sample_rate,
+            frames_per_point,
+            frames: 0,
+            current: vec![Accumulator::default(); info.channels as usize],
+            channel: 0,
+            levels: vec![Vec::new(); levels],
+        })
+    }
+
+    /// Adds interleaved samples, they can stop in the middle of a frame.
+    pub fn process(&mut self, samples: &[f32]) {
+        for sample in samples {
+            self.push_sample(*sample);
+        }
+    }
+
+    pub fn push_sample(&mut self, sample: f32) {
+        self.current[self.channel].push(sample);
+
+        self.channel += 1;
+        if self.channel < self.channels {
+            return;
+        }
+
+        self.channel = 0;
+        self.frames += 1;
+        if self.current[0].frames == self.frames_per_point {
+            self.levels[0].append(&mut self.current);
+            self.current = vec![Accumulator::default(); self.channels];
+            self.merge_up(1);
+        }
+    }
+
+    #[inline(always)]
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Everything so far, the last points can cover fewer frames.
+    pub fn waveform(&self) -> Waveform {
+        let mut levels = Vec::with_capacity(self.levels.len());
+        let mut previous: Vec<Accumulator> = Vec::new();
+
+        for (i, finished) in self.levels.iter().enumerate() {
+            let mut level = finished.clone();
+
+            if i == 0 {
+                if self.current[0].frames > 0 {
+                    level.extend_from_slice(&self.current);
+                }
+            } else {
+                // Points of the previous level not merged yet.
+                let merged = finished.len() / self.channels * 2;
+                for pair in previous[merged * self.channels..].chunks(self.channels * 2) {
+                    let (first, second) = pair.split_at(self.channels.min(pair.len()));
+                    for (channel, first) in first.iter().enumerate() {
+                        level.push(match second.get(channel) {
+                            Some(second) => first.merge(*second),
+                            None => *first,
+                        });
+                    }
+                }
+            }
+
+            levels.push(WaveformLevel {
+                frames_per_point: self.frames_per_point << i,
+                points: level.iter().map(Accumulator::point).collect(),
+            });
+            previous = level;
+        }
+
+        Waveform {
+            channels: self.channels as u16,
+            sample_rate: self.sample_rate,
+            frames: self.frames,
+            levels,
+        }
+    }
+}
+impl WaveformBuilder {
+    /// Merges the last two points of the previous level once they are finished.
+    fn merge_up(&mut self, level: usize) {
+        if level >= self.levels.len() {
+            return;
+        }
+
+        let previous = &self.levels[level - 1];
+        let points = previous.len() / self.channels;
+        if !points.is_multiple_of(2) {
+            return;
+        }
+
+        let start = (points - 2) * self.channels;
+        let merged: Vec<Accumulator> = (0..self.channels)
+            .map(|channel| {
+                previous[start + channel].merge(previous[start + self.channels + channel])
+            })
+            .collect();
+
+        self.levels[level].extend(merged);
+        self.merge_up(level + 1);
+    }
+}
+
+/// Wraps an encoder, building the waveform of what is encoded, like while recording.
+pub struct LgWaveformEncoder<E: LgEncoder> {
+    encoder: E,
+    builder: WaveformBuilder,
+}
+impl<E: LgEncoder> LgWaveformEncoder<E> {
+    pub fn new(encoder: E, frames_per_point: u32, levels: usize) -> Result<Self> {
+        Ok(Self {
+            builder: WaveformBuilder::new(encoder.info(), frames_per_point, levels)?,
+            encoder,
+        })
+    }
+
+    /// Everything encoded so far.
+    #[inline(always)]
+    pub fn waveform(&self) -> Waveform {
+        self.builder.waveform()
+    }
+
+    #[inline(always)]
+    pub fn encoder(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+
+    #[inline(always)]
+    pub fn into_parts(self) -> (E, Waveform) {
+        let waveform = self.builder.waveform();
+
+        (self.encoder, waveform)
+    }
+}
+impl<E: LgEncoder> LgEncoder for LgWaveformEncoder<E> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.encoder.info()
+    }
+
+    fn encode_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        let info = self.encoder.info();
+        let (sample, value) = stored_sample(
+            sample,
+            info.sample_type.unwrap_or(SampleType::INT),
+            info.bits_per_sample,
+        )?;
+
+        self.encoder.encode_sample(sample)?;
+        self.builder.push_sample(value);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encoded_samples(&self) -> usize {
+        self.encoder.encoded_samples()
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.encoder.duration()
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.encoder.len()
+    }
+}
+
+// ------------------------- RENDER --------------------------
+
+#[cfg(feature = "waveform_png")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveformStyle {
+    pub width: u32,
+    /// Height of every channel.
+    pub channel_height: u32,
+    pub background: [u8; 4],
+    pub peak_color: [u8; 4],
+    pub rms_color: [u8; 4],
+}
+#[cfg(feature = "waveform_png")]
+impl Default for WaveformStyle {
+    fn default() -> Self {
+        Self {
+            width: 1024,
+            channel_height: 128,
+            background: [0, 0, 0, 0],
+            peak_color: [80, 160, 255, 255],
+            rms_color: [170, 210, 255, 255],
+        }
+    }
+}
+
+#[cfg(feature = "waveform_png")]
+impl Waveform {
+    /// Channels are drawn one under the other, the peaks with the RMS over them.
+    pub fn render(&self, style: &WaveformStyle) -> image::RgbaImage {
+        let channels = self.channels.max(1) as u32;
+        let mut image = image::RgbaImage::from_pixel(
+            style.width,
+            style.channel_height * channels,
+            image::Rgba(style.background),
+        );
+        if style.width == 0 || style.channel_height == 0 {
+            return image;
+        }
+
+        let frames_per_pixel = self.frames as f64 / style.width as f64;
+        let Some(level) = self.level_for(frames_per_pixel) else {
+            return image;
+        };
+        let points = level.points.len() / channels as usize;
+        let points_per_pixel = frames_per_pixel / level.frames_per_point as f64;
+        let half_height = (style.channel_height - 1) as f32 / 2.0;
+
+        for x in 0..style.width {
+            let first = (x as f64 * points_per_pixel) as usize;
+            let last =
+                (((x + 1) as f64 * points_per_pixel).ceil() as usize).clamp(first + 1, points);
+            if first >= points {
+                break;
+            }
+
+            for channel in 0..channels {
+                let mut point = WaveformPoint {
+                    min: f32::MAX,
+                    max: f32::MIN,
+                    rms: 0.0,
+                };
+                for i in first..last {
+                    let p = level.points[i * channels as usize + channel as usize];
+                    point.min = point.min.min(p.min);
+                    point.max = point.max.max(p.max);
+                    point.rms = point.rms.max(p.rms);
+                }
+
+                let top = channel * style.channel_height;
+                let to_y = |value: f32| {
+                    top + ((1.0 - value.clamp(-1.0, 1.0)) * half_height).round() as u32
+                };
+
+                for y in to_y(point.max)..=to_y(point.min) {
+                    image.put_pixel(x, y, image::Rgba(style.peak_color));
+                }
+                for y in to_y(point.rms.min(point.max))..=to_y((-point.rms).max(point.min)) {
+                    image.put_pixel(x, y, image::Rgba(style.rms_color));
+                }
+            }
+        }
+
+        image
+    }
+
+    pub fn save_png(&self, path: impl AsRef<path::Path>, style: &WaveformStyle) -> Result<()> {
+        self.render(style)
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+}
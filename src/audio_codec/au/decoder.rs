@@ -0,0 +1,92 @@
+use super::super::{AudioInfo, Result, decoder::LgDecoder, error::Error};
+use super::{AuEncoding, LgAuSampleIter, reader::LgAuReader};
+use std::{fmt, fs, io, path};
+
+pub struct LgAuDecoder<R: io::Read> {
+    info: AudioInfo,
+    sample_len: usize,
+
+    reader: LgAuReader<R>,
+}
+impl<R: io::Read> fmt::Debug for LgAuDecoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LgAuDecoder")
+            .field("info", &self.info)
+            .field("encoding", &self.reader.encoding)
+            .field("sample_len", &self.sample_len)
+            .finish()
+    }
+}
+impl LgAuDecoder<io::BufReader<fs::File>> {
+    /// Without the data size in the header, the rest of the file is used.
+    pub fn new(path: impl AsRef<path::Path>) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let file_size = file.metadata()?.len() as usize;
+        let mut result = Self::from_reader(io::BufReader::new(file))?;
+
+        if result.reader.data_size.is_none() {
+            let data_size = file_size.saturating_sub(result.reader.header_size as usize);
+            result.reader.data_size = Some(data_size);
+            result.sample_len = data_size / result.reader.encoding.sample_size();
+        }
+
+        Ok(result)
+    }
+}
+impl<R: io::Read> LgAuDecoder<R> {
+    /// Without the data size in the header, the length is unknown so [`LgDecoder::len`] is 0.
+    pub fn from_reader(reader: R) -> Result<Self> {
+        // Already reads the header, so the format is known.
+        let reader = LgAuReader::new(reader)?;
+
+        let info = reader
+            .encoding
+            .info(reader.channels, reader.sample_rate)
+            .ok_or(Error::WrongFmt)?;
+
+        Ok(Self {
+            info,
+            sample_len: reader.data_size.unwrap_or(0) / reader.encoding.sample_size(),
+            reader,
+        })
+    }
+
+    #[inline(always)]
+    pub fn encoding(&self) -> AuEncoding {
+        self.reader.encoding
+    }
+
+    /// False for files without the data size opened with [`LgAuDecoder::from_reader`].
+    #[inline(always)]
+    pub fn is_len_known(&self) -> bool {
+        self.reader.data_size.is_some()
+    }
+}
+impl<R: io::Read> LgDecoder for LgAuDecoder<R> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn samples<S: super::Sample>(&mut self) -> impl Iterator<Item = S> {
+        let encoding = self.reader.encoding;
+
+        LgAuSampleIter::new(&mut self.reader, encoding, self.info)
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.sample_len / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.sample_len
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_len_known() && self.sample_len == 0
+    }
+}
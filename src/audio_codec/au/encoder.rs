@@ -0,0 +1,76 @@
+use super::writer::LgAuWriter;
+use super::{
+    super::{Result, encoder::LgEncoder, error::Error, sample::Sample},
+    AuEncoding, AudioInfo,
+};
+use std::{fs, io, path};
+
+pub struct LgAuEncoder<W: io::Write + io::Seek> {
+    pub(super) info: AudioInfo,
+    writer: LgAuWriter<W>,
+}
+impl LgAuEncoder<io::BufWriter<fs::File>> {
+    /// Linear or floating point encoding, with the format of the `info`.
+    pub fn new(path: impl AsRef<path::Path>, info: AudioInfo) -> Result<Self> {
+        let encoding = AuEncoding::from_info(&info).ok_or_else(|| {
+            Error::WrongFmtInfo(format!(
+                "{:?} with {} bits per sample can't be encoded!",
+                info.sample_type, info.bits_per_sample
+            ))
+        })?;
+
+        Self::with_encoding(path, info, encoding)
+    }
+
+    /// Only `channels` and `sample_rate` are used from the `info`, the format comes from the `encoding`.
+    /// μ-law and A-law take 16 bit samples.
+    pub fn with_encoding(
+        path: impl AsRef<path::Path>,
+        info: AudioInfo,
+        encoding: AuEncoding,
+    ) -> Result<Self> {
+        let file = fs::File::create(path)?;
+        let writer = LgAuWriter::new(io::BufWriter::new(file), &info, encoding)?;
+
+        Ok(Self {
+            info: encoding
+                .info(info.channels, info.sample_rate)
+                .ok_or(Error::WrongFmt)?,
+            writer,
+        })
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.finish()
+    }
+}
+impl<W: io::Write + io::Seek> LgEncoder for LgAuEncoder<W> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn encode_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        self.writer.write_sample(sample)
+    }
+
+    #[inline(always)]
+    fn encoded_samples(&self) -> usize {
+        self.writer.data_bytes_written / self.writer.encoding.sample_size()
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.len() / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.encoded_samples()
+    }
+}
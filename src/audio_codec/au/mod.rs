@@ -0,0 +1,173 @@
+use super::{
+    AudioInfo, g711,
+    sample::{ByteOrder, Sample, SampleType},
+};
+use std::io;
+use std::marker::PhantomData;
+
+pub mod decoder;
+pub mod encoder;
+pub mod reader;
+pub mod writer;
+
+#[cfg(test)]
+mod tests;
+
+pub use decoder::LgAuDecoder;
+pub use encoder::LgAuEncoder;
+
+use reader::LgAuReader;
+
+// ------------------------- LAYOUT --------------------------
+const AU_MAGIC: &[u8; 4] = b".snd";
+/// Header without the annotation.
+const AU_HEADER_SIZE: u32 = 24;
+/// The data size is optional, streams don't know it.
+const AU_UNKNOWN_SIZE: u32 = u32::MAX;
+
+// ------------------------- ENCODINGS --------------------------
+const AU_ENCODING_MULAW_8: u32 = 1;
+const AU_ENCODING_LINEAR_8: u32 = 2;
+const AU_ENCODING_LINEAR_16: u32 = 3;
+const AU_ENCODING_LINEAR_24: u32 = 4;
+const AU_ENCODING_LINEAR_32: u32 = 5;
+const AU_ENCODING_FLOAT: u32 = 6;
+const AU_ENCODING_DOUBLE: u32 = 7;
+const AU_ENCODING_ALAW_8: u32 = 27;
+
+#[allow(non_camel_case_types)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum AuEncoding {
+    MULAW_8,
+    LINEAR_8,
+    #[default]
+    LINEAR_16,
+    LINEAR_24,
+    LINEAR_32,
+    FLOAT,
+    DOUBLE,
+    ALAW_8,
+    OTHER(u32),
+}
+impl AuEncoding {
+    /// Linear or floating point encoding with the format of the `info`.
+    pub fn from_info(info: &AudioInfo) -> Option<Self> {
+        Some(match (info.sample_type, info.bits_per_sample) {
+            (Some(SampleType::INT) | None, 8) => Self::LINEAR_8,
+            (Some(SampleType::INT) | None, 16) => Self::LINEAR_16,
+            (Some(SampleType::INT) | None, 24) => Self::LINEAR_24,
+            (Some(SampleType::INT) | None, 32) => Self::LINEAR_32,
+            (Some(SampleType::FLOAT), 32) => Self::FLOAT,
+            (Some(SampleType::FLOAT), 64) => Self::DOUBLE,
+
+            _ => return None,
+        })
+    }
+
+    /// Format of the decoded samples, μ-law and A-law are expanded to 16 bits.
+    pub fn info(self, channels: u16, sample_rate: u32) -> Option<AudioInfo> {
+        let (sample_type, bits_per_sample) = match self {
+            Self::MULAW_8 | Self::ALAW_8 | Self::LINEAR_16 => (SampleType::INT, 16),
+            Self::LINEAR_8 => (SampleType::INT, 8),
+            Self::LINEAR_24 => (SampleType::INT, 24),
+            Self::LINEAR_32 => (SampleType::INT, 32),
+            Self::FLOAT => (SampleType::FLOAT, 32),
+            Self::DOUBLE => (SampleType::FLOAT, 64),
+            Self::OTHER(_) => return None,
+        };
+
+        Some(AudioInfo {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_type: Some(sample_type),
+        })
+    }
+
+    /// Bytes used by every sample in the file.
+    #[inline(always)]
+    fn sample_size(self) -> usize {
+        match self {
+            Self::MULAW_8 | Self::ALAW_8 | Self::LINEAR_8 => 1,
+            Self::LINEAR_16 => 2,
+            Self::LINEAR_24 => 3,
+            Self::LINEAR_32 | Self::FLOAT => 4,
+            Self::DOUBLE => 8,
+            Self::OTHER(_) => 0,
+        }
+    }
+}
+impl From<u32> for AuEncoding {
+    fn from(value: u32) -> Self {
+        match value {
+            AU_ENCODING_MULAW_8 => Self::MULAW_8,
+            AU_ENCODING_LINEAR_8 => Self::LINEAR_8,
+            AU_ENCODING_LINEAR_16 => Self::LINEAR_16,
+            AU_ENCODING_LINEAR_24 => Self::LINEAR_24,
+            AU_ENCODING_LINEAR_32 => Self::LINEAR_32,
+            AU_ENCODING_FLOAT => Self::FLOAT,
+            AU_ENCODING_DOUBLE => Self::DOUBLE,
+            AU_ENCODING_ALAW_8 => Self::ALAW_8,
+            _ => Self::OTHER(value),
+        }
+    }
+}
+impl From<AuEncoding> for u32 {
+    fn from(val: AuEncoding) -> u32 {
+        match val {
+            AuEncoding::MULAW_8 => AU_ENCODING_MULAW_8,
+            AuEncoding::LINEAR_8 => AU_ENCODING_LINEAR_8,
+            AuEncoding::LINEAR_16 => AU_ENCODING_LINEAR_16,
+            AuEncoding::LINEAR_24 => AU_ENCODING_LINEAR_24,
+            AuEncoding::LINEAR_32 => AU_ENCODING_LINEAR_32,
+            AuEncoding::FLOAT => AU_ENCODING_FLOAT,
+            AuEncoding::DOUBLE => AU_ENCODING_DOUBLE,
+            AuEncoding::ALAW_8 => AU_ENCODING_ALAW_8,
+            AuEncoding::OTHER(value) => value,
+        }
+    }
+}
+
+// ------------------------- SAMPLE --------------------------
+
+pub struct LgAuSampleIter<'si, R: io::Read, S: Sample> {
+    encoding: AuEncoding,
+    info: AudioInfo,
+    reader: &'si mut LgAuReader<R>,
+    _phantom: PhantomData<S>,
+}
+impl<'si, R: io::Read, S: Sample> LgAuSampleIter<'si, R, S> {
+    fn new(reader: &'si mut LgAuReader<R>, encoding: AuEncoding, info: AudioInfo) -> Self {
+        Self {
+            encoding,
+            info,
+            reader,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<R: io::Read, S: Sample> Iterator for LgAuSampleIter<'_, R, S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use crate::reader::LgReader;
+
+        let expanded = match self.encoding {
+            AuEncoding::MULAW_8 => g711::mulaw_to_linear(self.reader.read_u8().ok()?),
+            AuEncoding::ALAW_8 => g711::alaw_to_linear(self.reader.read_u8().ok()?),
+
+            _ => {
+                return S::read_as(
+                    self.reader,
+                    self.info.sample_type.unwrap_or(SampleType::INT),
+                    self.info.bits_per_sample,
+                    ByteOrder::BigEndian,
+                    true,
+                )
+                .ok();
+            }
+        };
+
+        Some(S::from_f32(expanded as f32 / 32768.0, SampleType::INT, 16))
+    }
+}
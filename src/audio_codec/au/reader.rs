@@ -0,0 +1,99 @@
+use super::super::error::Error;
+use super::{AU_HEADER_SIZE, AU_MAGIC, AU_UNKNOWN_SIZE, AuEncoding};
+use crate::reader::LgReader;
+use std::io;
+
+pub struct LgAuReader<R: io::Read> {
+    pub(super) reader: R,
+    pub(super) encoding: AuEncoding,
+    pub(super) channels: u16,
+    pub(super) sample_rate: u32,
+    /// Header with the annotation, the samples start after it.
+    pub(super) header_size: u32,
+    /// Bytes of sample data, `None` if the header doesn't say it.
+    pub(super) data_size: Option<usize>,
+    cursor: usize,
+}
+impl<R: io::Read> LgReader for LgAuReader<R> {
+    type Error = Error;
+
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.move_cursor(buffer.len())?;
+
+        Ok(self.reader.read_into(buffer)?)
+    }
+
+    fn read_next_bytes<const N: usize>(&mut self) -> Result<[u8; N], Self::Error> {
+        self.move_cursor(N)?;
+
+        Ok(self.reader.read_next_bytes()?)
+    }
+
+    fn skip_next_bytes<const N: usize>(&mut self) -> Result<(), Self::Error> {
+        self.move_cursor(N)?;
+
+        Ok(self.reader.skip_next_bytes::<N>()?)
+    }
+}
+impl<R: io::Read> LgAuReader<R> {
+    /// Reads the header and skips the annotation, leaving the reader at the samples.
+    pub(super) fn new(mut reader: R) -> Result<Self, Error> {
+        if AU_MAGIC != &reader.read_next_bytes()? {
+            return Err(Error::WrongHeader);
+        }
+
+        let header_size = reader.read_be_u32()?;
+        let data_size = reader.read_be_u32()?;
+        let encoding = AuEncoding::from(reader.read_be_u32()?);
+        let sample_rate = reader.read_be_u32()?;
+        let channels = reader.read_be_u32()?;
+
+        if header_size < AU_HEADER_SIZE {
+            return Err(Error::WrongHeader);
+        }
+
+        if let AuEncoding::OTHER(encoding) = encoding {
+            return Err(Error::WrongFmtInfo(format!(
+                "AU encoding {encoding} is not supported!"
+            )));
+        }
+
+        if channels == 0 || channels > u16::MAX as u32 || sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "AU channels and sample_rate must be > 0!".to_string(),
+            ));
+        }
+
+        // Annotation, usually a text.
+        io::copy(
+            &mut io::Read::take(&mut reader, (header_size - AU_HEADER_SIZE) as u64),
+            &mut io::sink(),
+        )?;
+
+        Ok(Self {
+            reader,
+            encoding,
+            channels: channels as u16,
+            sample_rate,
+            header_size,
+            data_size: (data_size != AU_UNKNOWN_SIZE).then_some(data_size as usize),
+            cursor: 0,
+        })
+    }
+}
+impl<R: io::Read> LgAuReader<R> {
+    fn move_cursor(&mut self, n: usize) -> Result<(), Error> {
+        if let Some(data_size) = self.data_size
+            && self.cursor + n > data_size
+        {
+            return Err(Error::Io(io::Error::new::<String>(
+                io::ErrorKind::UnexpectedEof,
+                "".into(),
+            )));
+        }
+
+        self.cursor += n;
+
+        Ok(())
+    }
+}
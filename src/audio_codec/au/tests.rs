@@ -0,0 +1,138 @@
+use super::super::{AudioInfo, decoder::LgDecoder, g711, sample::Sample};
+use super::{AuEncoding, LgAuDecoder, writer::LgAuWriter};
+use std::io;
+
+const CHANNELS: u16 = 2;
+const SAMPLE_RATE: u32 = 8_000;
+
+fn encode<S: Sample>(encoding: AuEncoding, samples: Vec<S>) -> Vec<u8> {
+    let info = AudioInfo {
+        channels: CHANNELS,
+        sample_rate: SAMPLE_RATE,
+        ..Default::default()
+    };
+
+    let mut bytes = io::Cursor::new(Vec::new());
+    let mut writer = LgAuWriter::new(&mut bytes, &info, encoding).unwrap();
+    for sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finish().unwrap();
+    drop(writer);
+
+    bytes.into_inner()
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// The samples after the header.
+fn data(bytes: &[u8]) -> &[u8] {
+    &bytes[be_u32(bytes, 4) as usize..]
+}
+
+fn decode<S: Sample>(encoding: AuEncoding, bytes: &[u8]) -> Vec<S> {
+    let mut decoder = LgAuDecoder::from_reader(bytes).unwrap();
+
+    assert_eq!(decoder.encoding(), encoding);
+    assert_eq!(decoder.info().channels, CHANNELS);
+    assert_eq!(decoder.info().sample_rate, SAMPLE_RATE);
+    assert!(decoder.is_len_known());
+
+    let len = decoder.len();
+    let samples: Vec<S> = decoder.samples().collect();
+    assert_eq!(samples.len(), len);
+
+    samples
+}
+
+#[test]
+fn header() {
+    let bytes = encode(AuEncoding::LINEAR_16, vec![0i32; 6]);
+
+    assert_eq!(&bytes[..4], b".snd");
+    assert_eq!(be_u32(&bytes, 8), 12);
+    assert_eq!(be_u32(&bytes, 12), 3);
+    assert_eq!(be_u32(&bytes, 16), SAMPLE_RATE);
+    assert_eq!(be_u32(&bytes, 20), CHANNELS as u32);
+    assert_eq!(data(&bytes).len(), 12);
+}
+
+#[test]
+fn linear_round_trips() {
+    for (encoding, bits) in [
+        (AuEncoding::LINEAR_8, 8),
+        (AuEncoding::LINEAR_16, 16),
+        (AuEncoding::LINEAR_24, 24),
+        (AuEncoding::LINEAR_32, 32),
+    ] {
+        let max = ((1i64 << (bits - 1)) - 1) as i32;
+        let samples = vec![-max - 1, -max / 3, -1, 0, 1, max / 2, max - 1, max];
+
+        let bytes = encode(encoding, samples.clone());
+        assert_eq!(data(&bytes).len(), samples.len() * bits / 8);
+        assert_eq!(decode::<i32>(encoding, &bytes), samples);
+    }
+}
+
+#[test]
+fn linear_is_big_endian() {
+    let bytes = encode(AuEncoding::LINEAR_16, vec![0x1234i32, -2]);
+    assert_eq!(data(&bytes), [0x12, 0x34, 0xff, 0xfe]);
+
+    let bytes = encode(AuEncoding::LINEAR_24, vec![0x123456i32, -2]);
+    assert_eq!(data(&bytes), [0x12, 0x34, 0x56, 0xff, 0xff, 0xfe]);
+}
+
+#[test]
+fn linear_8_is_signed() {
+    let samples = vec![-128i32, -1, 0, 127];
+    let bytes = encode(AuEncoding::LINEAR_8, samples.clone());
+
+    assert_eq!(data(&bytes), [0x80, 0xff, 0x00, 0x7f]);
+    assert_eq!(decode::<i32>(AuEncoding::LINEAR_8, &bytes), samples);
+    assert_eq!(
+        decode::<f32>(AuEncoding::LINEAR_8, &bytes),
+        [-1.0, -1.0 / 128.0, 0.0, 127.0 / 128.0]
+    );
+}
+
+#[test]
+fn float_round_trips() {
+    let samples = vec![-1.0f32, -0.25, 0.0, 0.1, 0.5, 1.0];
+
+    let bytes = encode(AuEncoding::FLOAT, samples.clone());
+    assert_eq!(
+        data(&bytes)[12..20],
+        [0x3d, 0xcc, 0xcc, 0xcd, 0x3f, 0, 0, 0]
+    );
+    assert_eq!(decode::<f32>(AuEncoding::FLOAT, &bytes), samples);
+
+    let bytes = encode(AuEncoding::DOUBLE, samples.clone());
+    assert_eq!(data(&bytes)[32..40], 0.5f64.to_be_bytes());
+    assert_eq!(decode::<f32>(AuEncoding::DOUBLE, &bytes), samples);
+}
+
+/// Every code expands to a value that compresses back to it.
+fn check_companding(encoding: AuEncoding, expand: fn(u8) -> i16) {
+    let samples: Vec<i32> = (0..=255).map(|code| expand(code) as i32).collect();
+    let bytes = encode(encoding, samples.clone());
+
+    assert_eq!(data(&bytes).len(), samples.len());
+    assert_eq!(decode::<i32>(encoding, &bytes), samples);
+}
+
+#[test]
+fn mulaw_round_trips() {
+    check_companding(AuEncoding::MULAW_8, g711::mulaw_to_linear);
+
+    // Both zeros of μ-law are stored as the positive one.
+    let bytes = encode(AuEncoding::MULAW_8, vec![0i32, 0]);
+    assert_eq!(data(&bytes), [0xff, 0xff]);
+}
+
+#[test]
+fn alaw_round_trips() {
+    check_companding(AuEncoding::ALAW_8, g711::alaw_to_linear);
+}
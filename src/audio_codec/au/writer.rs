@@ -0,0 +1,117 @@
+use super::super::Result;
+use super::super::error::Error;
+use super::super::sample::{ByteOrder, Sample, SampleType};
+use super::{AU_HEADER_SIZE, AU_MAGIC, AU_UNKNOWN_SIZE, AuEncoding, AudioInfo, g711};
+use crate::writer::LgWriter;
+use std::io;
+
+const DATA_SIZE_POSITION: usize = 8;
+/// Header with an empty annotation, keeps the samples aligned to 8 bytes.
+const HEADER_SIZE: u32 = AU_HEADER_SIZE + 8;
+
+pub struct LgAuWriter<W: io::Write + io::Seek> {
+    pub(super) writer: W,
+    pub(super) data_bytes_written: usize,
+    pub(super) encoding: AuEncoding,
+    /// Format of the samples given to [`LgAuWriter::write_sample`].
+    info: AudioInfo,
+}
+impl<W: io::Write + io::Seek> Drop for LgAuWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+impl<W: io::Write + io::Seek> LgAuWriter<W> {
+    /// Only `channels` and `sample_rate` are used from the `info`, the format comes from the `encoding`.
+    pub fn new(mut writer: W, info: &AudioInfo, encoding: AuEncoding) -> Result<Self> {
+        let info = encoding
+            .info(info.channels, info.sample_rate)
+            .ok_or_else(|| Error::WrongFmtInfo(format!("{encoding:?} can't be encoded!")))?;
+
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "AU channels and sample_rate must be > 0!".to_string(),
+            ));
+        }
+
+        writer.write_all(AU_MAGIC)?;
+        writer.write_be_u32(HEADER_SIZE)?;
+        // Empty for now, updated when finishing.
+        writer.write_be_u32(0)?;
+        writer.write_be_u32(encoding.into())?;
+        writer.write_be_u32(info.sample_rate)?;
+        writer.write_be_u32(info.channels as u32)?;
+        // Annotation.
+        writer.write_all(&[0; (HEADER_SIZE - AU_HEADER_SIZE) as usize])?;
+
+        Ok(Self {
+            writer,
+            data_bytes_written: 0,
+            encoding,
+            info,
+        })
+    }
+
+    pub fn write_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+
+        match self.encoding {
+            AuEncoding::MULAW_8 | AuEncoding::ALAW_8 => {
+                let value = i32::from_f32(
+                    sample.to_f32(sample_type, bits_per_sample),
+                    sample_type,
+                    bits_per_sample,
+                ) as i16;
+
+                self.writer
+                    .write_u8(if self.encoding == AuEncoding::MULAW_8 {
+                        g711::linear_to_mulaw(value)
+                    } else {
+                        g711::linear_to_alaw(value)
+                    })?;
+            }
+
+            _ => sample.write_as(
+                &mut self.writer,
+                sample_type,
+                bits_per_sample,
+                ByteOrder::BigEndian,
+                true,
+            )?,
+        }
+
+        self.data_bytes_written += self.encoding.sample_size();
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        let current_pos = self.writer.stream_position()?;
+        self.update_headers()?;
+        self.writer.flush()?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.update_headers()?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+impl<W: io::Write + io::Seek> LgAuWriter<W> {
+    fn update_headers(&mut self) -> Result<()> {
+        // Too big files are stored with an unknown size, readers will use the rest of the file.
+        let data_size = u32::try_from(self.data_bytes_written).unwrap_or(AU_UNKNOWN_SIZE);
+
+        let current_pos = self.writer.stream_position()?;
+        self.writer.go_to(DATA_SIZE_POSITION)?;
+        self.writer.write_be_u32(data_size)?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+}
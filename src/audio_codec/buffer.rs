@@ -0,0 +1,86 @@
+use super::{AudioInfo, Result, decoder::LgDecoder, encoder::LgEncoder};
+
+/// Interleaved samples normalized to `-1.0..=1.0`, kept in memory.
+/// The `info` keeps the format the samples came from, so they can be encoded back like it.
+#[derive(Debug, Default, Clone)]
+pub struct AudioBuffer {
+    pub info: AudioInfo,
+    pub samples: Vec<f32>,
+}
+impl AudioBuffer {
+    #[inline(always)]
+    pub fn new(info: AudioInfo, samples: Vec<f32>) -> Self {
+        Self { info, samples }
+    }
+
+    /// Silence with `frames` frames.
+    pub fn silence(info: AudioInfo, frames: usize) -> Self {
+        Self {
+            info,
+            samples: vec![0.0; frames * info.channels as usize],
+        }
+    }
+
+    /// Reads every remaining sample of the decoder.
+    pub fn from_decoder(decoder: &mut impl LgDecoder) -> Self {
+        let info = decoder.info();
+        let mut samples = Vec::with_capacity(decoder.len());
+        samples.extend(decoder.samples::<f32>());
+
+        Self { info, samples }
+    }
+
+    /// Encodes every sample, the encoder does the conversion to its format.
+    pub fn encode(&self, encoder: &mut impl LgEncoder) -> Result<()> {
+        for sample in &self.samples {
+            encoder.encode_sample(*sample)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn channels(&self) -> usize {
+        self.info.channels as usize
+    }
+
+    /// Samples per channel.
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels().max(1)
+    }
+
+    /// Duration in seconds.
+    #[inline(always)]
+    pub fn duration(&self) -> f64 {
+        self.frames() as f64 / self.info.sample_rate.max(1) as f64
+    }
+
+    /// Samples of every channel at the same time.
+    #[inline(always)]
+    pub fn frame(&self, index: usize) -> &[f32] {
+        let channels = self.channels();
+
+        &self.samples[index * channels..(index + 1) * channels]
+    }
+
+    #[inline(always)]
+    pub fn frame_mut(&mut self, index: usize) -> &mut [f32] {
+        let channels = self.channels();
+
+        &mut self.samples[index * channels..(index + 1) * channels]
+    }
+
+    /// Every sample of a single channel.
+    pub fn channel(&self, channel: usize) -> impl Iterator<Item = f32> + '_ {
+        self.samples
+            .iter()
+            .skip(channel)
+            .step_by(self.channels().max(1))
+            .copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
@@ -0,0 +1,104 @@
+use super::super::{AudioInfo, Result, decoder::LgDecoder};
+use super::{
+    CafEncoding, CafMetadata, CafPacketTable, LgCafSampleIter, reader::LgCafReader, sample_size,
+};
+use std::{fmt, fs, io, path};
+
+pub struct LgCafDecoder<R: io::Read> {
+    info: AudioInfo,
+    sample_len: usize,
+
+    reader: LgCafReader<R>,
+}
+impl<R: io::Read> fmt::Debug for LgCafDecoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LgCafDecoder")
+            .field("info", &self.info)
+            .field("encoding", &self.reader.encoding)
+            .field("sample_len", &self.sample_len)
+            .field("metadata", &self.reader.metadata)
+            .finish()
+    }
+}
+impl LgCafDecoder<io::BufReader<fs::File>> {
+    /// A data chunk with unknown size goes to the end of the file.
+    pub fn new(path: impl AsRef<path::Path>) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut result = Self::from_reader(io::BufReader::new(file))?;
+
+        if result.reader.data_size.is_none() {
+            let data_size = file_size.saturating_sub(result.reader.data_offset);
+            result.reader.data_size = Some(data_size);
+            result.sample_len =
+                data_size as usize / sample_size(result.reader.encoding, &result.info);
+        }
+
+        Ok(result)
+    }
+}
+impl<R: io::Read> LgCafDecoder<R> {
+    /// Without the data chunk size the length is unknown, so [`LgDecoder::len`] is 0.
+    pub fn from_reader(reader: R) -> Result<Self> {
+        // Already reads every chunk before the data.
+        let reader = LgCafReader::new(reader)?;
+        let info = reader.info;
+
+        Ok(Self {
+            info,
+            sample_len: reader.data_size.unwrap_or(0) as usize
+                / sample_size(reader.encoding, &info),
+            reader,
+        })
+    }
+
+    #[inline(always)]
+    pub fn encoding(&self) -> CafEncoding {
+        self.reader.encoding
+    }
+
+    /// Chunks found before the data.
+    #[inline(always)]
+    pub fn metadata(&self) -> &CafMetadata {
+        &self.reader.metadata
+    }
+
+    #[inline(always)]
+    pub fn packet_table(&self) -> Option<CafPacketTable> {
+        self.reader.packet_table
+    }
+
+    /// False for files without the data chunk size opened with [`LgCafDecoder::from_reader`].
+    #[inline(always)]
+    pub fn is_len_known(&self) -> bool {
+        self.reader.data_size.is_some()
+    }
+}
+impl<R: io::Read> LgDecoder for LgCafDecoder<R> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn samples<S: super::Sample>(&mut self) -> impl Iterator<Item = S> {
+        let encoding = self.reader.encoding;
+
+        LgCafSampleIter::new(&mut self.reader, encoding, self.info)
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.sample_len / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.sample_len
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_len_known() && self.sample_len == 0
+    }
+}
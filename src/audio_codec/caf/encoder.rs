@@ -0,0 +1,68 @@
+use super::writer::LgCafWriter;
+use super::{
+    super::{Result, encoder::LgEncoder, sample::Sample},
+    AudioInfo, CafEncoding, CafMetadata, caf_info, sample_size,
+};
+use std::{fs, io, path};
+
+pub struct LgCafEncoder<W: io::Write + io::Seek> {
+    pub(super) info: AudioInfo,
+    writer: LgCafWriter<W>,
+}
+impl LgCafEncoder<io::BufWriter<fs::File>> {
+    /// Little-endian LPCM with the format of the `info`.
+    pub fn new(path: impl AsRef<path::Path>, info: AudioInfo) -> Result<Self> {
+        Self::with_encoding(path, info, CafEncoding::default(), &CafMetadata::default())
+    }
+
+    /// A-law and μ-law take 16 bit samples, only `channels` and `sample_rate` are used from the `info`.
+    pub fn with_encoding(
+        path: impl AsRef<path::Path>,
+        info: AudioInfo,
+        encoding: CafEncoding,
+        metadata: &CafMetadata,
+    ) -> Result<Self> {
+        let file = fs::File::create(path)?;
+        let writer = LgCafWriter::new(io::BufWriter::new(file), &info, encoding, metadata)?;
+
+        Ok(Self {
+            info: caf_info(encoding, &info),
+            writer,
+        })
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.finish()
+    }
+}
+impl<W: io::Write + io::Seek> LgEncoder for LgCafEncoder<W> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn encode_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        self.writer.write_sample(sample)
+    }
+
+    #[inline(always)]
+    fn encoded_samples(&self) -> usize {
+        (self.writer.data_bytes_written / sample_size(self.writer.encoding, &self.info) as u64)
+            as usize
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.len() / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.encoded_samples()
+    }
+}
@@ -0,0 +1,180 @@
+use super::{
+    AudioInfo,
+    error::Error,
+    g711,
+    sample::{ByteOrder, Sample, SampleType},
+};
+use std::io;
+use std::marker::PhantomData;
+
+pub mod decoder;
+pub mod encoder;
+pub mod reader;
+pub mod writer;
+
+#[cfg(test)]
+mod tests;
+
+pub use decoder::LgCafDecoder;
+pub use encoder::LgCafEncoder;
+
+use reader::LgCafReader;
+
+// ------------------------- LAYOUT --------------------------
+const CAF_MAGIC: &[u8; 4] = b"caff";
+const CAF_VERSION: u16 = 1;
+/// Only the data chunk can have an unknown size, it then goes to the end of the file.
+const CAF_UNKNOWN_SIZE: i64 = -1;
+/// Size of the desc chunk data.
+const CAF_DESC_SIZE: i64 = 32;
+
+// ------------------------- CHUNKS --------------------------
+const CAF_CHUNK_DESC: &[u8; 4] = b"desc";
+const CAF_CHUNK_DATA: &[u8; 4] = b"data";
+const CAF_CHUNK_PAKT: &[u8; 4] = b"pakt";
+const CAF_CHUNK_INFO: &[u8; 4] = b"info";
+const CAF_CHUNK_CHAN: &[u8; 4] = b"chan";
+const CAF_CHUNK_KUKI: &[u8; 4] = b"kuki";
+
+// ------------------------- FORMATS --------------------------
+const CAF_FORMAT_LPCM: &[u8; 4] = b"lpcm";
+const CAF_FORMAT_ALAW: &[u8; 4] = b"alaw";
+const CAF_FORMAT_ULAW: &[u8; 4] = b"ulaw";
+
+const CAF_LPCM_FLAG_IS_FLOAT: u32 = 1 << 0;
+const CAF_LPCM_FLAG_IS_LITTLE_ENDIAN: u32 = 1 << 1;
+
+/// How the samples are stored in the data chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CafEncoding {
+    /// Signed integers or floats, with the format of the [`AudioInfo`].
+    Lpcm(ByteOrder),
+    ALaw,
+    MuLaw,
+}
+impl Default for CafEncoding {
+    fn default() -> Self {
+        Self::Lpcm(ByteOrder::LittleEndian)
+    }
+}
+
+/// The `chan` chunk, how the channels are placed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CafChannelLayout {
+    /// `kCAFChannelLayoutTag_*`, 0 means the descriptions are used.
+    pub tag: u32,
+    /// Used when the tag is `kCAFChannelLayoutTag_UseChannelBitmap` (`0x10000`).
+    pub bitmap: u32,
+    pub descriptions: Vec<CafChannelDescription>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CafChannelDescription {
+    /// `kCAFChannelLabel_*`.
+    pub label: u32,
+    pub flags: u32,
+    pub coordinates: [f32; 3],
+}
+
+/// Header of the `pakt` chunk, the packet sizes are not kept since only constant size packets are decoded.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CafPacketTable {
+    pub packets: i64,
+    pub valid_frames: i64,
+    pub priming_frames: i32,
+    pub remainder_frames: i32,
+}
+
+/// Optional chunks, written before the data by the encoder.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CafMetadata {
+    /// Key/value pairs of the `info` chunk, like `("title", "...")`.
+    pub info: Vec<(String, String)>,
+    pub channel_layout: Option<CafChannelLayout>,
+    /// The `kuki` chunk, codec specific data.
+    pub magic_cookie: Option<Vec<u8>>,
+}
+
+/// Format of the samples, A-law and μ-law are always 16 bits.
+fn caf_info(encoding: CafEncoding, info: &AudioInfo) -> AudioInfo {
+    match encoding {
+        CafEncoding::Lpcm(_) => *info,
+        CafEncoding::ALaw | CafEncoding::MuLaw => AudioInfo {
+            bits_per_sample: 16,
+            sample_type: Some(SampleType::INT),
+            ..*info
+        },
+    }
+}
+
+/// Bytes of every sample in the data chunk.
+#[inline(always)]
+fn sample_size(encoding: CafEncoding, info: &AudioInfo) -> usize {
+    match encoding {
+        CafEncoding::Lpcm(_) => info.bits_per_sample as usize / 8,
+        CafEncoding::ALaw | CafEncoding::MuLaw => 1,
+    }
+}
+
+fn check_info(info: &AudioInfo) -> Result<(), Error> {
+    if info.channels == 0 || info.sample_rate == 0 {
+        return Err(Error::WrongFmtInfo(
+            "CAF channels and sample_rate must be > 0!".to_string(),
+        ));
+    }
+
+    match (
+        info.sample_type.unwrap_or(SampleType::INT),
+        info.bits_per_sample,
+    ) {
+        (SampleType::INT, 8 | 16 | 24 | 32) | (SampleType::FLOAT, 32 | 64) => Ok(()),
+
+        (sample_type, bits_per_sample) => Err(Error::WrongFmtInfo(format!(
+            "{sample_type:?} with {bits_per_sample} bits per sample is not supported!"
+        ))),
+    }
+}
+
+// ------------------------- SAMPLE --------------------------
+
+pub struct LgCafSampleIter<'si, R: io::Read, S: Sample> {
+    encoding: CafEncoding,
+    info: AudioInfo,
+    reader: &'si mut LgCafReader<R>,
+    _phantom: PhantomData<S>,
+}
+impl<'si, R: io::Read, S: Sample> LgCafSampleIter<'si, R, S> {
+    fn new(reader: &'si mut LgCafReader<R>, encoding: CafEncoding, info: AudioInfo) -> Self {
+        Self {
+            encoding,
+            info,
+            reader,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<R: io::Read, S: Sample> Iterator for LgCafSampleIter<'_, R, S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use crate::reader::LgReader;
+
+        let expanded = match self.encoding {
+            CafEncoding::ALaw => g711::alaw_to_linear(self.reader.read_u8().ok()?),
+            CafEncoding::MuLaw => g711::mulaw_to_linear(self.reader.read_u8().ok()?),
+
+            CafEncoding::Lpcm(byte_order) => {
+                return S::read_as(
+                    self.reader,
+                    self.info.sample_type.unwrap_or(SampleType::INT),
+                    self.info.bits_per_sample,
+                    byte_order,
+                    true,
+                )
+                .ok();
+            }
+        };
+
+        Some(S::from_f32(expanded as f32 / 32768.0, SampleType::INT, 16))
+    }
+}
@@ -0,0 +1,302 @@
+use super::super::error::Error;
+use super::super::sample::{ByteOrder, SampleType};
+use super::{
+    AudioInfo, CAF_CHUNK_CHAN, CAF_CHUNK_DATA, CAF_CHUNK_DESC, CAF_CHUNK_INFO, CAF_CHUNK_KUKI,
+    CAF_CHUNK_PAKT, CAF_DESC_SIZE, CAF_FORMAT_ALAW, CAF_FORMAT_LPCM, CAF_FORMAT_ULAW,
+    CAF_LPCM_FLAG_IS_FLOAT, CAF_LPCM_FLAG_IS_LITTLE_ENDIAN, CAF_MAGIC, CAF_UNKNOWN_SIZE,
+    CafChannelDescription, CafChannelLayout, CafEncoding, CafMetadata, CafPacketTable, check_info,
+};
+use crate::reader::LgReader;
+use std::io::{self, Read};
+
+pub struct LgCafReader<R: io::Read> {
+    pub(super) reader: R,
+    pub(super) encoding: CafEncoding,
+    /// Format of the decoded samples.
+    pub(super) info: AudioInfo,
+    pub(super) metadata: CafMetadata,
+    pub(super) packet_table: Option<CafPacketTable>,
+    /// Where the samples start in the file.
+    pub(super) data_offset: u64,
+    /// Bytes of sample data, `None` if it goes to the end of the file.
+    pub(super) data_size: Option<u64>,
+    cursor: u64,
+}
+impl<R: io::Read> LgReader for LgCafReader<R> {
+    type Error = Error;
+
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.move_cursor(buffer.len())?;
+
+        Ok(self.reader.read_into(buffer)?)
+    }
+
+    fn read_next_bytes<const N: usize>(&mut self) -> Result<[u8; N], Self::Error> {
+        self.move_cursor(N)?;
+
+        Ok(self.reader.read_next_bytes()?)
+    }
+
+    fn skip_next_bytes<const N: usize>(&mut self) -> Result<(), Self::Error> {
+        self.move_cursor(N)?;
+
+        Ok(self.reader.skip_next_bytes::<N>()?)
+    }
+}
+impl<R: io::Read> LgCafReader<R> {
+    /// Reads every chunk until the data, leaving the reader at the samples.
+    pub(super) fn new(mut reader: R) -> Result<Self, Error> {
+        if CAF_MAGIC != &reader.read_next_bytes()? {
+            return Err(Error::WrongHeader);
+        }
+
+        // Version and flags.
+        if reader.read_be_u16()? != super::CAF_VERSION {
+            return Err(Error::WrongHeader);
+        }
+        reader.skip_next_bytes::<2>()?;
+
+        let mut result = Self {
+            reader,
+            encoding: CafEncoding::default(),
+            info: AudioInfo::default(),
+            metadata: CafMetadata::default(),
+            packet_table: None,
+            data_offset: 8,
+            data_size: None,
+            cursor: 0,
+        };
+
+        // The desc chunk is always the first.
+        let (ck_type, ck_size) = result.read_chunk_header()?;
+        if &ck_type != CAF_CHUNK_DESC || ck_size != CAF_DESC_SIZE {
+            return Err(Error::WrongFmt);
+        }
+        result.read_desc_chunk()?;
+
+        loop {
+            let (ck_type, ck_size) = result.read_chunk_header()?;
+
+            if &ck_type == CAF_CHUNK_DATA {
+                // Edit count.
+                result.reader.skip_next_bytes::<4>()?;
+                result.data_offset += 4;
+
+                // The edit count is part of the chunk, any other negative size is corrupted.
+                result.data_size = if ck_size == CAF_UNKNOWN_SIZE {
+                    None
+                } else {
+                    let ck_size = u64::try_from(ck_size).map_err(|_| Error::WrongFmt)?;
+                    Some(ck_size.checked_sub(4).ok_or(Error::WrongFmt)?)
+                };
+
+                return Ok(result);
+            }
+
+            let ck_size = u64::try_from(ck_size).map_err(|_| Error::WrongFmt)?;
+            match &ck_type {
+                CAF_CHUNK_PAKT => result.read_pakt_chunk(ck_size)?,
+                CAF_CHUNK_INFO => result.read_info_chunk(ck_size)?,
+                CAF_CHUNK_CHAN => result.read_chan_chunk(ck_size)?,
+                CAF_CHUNK_KUKI => result.metadata.magic_cookie = Some(result.read_chunk(ck_size)?),
+
+                // free, uuid, ...
+                _ => {
+                    let skipped = io::copy(
+                        &mut Read::take(&mut result.reader, ck_size),
+                        &mut io::sink(),
+                    )?;
+                    if skipped != ck_size {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                }
+            }
+            result.data_offset += ck_size;
+        }
+    }
+}
+impl<R: io::Read> LgCafReader<R> {
+    fn move_cursor(&mut self, n: usize) -> Result<(), Error> {
+        if let Some(data_size) = self.data_size
+            && self.cursor + n as u64 > data_size
+        {
+            return Err(Error::Io(io::Error::new::<String>(
+                io::ErrorKind::UnexpectedEof,
+                "".into(),
+            )));
+        }
+
+        self.cursor += n as u64;
+
+        Ok(())
+    }
+
+    fn read_chunk_header(&mut self) -> Result<([u8; 4], i64), Error> {
+        let ck_type = self.reader.read_next_bytes()?;
+        let ck_size = self.reader.read_be_u64()? as i64;
+        self.data_offset += 12;
+
+        Ok((ck_type, ck_size))
+    }
+
+    /// Whole chunk data, for the small metadata chunks.
+    fn read_chunk(&mut self, ck_size: u64) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        Read::take(&mut self.reader, ck_size).read_to_end(&mut data)?;
+
+        if data.len() as u64 != ck_size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(data)
+    }
+
+    fn read_desc_chunk(&mut self) -> Result<(), Error> {
+        let sample_rate = self.reader.read_be_f64()?;
+        let format_id: [u8; 4] = self.reader.read_next_bytes()?;
+        let format_flags = self.reader.read_be_u32()?;
+        let bytes_per_packet = self.reader.read_be_u32()?;
+        let frames_per_packet = self.reader.read_be_u32()?;
+        let channels = self.reader.read_be_u32()?;
+        let bits_per_channel = self.reader.read_be_u32()?;
+        self.data_offset += CAF_DESC_SIZE as u64;
+
+        if channels == 0 || channels > u16::MAX as u32 {
+            return Err(Error::WrongFmtInfo(
+                "CAF channels must be in 1..=65535!".to_string(),
+            ));
+        }
+        if !(1.0..=u32::MAX as f64).contains(&sample_rate) {
+            return Err(Error::WrongFmtInfo(
+                "CAF sample_rate must be > 0!".to_string(),
+            ));
+        }
+
+        let (encoding, sample_type, bits_per_sample) = match &format_id {
+            CAF_FORMAT_LPCM => {
+                let byte_order = if format_flags & CAF_LPCM_FLAG_IS_LITTLE_ENDIAN != 0 {
+                    ByteOrder::LittleEndian
+                } else {
+                    ByteOrder::BigEndian
+                };
+                let sample_type = if format_flags & CAF_LPCM_FLAG_IS_FLOAT != 0 {
+                    SampleType::FLOAT
+                } else {
+                    SampleType::INT
+                };
+
+                (
+                    CafEncoding::Lpcm(byte_order),
+                    sample_type,
+                    // Rejected by check_info.
+                    u16::try_from(bits_per_channel).unwrap_or(0),
+                )
+            }
+            CAF_FORMAT_ALAW => (CafEncoding::ALaw, SampleType::INT, 16),
+            CAF_FORMAT_ULAW => (CafEncoding::MuLaw, SampleType::INT, 16),
+
+            _ => {
+                return Err(Error::WrongFmtInfo(format!(
+                    "CAF format {:?} is not supported!",
+                    String::from_utf8_lossy(&format_id)
+                )));
+            }
+        };
+
+        let info = AudioInfo {
+            channels: channels as u16,
+            sample_rate: sample_rate.round() as u32,
+            bits_per_sample,
+            sample_type: Some(sample_type),
+        };
+        check_info(&info)?;
+
+        // Samples packed in bigger containers are not supported.
+        let frame_size = super::sample_size(encoding, &info) * channels as usize;
+        if frames_per_packet != 1 || bytes_per_packet as usize != frame_size {
+            return Err(Error::WrongFmtInfo(
+                "CAF packets must have 1 frame without padding!".to_string(),
+            ));
+        }
+
+        self.encoding = encoding;
+        self.info = info;
+
+        Ok(())
+    }
+
+    fn read_pakt_chunk(&mut self, ck_size: u64) -> Result<(), Error> {
+        let data = self.read_chunk(ck_size)?;
+        if data.len() < 24 {
+            return Err(Error::WrongFmt);
+        }
+
+        let i64_at = |at: usize| i64::from_be_bytes(data[at..at + 8].try_into().unwrap());
+        let i32_at = |at: usize| i32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+
+        self.packet_table = Some(CafPacketTable {
+            packets: i64_at(0),
+            valid_frames: i64_at(8),
+            priming_frames: i32_at(16),
+            remainder_frames: i32_at(20),
+        });
+
+        Ok(())
+    }
+
+    fn read_info_chunk(&mut self, ck_size: u64) -> Result<(), Error> {
+        let data = self.read_chunk(ck_size)?;
+        if data.len() < 4 {
+            return Err(Error::WrongFmt);
+        }
+
+        // Number of entries, then null terminated key/value strings.
+        let entries = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let mut strings = data[4..]
+            .split(|b| *b == 0)
+            .map(|s| String::from_utf8_lossy(s).into_owned());
+
+        for _ in 0..entries {
+            let (Some(key), Some(value)) = (strings.next(), strings.next()) else {
+                break;
+            };
+
+            self.metadata.info.push((key, value));
+        }
+
+        Ok(())
+    }
+
+    fn read_chan_chunk(&mut self, ck_size: u64) -> Result<(), Error> {
+        let data = self.read_chunk(ck_size)?;
+        if data.len() < 12 {
+            return Err(Error::WrongFmt);
+        }
+
+        let u32_at = |at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+
+        let count = u32_at(8) as usize;
+        let descriptions = data[12..]
+            .chunks_exact(20)
+            .take(count)
+            .map(|description| {
+                let f32_at =
+                    |at: usize| f32::from_be_bytes(description[at..at + 4].try_into().unwrap());
+
+                CafChannelDescription {
+                    label: u32::from_be_bytes(description[0..4].try_into().unwrap()),
+                    flags: u32::from_be_bytes(description[4..8].try_into().unwrap()),
+                    coordinates: [f32_at(8), f32_at(12), f32_at(16)],
+                }
+            })
+            .collect();
+
+        self.metadata.channel_layout = Some(CafChannelLayout {
+            tag: u32_at(0),
+            bitmap: u32_at(4),
+            descriptions,
+        });
+
+        Ok(())
+    }
+}
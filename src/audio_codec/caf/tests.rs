@@ -0,0 +1,186 @@
+use super::super::{
+    AudioInfo,
+    decoder::LgDecoder,
+    error::Error,
+    g711,
+    sample::{ByteOrder, Sample, SampleType},
+};
+use super::{
+    CafChannelDescription, CafChannelLayout, CafEncoding, CafMetadata, LgCafDecoder,
+    writer::LgCafWriter,
+};
+use std::io;
+
+/// Where the size of the data chunk is, in files without metadata.
+const DATA_CK_SIZE_POSITION: usize = 8 + 12 + 32 + 4;
+
+fn info(sample_type: SampleType, bits_per_sample: u16) -> AudioInfo {
+    AudioInfo {
+        channels: 2,
+        sample_rate: 44_100,
+        bits_per_sample,
+        sample_type: Some(sample_type),
+    }
+}
+
+fn encode<S: Sample>(
+    info: &AudioInfo,
+    encoding: CafEncoding,
+    metadata: &CafMetadata,
+    samples: Vec<S>,
+) -> Vec<u8> {
+    let mut bytes = io::Cursor::new(Vec::new());
+    let mut writer = LgCafWriter::new(&mut bytes, info, encoding, metadata).unwrap();
+    for sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finish().unwrap();
+    drop(writer);
+
+    bytes.into_inner()
+}
+
+fn decode<S: Sample>(bytes: &[u8]) -> Vec<S> {
+    let mut decoder = LgCafDecoder::from_reader(bytes).unwrap();
+    let len = decoder.len();
+
+    let samples: Vec<S> = decoder.samples().collect();
+    assert_eq!(samples.len(), len);
+
+    samples
+}
+
+fn with_data_ck_size(mut bytes: Vec<u8>, ck_size: i64) -> Vec<u8> {
+    bytes[DATA_CK_SIZE_POSITION..DATA_CK_SIZE_POSITION + 8].copy_from_slice(&ck_size.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn lpcm_round_trips() {
+    let ints = vec![i16::MIN as i32, -1234, -1, 0, 1, 4321, i16::MAX as i32, 7];
+    let floats = vec![-1.0f32, -0.5, 0.0, 0.125, 0.75, 1.0];
+
+    for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+        let encoding = CafEncoding::Lpcm(byte_order);
+
+        let bytes = encode(
+            &info(SampleType::INT, 16),
+            encoding,
+            &CafMetadata::default(),
+            ints.clone(),
+        );
+        let decoder = LgCafDecoder::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(decoder.encoding(), encoding);
+        assert_eq!(decoder.info().channels, 2);
+        assert_eq!(decoder.info().sample_rate, 44_100);
+        assert_eq!(decoder.info().bits_per_sample, 16);
+        assert_eq!(decode::<i32>(&bytes), ints);
+
+        let bytes = encode(
+            &info(SampleType::FLOAT, 32),
+            encoding,
+            &CafMetadata::default(),
+            floats.clone(),
+        );
+        assert_eq!(decode::<f32>(&bytes), floats);
+    }
+}
+
+#[test]
+fn g711_round_trips() {
+    for (encoding, expand) in [
+        (CafEncoding::ALaw, g711::alaw_to_linear as fn(u8) -> i16),
+        (CafEncoding::MuLaw, g711::mulaw_to_linear),
+    ] {
+        let samples: Vec<i32> = (0..=255).map(|code| expand(code) as i32).collect();
+        let bytes = encode(
+            &info(SampleType::INT, 16),
+            encoding,
+            &CafMetadata::default(),
+            samples.clone(),
+        );
+
+        assert_eq!(decode::<i32>(&bytes), samples);
+    }
+}
+
+#[test]
+fn metadata_round_trips() {
+    let metadata = CafMetadata {
+        info: vec![("title".to_string(), "Test".to_string())],
+        channel_layout: Some(CafChannelLayout {
+            tag: 0,
+            bitmap: 0,
+            descriptions: vec![
+                CafChannelDescription {
+                    label: 1,
+                    flags: 0,
+                    coordinates: [0.0; 3],
+                },
+                CafChannelDescription {
+                    label: 2,
+                    flags: 0,
+                    coordinates: [0.0; 3],
+                },
+            ],
+        }),
+        magic_cookie: Some(vec![1, 2, 3]),
+    };
+
+    let bytes = encode(
+        &info(SampleType::INT, 24),
+        CafEncoding::default(),
+        &metadata,
+        vec![1i32, -1],
+    );
+    let decoder = LgCafDecoder::from_reader(bytes.as_slice()).unwrap();
+
+    assert_eq!(decoder.metadata(), &metadata);
+    assert_eq!(decode::<i32>(&bytes), [1, -1]);
+}
+
+#[test]
+fn unknown_data_size_goes_to_the_end() {
+    let samples = vec![1i32, 2, 3, 4];
+    let bytes = encode(
+        &info(SampleType::INT, 16),
+        CafEncoding::default(),
+        &CafMetadata::default(),
+        samples.clone(),
+    );
+    let bytes = with_data_ck_size(bytes, -1);
+
+    let mut decoder = LgCafDecoder::from_reader(bytes.as_slice()).unwrap();
+    assert!(!decoder.is_len_known());
+    assert_eq!(decoder.len(), 0);
+    assert_eq!(decoder.samples::<i32>().collect::<Vec<_>>(), samples);
+}
+
+#[test]
+fn empty_data_chunk() {
+    let bytes = encode::<i32>(
+        &info(SampleType::INT, 16),
+        CafEncoding::default(),
+        &CafMetadata::default(),
+        vec![],
+    );
+
+    let decoder = LgCafDecoder::from_reader(bytes.as_slice()).unwrap();
+    assert!(decoder.is_empty());
+}
+
+#[test]
+fn rejects_data_chunk_without_edit_count() {
+    let bytes = encode(
+        &info(SampleType::INT, 16),
+        CafEncoding::default(),
+        &CafMetadata::default(),
+        vec![0i32; 8],
+    );
+
+    for ck_size in [0, 1, 3, -2, -5, i64::MIN] {
+        let bytes = with_data_ck_size(bytes.clone(), ck_size);
+        let result = LgCafDecoder::from_reader(bytes.as_slice());
+        assert!(matches!(result, Err(Error::WrongFmt)), "{ck_size}");
+    }
+}
@@ -0,0 +1,207 @@
+use super::super::Result;
+use super::super::sample::{ByteOrder, Sample, SampleType};
+use super::{
+    AudioInfo, CAF_CHUNK_CHAN, CAF_CHUNK_DATA, CAF_CHUNK_DESC, CAF_CHUNK_INFO, CAF_CHUNK_KUKI,
+    CAF_DESC_SIZE, CAF_FORMAT_ALAW, CAF_FORMAT_LPCM, CAF_FORMAT_ULAW, CAF_LPCM_FLAG_IS_FLOAT,
+    CAF_LPCM_FLAG_IS_LITTLE_ENDIAN, CAF_MAGIC, CAF_UNKNOWN_SIZE, CAF_VERSION, CafEncoding,
+    CafMetadata, caf_info, check_info, g711, sample_size,
+};
+use crate::writer::LgWriter;
+use std::io;
+
+pub struct LgCafWriter<W: io::Write + io::Seek> {
+    pub(super) writer: W,
+    pub(super) data_bytes_written: u64,
+    pub(super) encoding: CafEncoding,
+    /// Format of the samples given to [`LgCafWriter::write_sample`].
+    info: AudioInfo,
+    data_ck_size_position: usize,
+}
+impl<W: io::Write + io::Seek> Drop for LgCafWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+impl<W: io::Write + io::Seek> LgCafWriter<W> {
+    /// A-law and μ-law only use `channels` and `sample_rate` from the `info`.
+    pub fn new(
+        mut writer: W,
+        info: &AudioInfo,
+        encoding: CafEncoding,
+        metadata: &CafMetadata,
+    ) -> Result<Self> {
+        let info = caf_info(encoding, info);
+        check_info(&info)?;
+
+        writer.write_all(CAF_MAGIC)?;
+        writer.write_be_u16(CAF_VERSION)?;
+        // Flags.
+        writer.write_be_u16(0)?;
+
+        let mut position = 8;
+        position += write_desc_chunk(&mut writer, &info, encoding)?;
+
+        if let Some(layout) = &metadata.channel_layout {
+            let mut data = Vec::with_capacity(12 + layout.descriptions.len() * 20);
+            data.extend(layout.tag.to_be_bytes());
+            data.extend(layout.bitmap.to_be_bytes());
+            data.extend((layout.descriptions.len() as u32).to_be_bytes());
+
+            for description in &layout.descriptions {
+                data.extend(description.label.to_be_bytes());
+                data.extend(description.flags.to_be_bytes());
+                for coordinate in description.coordinates {
+                    data.extend(coordinate.to_be_bytes());
+                }
+            }
+
+            position += write_chunk(&mut writer, CAF_CHUNK_CHAN, &data)?;
+        }
+
+        if let Some(magic_cookie) = &metadata.magic_cookie {
+            position += write_chunk(&mut writer, CAF_CHUNK_KUKI, magic_cookie)?;
+        }
+
+        if !metadata.info.is_empty() {
+            let mut data = (metadata.info.len() as u32).to_be_bytes().to_vec();
+            for (key, value) in &metadata.info {
+                data.extend(key.as_bytes());
+                data.push(0);
+                data.extend(value.as_bytes());
+                data.push(0);
+            }
+
+            position += write_chunk(&mut writer, CAF_CHUNK_INFO, &data)?;
+        }
+
+        writer.write_all(CAF_CHUNK_DATA)?;
+        // Unknown until finishing, so an unfinished file can still be read.
+        writer.write_be_u64(CAF_UNKNOWN_SIZE as u64)?;
+        // Edit count.
+        writer.write_be_u32(0)?;
+
+        Ok(Self {
+            writer,
+            data_bytes_written: 0,
+            encoding,
+            info,
+            data_ck_size_position: position + 4,
+        })
+    }
+
+    pub fn write_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+
+        match self.encoding {
+            CafEncoding::Lpcm(byte_order) => sample.write_as(
+                &mut self.writer,
+                sample_type,
+                bits_per_sample,
+                byte_order,
+                true,
+            )?,
+
+            CafEncoding::ALaw | CafEncoding::MuLaw => {
+                let value = i32::from_f32(
+                    sample.to_f32(sample_type, bits_per_sample),
+                    sample_type,
+                    bits_per_sample,
+                ) as i16;
+
+                self.writer
+                    .write_u8(if self.encoding == CafEncoding::ALaw {
+                        g711::linear_to_alaw(value)
+                    } else {
+                        g711::linear_to_mulaw(value)
+                    })?;
+            }
+        }
+
+        self.data_bytes_written += sample_size(self.encoding, &self.info) as u64;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        let current_pos = self.writer.stream_position()?;
+        self.update_headers()?;
+        self.writer.flush()?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.update_headers()?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+impl<W: io::Write + io::Seek> LgCafWriter<W> {
+    fn update_headers(&mut self) -> Result<()> {
+        let current_pos = self.writer.stream_position()?;
+
+        // The edit count is part of the data chunk.
+        self.writer.go_to(self.data_ck_size_position)?;
+        self.writer.write_be_u64(self.data_bytes_written + 4)?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the bytes written.
+fn write_chunk<W: io::Write + io::Seek>(
+    writer: &mut W,
+    ck_type: &[u8; 4],
+    data: &[u8],
+) -> Result<usize> {
+    writer.write_all(ck_type)?;
+    writer.write_be_u64(data.len() as u64)?;
+    writer.write_all(data)?;
+
+    Ok(12 + data.len())
+}
+
+/// Returns the bytes written.
+fn write_desc_chunk<W: io::Write + io::Seek>(
+    writer: &mut W,
+    info: &AudioInfo,
+    encoding: CafEncoding,
+) -> Result<usize> {
+    let (format_id, format_flags) = match encoding {
+        CafEncoding::Lpcm(byte_order) => {
+            let mut flags = 0;
+            if info.sample_type == Some(SampleType::FLOAT) {
+                flags |= CAF_LPCM_FLAG_IS_FLOAT;
+            }
+            if byte_order == ByteOrder::LittleEndian {
+                flags |= CAF_LPCM_FLAG_IS_LITTLE_ENDIAN;
+            }
+
+            (CAF_FORMAT_LPCM, flags)
+        }
+        CafEncoding::ALaw => (CAF_FORMAT_ALAW, 0),
+        CafEncoding::MuLaw => (CAF_FORMAT_ULAW, 0),
+    };
+
+    let bytes_per_sample = sample_size(encoding, info) as u32;
+
+    writer.write_all(CAF_CHUNK_DESC)?;
+    writer.write_be_u64(CAF_DESC_SIZE as u64)?;
+
+    // sample_rate.
+    writer.write_be_u64((info.sample_rate as f64).to_bits())?;
+    writer.write_all(format_id)?;
+    writer.write_be_u32(format_flags)?;
+    // bytes_per_packet, frames_per_packet.
+    writer.write_be_u32(bytes_per_sample * info.channels as u32)?;
+    writer.write_be_u32(1)?;
+    writer.write_be_u32(info.channels as u32)?;
+    // bits_per_channel.
+    writer.write_be_u32(bytes_per_sample * 8)?;
+
+    Ok(12 + CAF_DESC_SIZE as usize)
+}
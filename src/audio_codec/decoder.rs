@@ -17,3 +17,16 @@ pub trait LgDecoder: Sized {
 
     fn is_empty(&self) -> bool;
 }
+
+/// Frames read from a decoder at a time, when the whole of it is measured or processed.
+pub(crate) const DECODER_CHUNK_FRAMES: usize = 4096;
+
+/// Replaces `chunk` with the next [`DECODER_CHUNK_FRAMES`] frames, false once the decoder is done.
+pub(crate) fn read_chunk(decoder: &mut impl LgDecoder, chunk: &mut Vec<f32>) -> bool {
+    let len = DECODER_CHUNK_FRAMES * decoder.info().channels as usize;
+
+    chunk.clear();
+    chunk.extend(decoder.samples::<f32>().take(len));
+
+    !chunk.is_empty()
+}
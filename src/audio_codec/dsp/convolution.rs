@@ -0,0 +1,533 @@
+//! Convolution with impulse responses, like room reverbs, by partitioned FFT convolution.
+//!
+//! The impulse response is split in partitions convolved in the frequency domain by overlap-save.
+//! Uniform partitions all have the same size. Non-uniform ones start small and double up to a
+//! maximum, so the latency is of the first partition while the long tail is cheap.
+
+use super::super::{
+    AudioInfo, Result,
+    analysis::{Complex, RealFft},
+    buffer::AudioBuffer,
+    error::Error,
+    wav::LgWavDecoder,
+};
+use super::{ResampleQuality, resample};
+use std::{collections::VecDeque, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Partitioning {
+    /// Frames of every partition, rounded up to a power of two.
+    Uniform(usize),
+    /// Two partitions of every size, from `first` doubling up to `max`, which is used for the
+    /// rest. Both are rounded up to a power of two.
+    NonUniform { first: usize, max: usize },
+}
+impl Default for Partitioning {
+    fn default() -> Self {
+        Self::NonUniform {
+            first: 256,
+            max: 8192,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvolutionParams {
+    /// Gain of the convolved signal.
+    pub wet: f32,
+    /// Gain of the input.
+    pub dry: f32,
+    /// Delay of the wet signal, in seconds.
+    pub pre_delay: f64,
+    pub partitioning: Partitioning,
+}
+impl Default for ConvolutionParams {
+    fn default() -> Self {
+        Self {
+            wet: 1.0,
+            dry: 0.0,
+            pre_delay: 0.0,
+            partitioning: Partitioning::default(),
+        }
+    }
+}
+
+/// Mono, stereo or true stereo impulse response, the channels of true stereo ones are the left
+/// input to the left and right outputs, then the right input to both.
+#[derive(Debug, Clone)]
+pub struct ImpulseResponse {
+    buffer: AudioBuffer,
+}
+impl ImpulseResponse {
+    /// The `buffer` has 1, 2 or 4 channels.
+    pub fn new(buffer: AudioBuffer) -> Result<Self> {
+        if !matches!(buffer.channels(), 1 | 2 | 4) || buffer.frames() == 0 {
+            return Err(Error::WrongFmtInfo(format!(
+                "Impulse responses must have 1, 2 or 4 channels and samples, got {} channels of {} frames!",
+                buffer.channels(),
+                buffer.frames()
+            )));
+        }
+
+        Ok(Self { buffer })
+    }
+
+    /// Decodes the impulse response with [`LgWavDecoder`].
+    pub fn from_wav(path: impl AsRef<Path>) -> Result<Self> {
+        let mut decoder = LgWavDecoder::new(path)?;
+        Self::new(AudioBuffer::from_decoder(&mut decoder))
+    }
+
+    #[inline(always)]
+    pub fn buffer(&self) -> &AudioBuffer {
+        &self.buffer
+    }
+
+    /// Channels given from `input_channels`, stereo and true stereo ones take mono or stereo.
+    pub fn output_channels(&self, input_channels: usize) -> Result<usize> {
+        match (self.buffer.channels(), input_channels) {
+            (_, 0) => Err(Error::WrongFmtInfo(
+                "Convolution needs channels > 0!".to_string(),
+            )),
+            (1, channels) => Ok(channels),
+            (_, 1 | 2) => Ok(2),
+            (ir_channels, channels) => Err(Error::WrongFmtInfo(format!(
+                "Impulse responses of {ir_channels} channels take mono or stereo, got {channels} channels!"
+            ))),
+        }
+    }
+
+    /// Input, impulse response and output channels of every convolution.
+    fn paths(&self, input_channels: usize) -> Vec<(usize, usize, usize)> {
+        let right = input_channels - 1;
+
+        match self.buffer.channels() {
+            1 => (0..input_channels)
+                .map(|channel| (channel, 0, channel))
+                .collect(),
+            2 => vec![(0, 0, 0), (right, 1, 1)],
+            _ => vec![(0, 0, 0), (0, 1, 1), (right, 2, 0), (right, 3, 1)],
+        }
+    }
+}
+
+/// Convolves interleaved samples as they come, the tail of the impulse response is given on
+/// [`Convolver::flush`].
+pub struct Convolver {
+    input_channels: usize,
+    output_channels: usize,
+    paths: Vec<(usize, usize, usize)>,
+    /// Frames of the first partition, processed at a time.
+    block: usize,
+    segments: Vec<Segment>,
+    wet: f32,
+    dry: f32,
+    pre_delay: usize,
+    /// Frames after the input ends, of the impulse response and the pre-delay.
+    tail: usize,
+
+    /// Input of every channel, up to a block.
+    input: Vec<Vec<f32>>,
+    /// Wet output of every channel, from the current block.
+    output: Vec<VecDeque<f32>>,
+    frames_in: usize,
+    frames_out: usize,
+}
+impl Convolver {
+    /// The impulse response is resampled to the sample rate of the input if needed.
+    pub fn new(
+        info: AudioInfo,
+        response: &ImpulseResponse,
+        params: ConvolutionParams,
+    ) -> Result<Self> {
+        let input_channels = info.channels as usize;
+        let output_channels = response.output_channels(input_channels)?;
+        if info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Convolution needs a sample_rate > 0!".to_string(),
+            ));
+        }
+
+        let mut buffer = response.buffer.clone();
+        if buffer.info.sample_rate != info.sample_rate {
+            // Keeps the gain of the response, which has fewer or more samples.
+            let gain = buffer.info.sample_rate as f32 / info.sample_rate as f32;
+            buffer = resample(&buffer, info.sample_rate, ResampleQuality::HIGH);
+            buffer.samples.iter_mut().for_each(|sample| *sample *= gain);
+        }
+        let responses: Vec<Vec<f32>> = (0..buffer.channels())
+            .map(|channel| {
+                buffer
+                    .samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(buffer.channels())
+                    .copied()
+                    .collect()
+            })
+            .collect();
+
+        let segments = plan(params.partitioning, buffer.frames())
+            .into_iter()
+            .map(|(block, offset, frames)| {
+                Segment::new(block, offset, frames, &responses, input_channels)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let pre_delay = (params.pre_delay.max(0.0) * info.sample_rate as f64).round() as usize;
+
+        Ok(Self {
+            input_channels,
+            output_channels,
+            paths: response.paths(input_channels),
+            block: segments[0].block,
+            segments,
+            wet: params.wet,
+            dry: params.dry,
+            pre_delay,
+            tail: buffer.frames() - 1 + pre_delay,
+            input: vec![Vec::new(); input_channels],
+            output: vec![VecDeque::new(); output_channels],
+            frames_in: 0,
+            frames_out: 0,
+        })
+    }
+
+    #[inline(always)]
+    pub fn output_channels(&self) -> usize {
+        self.output_channels
+    }
+
+    /// Frames held back before being given, the size of the first partition.
+    #[inline(always)]
+    pub fn latency(&self) -> usize {
+        self.block
+    }
+
+    /// Appends the convolved frames that are ready to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        for frame in input.chunks_exact(self.input_channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                self.input[channel].push(*sample);
+            }
+            self.frames_in += 1;
+
+            if self.input[0].len() == self.block {
+                self.run_block(usize::MAX, output);
+            }
+        }
+    }
+
+    /// Ends the input, appending the remaining frames and the tail to the `output`.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let total = self.frames_in + self.tail;
+
+        while self.frames_out < total {
+            for input in &mut self.input {
+                input.resize(self.block, 0.0);
+            }
+            self.run_block(total - self.frames_out, output);
+        }
+    }
+
+    /// Back to the start, for a new input.
+    pub fn reset(&mut self) {
+        for segment in &mut self.segments {
+            segment.reset();
+        }
+        self.input.iter_mut().for_each(Vec::clear);
+        self.output.iter_mut().for_each(VecDeque::clear);
+        self.frames_in = 0;
+        self.frames_out = 0;
+    }
+}
+impl Convolver {
+    /// Convolves a whole block of input, appending up to `frames` frames.
+    fn run_block(&mut self, frames: usize, output: &mut Vec<f32>) {
+        for segment in &mut self.segments {
+            if !segment.push(&self.input) {
+                continue;
+            }
+
+            // The segment block ended with this block, the later partitions are never late.
+            let at = self.block + segment.offset + self.pre_delay - segment.block;
+            for &(input, response, channel) in &self.paths {
+                let wet = &mut self.output[channel];
+                if wet.len() < at + segment.block {
+                    wet.resize(at + segment.block, 0.0);
+                }
+
+                for (i, sample) in segment.convolve(input, response).iter().enumerate() {
+                    wet[at + i] += sample * self.wet;
+                }
+            }
+        }
+
+        let given = frames.min(self.block);
+        for i in 0..given {
+            for (channel, wet) in self.output.iter().enumerate() {
+                let dry = self.input[channel.min(self.input_channels - 1)][i];
+                output.push(dry * self.dry + wet.get(i).copied().unwrap_or(0.0));
+            }
+        }
+        for wet in &mut self.output {
+            wet.drain(..self.block.min(wet.len()));
+        }
+        for input in &mut self.input {
+            input.clear();
+        }
+
+        self.frames_out += given;
+    }
+}
+
+/// Convolves a whole buffer, with the tail of the impulse response.
+pub fn convolve(
+    buffer: &AudioBuffer,
+    response: &ImpulseResponse,
+    params: ConvolutionParams,
+) -> Result<AudioBuffer> {
+    let mut convolver = Convolver::new(buffer.info, response, params)?;
+
+    let mut samples =
+        Vec::with_capacity((buffer.frames() + convolver.tail) * convolver.output_channels);
+    convolver.process(&buffer.samples, &mut samples);
+    convolver.flush(&mut samples);
+
+    Ok(AudioBuffer::new(
+        AudioInfo {
+            channels: convolver.output_channels as u16,
+            ..buffer.info
+        },
+        samples,
+    ))
+}
+
+/// Partitions of the same size, convolved by uniformly partitioned overlap-save.
+struct Segment {
+    block: usize,
+    /// Of the first partition, in the impulse response.
+    offset: usize,
+    fft: RealFft,
+    /// Spectrum of every partition, of every channel of the impulse response.
+    responses: Vec<Vec<Vec<Complex>>>,
+    /// Spectra of the last inputs, newest first, of every input channel.
+    history: Vec<VecDeque<Vec<Complex>>>,
+    /// Last two blocks of every input channel.
+    windows: Vec<Vec<f32>>,
+    gathered: usize,
+
+    sum: Vec<Complex>,
+    result: Vec<f32>,
+}
+impl Segment {
+    fn new(
+        block: usize,
+        offset: usize,
+        frames: usize,
+        responses: &[Vec<f32>],
+        input_channels: usize,
+    ) -> Result<Self> {
+        let mut fft = RealFft::new(block * 2)?;
+        let mut padded = vec![0.0; block * 2];
+
+        let responses = responses
+            .iter()
+            .map(|response| {
+                (offset..offset + frames)
+                    .step_by(block)
+                    .map(|start| {
+                        let end = (start + block).min(offset + frames);
+                        padded.fill(0.0);
+                        padded[..end - start].copy_from_slice(&response[start..end]);
+
+                        let mut spectrum = Vec::new();
+                        fft.forward(&padded, &mut spectrum);
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            block,
+            offset,
+            fft,
+            responses,
+            history: vec![VecDeque::new(); input_channels],
+            windows: vec![vec![0.0; block * 2]; input_channels],
+            gathered: 0,
+            sum: Vec::new(),
+            result: Vec::new(),
+        })
+    }
+
+    /// Adds a block of every channel, true once a segment block was gathered.
+    fn push(&mut self, input: &[Vec<f32>]) -> bool {
+        let frames = input[0].len();
+        for (window, input) in self.windows.iter_mut().zip(input) {
+            window.drain(..frames);
+            window.extend_from_slice(input);
+        }
+
+        self.gathered += frames;
+        if self.gathered < self.block {
+            return false;
+        }
+        self.gathered = 0;
+
+        let partitions = self.responses[0].len();
+        for (history, window) in self.history.iter_mut().zip(&self.windows) {
+            let mut spectrum = match history.len() == partitions {
+                true => history.pop_back().unwrap_or_default(),
+                false => Vec::new(),
+            };
+            self.fft.forward(window, &mut spectrum);
+            history.push_front(spectrum);
+        }
+
+        true
+    }
+
+    /// Output of the last segment block, from an input channel through a response channel.
+    fn convolve(&mut self, input: usize, response: usize) -> &[f32] {
+        self.sum.clear();
+        self.sum.resize(self.fft.bins(), Complex::default());
+
+        for (spectrum, partition) in self.history[input].iter().zip(&self.responses[response]) {
+            for ((sum, x), h) in self.sum.iter_mut().zip(spectrum).zip(partition) {
+                *sum += *x * *h;
+            }
+        }
+        self.fft.inverse(&self.sum, &mut self.result);
+
+        &self.result[self.block..]
+    }
+
+    fn reset(&mut self) {
+        self.history.iter_mut().for_each(VecDeque::clear);
+        self.windows.iter_mut().for_each(|window| window.fill(0.0));
+        self.gathered = 0;
+    }
+}
+
+/// Block, offset and frames of every segment.
+fn plan(partitioning: Partitioning, frames: usize) -> Vec<(usize, usize, usize)> {
+    let (first, max) = match partitioning {
+        Partitioning::Uniform(block) => (block, block),
+        Partitioning::NonUniform { first, max } => (first, max.max(first)),
+    };
+    let (first, max) = (
+        first.max(2).next_power_of_two(),
+        max.max(2).next_power_of_two(),
+    );
+
+    let mut segments = Vec::new();
+    let (mut block, mut offset) = (first, 0);
+    while offset < frames {
+        if block >= max {
+            segments.push((max, offset, frames - offset));
+            break;
+        }
+
+        segments.push((block, offset, (block * 2).min(frames - offset)));
+        offset += block * 2;
+        block *= 2;
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(channels: u16) -> AudioInfo {
+        AudioInfo {
+            channels,
+            sample_rate: 48_000,
+            ..Default::default()
+        }
+    }
+
+    /// Something that isn't periodic, between -1 and 1.
+    fn signal(len: usize, seed: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (((i * 7919 + seed) * 104_729) % 2001) as f32 / 1000.0 - 1.0)
+            .collect()
+    }
+
+    #[test]
+    fn unit_impulse_returns_the_input() {
+        let input = AudioBuffer::new(info(2), signal(3000, 1));
+        let response = ImpulseResponse::new(AudioBuffer::new(info(1), vec![1.0])).unwrap();
+
+        for partitioning in [Partitioning::Uniform(64), Partitioning::default()] {
+            let params = ConvolutionParams {
+                partitioning,
+                ..Default::default()
+            };
+            let output = convolve(&input, &response, params).unwrap();
+
+            assert_eq!(output.info.channels, 2);
+            assert_eq!(output.samples.len(), input.samples.len());
+            for (value, expected) in output.samples.iter().zip(&input.samples) {
+                assert!((value - expected).abs() < 1e-5, "{value} is not {expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn delayed_impulse_and_pre_delay() {
+        let input = AudioBuffer::new(info(1), signal(500, 2));
+        let response =
+            ImpulseResponse::new(AudioBuffer::new(info(1), vec![0.0, 0.0, 0.0, 0.5])).unwrap();
+        let params = ConvolutionParams {
+            // 2 frames.
+            pre_delay: 2.0 / 48_000.0,
+            dry: 1.0,
+            ..Default::default()
+        };
+
+        let output = convolve(&input, &response, params).unwrap();
+        assert_eq!(output.samples.len(), 500 + 5);
+        for (i, value) in output.samples.iter().enumerate() {
+            let dry = input.samples.get(i).copied().unwrap_or(0.0);
+            let wet = i
+                .checked_sub(5)
+                .and_then(|i| input.samples.get(i))
+                .map_or(0.0, |x| x * 0.5);
+            assert!((value - (dry + wet)).abs() < 1e-5, "{i}: {value}");
+        }
+    }
+
+    #[test]
+    fn partitions_match_direct_convolution() {
+        let input = signal(2000, 3);
+        let response = signal(3000, 4);
+        let mut expected = vec![0.0f64; input.len() + response.len() - 1];
+        for (i, x) in input.iter().enumerate() {
+            for (j, h) in response.iter().enumerate() {
+                expected[i + j] += *x as f64 * *h as f64;
+            }
+        }
+
+        let input = AudioBuffer::new(info(1), input);
+        let response = ImpulseResponse::new(AudioBuffer::new(info(1), response)).unwrap();
+        let params = ConvolutionParams {
+            partitioning: Partitioning::NonUniform {
+                first: 32,
+                max: 512,
+            },
+            ..Default::default()
+        };
+
+        let output = convolve(&input, &response, params).unwrap();
+        assert_eq!(output.samples.len(), expected.len());
+        for (value, expected) in output.samples.iter().zip(expected) {
+            assert!(
+                (*value as f64 - expected).abs() < 1e-3,
+                "{value} is not {expected}"
+            );
+        }
+    }
+}
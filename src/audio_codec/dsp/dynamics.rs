@@ -0,0 +1,590 @@
+//! Compressor, expander and gate with a shared gain computer, and the look-ahead brickwall
+//! [`Limiter`] on the true peak.
+
+use super::{
+    super::{AudioInfo, Result, analysis::loudness::TruePeak, buffer::AudioBuffer, error::Error},
+    db_to_gain,
+    filter::{FilterChain, FilterSpec},
+};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DynamicsMode {
+    /// Reduces what goes over the threshold by the ratio.
+    Compressor,
+    /// Reduces what goes under the threshold by the ratio, down to the range.
+    Expander,
+    /// Reduces what goes under the threshold by the whole range.
+    Gate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detector {
+    Peak,
+    /// Averaged over about the given seconds.
+    Rms(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicsParams {
+    pub mode: DynamicsMode,
+    pub detector: Detector,
+    /// In dBFS.
+    pub threshold: f64,
+    /// 4 is 4:1, ignored by the gate.
+    pub ratio: f64,
+    /// Width in dB of the soft change around the threshold, 0 for a hard knee.
+    pub knee: f64,
+    /// Seconds for the gain to go about 63% of the way down.
+    pub attack: f64,
+    /// Seconds for the gain to go about 63% of the way back up.
+    pub release: f64,
+    /// Gain added after, in dB.
+    pub makeup: f64,
+    /// Most gain the expander and gate take, in dB.
+    pub range: f64,
+    /// Every channel gets the gain of the loudest one, otherwise every channel has its own.
+    pub linked: bool,
+}
+impl Default for DynamicsParams {
+    fn default() -> Self {
+        Self::compressor(-18.0, 4.0)
+    }
+}
+impl DynamicsParams {
+    pub fn compressor(threshold: f64, ratio: f64) -> Self {
+        Self {
+            mode: DynamicsMode::Compressor,
+            detector: Detector::Peak,
+            threshold,
+            ratio,
+            knee: 6.0,
+            attack: 0.01,
+            release: 0.1,
+            makeup: 0.0,
+            range: 80.0,
+            linked: true,
+        }
+    }
+
+    pub fn expander(threshold: f64, ratio: f64) -> Self {
+        Self {
+            mode: DynamicsMode::Expander,
+            attack: 0.001,
+            range: 40.0,
+            ..Self::compressor(threshold, ratio)
+        }
+    }
+
+    pub fn gate(threshold: f64) -> Self {
+        Self {
+            mode: DynamicsMode::Gate,
+            knee: 0.0,
+            ..Self::expander(threshold, 1.0)
+        }
+    }
+
+    /// Gain for a level, before the attack and release, in dB.
+    pub fn static_gain(&self, level: f64) -> f64 {
+        let over = level - self.threshold;
+        let knee = self.knee.max(0.0);
+        let soft = 2.0 * over.abs() <= knee && knee > 0.0;
+
+        let gain = match self.mode {
+            DynamicsMode::Compressor => {
+                let slope = 1.0 / self.ratio - 1.0;
+                if soft {
+                    slope * (over + knee / 2.0).powi(2) / (2.0 * knee)
+                } else {
+                    slope * over.max(0.0)
+                }
+            }
+            DynamicsMode::Expander => {
+                let slope = self.ratio - 1.0;
+                if soft {
+                    -slope * (over - knee / 2.0).powi(2) / (2.0 * knee)
+                } else {
+                    slope * over.min(0.0)
+                }
+            }
+            DynamicsMode::Gate => {
+                if soft {
+                    -self.range * (knee / 2.0 - over) / knee
+                } else if over < 0.0 {
+                    -self.range
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        gain.max(-self.range.abs())
+    }
+}
+
+/// Compressor, expander or gate on interleaved samples, detecting from them or from a sidechain.
+#[derive(Debug, Clone)]
+pub struct Dynamics {
+    channels: usize,
+    sample_rate: u32,
+    params: DynamicsParams,
+    attack_coefficient: f64,
+    release_coefficient: f64,
+    rms_coefficient: f64,
+    makeup: f64,
+    sidechain_specs: Vec<FilterSpec>,
+    /// Built for the channels heard.
+    sidechain_filter: Option<FilterChain>,
+
+    /// Mean square of every channel heard, for the RMS detector.
+    mean_squares: Vec<f64>,
+    /// Gain of every channel, or a single one when linked, in dB.
+    gains: Vec<f64>,
+    max_reduction: f64,
+    detection: Vec<f32>,
+    levels: Vec<f64>,
+}
+impl Dynamics {
+    pub fn new(info: AudioInfo, params: DynamicsParams) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Dynamics need a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        let mut dynamics = Self {
+            channels: info.channels as usize,
+            sample_rate: info.sample_rate,
+            params,
+            attack_coefficient: 0.0,
+            release_coefficient: 0.0,
+            rms_coefficient: 0.0,
+            makeup: 1.0,
+            sidechain_specs: Vec::new(),
+            sidechain_filter: None,
+            mean_squares: Vec::new(),
+            gains: Vec::new(),
+            max_reduction: 0.0,
+            detection: Vec::new(),
+            levels: Vec::new(),
+        };
+        dynamics.set_params(params)?;
+
+        Ok(dynamics)
+    }
+
+    #[inline(always)]
+    pub fn params(&self) -> DynamicsParams {
+        self.params
+    }
+
+    /// Changes the params, keeping the current gain so it can be automated while playing.
+    pub fn set_params(&mut self, params: DynamicsParams) -> Result<()> {
+        if params.ratio < 1.0 || params.ratio.is_nan() {
+            return Err(Error::Custom(format!(
+                "Dynamics ratio {} must be at least 1!",
+                params.ratio
+            )));
+        }
+
+        let coefficient = |seconds: f64| {
+            let frames = seconds * self.sample_rate as f64;
+            if frames > 0.0 {
+                (-1.0 / frames).exp()
+            } else {
+                0.0
+            }
+        };
+        self.attack_coefficient = coefficient(params.attack);
+        self.release_coefficient = coefficient(params.release);
+        self.rms_coefficient = match params.detector {
+            Detector::Peak => 0.0,
+            Detector::Rms(window) => coefficient(window),
+        };
+        self.makeup = db_to_gain(params.makeup);
+
+        let gains = if params.linked { 1 } else { self.channels };
+        if self.gains.len() != gains {
+            self.gains = vec![0.0; gains];
+        }
+        self.params = params;
+
+        Ok(())
+    }
+
+    /// Filters what the detector hears, like a high-pass so the bass doesn't pump the gain.
+    pub fn set_sidechain_filter(&mut self, specs: &[FilterSpec]) -> Result<()> {
+        // Checked now, built once the channels heard are known.
+        FilterChain::new(1, self.sample_rate, specs)?;
+        self.sidechain_specs = specs.to_vec();
+        self.sidechain_filter = None;
+
+        Ok(())
+    }
+
+    /// Current gain before the make-up of every channel, or a single one when linked, in dB.
+    #[inline(always)]
+    pub fn gain_reduction(&self) -> &[f64] {
+        &self.gains
+    }
+
+    /// Most gain taken so far, in dB.
+    #[inline(always)]
+    pub fn max_reduction(&self) -> f64 {
+        self.max_reduction
+    }
+
+    /// Processes the interleaved `input`, appending it to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.run(input, input, self.channels, output);
+    }
+
+    /// Like [`Dynamics::process`], hearing the interleaved `sidechain` instead, it can have
+    /// any channels. Frames missing from it are silent.
+    pub fn process_sidechain(
+        &mut self,
+        input: &[f32],
+        sidechain: &[f32],
+        sidechain_channels: usize,
+        output: &mut Vec<f32>,
+    ) {
+        self.run(input, sidechain, sidechain_channels.max(1), output);
+    }
+
+    pub fn reset(&mut self) {
+        self.mean_squares.fill(0.0);
+        self.gains.fill(0.0);
+        if let Some(filter) = &mut self.sidechain_filter {
+            filter.reset();
+        }
+    }
+}
+impl Dynamics {
+    fn run(&mut self, input: &[f32], heard: &[f32], heard_channels: usize, output: &mut Vec<f32>) {
+        let frames = input.len() / self.channels;
+
+        let mut detection = std::mem::take(&mut self.detection);
+        detection.clear();
+        detection.extend_from_slice(&heard[..heard.len().min(frames * heard_channels)]);
+        detection.resize(frames * heard_channels, 0.0);
+
+        if !self.sidechain_specs.is_empty() {
+            if self
+                .sidechain_filter
+                .as_ref()
+                .is_none_or(|filter| filter.channels() != heard_channels)
+            {
+                self.sidechain_filter =
+                    FilterChain::new(heard_channels, self.sample_rate, &self.sidechain_specs).ok();
+            }
+            if let Some(filter) = &mut self.sidechain_filter {
+                filter.process(&mut detection);
+            }
+        }
+        if self.mean_squares.len() != heard_channels {
+            self.mean_squares = vec![0.0; heard_channels];
+        }
+
+        for (frame, heard) in input
+            .chunks_exact(self.channels)
+            .zip(detection.chunks_exact(heard_channels))
+        {
+            let mut loudest = 0.0f64;
+            self.levels.clear();
+            for (channel, sample) in heard.iter().enumerate() {
+                let level = self.level(channel, *sample as f64);
+                loudest = loudest.max(level);
+                self.levels.push(level);
+            }
+
+            if self.params.linked {
+                self.smooth(0, loudest);
+            } else {
+                // A sidechain with fewer channels repeats them.
+                for channel in 0..self.channels {
+                    self.smooth(channel, self.levels[channel % heard_channels]);
+                }
+            }
+
+            for (channel, sample) in frame.iter().enumerate() {
+                let gain = self.gains[channel % self.gains.len()];
+                output.push(*sample * (db_to_gain(gain) * self.makeup) as f32);
+            }
+        }
+
+        self.detection = detection;
+    }
+
+    #[inline(always)]
+    fn level(&mut self, channel: usize, sample: f64) -> f64 {
+        match self.params.detector {
+            Detector::Peak => sample.abs(),
+            Detector::Rms(_) => {
+                let mean_square = &mut self.mean_squares[channel];
+                *mean_square =
+                    sample * sample + (*mean_square - sample * sample) * self.rms_coefficient;
+                mean_square.sqrt()
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn smooth(&mut self, index: usize, level: f64) {
+        let target = self.params.static_gain(20.0 * level.max(1e-10).log10());
+        let gain = &mut self.gains[index];
+
+        let coefficient = if target < *gain {
+            self.attack_coefficient
+        } else {
+            self.release_coefficient
+        };
+        *gain = target + (*gain - target) * coefficient;
+
+        self.max_reduction = self.max_reduction.max(-*gain);
+    }
+}
+
+/// Processes the whole buffer, hearing the `sidechain` instead if given.
+pub fn apply_dynamics(
+    buffer: &AudioBuffer,
+    params: DynamicsParams,
+    sidechain: Option<&AudioBuffer>,
+) -> Result<AudioBuffer> {
+    let mut dynamics = Dynamics::new(buffer.info, params)?;
+
+    let mut samples = Vec::with_capacity(buffer.samples.len());
+    match sidechain {
+        Some(sidechain) => dynamics.process_sidechain(
+            &buffer.samples,
+            &sidechain.samples,
+            sidechain.channels(),
+            &mut samples,
+        ),
+        None => dynamics.process(&buffer.samples, &mut samples),
+    }
+
+    Ok(AudioBuffer::new(buffer.info, samples))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimiterParams {
+    /// Highest true peak allowed, in dBTP.
+    pub ceiling: f64,
+    /// Seconds the gain starts going down before a peak, also the added latency.
+    pub lookahead: f64,
+    /// Seconds for the gain to recover about 63% after a peak.
+    pub release: f64,
+}
+impl Default for LimiterParams {
+    fn default() -> Self {
+        Self {
+            ceiling: -1.0,
+            lookahead: 0.005,
+            release: 0.05,
+        }
+    }
+}
+
+/// Look-ahead limiter on the true peak of interleaved samples, every channel gets the same gain.
+/// The output is delayed internally, but has the same frames as the input once flushed.
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    channels: usize,
+    ceiling: f64,
+    lookahead_frames: usize,
+    release_coefficient: f64,
+
+    detectors: Vec<TruePeak>,
+    /// Gains needed by the frames in the look-ahead, increasing, for the sliding minimum.
+    needed: VecDeque<(usize, f64)>,
+    /// Last sliding minimums, averaged so the gain goes down smoothly.
+    minimums: VecDeque<f64>,
+    minimums_sum: f64,
+    envelope: f64,
+    min_envelope: f64,
+
+    /// Interleaved frames waiting for their gain.
+    delay: VecDeque<f32>,
+    frame_index: usize,
+}
+impl Limiter {
+    pub fn new(channels: usize, sample_rate: u32, params: LimiterParams) -> Self {
+        let lookahead_frames = ((params.lookahead * sample_rate as f64).round() as usize).max(1);
+        let release_frames = (params.release * sample_rate as f64).max(1.0);
+
+        Self {
+            channels: channels.max(1),
+            ceiling: db_to_gain(params.ceiling),
+            lookahead_frames,
+            release_coefficient: (-1.0 / release_frames).exp(),
+            detectors: vec![TruePeak::new(sample_rate); channels.max(1)],
+            needed: VecDeque::with_capacity(lookahead_frames),
+            minimums: VecDeque::from(vec![1.0; lookahead_frames]),
+            minimums_sum: lookahead_frames as f64,
+            envelope: 1.0,
+            min_envelope: 1.0,
+            delay: VecDeque::new(),
+            frame_index: 0,
+        }
+    }
+
+    /// Frames between an input and its output.
+    #[inline(always)]
+    pub fn latency(&self) -> usize {
+        TruePeak::LATENCY + self.lookahead_frames - 1
+    }
+
+    /// Most gain taken so far, in dB.
+    #[inline(always)]
+    pub fn max_reduction(&self) -> f64 {
+        20.0 * (1.0 / self.min_envelope).log10()
+    }
+
+    /// Limits the interleaved `input`, appending the frames that left the delay to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        for frame in input.chunks_exact(self.channels) {
+            self.push_frame(frame, output);
+        }
+    }
+
+    /// Appends the frames still in the delay.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let silence = vec![0.0; self.channels];
+        for _ in 0..self.latency() {
+            self.push_frame(&silence, output);
+        }
+
+        // Only the silence pushed is left.
+        self.delay.clear();
+    }
+}
+impl Limiter {
+    fn push_frame(&mut self, frame: &[f32], output: &mut Vec<f32>) {
+        // The detectors are late by their latency, which is part of the delay.
+        let mut peak = 0.0f64;
+        for (detector, sample) in self.detectors.iter_mut().zip(frame) {
+            peak = peak.max(detector.push(*sample as f64));
+        }
+        let needed = if peak > self.ceiling {
+            self.ceiling / peak
+        } else {
+            1.0
+        };
+
+        // Sliding minimum over the look-ahead.
+        let index = self.frame_index;
+        while self.needed.back().is_some_and(|(_, gain)| *gain >= needed) {
+            self.needed.pop_back();
+        }
+        self.needed.push_back((index, needed));
+        while self
+            .needed
+            .front()
+            .is_some_and(|(i, _)| i + self.lookahead_frames <= index)
+        {
+            self.needed.pop_front();
+        }
+        let minimum = self.needed.front().map_or(1.0, |(_, gain)| *gain);
+
+        // Averaging as many minimums as the look-ahead reaches the needed gain right on the peak.
+        self.minimums_sum += minimum - self.minimums.pop_front().unwrap_or(1.0);
+        self.minimums.push_back(minimum);
+        if index.is_multiple_of(self.lookahead_frames) {
+            self.minimums_sum = self.minimums.iter().sum();
+        }
+        let target = self.minimums_sum / self.lookahead_frames as f64;
+
+        self.envelope = if target < self.envelope {
+            target
+        } else {
+            target + (self.envelope - target) * self.release_coefficient
+        };
+
+        self.frame_index += 1;
+        self.delay.extend(frame);
+        if self.delay.len() > self.latency() * self.channels {
+            self.min_envelope = self.min_envelope.min(self.envelope);
+            let gain = self.envelope as f32;
+            output.extend(self.delay.drain(..self.channels).map(|s| s * gain));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{value} is not {expected} ± {tolerance}"
+        );
+    }
+
+    #[test]
+    fn compressor_curve() {
+        let hard = DynamicsParams {
+            knee: 0.0,
+            ..DynamicsParams::compressor(-20.0, 4.0)
+        };
+        for (level, gain) in [(-40.0, 0.0), (-20.0, 0.0), (-10.0, -7.5), (0.0, -15.0)] {
+            assert_near(hard.static_gain(level), gain, 1e-9);
+        }
+
+        // The soft knee is 6 dB wide, meeting the hard knee at its edges.
+        let soft = DynamicsParams::compressor(-20.0, 4.0);
+        assert_near(soft.static_gain(-23.0), 0.0, 1e-9);
+        assert_near(soft.static_gain(-20.0), -0.5625, 1e-9);
+        assert_near(soft.static_gain(-17.0), -2.25, 1e-9);
+        assert_near(soft.static_gain(-10.0), -7.5, 1e-9);
+        for step in 0..=60 {
+            let level = -26.0 + step as f64 * 0.2;
+            assert!(soft.static_gain(level) <= hard.static_gain(level) + 1e-9);
+            assert!(soft.static_gain(level + 0.2) <= soft.static_gain(level));
+        }
+    }
+
+    #[test]
+    fn expander_and_gate_curves() {
+        let expander = DynamicsParams {
+            knee: 0.0,
+            ..DynamicsParams::expander(-40.0, 2.0)
+        };
+        assert_near(expander.static_gain(-30.0), 0.0, 1e-9);
+        assert_near(expander.static_gain(-50.0), -10.0, 1e-9);
+        // Down to the range.
+        assert_near(expander.static_gain(-100.0), -40.0, 1e-9);
+
+        let gate = DynamicsParams::gate(-50.0);
+        assert_near(gate.static_gain(-49.0), 0.0, 1e-9);
+        assert_near(gate.static_gain(-51.0), -40.0, 1e-9);
+    }
+
+    #[test]
+    fn compressor_settles_on_the_curve() {
+        let info = AudioInfo {
+            channels: 2,
+            sample_rate: 48_000,
+            ..Default::default()
+        };
+        let params = DynamicsParams {
+            knee: 0.0,
+            makeup: 3.0,
+            ..DynamicsParams::compressor(-20.0, 4.0)
+        };
+        let mut dynamics = Dynamics::new(info, params).unwrap();
+
+        // A constant -10 dBFS is reduced by 7.5 dB, then made up by 3 dB.
+        let input = vec![db_to_gain(-10.0) as f32; 48_000 * 2];
+        let mut output = Vec::new();
+        dynamics.process(&input, &mut output);
+
+        assert_eq!(output.len(), input.len());
+        assert_near(
+            20.0 * (output[output.len() - 1] as f64).log10(),
+            -14.5,
+            1e-3,
+        );
+        assert_near(dynamics.max_reduction(), 7.5, 1e-3);
+    }
+}
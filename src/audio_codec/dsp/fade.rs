@@ -0,0 +1,471 @@
+//! Fades, crossfades between two sounds and crossfaded loop points.
+
+use super::super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::LgDecoder,
+    error::Error,
+    sample::{Sample, SampleType},
+};
+use std::{
+    collections::VecDeque,
+    f32::consts::{FRAC_PI_2, PI},
+};
+
+/// Frames read from the decoders at a time.
+const STREAM_CHUNK_FRAMES: usize = 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FadeCurve {
+    Linear,
+    /// Constant power through a crossfade of unrelated sounds.
+    #[default]
+    EqualPower,
+    /// Linear in dB, from -60 dB.
+    Logarithmic,
+    /// Half a cosine, slow at both ends.
+    SCurve,
+}
+impl FadeCurve {
+    /// Gain of a fade in at `t` in `0.0..=1.0`, a fade out is `gain(1.0 - t)`.
+    pub fn gain(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Self::Linear => t,
+            Self::EqualPower => (t * FRAC_PI_2).sin(),
+            Self::Logarithmic => {
+                if t > 0.0 {
+                    10f32.powf(3.0 * (t - 1.0))
+                } else {
+                    0.0
+                }
+            }
+            Self::SCurve => (1.0 - (t * PI).cos()) / 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FadeLength {
+    Frames(usize),
+    Seconds(f64),
+}
+impl FadeLength {
+    #[inline(always)]
+    pub fn frames(&self, sample_rate: u32) -> usize {
+        match self {
+            Self::Frames(frames) => *frames,
+            Self::Seconds(seconds) => (seconds.max(0.0) * sample_rate as f64).round() as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fade {
+    pub length: FadeLength,
+    pub curve: FadeCurve,
+}
+impl Fade {
+    #[inline(always)]
+    pub fn new(length: FadeLength, curve: FadeCurve) -> Self {
+        Self { length, curve }
+    }
+}
+
+/// Fades in the start and out the end of interleaved samples as they come. The fade out holds
+/// back its length, since the end is only known once flushed.
+#[derive(Debug, Clone)]
+pub struct Fader {
+    channels: usize,
+    fade_in: Option<(usize, FadeCurve)>,
+    fade_out: Option<(usize, FadeCurve)>,
+
+    frame: usize,
+    held: VecDeque<f32>,
+}
+impl Fader {
+    pub fn new(info: AudioInfo, fade_in: Option<Fade>, fade_out: Option<Fade>) -> Result<Self> {
+        if info.channels == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Fading needs channels > 0!".to_string(),
+            ));
+        }
+
+        let frames = |fade: Fade| (fade.length.frames(info.sample_rate), fade.curve);
+        Ok(Self {
+            channels: info.channels as usize,
+            fade_in: fade_in.map(frames),
+            fade_out: fade_out.map(frames),
+            frame: 0,
+            held: VecDeque::new(),
+        })
+    }
+
+    /// Appends the frames known not to be in the fade out to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        for frame in input.chunks_exact(self.channels) {
+            let gain = match self.fade_in {
+                Some((length, curve)) if self.frame < length => {
+                    curve.gain(self.frame as f32 / length as f32)
+                }
+                _ => 1.0,
+            };
+            self.frame += 1;
+
+            match self.fade_out {
+                Some((length, _)) => {
+                    self.held.extend(frame.iter().map(|sample| sample * gain));
+                    if self.held.len() > length * self.channels {
+                        output.extend(self.held.drain(..self.channels));
+                    }
+                }
+                None => output.extend(frame.iter().map(|sample| sample * gain)),
+            }
+        }
+    }
+
+    /// Appends the held frames faded out.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let Some((length, curve)) = self.fade_out else {
+            return;
+        };
+
+        let held = self.held.len() / self.channels;
+        for (i, sample) in self.held.drain(..).enumerate() {
+            // A sound shorter than the fade starts partway through it.
+            let remaining = held - i / self.channels;
+            output.push(sample * curve.gain((remaining - 1) as f32 / length as f32));
+        }
+    }
+}
+
+pub fn fade_in(buffer: &AudioBuffer, fade: Fade) -> Result<AudioBuffer> {
+    fade_buffer(buffer, Some(fade), None)
+}
+
+pub fn fade_out(buffer: &AudioBuffer, fade: Fade) -> Result<AudioBuffer> {
+    fade_buffer(buffer, None, Some(fade))
+}
+
+/// Plays `first` then `second`, overlapping them by the fade length, or by the shortest one.
+pub fn crossfade(first: &AudioBuffer, second: &AudioBuffer, fade: Fade) -> Result<AudioBuffer> {
+    check_formats(first.info, second.info)?;
+
+    let channels = first.channels();
+    let length = fade
+        .length
+        .frames(first.info.sample_rate)
+        .min(first.frames())
+        .min(second.frames());
+    let overlap_start = (first.frames() - length) * channels;
+
+    let mut samples = Vec::with_capacity(first.samples.len() + second.samples.len());
+    samples.extend_from_slice(&first.samples[..overlap_start]);
+    mix_crossfade(
+        &first.samples[overlap_start..],
+        &second.samples[..length * channels],
+        channels,
+        fade.curve,
+        &mut samples,
+    );
+    samples.extend_from_slice(&second.samples[length * channels..]);
+
+    Ok(AudioBuffer::new(first.info, samples))
+}
+
+/// Crossfades the end of the loop, from `loop_start` up to `loop_end` not included, with what
+/// comes before its start, so jumping from its end back to its start is seamless. The loop
+/// needs as many frames as the fade before its start, and the fade has to fit in it.
+pub fn crossfade_loop(
+    buffer: &AudioBuffer,
+    loop_start: usize,
+    loop_end: usize,
+    fade: Fade,
+) -> Result<AudioBuffer> {
+    let channels = buffer.channels();
+    let length = fade.length.frames(buffer.info.sample_rate);
+    if loop_end > buffer.frames() || loop_start + length > loop_end || loop_start < length {
+        return Err(Error::Custom(format!(
+            "Loop {loop_start}..{loop_end} of {} frames can't fit a crossfade of {length} frames!",
+            buffer.frames()
+        )));
+    }
+
+    let fade_start = (loop_end - length) * channels;
+    let mut crossfaded = Vec::with_capacity(length * channels);
+    mix_crossfade(
+        &buffer.samples[fade_start..loop_end * channels],
+        &buffer.samples[(loop_start - length) * channels..loop_start * channels],
+        channels,
+        fade.curve,
+        &mut crossfaded,
+    );
+
+    let mut looped = buffer.clone();
+    looped.samples[fade_start..loop_end * channels].copy_from_slice(&crossfaded);
+
+    Ok(looped)
+}
+
+/// Plays the first decoder then the second, crossfading them. The end of the first is held back
+/// by the fade length, since it is only known once it ends.
+pub struct LgCrossfadeDecoder<A: LgDecoder, B: LgDecoder> {
+    first: A,
+    second: B,
+    info: AudioInfo,
+    length: usize,
+    curve: FadeCurve,
+
+    /// Last frames of the first decoder, while it plays.
+    held: VecDeque<f32>,
+    crossfaded: bool,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    cursor: usize,
+}
+impl<A: LgDecoder, B: LgDecoder> LgCrossfadeDecoder<A, B> {
+    /// Both decoders need the same channels and sample rate, the format is of the first.
+    pub fn new(first: A, second: B, fade: Fade) -> Result<Self> {
+        let info = first.info();
+        check_formats(info, second.info())?;
+
+        Ok(Self {
+            length: fade.length.frames(info.sample_rate),
+            curve: fade.curve,
+            first,
+            second,
+            info,
+            held: VecDeque::new(),
+            crossfaded: false,
+            input: Vec::new(),
+            output: Vec::new(),
+            cursor: 0,
+        })
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+impl<A: LgDecoder, B: LgDecoder> LgCrossfadeDecoder<A, B> {
+    /// False once both decoders have no samples left.
+    fn refill(&mut self) -> bool {
+        let channels = self.info.channels.max(1) as usize;
+        self.output.clear();
+        self.cursor = 0;
+
+        while self.output.is_empty() {
+            self.input.clear();
+
+            if self.crossfaded {
+                self.output.extend(
+                    self.second
+                        .samples::<f32>()
+                        .take(STREAM_CHUNK_FRAMES * channels),
+                );
+                return !self.output.is_empty();
+            }
+
+            self.input.extend(
+                self.first
+                    .samples::<f32>()
+                    .take(STREAM_CHUNK_FRAMES * channels),
+            );
+            if self.input.is_empty() {
+                // The first ended, what is held is its tail.
+                let tail: Vec<f32> = self.held.drain(..).collect();
+                let head: Vec<f32> = self.second.samples::<f32>().take(tail.len()).collect();
+                let length = head.len() / channels;
+                let overlap_start = tail.len() - length * channels;
+
+                self.output.extend_from_slice(&tail[..overlap_start]);
+                mix_crossfade(
+                    &tail[overlap_start..],
+                    &head[..length * channels],
+                    channels,
+                    self.curve,
+                    &mut self.output,
+                );
+                self.crossfaded = true;
+                continue;
+            }
+
+            self.held.extend(&self.input);
+            let excess = self.held.len().saturating_sub(self.length * channels);
+            self.output
+                .extend(self.held.drain(..excess - excess % channels));
+        }
+
+        true
+    }
+}
+impl<A: LgDecoder, B: LgDecoder> LgDecoder for LgCrossfadeDecoder<A, B> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn samples<S: Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+
+        std::iter::from_fn(move || {
+            if self.cursor >= self.output.len() && !self.refill() {
+                return None;
+            }
+
+            self.cursor += 1;
+            Some(S::from_f32(
+                self.output[self.cursor - 1],
+                sample_type,
+                bits_per_sample,
+            ))
+        })
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        let frames_per_second = self.info.channels.max(1) as usize * self.info.sample_rate as usize;
+        self.len() / frames_per_second.max(1)
+    }
+
+    fn len(&self) -> usize {
+        let overlap = self.length * self.info.channels as usize;
+        let overlap = overlap.min(self.first.len()).min(self.second.len());
+
+        self.first.len() + self.second.len() - overlap
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.first.is_empty() && self.second.is_empty()
+    }
+}
+
+fn fade_buffer(
+    buffer: &AudioBuffer,
+    fade_in: Option<Fade>,
+    fade_out: Option<Fade>,
+) -> Result<AudioBuffer> {
+    let mut fader = Fader::new(buffer.info, fade_in, fade_out)?;
+
+    let mut samples = Vec::with_capacity(buffer.samples.len());
+    fader.process(&buffer.samples, &mut samples);
+    fader.flush(&mut samples);
+
+    Ok(AudioBuffer::new(buffer.info, samples))
+}
+
+/// Appends `from` faded out mixed with `to` faded in, both of the same length.
+fn mix_crossfade(
+    from: &[f32],
+    to: &[f32],
+    channels: usize,
+    curve: FadeCurve,
+    output: &mut Vec<f32>,
+) {
+    let length = from.len() / channels;
+
+    for (i, (from, to)) in from
+        .chunks_exact(channels)
+        .zip(to.chunks_exact(channels))
+        .enumerate()
+    {
+        let t = (i as f32 + 0.5) / length as f32;
+        let (out_gain, in_gain) = (curve.gain(1.0 - t), curve.gain(t));
+        output.extend(
+            from.iter()
+                .zip(to)
+                .map(|(from, to)| from * out_gain + to * in_gain),
+        );
+    }
+}
+
+fn check_formats(first: AudioInfo, second: AudioInfo) -> Result<()> {
+    if first.channels != second.channels || first.sample_rate != second.sample_rate {
+        return Err(Error::WrongFmtInfo(format!(
+            "Can't crossfade {} channels at {} Hz with {} channels at {} Hz!",
+            first.channels, first.sample_rate, second.channels, second.sample_rate
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(channels: u16) -> AudioInfo {
+        AudioInfo {
+            channels,
+            sample_rate: 1000,
+            bits_per_sample: 32,
+            sample_type: Some(SampleType::FLOAT),
+        }
+    }
+
+    #[test]
+    fn curves_add_up() {
+        for i in 0..=100 {
+            let t = i as f32 / 100.0;
+
+            let linear = FadeCurve::Linear.gain(t) + FadeCurve::Linear.gain(1.0 - t);
+            assert!((linear - 1.0).abs() < 1e-6);
+            let power =
+                FadeCurve::EqualPower.gain(t).powi(2) + FadeCurve::EqualPower.gain(1.0 - t).powi(2);
+            assert!((power - 1.0).abs() < 1e-6);
+            let s_curve = FadeCurve::SCurve.gain(t) + FadeCurve::SCurve.gain(1.0 - t);
+            assert!((s_curve - 1.0).abs() < 1e-6);
+        }
+
+        for curve in [
+            FadeCurve::Linear,
+            FadeCurve::EqualPower,
+            FadeCurve::Logarithmic,
+            FadeCurve::SCurve,
+        ] {
+            assert_eq!(curve.gain(0.0), 0.0);
+            assert_eq!(curve.gain(1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn equal_power_crossfade() {
+        // The first sound on the left, the second on the right, the power stays the same.
+        let first = AudioBuffer::new(info(2), [1.0, 0.0].repeat(300));
+        let second = AudioBuffer::new(info(2), [0.0, 1.0].repeat(200));
+        let fade = Fade::new(FadeLength::Frames(100), FadeCurve::EqualPower);
+
+        let crossfaded = crossfade(&first, &second, fade).unwrap();
+        assert_eq!(crossfaded.frames(), 400);
+        for frame in crossfaded.samples.chunks_exact(2) {
+            assert!((frame[0].powi(2) + frame[1].powi(2) - 1.0).abs() < 1e-6);
+        }
+
+        // Through the overlap, the first fades out as the second fades in.
+        let overlap = &crossfaded.samples[200 * 2..300 * 2];
+        assert!(
+            overlap
+                .chunks_exact(2)
+                .all(|frame| frame[0] > 0.0 && frame[1] > 0.0)
+        );
+        assert!(
+            overlap
+                .chunks_exact(2)
+                .is_sorted_by(|a, b| a[0] > b[0] && a[1] < b[1])
+        );
+    }
+
+    #[test]
+    fn linear_crossfade_of_the_same_sound() {
+        let first = AudioBuffer::new(info(1), vec![0.5; 50]);
+        let fade = Fade::new(FadeLength::Seconds(0.02), FadeCurve::Linear);
+
+        let crossfaded = crossfade(&first, &first, fade).unwrap();
+        assert_eq!(crossfaded.frames(), 80);
+        assert!(crossfaded.samples.iter().all(|x| (x - 0.5).abs() < 1e-6));
+    }
+}
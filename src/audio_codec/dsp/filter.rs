@@ -0,0 +1,532 @@
+//! Biquad filters from the RBJ audio EQ cookbook, cascaded for higher order designs and
+//! parametric EQs.
+
+use super::super::{Result, buffer::AudioBuffer, error::Error};
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    LowPass,
+    HighPass,
+    /// Peak gain of 0 dB at the frequency.
+    BandPass,
+    Notch,
+    AllPass,
+    /// With the gain in dB.
+    Peaking(f64),
+    /// With the gain in dB, a Q of `1/√2` is the steepest without overshoot.
+    LowShelf(f64),
+    /// With the gain in dB, a Q of `1/√2` is the steepest without overshoot.
+    HighShelf(f64),
+    /// 6 dB per octave, the Q is ignored.
+    FirstOrderLowPass,
+    /// 6 dB per octave, the Q is ignored.
+    FirstOrderHighPass,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadParams {
+    pub kind: FilterKind,
+    /// Cutoff or center frequency, in Hz.
+    pub frequency: f64,
+    pub q: f64,
+}
+impl BiquadParams {
+    #[inline(always)]
+    pub fn new(kind: FilterKind, frequency: f64, q: f64) -> Self {
+        Self { kind, frequency, q }
+    }
+}
+
+/// Normalized so `a0` is 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients {
+    pub b: [f64; 3],
+    pub a: [f64; 2],
+}
+impl BiquadCoefficients {
+    /// The frequency is kept under the Nyquist frequency.
+    pub fn new(params: BiquadParams, sample_rate: u32) -> Self {
+        let rate = sample_rate.max(1) as f64;
+        let frequency = params.frequency.clamp(1e-3, rate * 0.4999);
+        let w0 = 2.0 * PI * frequency / rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * params.q.max(1e-3));
+        let shelf_gain = |gain: f64| 10f64.powf(gain / 40.0);
+
+        let (b, a0, a) = match params.kind {
+            FilterKind::LowPass => (
+                [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::HighPass => (
+                [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::BandPass => ([alpha, 0.0, -alpha], 1.0 + alpha, [-2.0 * cos, 1.0 - alpha]),
+            FilterKind::Notch => (
+                [1.0, -2.0 * cos, 1.0],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::AllPass => (
+                [1.0 - alpha, -2.0 * cos, 1.0 + alpha],
+                1.0 + alpha,
+                [-2.0 * cos, 1.0 - alpha],
+            ),
+            FilterKind::Peaking(gain) => {
+                let a = shelf_gain(gain);
+                (
+                    [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                    1.0 + alpha / a,
+                    [-2.0 * cos, 1.0 - alpha / a],
+                )
+            }
+            FilterKind::LowShelf(gain) => {
+                let a = shelf_gain(gain);
+                let s = 2.0 * a.sqrt() * alpha;
+                (
+                    [
+                        a * ((a + 1.0) - (a - 1.0) * cos + s),
+                        2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                        a * ((a + 1.0) - (a - 1.0) * cos - s),
+                    ],
+                    (a + 1.0) + (a - 1.0) * cos + s,
+                    [
+                        -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                        (a + 1.0) + (a - 1.0) * cos - s,
+                    ],
+                )
+            }
+            FilterKind::HighShelf(gain) => {
+                let a = shelf_gain(gain);
+                let s = 2.0 * a.sqrt() * alpha;
+                (
+                    [
+                        a * ((a + 1.0) + (a - 1.0) * cos + s),
+                        -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                        a * ((a + 1.0) + (a - 1.0) * cos - s),
+                    ],
+                    (a + 1.0) - (a - 1.0) * cos + s,
+                    [
+                        2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                        (a + 1.0) - (a - 1.0) * cos - s,
+                    ],
+                )
+            }
+            FilterKind::FirstOrderLowPass => {
+                let k = (w0 / 2.0).tan();
+                ([k, k, 0.0], 1.0 + k, [k - 1.0, 0.0])
+            }
+            FilterKind::FirstOrderHighPass => {
+                let k = (w0 / 2.0).tan();
+                ([1.0, -1.0, 0.0], 1.0 + k, [k - 1.0, 0.0])
+            }
+        };
+
+        Self {
+            b: b.map(|b| b / a0),
+            a: a.map(|a| a / a0),
+        }
+    }
+
+    /// Gain at the frequency, in dB.
+    pub fn response(&self, frequency: f64, sample_rate: u32) -> f64 {
+        let w = 2.0 * PI * frequency / sample_rate.max(1) as f64;
+        let (sin, cos) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+
+        let numerator = (
+            self.b[0] + self.b[1] * cos + self.b[2] * cos2,
+            -(self.b[1] * sin + self.b[2] * sin2),
+        );
+        let denominator = (
+            1.0 + self.a[0] * cos + self.a[1] * cos2,
+            -(self.a[0] * sin + self.a[1] * sin2),
+        );
+
+        10.0 * ((numerator.0 * numerator.0 + numerator.1 * numerator.1)
+            / (denominator.0 * denominator.0 + denominator.1 * denominator.1))
+            .log10()
+    }
+}
+
+/// Direct form II transposed biquad, with the state of every channel.
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    sample_rate: u32,
+    params: BiquadParams,
+    coefficients: BiquadCoefficients,
+    state: Vec<[f64; 2]>,
+}
+impl Biquad {
+    pub fn new(channels: usize, sample_rate: u32, params: BiquadParams) -> Self {
+        Self {
+            sample_rate,
+            params,
+            coefficients: BiquadCoefficients::new(params, sample_rate),
+            state: vec![[0.0; 2]; channels.max(1)],
+        }
+    }
+
+    #[inline(always)]
+    pub fn params(&self) -> BiquadParams {
+        self.params
+    }
+
+    /// Recomputes the coefficients, keeping the state so it can be automated while playing.
+    pub fn set_params(&mut self, params: BiquadParams) {
+        self.params = params;
+        self.coefficients = BiquadCoefficients::new(params, self.sample_rate);
+    }
+
+    #[inline(always)]
+    pub fn coefficients(&self) -> BiquadCoefficients {
+        self.coefficients
+    }
+
+    #[inline(always)]
+    pub fn channels(&self) -> usize {
+        self.state.len()
+    }
+
+    /// Filters interleaved samples in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.state.len()) {
+            for (sample, state) in frame.iter_mut().zip(&mut self.state) {
+                *sample = Self::tick(&self.coefficients, state, *sample as f64) as f32;
+            }
+        }
+    }
+
+    /// Filters a single sample of the channel.
+    #[inline(always)]
+    pub fn process_sample(&mut self, channel: usize, sample: f32) -> f32 {
+        Self::tick(&self.coefficients, &mut self.state[channel], sample as f64) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.state.fill([0.0; 2]);
+    }
+}
+impl Biquad {
+    #[inline(always)]
+    fn tick(coefficients: &BiquadCoefficients, state: &mut [f64; 2], x: f64) -> f64 {
+        let y = coefficients.b[0] * x + state[0];
+        state[0] = coefficients.b[1] * x - coefficients.a[0] * y + state[1];
+        state[1] = coefficients.b[2] * x - coefficients.a[1] * y;
+
+        y
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    Low,
+    High,
+}
+
+/// A filter of a [`FilterChain`], made of one or more biquads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterSpec {
+    Biquad(BiquadParams),
+    /// Maximally flat, -3 dB at the frequency, 6 dB per octave for every order.
+    Butterworth {
+        pass: Pass,
+        order: usize,
+        frequency: f64,
+    },
+    /// Two Butterworth of half the order, -6 dB at the frequency so the low and high pass
+    /// add up flat, for crossovers. The order is even.
+    LinkwitzRiley {
+        pass: Pass,
+        order: usize,
+        frequency: f64,
+    },
+}
+impl FilterSpec {
+    /// The biquads it is made of.
+    pub fn sections(&self) -> Result<Vec<BiquadParams>> {
+        match *self {
+            Self::Biquad(params) => Ok(vec![params]),
+
+            Self::Butterworth {
+                pass,
+                order,
+                frequency,
+            } => {
+                if order == 0 {
+                    return Err(Error::Custom(
+                        "Butterworth order must be at least 1!".to_string(),
+                    ));
+                }
+
+                let (second_order, first_order) = match pass {
+                    Pass::Low => (FilterKind::LowPass, FilterKind::FirstOrderLowPass),
+                    Pass::High => (FilterKind::HighPass, FilterKind::FirstOrderHighPass),
+                };
+
+                let mut sections: Vec<BiquadParams> = (0..order / 2)
+                    .map(|k| {
+                        let q = 1.0 / (2.0 * (PI * (2 * k + 1) as f64 / (2 * order) as f64).sin());
+                        BiquadParams::new(second_order, frequency, q)
+                    })
+                    .collect();
+                if order % 2 == 1 {
+                    sections.push(BiquadParams::new(first_order, frequency, 0.5));
+                }
+
+                Ok(sections)
+            }
+
+            Self::LinkwitzRiley {
+                pass,
+                order,
+                frequency,
+            } => {
+                if order == 0 || order % 2 != 0 {
+                    return Err(Error::Custom(format!(
+                        "Linkwitz-Riley order {order} must be even and at least 2!"
+                    )));
+                }
+
+                let half = Self::Butterworth {
+                    pass,
+                    order: order / 2,
+                    frequency,
+                }
+                .sections()?;
+
+                Ok([half.as_slice(), half.as_slice()].concat())
+            }
+        }
+    }
+}
+
+/// Filters in series, like the bands of a parametric EQ, every one can be changed while playing.
+#[derive(Debug, Clone)]
+pub struct FilterChain {
+    channels: usize,
+    sample_rate: u32,
+    specs: Vec<FilterSpec>,
+    /// Biquads of every spec.
+    sections: Vec<Vec<Biquad>>,
+}
+impl FilterChain {
+    pub fn new(channels: usize, sample_rate: u32, specs: &[FilterSpec]) -> Result<Self> {
+        let mut chain = Self {
+            channels: channels.max(1),
+            sample_rate,
+            specs: Vec::with_capacity(specs.len()),
+            sections: Vec::with_capacity(specs.len()),
+        };
+        for spec in specs {
+            chain.push(*spec)?;
+        }
+
+        Ok(chain)
+    }
+
+    pub fn push(&mut self, spec: FilterSpec) -> Result<()> {
+        let sections = self.build(spec)?;
+        self.specs.push(spec);
+        self.sections.push(sections);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    #[inline(always)]
+    pub fn specs(&self) -> &[FilterSpec] {
+        &self.specs
+    }
+
+    /// Replaces a filter, its state is kept unless the number of biquads changes.
+    pub fn set_spec(&mut self, index: usize, spec: FilterSpec) -> Result<()> {
+        if index >= self.specs.len() {
+            return Err(Error::Custom(format!(
+                "Filter {index} is not in a chain of {}!",
+                self.specs.len()
+            )));
+        }
+
+        let params = spec.sections()?;
+        if params.len() == self.sections[index].len() {
+            for (biquad, params) in self.sections[index].iter_mut().zip(params) {
+                biquad.set_params(params);
+            }
+        } else {
+            self.sections[index] = self.build(spec)?;
+        }
+        self.specs[index] = spec;
+
+        Ok(())
+    }
+
+    /// Gain of the whole chain at the frequency, in dB.
+    pub fn response(&self, frequency: f64) -> f64 {
+        self.sections
+            .iter()
+            .flatten()
+            .map(|biquad| biquad.coefficients().response(frequency, self.sample_rate))
+            .sum()
+    }
+
+    /// Filters interleaved samples in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for biquad in self.sections.iter_mut().flatten() {
+            biquad.process(samples);
+        }
+    }
+
+    /// Filters only the `channel` of interleaved samples in place.
+    pub fn process_channel(&mut self, channel: usize, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let mut sample = frame[channel];
+            for biquad in self.sections.iter_mut().flatten() {
+                sample = biquad.process_sample(channel, sample);
+            }
+            frame[channel] = sample;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for biquad in self.sections.iter_mut().flatten() {
+            biquad.reset();
+        }
+    }
+}
+impl FilterChain {
+    fn build(&self, spec: FilterSpec) -> Result<Vec<Biquad>> {
+        Ok(spec
+            .sections()?
+            .into_iter()
+            .map(|params| Biquad::new(self.channels, self.sample_rate, params))
+            .collect())
+    }
+}
+
+/// Filters every channel of the buffer, or only the `channel` if given.
+pub fn filter(
+    buffer: &AudioBuffer,
+    specs: &[FilterSpec],
+    channel: Option<usize>,
+) -> Result<AudioBuffer> {
+    let channels = buffer.channels();
+    if channel.is_some_and(|channel| channel >= channels) {
+        return Err(Error::Custom(format!(
+            "Channel {channel:?} is not in {channels} channels!"
+        )));
+    }
+
+    let mut chain = FilterChain::new(channels, buffer.info.sample_rate, specs)?;
+    let mut filtered = buffer.clone();
+    match channel {
+        Some(channel) => chain.process_channel(channel, &mut filtered.samples),
+        None => chain.process(&mut filtered.samples),
+    }
+
+    Ok(filtered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn assert_near(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{value} is not {expected} ± {tolerance}"
+        );
+    }
+
+    /// Level in dB of a sine at `frequency` once through the chain, after it settles.
+    fn sine_gain(chain: &mut FilterChain, frequency: f64) -> f64 {
+        let mut samples: Vec<f32> = (0..RATE as usize)
+            .map(|n| (2.0 * PI * frequency * n as f64 / RATE as f64).sin() as f32)
+            .collect();
+        chain.process(&mut samples);
+
+        let settled = &samples[RATE as usize / 2..];
+        let mean_square =
+            settled.iter().map(|x| (*x as f64).powi(2)).sum::<f64>() / settled.len() as f64;
+        10.0 * (mean_square * 2.0).log10()
+    }
+
+    #[test]
+    fn butterworth_cutoff() {
+        for pass in [Pass::Low, Pass::High] {
+            for order in 1..=6 {
+                let spec = FilterSpec::Butterworth {
+                    pass,
+                    order,
+                    frequency: 1000.0,
+                };
+                let mut chain = FilterChain::new(1, RATE, &[spec]).unwrap();
+
+                assert_near(chain.response(1000.0), -3.01, 0.01);
+                assert_near(sine_gain(&mut chain, 1000.0), -3.01, 0.01);
+
+                // 6 dB per octave and order, far from the cutoff.
+                let stop = match pass {
+                    Pass::Low => 16_000.0,
+                    Pass::High => 62.5,
+                };
+                assert!(chain.response(stop) < -22.0 * order as f64);
+            }
+        }
+    }
+
+    #[test]
+    fn linkwitz_riley_crossover() {
+        let low = FilterSpec::LinkwitzRiley {
+            pass: Pass::Low,
+            order: 4,
+            frequency: 2000.0,
+        };
+        let high = FilterSpec::LinkwitzRiley {
+            pass: Pass::High,
+            order: 4,
+            frequency: 2000.0,
+        };
+        let low = FilterChain::new(1, RATE, &[low]).unwrap();
+        let high = FilterChain::new(1, RATE, &[high]).unwrap();
+
+        assert_near(low.response(2000.0), -6.02, 0.01);
+        assert_near(high.response(2000.0), -6.02, 0.01);
+    }
+
+    #[test]
+    fn biquad_center() {
+        let cases = [
+            (FilterKind::LowPass, -3.01),
+            (FilterKind::HighPass, -3.01),
+            (FilterKind::BandPass, 0.0),
+            (FilterKind::AllPass, 0.0),
+            (FilterKind::Peaking(6.0), 6.0),
+            (FilterKind::Peaking(-12.0), -12.0),
+            // Shelves are at half their gain at the frequency.
+            (FilterKind::LowShelf(6.0), 3.0),
+            (FilterKind::HighShelf(-6.0), -3.0),
+        ];
+
+        for (kind, gain) in cases {
+            let params = BiquadParams::new(kind, 500.0, std::f64::consts::FRAC_1_SQRT_2);
+            let mut chain = FilterChain::new(1, RATE, &[FilterSpec::Biquad(params)]).unwrap();
+
+            assert_near(chain.response(500.0), gain, 0.01);
+            assert_near(sine_gain(&mut chain, 500.0), gain, 0.01);
+        }
+
+        let notch = BiquadCoefficients::new(BiquadParams::new(FilterKind::Notch, 500.0, 1.0), RATE);
+        assert!(notch.response(500.0, RATE) < -100.0);
+    }
+}
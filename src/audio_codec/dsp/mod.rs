@@ -0,0 +1,56 @@
+pub mod convolution;
+pub mod dynamics;
+pub mod fade;
+pub mod filter;
+pub mod normalize;
+pub mod remix;
+pub mod resample;
+pub mod spatial;
+pub mod stretch;
+pub mod trim;
+
+pub use convolution::{ConvolutionParams, Convolver, ImpulseResponse, Partitioning, convolve};
+pub use dynamics::{
+    Detector, Dynamics, DynamicsMode, DynamicsParams, Limiter, LimiterParams, apply_dynamics,
+};
+pub use fade::{
+    Fade, FadeCurve, FadeLength, Fader, LgCrossfadeDecoder, crossfade, crossfade_loop, fade_in,
+    fade_out,
+};
+pub use filter::{
+    Biquad, BiquadCoefficients, BiquadParams, FilterChain, FilterKind, FilterSpec, Pass, filter,
+};
+pub use normalize::{NormalizeReport, NormalizeTarget, normalize, normalize_wav};
+pub use remix::{ChannelLayout, LgRemixDecoder, RemixMatrix, Speaker, remix};
+pub use resample::{LgResampleDecoder, ResampleQuality, Resampler, SincParams, resample};
+pub use spatial::{
+    Attenuation, Cone, DistanceModel, Doppler, Emitter, HrtfSet, Listener, Spatializer, Vec3,
+    spatialize,
+};
+pub use stretch::{
+    PitchShifter, StretchParams, TimeStretcher, fit_duration, pitch_shift, time_stretch,
+};
+pub use trim::{SilenceTrimmer, TrimParams, TrimReport, trim_silence};
+
+/// Amplitude ratio of a level in dB.
+#[inline(always)]
+pub(crate) fn db_to_gain(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Modified Bessel function of the first kind, order 0, for Kaiser windows.
+pub(crate) fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+
+    for k in 1..64 {
+        term *= (half_x / k as f64) * (half_x / k as f64);
+        sum += term;
+        if term < sum * 1e-17 {
+            break;
+        }
+    }
+
+    sum
+}
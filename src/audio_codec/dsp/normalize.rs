@@ -0,0 +1,153 @@
+//! Peak and loudness normalization, with the look-ahead [`Limiter`] to catch what the gain
+//! pushes over the ceiling.
+
+use super::{
+    super::{
+        AudioInfo, Result,
+        analysis::{
+            Loudness,
+            loudness::{measure, measure_decoder},
+        },
+        buffer::AudioBuffer,
+        decoder::{LgDecoder, read_chunk},
+        encoder::LgEncoder,
+        wav::{LgWavDecoder, LgWavEncoder},
+    },
+    db_to_gain,
+    dynamics::{Limiter, LimiterParams},
+};
+use std::path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalizeTarget {
+    /// Highest sample, in dBFS.
+    Peak(f64),
+    /// Integrated loudness, in LUFS.
+    Loudness(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizeReport {
+    /// Loudness of the input.
+    pub measured: Loudness,
+    /// Gain applied before the limiter, in dB.
+    pub gain: f64,
+    /// Most gain taken by the limiter, in dB, 0 without it.
+    pub max_reduction: f64,
+}
+
+/// Gain in dB to reach the target, 0 for silence.
+pub fn normalization_gain(measured: &Loudness, target: NormalizeTarget) -> f64 {
+    let gain = match target {
+        NormalizeTarget::Peak(peak) => peak - measured.sample_peak,
+        NormalizeTarget::Loudness(loudness) => loudness - measured.integrated,
+    };
+
+    if gain.is_finite() { gain } else { 0.0 }
+}
+
+/// Normalizes the whole buffer, limiting it after the gain if asked.
+pub fn normalize(
+    buffer: &AudioBuffer,
+    target: NormalizeTarget,
+    limiter: Option<LimiterParams>,
+) -> Result<(AudioBuffer, NormalizeReport)> {
+    let measured = measure(buffer)?;
+    let gain = normalization_gain(&measured, target);
+
+    let mut normalizer = Normalizer::new(buffer.info, gain, limiter);
+    let mut samples = Vec::with_capacity(buffer.samples.len());
+    normalizer.process(&buffer.samples, &mut samples);
+    normalizer.flush(&mut samples);
+
+    Ok((
+        AudioBuffer::new(buffer.info, samples),
+        NormalizeReport {
+            measured,
+            gain,
+            max_reduction: normalizer.max_reduction(),
+        },
+    ))
+}
+
+/// Measures the `input` file, then writes it normalized to the `output` file with the same format.
+pub fn normalize_wav(
+    input: impl AsRef<path::Path>,
+    output: impl AsRef<path::Path>,
+    target: NormalizeTarget,
+    limiter: Option<LimiterParams>,
+) -> Result<NormalizeReport> {
+    let measured = measure_decoder(&mut LgWavDecoder::new(&input)?)?;
+    let gain = normalization_gain(&measured, target);
+
+    let mut decoder = LgWavDecoder::new(&input)?;
+    let info = decoder.info();
+    let mut encoder = LgWavEncoder::new(output, info)?;
+
+    let mut normalizer = Normalizer::new(info, gain, limiter);
+    let mut chunk = Vec::new();
+    let mut normalized = Vec::new();
+    loop {
+        normalized.clear();
+        let more = read_chunk(&mut decoder, &mut chunk);
+
+        if more {
+            normalizer.process(&chunk, &mut normalized);
+        } else {
+            normalizer.flush(&mut normalized);
+        }
+
+        for sample in &normalized {
+            encoder.encode_sample(*sample)?;
+        }
+
+        if !more {
+            break;
+        }
+    }
+    encoder.finish()?;
+
+    Ok(NormalizeReport {
+        measured,
+        gain,
+        max_reduction: normalizer.max_reduction(),
+    })
+}
+
+/// Gain followed by the optional limiter.
+struct Normalizer {
+    gain: f32,
+    limiter: Option<Limiter>,
+    gained: Vec<f32>,
+}
+impl Normalizer {
+    fn new(info: AudioInfo, gain: f64, limiter: Option<LimiterParams>) -> Self {
+        Self {
+            gain: db_to_gain(gain) as f32,
+            limiter: limiter
+                .map(|params| Limiter::new(info.channels as usize, info.sample_rate, params)),
+            gained: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        match &mut self.limiter {
+            Some(limiter) => {
+                self.gained.clear();
+                self.gained.extend(input.iter().map(|s| s * self.gain));
+                limiter.process(&self.gained, output);
+            }
+            None => output.extend(input.iter().map(|s| s * self.gain)),
+        }
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        if let Some(limiter) = &mut self.limiter {
+            limiter.flush(output);
+        }
+    }
+
+    fn max_reduction(&self) -> f64 {
+        self.limiter.as_ref().map_or(0.0, Limiter::max_reduction)
+    }
+}
@@ -0,0 +1,477 @@
+//! Channel remixing between speaker layouts.
+//!
+//! The default matrices follow ITU-R BS.775: the center goes to the front speakers at -3 dB,
+//! the surrounds go to their front side at -3 dB and the LFE is dropped. Missing speakers fall
+//! back to their neighbours the same way, so any layout can be converted to any other.
+//! The sums are not normalized, so downmixing loud material can clip.
+
+use super::super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::LgDecoder,
+    error::Error,
+    sample::{Sample, SampleType},
+};
+use std::f32::consts::FRAC_1_SQRT_2;
+
+/// Frames read from the wrapped decoder at a time.
+const STREAM_CHUNK_FRAMES: usize = 1024;
+
+/// Speaker positions, in the order of the WAV `channel_mask` bits (also used by CAF bitmaps).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    FrontLeftOfCenter,
+    FrontRightOfCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+}
+impl Speaker {
+    pub const ALL: [Self; 18] = [
+        Self::FrontLeft,
+        Self::FrontRight,
+        Self::FrontCenter,
+        Self::LowFrequency,
+        Self::BackLeft,
+        Self::BackRight,
+        Self::FrontLeftOfCenter,
+        Self::FrontRightOfCenter,
+        Self::BackCenter,
+        Self::SideLeft,
+        Self::SideRight,
+        Self::TopCenter,
+        Self::TopFrontLeft,
+        Self::TopFrontCenter,
+        Self::TopFrontRight,
+        Self::TopBackLeft,
+        Self::TopBackCenter,
+        Self::TopBackRight,
+    ];
+
+    /// Bit of the WAV `channel_mask`.
+    #[inline(always)]
+    pub fn wav_bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// Where the speaker goes when the output doesn't have it.
+    fn fallback(self, output: &ChannelLayout) -> Vec<(Self, f32)> {
+        use Speaker::*;
+
+        let side_or = |side: Self, front: Self| {
+            if output.contains(side) {
+                vec![(side, 1.0)]
+            } else {
+                vec![(front, FRAC_1_SQRT_2)]
+            }
+        };
+
+        match self {
+            FrontLeft | FrontRight => vec![(FrontCenter, FRAC_1_SQRT_2)],
+            FrontCenter => vec![(FrontLeft, FRAC_1_SQRT_2), (FrontRight, FRAC_1_SQRT_2)],
+            LowFrequency => Vec::new(),
+
+            FrontLeftOfCenter => vec![(FrontLeft, 1.0)],
+            FrontRightOfCenter => vec![(FrontRight, 1.0)],
+
+            BackLeft => side_or(SideLeft, FrontLeft),
+            BackRight => side_or(SideRight, FrontRight),
+            SideLeft => side_or(BackLeft, FrontLeft),
+            SideRight => side_or(BackRight, FrontRight),
+            BackCenter => vec![(BackLeft, FRAC_1_SQRT_2), (BackRight, FRAC_1_SQRT_2)],
+
+            TopCenter | TopFrontCenter => vec![(FrontCenter, FRAC_1_SQRT_2)],
+            TopFrontLeft => vec![(FrontLeft, FRAC_1_SQRT_2)],
+            TopFrontRight => vec![(FrontRight, FRAC_1_SQRT_2)],
+            TopBackLeft => vec![(BackLeft, FRAC_1_SQRT_2)],
+            TopBackRight => vec![(BackRight, FRAC_1_SQRT_2)],
+            TopBackCenter => vec![(BackCenter, FRAC_1_SQRT_2)],
+        }
+    }
+}
+
+/// The speaker of every channel, in the order they are interleaved.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChannelLayout {
+    pub speakers: Vec<Speaker>,
+}
+impl ChannelLayout {
+    #[inline(always)]
+    pub fn new(speakers: Vec<Speaker>) -> Self {
+        Self { speakers }
+    }
+
+    pub fn mono() -> Self {
+        Self::new(vec![Speaker::FrontCenter])
+    }
+
+    pub fn stereo() -> Self {
+        Self::new(vec![Speaker::FrontLeft, Speaker::FrontRight])
+    }
+
+    pub fn quad() -> Self {
+        use Speaker::*;
+        Self::new(vec![FrontLeft, FrontRight, BackLeft, BackRight])
+    }
+
+    /// L, R, C, LFE, Ls, Rs, the WAV order.
+    pub fn surround_5_1() -> Self {
+        use Speaker::*;
+        Self::new(vec![
+            FrontLeft,
+            FrontRight,
+            FrontCenter,
+            LowFrequency,
+            BackLeft,
+            BackRight,
+        ])
+    }
+
+    /// L, R, C, LFE, Lb, Rb, Ls, Rs, the WAV order.
+    pub fn surround_7_1() -> Self {
+        use Speaker::*;
+        Self::new(vec![
+            FrontLeft,
+            FrontRight,
+            FrontCenter,
+            LowFrequency,
+            BackLeft,
+            BackRight,
+            SideLeft,
+            SideRight,
+        ])
+    }
+
+    /// The usual layout for the number of channels, like players assume for WAV files
+    /// without a `channel_mask`. Unusual counts use the first speakers of the mask order.
+    pub fn default_for(channels: u16) -> Self {
+        match channels {
+            1 => Self::mono(),
+            2 => Self::stereo(),
+            4 => Self::quad(),
+            6 => Self::surround_5_1(),
+            8 => Self::surround_7_1(),
+            _ => Self::new(Speaker::ALL.into_iter().take(channels as usize).collect()),
+        }
+    }
+
+    /// Speakers of a WAV `channel_mask` or CAF bitmap, which are always in the bit order.
+    pub fn from_wav_mask(mask: u32) -> Self {
+        Self::new(
+            Speaker::ALL
+                .into_iter()
+                .filter(|speaker| mask & speaker.wav_bit() != 0)
+                .collect(),
+        )
+    }
+
+    /// Only exact when the speakers are in the bit order, see [`ChannelLayout::is_wav_order`].
+    pub fn wav_mask(&self) -> u32 {
+        self.speakers
+            .iter()
+            .fold(0, |mask, speaker| mask | speaker.wav_bit())
+    }
+
+    /// If the channels can be stored in a WAV file as they are.
+    pub fn is_wav_order(&self) -> bool {
+        self.speakers
+            .windows(2)
+            .all(|pair| (pair[0] as u32) < (pair[1] as u32))
+    }
+
+    /// Same speakers, in the WAV order.
+    pub fn to_wav_order(&self) -> Self {
+        Self::from_wav_mask(self.wav_mask())
+    }
+
+    #[inline(always)]
+    pub fn channels(&self) -> usize {
+        self.speakers.len()
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, speaker: Speaker) -> bool {
+        self.speakers.contains(&speaker)
+    }
+
+    #[inline(always)]
+    pub fn position(&self, speaker: Speaker) -> Option<usize> {
+        self.speakers.iter().position(|s| *s == speaker)
+    }
+
+    /// Sets the `gains` of every channel to `gain`, with the front left and right speakers
+    /// panned from -1.0, left, to 1.0, right.
+    pub(crate) fn pan_gains(&self, gain: f32, pan: f32, gains: &mut [f32]) {
+        gains.fill(gain);
+        if let Some(left) = self.position(Speaker::FrontLeft) {
+            gains[left] *= (1.0 - pan).min(1.0);
+        }
+        if let Some(right) = self.position(Speaker::FrontRight) {
+            gains[right] *= (1.0 + pan).min(1.0);
+        }
+    }
+}
+
+/// Gain from every input channel to every output channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemixMatrix {
+    input_channels: usize,
+    output_channels: usize,
+    /// Row of input gains for every output channel.
+    gains: Vec<f32>,
+}
+impl RemixMatrix {
+    /// BS.775 coefficients, speakers in both layouts are copied, so reordering is free of loss.
+    pub fn new(input: &ChannelLayout, output: &ChannelLayout) -> Result<Self> {
+        check_sizes(input.channels(), output.channels())?;
+
+        let mut result = Self {
+            input_channels: input.channels(),
+            output_channels: output.channels(),
+            gains: vec![0.0; input.channels() * output.channels()],
+        };
+
+        for (input_channel, speaker) in input.speakers.iter().enumerate() {
+            result.route(output, input_channel, *speaker, 1.0, 0);
+        }
+
+        Ok(result)
+    }
+
+    /// `gains` has a row with `input_channels` gains for every output channel.
+    pub fn custom(input_channels: usize, output_channels: usize, gains: Vec<f32>) -> Result<Self> {
+        check_sizes(input_channels, output_channels)?;
+        if gains.len() != input_channels * output_channels {
+            return Err(Error::Custom(format!(
+                "Remix matrix of {output_channels}x{input_channels} must have {} gains, got {}!",
+                input_channels * output_channels,
+                gains.len()
+            )));
+        }
+
+        Ok(Self {
+            input_channels,
+            output_channels,
+            gains,
+        })
+    }
+
+    #[inline(always)]
+    pub fn input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    #[inline(always)]
+    pub fn output_channels(&self) -> usize {
+        self.output_channels
+    }
+
+    #[inline(always)]
+    pub fn gain(&self, input_channel: usize, output_channel: usize) -> f32 {
+        self.gains[output_channel * self.input_channels + input_channel]
+    }
+
+    #[inline(always)]
+    pub fn set_gain(&mut self, input_channel: usize, output_channel: usize, gain: f32) {
+        self.gains[output_channel * self.input_channels + input_channel] = gain;
+    }
+
+    /// Mixes a single frame, `output` is overwritten.
+    pub fn apply(&self, input: &[f32], output: &mut [f32]) {
+        for (value, gains) in output
+            .iter_mut()
+            .zip(self.gains.chunks_exact(self.input_channels))
+        {
+            *value = input.iter().zip(gains).map(|(s, g)| s * g).sum();
+        }
+    }
+
+    /// Mixes whole interleaved frames, appending them to the `output`.
+    /// An incomplete frame at the end of the `input` is ignored.
+    pub fn apply_interleaved(&self, input: &[f32], output: &mut Vec<f32>) {
+        for frame in input.chunks_exact(self.input_channels) {
+            let start = output.len();
+            output.resize(start + self.output_channels, 0.0);
+            self.apply(frame, &mut output[start..]);
+        }
+    }
+}
+impl RemixMatrix {
+    fn route(
+        &mut self,
+        output: &ChannelLayout,
+        input_channel: usize,
+        speaker: Speaker,
+        gain: f32,
+        depth: usize,
+    ) {
+        if let Some(output_channel) = output.position(speaker) {
+            self.gains[output_channel * self.input_channels + input_channel] += gain;
+            return;
+        }
+
+        // Layouts without any front speaker could loop, like front left -> center -> front left.
+        if depth >= 3 {
+            return;
+        }
+
+        for (target, target_gain) in speaker.fallback(output) {
+            self.route(output, input_channel, target, gain * target_gain, depth + 1);
+        }
+    }
+}
+
+/// Remixes the whole buffer, the output has the channels of the matrix.
+pub fn remix(buffer: &AudioBuffer, matrix: &RemixMatrix) -> Result<AudioBuffer> {
+    check_channels(buffer.channels(), matrix)?;
+
+    let mut samples = Vec::with_capacity(buffer.frames() * matrix.output_channels);
+    matrix.apply_interleaved(&buffer.samples, &mut samples);
+
+    Ok(AudioBuffer::new(
+        AudioInfo {
+            channels: matrix.output_channels as u16,
+            ..buffer.info
+        },
+        samples,
+    ))
+}
+
+/// Remixes any decoder while it is decoded, keeping its sample format.
+pub struct LgRemixDecoder<D: LgDecoder> {
+    decoder: D,
+    matrix: RemixMatrix,
+    info: AudioInfo,
+
+    input: Vec<f32>,
+    output: Vec<f32>,
+    cursor: usize,
+}
+impl<D: LgDecoder> LgRemixDecoder<D> {
+    /// The matrix must have the input channels of the decoder.
+    pub fn new(decoder: D, matrix: RemixMatrix) -> Result<Self> {
+        let inner_info = decoder.info();
+        check_channels(inner_info.channels as usize, &matrix)?;
+
+        Ok(Self {
+            info: AudioInfo {
+                channels: matrix.output_channels as u16,
+                ..inner_info
+            },
+            decoder,
+            matrix,
+            input: Vec::new(),
+            output: Vec::new(),
+            cursor: 0,
+        })
+    }
+
+    /// Layouts of the decoder and of the output.
+    pub fn with_layouts(decoder: D, input: &ChannelLayout, output: &ChannelLayout) -> Result<Self> {
+        Self::new(decoder, RemixMatrix::new(input, output)?)
+    }
+
+    #[inline(always)]
+    pub fn matrix(&self) -> &RemixMatrix {
+        &self.matrix
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &D {
+        &self.decoder
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+impl<D: LgDecoder> LgRemixDecoder<D> {
+    /// False once the decoder has no full frames left.
+    fn refill(&mut self) -> bool {
+        self.output.clear();
+        self.cursor = 0;
+
+        self.input.clear();
+        self.input.extend(
+            self.decoder
+                .samples::<f32>()
+                .take(STREAM_CHUNK_FRAMES * self.matrix.input_channels),
+        );
+        self.matrix.apply_interleaved(&self.input, &mut self.output);
+
+        !self.output.is_empty()
+    }
+}
+impl<D: LgDecoder> LgDecoder for LgRemixDecoder<D> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn samples<S: Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+
+        std::iter::from_fn(move || {
+            if self.cursor >= self.output.len() && !self.refill() {
+                return None;
+            }
+
+            self.cursor += 1;
+            Some(S::from_f32(
+                self.output[self.cursor - 1],
+                sample_type,
+                bits_per_sample,
+            ))
+        })
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.decoder.duration()
+    }
+
+    fn len(&self) -> usize {
+        self.decoder.len() / self.matrix.input_channels * self.matrix.output_channels
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.decoder.is_empty()
+    }
+}
+
+fn check_sizes(input_channels: usize, output_channels: usize) -> Result<()> {
+    if input_channels == 0 || output_channels == 0 {
+        return Err(Error::WrongFmtInfo(
+            "Remix matrix must have at least one input and output channel!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_channels(channels: usize, matrix: &RemixMatrix) -> Result<()> {
+    if channels != matrix.input_channels {
+        return Err(Error::WrongFmtInfo(format!(
+            "Remix matrix takes {} channels, got {channels}!",
+            matrix.input_channels
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,594 @@
+//! Sample rate conversion.
+//!
+//! The sinc modes use a Kaiser windowed sinc, evaluated at any position from a table, so the
+//! ratio can be anything and can change while resampling. When downsampling the filter is
+//! stretched, so the cutoff follows the output Nyquist frequency.
+//!
+//! Measured by resampling sines between 22.05, 44.1, 48 and 96 kHz, frequencies relative to the
+//! lower of the two Nyquist frequencies. Images and aliases are attenuated at least as much as
+//! the stopband.
+//!
+//! | Quality  | Passband ripple      | Stopband attenuation |
+//! |----------|----------------------|----------------------|
+//! | `LOW`    | ±0.01 dB up to 0.70  | 60 dB from 1.0       |
+//! | `MEDIUM` | ±0.001 dB up to 0.83 | 90 dB from 1.0       |
+//! | `HIGH`   | ±0.001 dB up to 0.89 | 110 dB from 1.0      |
+//!
+//! [`ResampleQuality::Linear`] has no filter, it rolls off the highs and aliases everything above
+//! the output Nyquist frequency.
+
+use super::super::{
+    AudioInfo,
+    buffer::AudioBuffer,
+    decoder::LgDecoder,
+    sample::{Sample, SampleType},
+};
+use super::bessel_i0;
+
+/// Table entries per zero crossing of the sinc.
+const TABLE_PHASES: usize = 1024;
+/// Frames read from the wrapped decoder at a time.
+const STREAM_CHUNK_FRAMES: usize = 1024;
+
+/// Kaiser windowed sinc filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SincParams {
+    /// Zero crossings on each side of the sinc, the cost grows linearly with it.
+    pub half_taps: usize,
+    /// Kaiser window shape, the stopband attenuation is about `beta / 0.1102 + 8.7` dB.
+    pub beta: f64,
+    /// Where the filter is at -6 dB, as a fraction of the lower Nyquist frequency.
+    /// Lower values leave more room for the transition band, so less aliasing but duller highs.
+    pub cutoff: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResampleQuality {
+    /// Linear interpolation between two frames.
+    Linear,
+    Sinc(SincParams),
+}
+impl ResampleQuality {
+    pub const LOW: Self = Self::Sinc(SincParams {
+        half_taps: 12,
+        beta: 6.0,
+        cutoff: 0.85,
+    });
+    pub const MEDIUM: Self = Self::Sinc(SincParams {
+        half_taps: 32,
+        beta: 9.0,
+        cutoff: 0.91,
+    });
+    pub const HIGH: Self = Self::Sinc(SincParams {
+        half_taps: 64,
+        beta: 11.5,
+        cutoff: 0.94,
+    });
+}
+impl Default for ResampleQuality {
+    fn default() -> Self {
+        Self::MEDIUM
+    }
+}
+
+/// Streaming resampler over interleaved samples.
+///
+/// The output is aligned with the input, frame `n` of the output is at `n / ratio` in the input,
+/// the input before the first frame is treated as silence.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    channels: usize,
+    /// Output rate / input rate.
+    ratio: f64,
+    quality: ResampleQuality,
+    /// Half of the windowed sinc, from 0 to `half_taps` zero crossings.
+    table: Vec<f32>,
+
+    /// Interleaved input, starting at `first_frame`.
+    input: Vec<f32>,
+    first_frame: usize,
+    /// Position of the next output frame, in input frames.
+    position: f64,
+    ended: bool,
+
+    weights: Vec<f32>,
+}
+impl Resampler {
+    pub fn new(channels: usize, ratio: f64, quality: ResampleQuality) -> Self {
+        let table = match quality {
+            ResampleQuality::Linear => Vec::new(),
+            ResampleQuality::Sinc(params) => sinc_table(params),
+        };
+
+        let mut result = Self {
+            channels: channels.max(1),
+            ratio: 1.0,
+            quality,
+            table,
+            input: Vec::new(),
+            first_frame: 0,
+            position: 0.0,
+            ended: false,
+            weights: Vec::new(),
+        };
+        result.set_ratio(ratio);
+
+        result
+    }
+
+    /// From `input_rate` to `output_rate`.
+    pub fn from_rates(
+        channels: usize,
+        input_rate: u32,
+        output_rate: u32,
+        quality: ResampleQuality,
+    ) -> Self {
+        Self::new(
+            channels,
+            output_rate as f64 / input_rate.max(1) as f64,
+            quality,
+        )
+    }
+
+    #[inline(always)]
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Output rate / input rate, takes effect from the next output frame.
+    /// Can be changed at any time, like every chunk for a doppler effect.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        if ratio.is_finite() && ratio > 0.0 {
+            self.ratio = ratio;
+        }
+    }
+
+    #[inline(always)]
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    #[inline(always)]
+    pub fn quality(&self) -> ResampleQuality {
+        self.quality
+    }
+
+    /// Resamples the interleaved `input`, appending the frames that are ready to the `output`.
+    /// The last frames are only ready once the following input arrives, or on [`Resampler::flush`].
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.input.extend_from_slice(input);
+        self.produce(output);
+    }
+
+    /// Ends the input, appending the remaining frames to the `output`.
+    /// The output then has `ceil(input_frames * ratio)` frames in total, for a constant ratio.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        self.ended = true;
+        self.produce(output);
+    }
+
+    /// Reserves room for inputs of up to `input_frames` frames while the ratio stays above
+    /// `lowest_ratio`, so [`Resampler::process`] and [`Resampler::flush`] don't allocate.
+    /// Returns the most input frames held back until the following input arrives.
+    pub fn reserve(&mut self, input_frames: usize, lowest_ratio: f64) -> usize {
+        let (reach, _) = self.reach_at(lowest_ratio.min(self.ratio));
+
+        // Frames before the position are kept too, in case the reach grows.
+        let frames = input_frames + reach * 3 + 2;
+        self.input
+            .reserve((frames * self.channels).saturating_sub(self.input.len()));
+        self.weights.reserve(reach * 2 + 2);
+
+        reach + 1
+    }
+
+    /// Back to the start, for a new input.
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.first_frame = 0;
+        self.position = 0.0;
+        self.ended = false;
+    }
+}
+impl Resampler {
+    /// Frames needed on each side of the output position, and the filter cutoff.
+    fn reach(&self) -> (usize, f64) {
+        self.reach_at(self.ratio)
+    }
+
+    fn reach_at(&self, ratio: f64) -> (usize, f64) {
+        match self.quality {
+            ResampleQuality::Linear => (1, 1.0),
+            ResampleQuality::Sinc(params) => {
+                let cutoff = params.cutoff * ratio.min(1.0);
+
+                ((params.half_taps as f64 / cutoff).ceil() as usize, cutoff)
+            }
+        }
+    }
+
+    fn input_frames(&self) -> usize {
+        self.first_frame + self.input.len() / self.channels
+    }
+
+    fn produce(&mut self, output: &mut Vec<f32>) {
+        let (reach, cutoff) = self.reach();
+
+        loop {
+            let input_frames = self.input_frames();
+            let center = self.position.floor() as usize;
+
+            if self.ended {
+                if self.position >= input_frames as f64 {
+                    break;
+                }
+            } else if center + reach >= input_frames {
+                break;
+            }
+
+            let last = (center + reach).min(input_frames - 1);
+            self.weights.clear();
+
+            // The first frame with a weight, the ones before the input are silence.
+            let start = match self.quality {
+                ResampleQuality::Linear => {
+                    let fraction = (self.position - center as f64) as f32;
+                    self.weights.push(1.0 - fraction);
+                    if last > center {
+                        self.weights.push(fraction);
+                    }
+
+                    center
+                }
+                ResampleQuality::Sinc(params) => {
+                    let start = (center + 1).saturating_sub(reach).max(self.first_frame);
+                    for frame in start..=last {
+                        let distance = ((self.position - frame as f64) * cutoff).abs();
+                        self.weights.push(
+                            table_value(&self.table, params.half_taps, distance) * cutoff as f32,
+                        );
+                    }
+
+                    start
+                }
+            };
+
+            let offset = (start - self.first_frame) * self.channels;
+            for channel in 0..self.channels {
+                let mut value = 0.0;
+                for (i, weight) in self.weights.iter().enumerate() {
+                    value += self.input[offset + i * self.channels + channel] * weight;
+                }
+
+                output.push(value);
+            }
+
+            self.position += 1.0 / self.ratio;
+        }
+
+        // The reach can grow with the ratio, so some extra frames are kept.
+        let keep_from = (self.position.floor() as usize)
+            .saturating_sub(reach * 2)
+            .max(self.first_frame)
+            .min(self.input_frames());
+        self.input
+            .drain(..(keep_from - self.first_frame) * self.channels);
+        self.first_frame = keep_from;
+    }
+}
+
+/// Resamples the whole buffer, the output has `ceil(frames * sample_rate / buffer_rate)` frames.
+pub fn resample(buffer: &AudioBuffer, sample_rate: u32, quality: ResampleQuality) -> AudioBuffer {
+    let mut resampler = Resampler::from_rates(
+        buffer.channels(),
+        buffer.info.sample_rate,
+        sample_rate,
+        quality,
+    );
+
+    let mut samples = Vec::with_capacity(
+        (buffer.samples.len() as f64 * resampler.ratio()).ceil() as usize + buffer.channels(),
+    );
+    resampler.process(&buffer.samples, &mut samples);
+    resampler.flush(&mut samples);
+
+    AudioBuffer::new(
+        AudioInfo {
+            sample_rate,
+            ..buffer.info
+        },
+        samples,
+    )
+}
+
+/// Resamples any decoder while it is decoded, keeping its sample format.
+pub struct LgResampleDecoder<D: LgDecoder> {
+    decoder: D,
+    resampler: Resampler,
+    info: AudioInfo,
+
+    input: Vec<f32>,
+    output: Vec<f32>,
+    cursor: usize,
+}
+impl<D: LgDecoder> LgResampleDecoder<D> {
+    pub fn new(decoder: D, sample_rate: u32, quality: ResampleQuality) -> Self {
+        let inner_info = decoder.info();
+
+        Self {
+            resampler: Resampler::from_rates(
+                inner_info.channels as usize,
+                inner_info.sample_rate,
+                sample_rate,
+                quality,
+            ),
+            info: AudioInfo {
+                sample_rate,
+                ..inner_info
+            },
+            decoder,
+            input: Vec::new(),
+            output: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn ratio(&self) -> f64 {
+        self.resampler.ratio()
+    }
+
+    /// Changes the ratio from the next samples, [`LgDecoder::info`] keeps the rate given on creation.
+    #[inline(always)]
+    pub fn set_ratio(&mut self, ratio: f64) {
+        self.resampler.set_ratio(ratio);
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &D {
+        &self.decoder
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+impl<D: LgDecoder> LgResampleDecoder<D> {
+    /// False once everything was resampled.
+    fn refill(&mut self) -> bool {
+        self.output.clear();
+        self.cursor = 0;
+
+        while self.output.is_empty() {
+            if self.resampler.ended {
+                return false;
+            }
+
+            self.input.clear();
+            self.input.extend(
+                self.decoder
+                    .samples::<f32>()
+                    .take(STREAM_CHUNK_FRAMES * self.resampler.channels),
+            );
+
+            if self.input.is_empty() {
+                self.resampler.flush(&mut self.output);
+            } else {
+                self.resampler.process(&self.input, &mut self.output);
+            }
+        }
+
+        true
+    }
+}
+impl<D: LgDecoder> LgDecoder for LgResampleDecoder<D> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn samples<S: Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+
+        std::iter::from_fn(move || {
+            if self.cursor >= self.output.len() && !self.refill() {
+                return None;
+            }
+
+            self.cursor += 1;
+            Some(S::from_f32(
+                self.output[self.cursor - 1],
+                sample_type,
+                bits_per_sample,
+            ))
+        })
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.len() / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    /// Expected length with the ratio given on creation.
+    fn len(&self) -> usize {
+        let frames = self.decoder.len() / self.resampler.channels;
+        let ratio = self.info.sample_rate as f64 / self.decoder.info().sample_rate.max(1) as f64;
+
+        (frames as f64 * ratio).ceil() as usize * self.resampler.channels
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.decoder.is_empty()
+    }
+}
+
+/// The windowed sinc at `distance` zero crossings, interpolated from the table.
+#[inline(always)]
+fn table_value(table: &[f32], half_taps: usize, distance: f64) -> f32 {
+    if distance >= half_taps as f64 {
+        return 0.0;
+    }
+
+    let position = distance * TABLE_PHASES as f64;
+    let index = position as usize;
+    let fraction = (position - index as f64) as f32;
+
+    table[index] + (table[index + 1] - table[index]) * fraction
+}
+
+fn sinc_table(params: SincParams) -> Vec<f32> {
+    let half_taps = params.half_taps.max(1);
+    let len = half_taps * TABLE_PHASES + 2;
+    let i0_beta = bessel_i0(params.beta);
+
+    (0..len)
+        .map(|i| {
+            let x = i as f64 / TABLE_PHASES as f64;
+            let t = x / half_taps as f64;
+            if t >= 1.0 {
+                return 0.0;
+            }
+
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x)
+            };
+            let window = bessel_i0(params.beta * (1.0 - t * t).sqrt()) / i0_beta;
+
+            (sinc * window) as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Checks the table of the module documentation.
+
+    use super::*;
+
+    const RATES: [u32; 4] = [22_050, 44_100, 48_000, 96_000];
+    const AMPLITUDE: f64 = 0.5;
+
+    /// Resamples a sine of `frequency` Hz, keeping the output away from the silence around it.
+    fn resample_sine(
+        quality: ResampleQuality,
+        input_rate: u32,
+        output_rate: u32,
+        frequency: f64,
+    ) -> Vec<(usize, f64)> {
+        let omega = std::f64::consts::TAU * frequency / input_rate as f64;
+        let input: Vec<f32> = (0..input_rate as usize / 10)
+            .map(|i| (AMPLITUDE * (omega * i as f64).sin()) as f32)
+            .collect();
+
+        let mut resampler = Resampler::from_rates(1, input_rate, output_rate, quality);
+        let mut output = Vec::new();
+        resampler.process(&input, &mut output);
+        resampler.flush(&mut output);
+
+        let margin = output_rate as usize / 100;
+        output
+            .iter()
+            .enumerate()
+            .take(output.len() - margin)
+            .skip(margin)
+            .map(|(n, value)| (n, *value as f64))
+            .collect()
+    }
+
+    /// Level in dB relative to the sine.
+    fn level(squares: f64, len: usize) -> f64 {
+        10.0 * (squares / len as f64 / (AMPLITUDE * AMPLITUDE / 2.0)).log10()
+    }
+
+    /// Least squares fit of the sine, returns its gain in dB and the level of everything else,
+    /// the images and aliases.
+    fn fit(output: &[(usize, f64)], output_rate: u32, frequency: f64) -> (f64, f64) {
+        let omega = std::f64::consts::TAU * frequency / output_rate as f64;
+
+        let (mut ss, mut sc, mut cc, mut ys, mut yc) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (n, y) in output {
+            let (s, c) = (omega * *n as f64).sin_cos();
+            ss += s * s;
+            sc += s * c;
+            cc += c * c;
+            ys += y * s;
+            yc += y * c;
+        }
+        let determinant = ss * cc - sc * sc;
+        let a = (ys * cc - yc * sc) / determinant;
+        let b = (yc * ss - ys * sc) / determinant;
+
+        let residual: f64 = output
+            .iter()
+            .map(|(n, y)| {
+                let (s, c) = (omega * *n as f64).sin_cos();
+                (y - a * s - b * c).powi(2)
+            })
+            .sum();
+
+        (
+            20.0 * (a.hypot(b) / AMPLITUDE).log10(),
+            level(residual, output.len()),
+        )
+    }
+
+    fn check(quality: ResampleQuality, ripple: f64, passband: f64, stopband: f64) {
+        for input_rate in RATES {
+            for output_rate in RATES.into_iter().filter(|rate| *rate != input_rate) {
+                let nyquist = input_rate.min(output_rate) as f64 / 2.0;
+
+                for step in 1..=20 {
+                    let frequency = passband * nyquist * step as f64 / 20.0;
+                    let output = resample_sine(quality, input_rate, output_rate, frequency);
+                    let (gain, rest) = fit(&output, output_rate, frequency);
+
+                    assert!(
+                        gain.abs() <= ripple,
+                        "{input_rate} -> {output_rate}, {frequency} Hz: {gain} dB"
+                    );
+                    assert!(
+                        rest <= -stopband,
+                        "{input_rate} -> {output_rate}, {frequency} Hz: images at {rest} dB"
+                    );
+                }
+
+                // Only a lower output rate has input above its Nyquist frequency.
+                if output_rate < input_rate {
+                    let input_nyquist = input_rate as f64 / 2.0;
+                    for step in 0..10 {
+                        let frequency =
+                            nyquist + (0.98 * input_nyquist - nyquist) * step as f64 / 9.0;
+                        let output = resample_sine(quality, input_rate, output_rate, frequency);
+                        let squares: f64 = output.iter().map(|(_, y)| y * y).sum();
+
+                        let aliases = level(squares, output.len());
+                        assert!(
+                            aliases <= -stopband,
+                            "{input_rate} -> {output_rate}, {frequency} Hz: {aliases} dB"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn low() {
+        check(ResampleQuality::LOW, 0.01, 0.70, 60.0);
+    }
+
+    #[test]
+    fn medium() {
+        check(ResampleQuality::MEDIUM, 0.001, 0.83, 90.0);
+    }
+
+    #[test]
+    fn high() {
+        check(ResampleQuality::HIGH, 0.001, 0.89, 110.0);
+    }
+}
@@ -0,0 +1,807 @@
+//! Positional audio for mono sources: distance and cone attenuation, panning to any speaker
+//! layout, doppler and HRTF convolution.
+//!
+//! Positions are in a right handed space, `x` to the right, `y` up and `-z` forward, in any unit
+//! as long as the speed of sound of [`Doppler`] is in the same one. Panning is 2D VBAP over the
+//! speakers around the listener, following the direction in its horizontal plane. Layouts
+//! without speakers behind, like stereo, mirror what is behind to the front. The LFE and height
+//! speakers are left silent.
+
+use super::super::{AudioInfo, Result, buffer::AudioBuffer, error::Error, wav::LgWavDecoder};
+use super::{ChannelLayout, ResampleQuality, Resampler, Speaker, resample};
+use std::{
+    ops::{Add, Mul, Neg, Sub},
+    path::Path,
+    sync::Arc,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+impl Vec3 {
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+    pub const UP: Self = Self::new(0.0, 1.0, 0.0);
+    pub const FORWARD: Self = Self::new(0.0, 0.0, -1.0);
+
+    #[inline(always)]
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    #[inline(always)]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    #[inline(always)]
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    #[inline(always)]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Zero stays zero.
+    pub fn normalized(self) -> Self {
+        let length = self.length();
+        if length > 0.0 {
+            self * (1.0 / length)
+        } else {
+            self
+        }
+    }
+}
+impl Add for Vec3 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+impl Sub for Vec3 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+impl Mul<f32> for Vec3 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+impl Neg for Vec3 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub forward: Vec3,
+    pub up: Vec3,
+}
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            forward: Vec3::FORWARD,
+            up: Vec3::UP,
+        }
+    }
+}
+impl Listener {
+    /// `position` in the space of the listener, `x` to its right, `y` above and `z` in front.
+    pub fn local(&self, position: Vec3) -> Vec3 {
+        let forward = self.forward.normalized();
+        let right = forward.cross(self.up).normalized();
+        let up = right.cross(forward);
+        let relative = position - self.position;
+
+        Vec3::new(relative.dot(right), relative.dot(up), relative.dot(forward))
+    }
+}
+
+/// Directional emitter, full gain inside the inner cone, `outer_gain` outside the outer one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cone {
+    pub direction: Vec3,
+    /// Whole angles of the cones, in degrees.
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub outer_gain: f32,
+}
+impl Cone {
+    /// Gain heard from the `listener` position by an emitter at `position`.
+    pub fn gain(&self, position: Vec3, listener: Vec3) -> f32 {
+        let to_listener = (listener - position).normalized();
+        let direction = self.direction.normalized();
+        if to_listener == Vec3::ZERO || direction == Vec3::ZERO {
+            return 1.0;
+        }
+
+        let angle = to_listener
+            .dot(direction)
+            .clamp(-1.0, 1.0)
+            .acos()
+            .to_degrees();
+        let (inner, outer) = (
+            self.inner_angle / 2.0,
+            self.outer_angle.max(self.inner_angle) / 2.0,
+        );
+        if angle <= inner {
+            1.0
+        } else if angle >= outer {
+            self.outer_gain
+        } else {
+            let t = (angle - inner) / (outer - inner);
+            1.0 + (self.outer_gain - 1.0) * t
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub gain: f32,
+    pub cone: Option<Cone>,
+}
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            gain: 1.0,
+            cone: None,
+        }
+    }
+}
+
+/// Models of OpenAL, clamped between the reference and max distances.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DistanceModel {
+    /// `reference / (reference + rolloff * (distance - reference))`.
+    #[default]
+    Inverse,
+    /// `1 - rolloff * (distance - reference) / (max - reference)`.
+    Linear,
+    /// `(distance / reference) ^ -rolloff`.
+    Exponential,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub model: DistanceModel,
+    /// Distance of full gain.
+    pub reference_distance: f32,
+    /// Distance the gain stops changing.
+    pub max_distance: f32,
+    pub rolloff: f32,
+}
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            model: DistanceModel::Inverse,
+            reference_distance: 1.0,
+            max_distance: 1000.0,
+            rolloff: 1.0,
+        }
+    }
+}
+impl Attenuation {
+    pub fn gain(&self, distance: f32) -> f32 {
+        let reference = self.reference_distance.max(f32::EPSILON);
+        let max = self.max_distance.max(reference);
+        let distance = distance.clamp(reference, max);
+
+        match self.model {
+            DistanceModel::Inverse => {
+                reference / (reference + self.rolloff * (distance - reference))
+            }
+            DistanceModel::Linear if max > reference => {
+                (1.0 - self.rolloff * (distance - reference) / (max - reference)).clamp(0.0, 1.0)
+            }
+            DistanceModel::Linear => 1.0,
+            DistanceModel::Exponential => (distance / reference).powf(-self.rolloff),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Doppler {
+    /// In units of the positions per second.
+    pub speed_of_sound: f32,
+    /// Exaggerates or reduces the effect, 0.0 disables it.
+    pub factor: f32,
+}
+impl Default for Doppler {
+    fn default() -> Self {
+        Self {
+            speed_of_sound: 343.3,
+            factor: 1.0,
+        }
+    }
+}
+impl Doppler {
+    /// Pitch of the emitter as heard by the listener, like OpenAL.
+    pub fn pitch(&self, listener: &Listener, emitter: &Emitter) -> f32 {
+        let to_listener = listener.position - emitter.position;
+        let distance = to_listener.length();
+        if distance <= 0.0 || self.factor <= 0.0 || self.speed_of_sound <= 0.0 {
+            return 1.0;
+        }
+
+        // Speeds away from the emitter, clamped below the speed of sound.
+        let limit = self.speed_of_sound / self.factor;
+        let listener_speed = (to_listener.dot(listener.velocity) / distance).min(limit);
+        let emitter_speed = (to_listener.dot(emitter.velocity) / distance).min(limit);
+
+        let pitch = (self.speed_of_sound - self.factor * listener_speed)
+            / (self.speed_of_sound - self.factor * emitter_speed);
+        if pitch.is_finite() {
+            pitch.clamp(0.0, 16.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Head related impulse responses, a stereo impulse response for the left and right ears at
+/// every measured direction.
+#[derive(Debug, Clone)]
+pub struct HrtfSet {
+    sample_rate: u32,
+    taps: usize,
+    /// Directions in the space of the listener, with the left and right impulse responses.
+    responses: Vec<(Vec3, Vec<f32>, Vec<f32>)>,
+}
+impl HrtfSet {
+    /// `responses` has the azimuth, clockwise from the front, and elevation, in degrees, of stereo
+    /// impulse responses. They are resampled to the `sample_rate`.
+    pub fn new(sample_rate: u32, responses: Vec<(f32, f32, AudioBuffer)>) -> Result<Self> {
+        if sample_rate == 0 || responses.is_empty() {
+            return Err(Error::Custom(
+                "HRTF needs a sample_rate > 0 and impulse responses!".to_string(),
+            ));
+        }
+
+        let mut result = Self {
+            sample_rate,
+            taps: 0,
+            responses: Vec::with_capacity(responses.len()),
+        };
+        for (azimuth, elevation, mut buffer) in responses {
+            if buffer.channels() != 2 {
+                return Err(Error::WrongFmtInfo(format!(
+                    "HRTF impulse responses must be stereo, got {} channels!",
+                    buffer.channels()
+                )));
+            }
+            if buffer.info.sample_rate != sample_rate {
+                buffer = resample(&buffer, sample_rate, ResampleQuality::HIGH);
+            }
+
+            let (left, right) = buffer.samples.chunks_exact(2).map(|f| (f[0], f[1])).unzip();
+            result.taps = result.taps.max(buffer.frames());
+            result
+                .responses
+                .push((direction(azimuth, elevation), left, right));
+        }
+        if result.taps == 0 {
+            return Err(Error::Custom(
+                "HRTF impulse responses are empty!".to_string(),
+            ));
+        }
+        for (_, left, right) in &mut result.responses {
+            left.resize(result.taps, 0.0);
+            right.resize(result.taps, 0.0);
+        }
+
+        Ok(result)
+    }
+
+    /// Decodes the impulse responses with [`LgWavDecoder`].
+    pub fn from_wavs(sample_rate: u32, responses: &[(f32, f32, impl AsRef<Path>)]) -> Result<Self> {
+        let responses = responses
+            .iter()
+            .map(|(azimuth, elevation, path)| {
+                let mut decoder = LgWavDecoder::new(path)?;
+                Ok((
+                    *azimuth,
+                    *elevation,
+                    AudioBuffer::from_decoder(&mut decoder),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Self::new(sample_rate, responses)
+    }
+
+    #[inline(always)]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Length of the impulse responses.
+    #[inline(always)]
+    pub fn taps(&self) -> usize {
+        self.taps
+    }
+
+    /// Index of the response measured closest to the `local` direction.
+    pub fn nearest(&self, local: Vec3) -> usize {
+        let local = local.normalized();
+
+        self.responses
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.0.dot(local).total_cmp(&b.0.dot(local)))
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// Spatializes a mono source to a speaker layout, block by block. Changes of the listener and
+/// emitter are smoothed over the next block.
+pub struct Spatializer {
+    layout: ChannelLayout,
+    sample_rate: u32,
+    /// Channel and azimuth of the speakers used for panning, sorted by azimuth.
+    speakers: Vec<(usize, f32)>,
+
+    listener: Listener,
+    emitter: Emitter,
+    attenuation: Attenuation,
+    doppler: Option<(Doppler, Resampler)>,
+    hrtf: Option<Arc<HrtfSet>>,
+
+    /// Gains of every channel, or of both ears with HRTF, at the end of the last block.
+    gains: Option<Vec<f32>>,
+    response: Option<usize>,
+    mono: Vec<f32>,
+    targets: Vec<f32>,
+    /// Input of the HRTF, from the last `taps - 1` samples of the previous block.
+    signal: Vec<f32>,
+}
+impl Spatializer {
+    pub fn new(layout: ChannelLayout, sample_rate: u32) -> Result<Self> {
+        let mut speakers: Vec<(usize, f32)> = layout
+            .speakers
+            .iter()
+            .enumerate()
+            .filter_map(|(channel, speaker)| Some((channel, azimuth(*speaker)?)))
+            .collect();
+        speakers.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        if speakers.is_empty() || sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Spatializing needs a sample_rate and a layout with speakers around the listener!"
+                    .to_string(),
+            ));
+        }
+
+        Ok(Self {
+            layout,
+            sample_rate,
+            speakers,
+            listener: Listener::default(),
+            emitter: Emitter::default(),
+            attenuation: Attenuation::default(),
+            doppler: None,
+            hrtf: None,
+            gains: None,
+            response: None,
+            mono: Vec::new(),
+            targets: Vec::new(),
+            signal: Vec::new(),
+        })
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// Shifts the pitch by resampling, so the output has more or fewer frames than the input.
+    pub fn with_doppler(mut self, doppler: Doppler) -> Self {
+        self.doppler = Some((doppler, Resampler::new(1, 1.0, ResampleQuality::LOW)));
+        self
+    }
+
+    /// Convolves with the response closest to the emitter instead of panning, for a stereo
+    /// layout at the sample rate of the set.
+    pub fn with_hrtf(mut self, hrtf: Arc<HrtfSet>) -> Result<Self> {
+        if self.layout.channels() != 2 || hrtf.sample_rate != self.sample_rate {
+            return Err(Error::WrongFmtInfo(format!(
+                "HRTF of {} Hz can't output {} channels at {} Hz!",
+                hrtf.sample_rate,
+                self.layout.channels(),
+                self.sample_rate
+            )));
+        }
+
+        self.signal = vec![0.0; hrtf.taps - 1];
+        self.hrtf = Some(hrtf);
+        Ok(self)
+    }
+
+    #[inline(always)]
+    pub fn layout(&self) -> &ChannelLayout {
+        &self.layout
+    }
+
+    #[inline(always)]
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    #[inline(always)]
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+    }
+
+    #[inline(always)]
+    pub fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    #[inline(always)]
+    pub fn set_emitter(&mut self, emitter: Emitter) {
+        self.emitter = emitter;
+    }
+
+    /// Gain of the emitter, its distance and cone.
+    pub fn gain(&self) -> f32 {
+        let distance = (self.emitter.position - self.listener.position).length();
+        let cone = self.emitter.cone.map_or(1.0, |cone| {
+            cone.gain(self.emitter.position, self.listener.position)
+        });
+
+        self.emitter.gain * self.attenuation.gain(distance) * cone
+    }
+
+    /// Doppler pitch of the emitter, 1.0 without doppler.
+    pub fn pitch(&self) -> f32 {
+        self.doppler.as_ref().map_or(1.0, |(doppler, _)| {
+            doppler.pitch(&self.listener, &self.emitter)
+        })
+    }
+
+    /// Gains of every channel, with the gain of the emitter.
+    pub fn channel_gains(&self) -> Vec<f32> {
+        let mut gains = vec![0.0; self.layout.channels()];
+        let local = self.listener.local(self.emitter.position);
+        let gain = self.gain();
+
+        for (channel, pan) in vbap(&self.speakers, local) {
+            gains[channel] = pan * gain;
+        }
+
+        gains
+    }
+
+    /// Appends the spatialized frames of the mono `input` that are ready to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.mono.clear();
+        match &mut self.doppler {
+            Some((doppler, resampler)) => {
+                let pitch = doppler.pitch(&self.listener, &self.emitter);
+                resampler.set_ratio(1.0 / pitch.max(1e-3) as f64);
+                resampler.process(input, &mut self.mono);
+            }
+            None => self.mono.extend_from_slice(input),
+        }
+
+        self.render(output);
+    }
+
+    /// Appends the frames held by the doppler and the tail of the HRTF.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        self.mono.clear();
+        if let Some((_, resampler)) = &mut self.doppler {
+            resampler.flush(&mut self.mono);
+        }
+        if let Some(hrtf) = &self.hrtf {
+            self.mono.resize(self.mono.len() + hrtf.taps - 1, 0.0);
+        }
+
+        self.render(output);
+    }
+
+    /// Back to the start, for a new input.
+    pub fn reset(&mut self) {
+        if let Some((_, resampler)) = &mut self.doppler {
+            resampler.reset();
+        }
+        self.signal.fill(0.0);
+        self.gains = None;
+        self.response = None;
+    }
+}
+impl Spatializer {
+    fn render(&mut self, output: &mut Vec<f32>) {
+        let local = self.listener.local(self.emitter.position);
+        self.targets = match &self.hrtf {
+            Some(_) => vec![self.gain(); 2],
+            None => self.channel_gains(),
+        };
+        let gains = self.gains.get_or_insert_with(|| self.targets.clone());
+
+        let frames = self.mono.len();
+        let ramp = 1.0 / frames.max(1) as f32;
+        let start = output.len();
+
+        match &self.hrtf {
+            Some(hrtf) => {
+                let response = hrtf.nearest(local);
+                let previous = self.response.unwrap_or(response);
+                self.response = Some(response);
+
+                let taps = hrtf.taps;
+                self.signal.truncate(taps - 1);
+                self.signal.extend_from_slice(&self.mono);
+
+                for i in 0..frames {
+                    let window = &self.signal[i..i + taps];
+                    let (left, right) = convolve(&hrtf.responses[response], window);
+                    let t = (i + 1) as f32 * ramp;
+
+                    // Crossfades from the previous response, when it changed.
+                    let (left, right) = if previous == response {
+                        (left, right)
+                    } else {
+                        let (old_left, old_right) = convolve(&hrtf.responses[previous], window);
+                        (
+                            old_left + (left - old_left) * t,
+                            old_right + (right - old_right) * t,
+                        )
+                    };
+
+                    output.push(left * (gains[0] + (self.targets[0] - gains[0]) * t));
+                    output.push(right * (gains[1] + (self.targets[1] - gains[1]) * t));
+                }
+
+                self.signal.drain(..frames);
+            }
+            None => {
+                for (i, sample) in self.mono.iter().enumerate() {
+                    let t = (i + 1) as f32 * ramp;
+                    output.extend(
+                        gains
+                            .iter()
+                            .zip(&self.targets)
+                            .map(|(gain, target)| sample * (gain + (target - gain) * t)),
+                    );
+                }
+            }
+        }
+
+        debug_assert_eq!(output.len() - start, frames * self.targets.len());
+        gains.copy_from_slice(&self.targets);
+    }
+}
+
+/// Spatializes a mono buffer from a fixed listener and emitter.
+pub fn spatialize(
+    buffer: &AudioBuffer,
+    layout: ChannelLayout,
+    listener: Listener,
+    emitter: Emitter,
+    attenuation: Attenuation,
+) -> Result<AudioBuffer> {
+    if buffer.channels() != 1 {
+        return Err(Error::WrongFmtInfo(format!(
+            "Only mono sounds can be spatialized, got {} channels!",
+            buffer.channels()
+        )));
+    }
+
+    let channels = layout.channels();
+    let mut spatializer =
+        Spatializer::new(layout, buffer.info.sample_rate)?.with_attenuation(attenuation);
+    spatializer.set_listener(listener);
+    spatializer.set_emitter(emitter);
+
+    let mut samples = Vec::with_capacity(buffer.samples.len() * channels);
+    spatializer.process(&buffer.samples, &mut samples);
+    spatializer.flush(&mut samples);
+
+    Ok(AudioBuffer::new(
+        AudioInfo {
+            channels: channels as u16,
+            ..buffer.info
+        },
+        samples,
+    ))
+}
+
+/// Clockwise from the front, in degrees, of the speakers around the listener.
+fn azimuth(speaker: Speaker) -> Option<f32> {
+    match speaker {
+        Speaker::FrontCenter => Some(0.0),
+        Speaker::FrontLeft => Some(-30.0),
+        Speaker::FrontRight => Some(30.0),
+        Speaker::FrontLeftOfCenter => Some(-15.0),
+        Speaker::FrontRightOfCenter => Some(15.0),
+        Speaker::SideLeft => Some(-90.0),
+        Speaker::SideRight => Some(90.0),
+        Speaker::BackLeft => Some(-110.0),
+        Speaker::BackRight => Some(110.0),
+        Speaker::BackCenter => Some(180.0),
+        _ => None,
+    }
+}
+
+/// Unit vector of an azimuth and elevation, in the space of the listener.
+fn direction(azimuth: f32, elevation: f32) -> Vec3 {
+    let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+    Vec3::new(
+        azimuth.sin() * elevation.cos(),
+        elevation.sin(),
+        azimuth.cos() * elevation.cos(),
+    )
+}
+
+/// Channels and gains, of constant power, panning to the horizontal direction of `local`.
+fn vbap(speakers: &[(usize, f32)], local: Vec3) -> Vec<(usize, f32)> {
+    if speakers.len() == 1 {
+        return vec![(speakers[0].0, 1.0)];
+    }
+    // Right above, below or on the listener, every speaker plays it.
+    if local.x.hypot(local.z) <= f32::EPSILON {
+        let gain = (speakers.len() as f32).sqrt().recip();
+        return speakers
+            .iter()
+            .map(|(channel, _)| (*channel, gain))
+            .collect();
+    }
+
+    let azimuth = local.x.atan2(local.z).to_degrees();
+    // Without speakers behind, it comes from the front like stereo does.
+    let mirrored = 180.0_f32.copysign(azimuth) - azimuth;
+
+    for azimuth in [azimuth, mirrored] {
+        for (i, first) in speakers.iter().enumerate() {
+            let second = &speakers[(i + 1) % speakers.len()];
+            let gap = (second.1 - first.1).rem_euclid(360.0);
+            if gap <= 0.0 || gap >= 180.0 {
+                continue;
+            }
+
+            if let Some((a, b)) = pair_gains(first.1, second.1, azimuth) {
+                return vec![(first.0, a), (second.0, b)];
+            }
+        }
+    }
+
+    // Outside of every pair, the nearest speaker plays it.
+    let nearest = speakers
+        .iter()
+        .min_by(|a, b| {
+            let distance = |s: f32| (s - azimuth + 180.0).rem_euclid(360.0) - 180.0;
+            distance(a.1).abs().total_cmp(&distance(b.1).abs())
+        })
+        .map_or(0, |(channel, _)| *channel);
+
+    vec![(nearest, 1.0)]
+}
+
+/// Gains of two speakers for a source between them, normalized to constant power.
+fn pair_gains(first: f32, second: f32, azimuth: f32) -> Option<(f32, f32)> {
+    let unit = |degrees: f32| {
+        let radians = degrees.to_radians();
+        (radians.sin(), radians.cos())
+    };
+    let ((x1, y1), (x2, y2), (x, y)) = (unit(first), unit(second), unit(azimuth));
+
+    let determinant = x1 * y2 - x2 * y1;
+    let a = (x * y2 - x2 * y) / determinant;
+    let b = (x1 * y - x * y1) / determinant;
+    if a < -1e-5 || b < -1e-5 {
+        return None;
+    }
+
+    let (a, b) = (a.max(0.0), b.max(0.0));
+    let power = a.hypot(b);
+    Some((a / power, b / power))
+}
+
+fn convolve(response: &(Vec3, Vec<f32>, Vec<f32>), window: &[f32]) -> (f32, f32) {
+    let (_, left, right) = response;
+    let taps = window.len();
+
+    window
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(l, r), (i, sample)| {
+            (
+                l + sample * left[taps - 1 - i],
+                r + sample * right[taps - 1 - i],
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-6, "{value} is not {expected}");
+    }
+
+    #[test]
+    fn inverse_distance() {
+        let attenuation = Attenuation::default();
+        assert_near(attenuation.gain(1.0), 1.0);
+        assert_near(attenuation.gain(2.0), 0.5);
+        assert_near(attenuation.gain(4.0), 0.25);
+        // Clamped to the reference and max distances.
+        assert_near(attenuation.gain(0.1), 1.0);
+        assert_near(attenuation.gain(5000.0), 0.001);
+
+        let attenuation = Attenuation {
+            reference_distance: 2.0,
+            rolloff: 2.0,
+            ..Default::default()
+        };
+        assert_near(attenuation.gain(4.0), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn other_distance_models() {
+        let linear = Attenuation {
+            model: DistanceModel::Linear,
+            max_distance: 11.0,
+            ..Default::default()
+        };
+        assert_near(linear.gain(6.0), 0.5);
+        assert_near(linear.gain(20.0), 0.0);
+
+        let exponential = Attenuation {
+            model: DistanceModel::Exponential,
+            rolloff: 2.0,
+            ..Default::default()
+        };
+        assert_near(exponential.gain(4.0), 1.0 / 16.0);
+    }
+
+    #[test]
+    fn emitter_gains_follow_the_distance() {
+        let mut spatializer = Spatializer::new(ChannelLayout::stereo(), 48_000).unwrap();
+
+        for distance in [1.0, 2.0, 8.0] {
+            spatializer.set_emitter(Emitter {
+                position: Vec3::FORWARD * distance,
+                gain: 0.5,
+                ..Default::default()
+            });
+            let gain = 0.5 / distance;
+            assert_near(spatializer.gain(), gain);
+
+            // In front, split evenly with the same power.
+            let gains = spatializer.channel_gains();
+            assert_near(gains[0], gains[1]);
+            assert_near(gains[0].powi(2) + gains[1].powi(2), gain * gain);
+        }
+    }
+}
@@ -0,0 +1,454 @@
+//! Time-stretching and pitch-shifting by WSOLA.
+//!
+//! Windows of the input are overlap-added at a fixed hop in the output, read at the hop divided
+//! by the stretch. Each window is moved, within a tolerance, to where it best continues the last
+//! one, so periodic sounds like voices keep their waveform. The offset is searched on the sum of
+//! the channels and applied to all of them, keeping their phases coherent.
+
+use super::super::{AudioInfo, Result, analysis::Window, buffer::AudioBuffer, error::Error};
+use super::{ResampleQuality, Resampler};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StretchParams {
+    /// Length of the windows, in seconds, longer suits music and shorter suits voices.
+    pub window: f64,
+    /// How far a window can move to fit the last one, in seconds, around the longest period.
+    pub tolerance: f64,
+}
+impl Default for StretchParams {
+    fn default() -> Self {
+        Self {
+            window: 0.03,
+            tolerance: 0.01,
+        }
+    }
+}
+
+/// Changes the duration of interleaved samples as they come, keeping their pitch.
+#[derive(Debug, Clone)]
+pub struct TimeStretcher {
+    channels: usize,
+    stretch: f64,
+    /// Frames of a window, twice the hop in the output.
+    window: Vec<f32>,
+    tolerance: usize,
+
+    /// Input from the frame `input_start`, with half a window of silence before the first frame.
+    input: Vec<f32>,
+    mono: Vec<f32>,
+    input_start: usize,
+    input_frames: usize,
+    /// Where the next window is read in the input, before moving it.
+    position: f64,
+    /// Where the last window was read.
+    last: Option<usize>,
+    /// Output from the frame `output_start`, shifted by half a window like the input.
+    output: Vec<f32>,
+    output_start: usize,
+    windows: usize,
+}
+impl TimeStretcher {
+    /// `stretch` is the output duration over the input duration.
+    pub fn new(info: AudioInfo, stretch: f64, params: StretchParams) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Stretching needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+        check_stretch(stretch)?;
+
+        let rate = info.sample_rate as f64;
+        let frames = ((params.window * rate / 2.0).round() as usize).max(8) * 2;
+        let channels = info.channels as usize;
+
+        Ok(Self {
+            channels,
+            stretch,
+            window: Window::Hann.coefficients(frames),
+            tolerance: (params.tolerance.max(0.0) * rate).round() as usize,
+            input: vec![0.0; frames / 2 * channels],
+            mono: vec![0.0; frames / 2],
+            input_start: 0,
+            input_frames: 0,
+            position: 0.0,
+            last: None,
+            output: Vec::new(),
+            output_start: 0,
+            windows: 0,
+        })
+    }
+
+    #[inline(always)]
+    pub fn stretch(&self) -> f64 {
+        self.stretch
+    }
+
+    /// Takes effect from the next window.
+    pub fn set_stretch(&mut self, stretch: f64) -> Result<()> {
+        check_stretch(stretch)?;
+        self.stretch = stretch;
+
+        Ok(())
+    }
+
+    /// Appends the frames that are ready to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        for frame in input.chunks_exact(self.channels) {
+            self.input.extend_from_slice(frame);
+            self.mono.push(frame.iter().sum());
+        }
+        self.input_frames += input.len() / self.channels;
+
+        while self.next_window(false) {}
+        self.give(None, output);
+    }
+
+    /// Ends the input, appending the remaining frames to the `output`. For a constant stretch
+    /// the output has `round(input_frames * stretch)` frames in total.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let end = self.input_frames as f64;
+        let hop = self.window.len() / 2;
+        // Until the windows cover the end in the output.
+        while self.position < end + hop as f64 / self.stretch && self.next_window(true) {}
+
+        // The next window would read past the end, as far as its hop in the output.
+        let overshoot = (self.position - end) * self.stretch;
+        let frames = (self.windows * hop) as f64 - overshoot;
+        self.give(Some(frames.round().max(0.0) as usize), output);
+    }
+
+    /// Back to the start, for a new input.
+    pub fn reset(&mut self) {
+        let half = self.window.len() / 2;
+        self.input.clear();
+        self.input.resize(half * self.channels, 0.0);
+        self.mono.clear();
+        self.mono.resize(half, 0.0);
+        self.input_start = 0;
+        self.input_frames = 0;
+        self.position = 0.0;
+        self.last = None;
+        self.output.clear();
+        self.output_start = 0;
+        self.windows = 0;
+    }
+}
+impl TimeStretcher {
+    /// Overlap-adds the next window, false if the input doesn't have it yet. Past the `end`,
+    /// the input is padded with silence.
+    fn next_window(&mut self, end: bool) -> bool {
+        let size = self.window.len();
+        let hop = size / 2;
+        let nominal = self.position.round() as usize;
+
+        let (start, search) = match self.last {
+            Some(last) => (nominal.saturating_sub(self.tolerance), Some(last + hop)),
+            None => (nominal, None),
+        };
+        let needed = (nominal + self.tolerance).max(search.unwrap_or(0)) + size;
+        let available = self.input_start + self.mono.len();
+        if needed > available {
+            if !end {
+                return false;
+            }
+
+            let missing = needed - available;
+            self.input
+                .resize(self.input.len() + missing * self.channels, 0.0);
+            self.mono.resize(self.mono.len() + missing, 0.0);
+        }
+
+        let chosen = match search {
+            Some(target) => self.best_match(target, start, nominal + self.tolerance),
+            None => start,
+        };
+
+        let at = self.windows * hop - self.output_start;
+        self.output.resize((at + size) * self.channels, 0.0);
+        let from = (chosen - self.input_start) * self.channels;
+        for (i, gain) in self.window.iter().enumerate() {
+            for channel in 0..self.channels {
+                self.output[(at + i) * self.channels + channel] +=
+                    self.input[from + i * self.channels + channel] * gain;
+            }
+        }
+
+        self.last = Some(chosen);
+        self.windows += 1;
+        self.position += hop as f64 / self.stretch;
+        self.discard();
+
+        true
+    }
+
+    /// Where between `first` and `last` the input best matches the one at `target`.
+    fn best_match(&self, target: usize, first: usize, last: usize) -> usize {
+        let size = self.window.len();
+        let target = &self.mono[target - self.input_start..][..size];
+        let correlation = |candidate: usize, step: usize| {
+            let candidate = &self.mono[candidate - self.input_start..][..size];
+            let (mut product, mut energy) = (0.0, 0.0);
+            for (a, b) in target.iter().zip(candidate).step_by(step) {
+                product += a * b;
+                energy += b * b;
+            }
+
+            if energy > 0.0 {
+                product / energy.sqrt()
+            } else {
+                0.0
+            }
+        };
+        let search = |first: usize, last: usize, step: usize| {
+            (first..=last)
+                .step_by(step)
+                .map(|candidate| (candidate, correlation(candidate, step)))
+                .fold((first, f32::MIN), |best, current| {
+                    if current.1 > best.1 { current } else { best }
+                })
+                .0
+        };
+
+        // Coarse first, then around the best.
+        let step = (size / 256).max(1);
+        let coarse = search(first, last, step);
+        search(
+            coarse.saturating_sub(step).max(first),
+            (coarse + step).min(last),
+            1,
+        )
+    }
+
+    /// Drops the input no window can read anymore.
+    fn discard(&mut self) {
+        let hop = self.window.len() / 2;
+        let next = (self.position.round() as usize).saturating_sub(self.tolerance);
+        let keep = next.min(self.last.map_or(next, |last| last + hop));
+
+        let drop = keep.saturating_sub(self.input_start);
+        if drop > self.mono.len() / 2 {
+            self.input.drain(..drop * self.channels);
+            self.mono.drain(..drop);
+            self.input_start += drop;
+        }
+    }
+
+    /// Appends what no later window adds to, or up to `end` frames of the output once flushed.
+    fn give(&mut self, end: Option<usize>, output: &mut Vec<f32>) {
+        let half = self.window.len() / 2;
+        let ready = match end {
+            // The output starts at half a window, like the input.
+            Some(frames) => frames + half,
+            None => self.windows * half,
+        };
+
+        let ready = ready.saturating_sub(self.output_start);
+        self.output
+            .resize(self.output.len().max(ready * self.channels), 0.0);
+
+        // The first half window is before the first frame of the input.
+        let skip = half.saturating_sub(self.output_start).min(ready);
+        output.extend_from_slice(&self.output[skip * self.channels..ready * self.channels]);
+        self.output.drain(..ready * self.channels);
+        self.output_start += ready;
+    }
+}
+
+/// Changes the pitch of interleaved samples as they come, keeping their duration, by stretching
+/// then resampling them.
+#[derive(Debug, Clone)]
+pub struct PitchShifter {
+    stretcher: TimeStretcher,
+    resampler: Resampler,
+    stretched: Vec<f32>,
+}
+impl PitchShifter {
+    pub fn new(info: AudioInfo, semitones: f64, params: StretchParams) -> Result<Self> {
+        let pitch = pitch_ratio(semitones);
+
+        Ok(Self {
+            stretcher: TimeStretcher::new(info, pitch, params)?,
+            resampler: Resampler::new(info.channels as usize, 1.0 / pitch, ResampleQuality::HIGH),
+            stretched: Vec::new(),
+        })
+    }
+
+    /// Semitones of the shift.
+    #[inline(always)]
+    pub fn semitones(&self) -> f64 {
+        12.0 * self.stretcher.stretch().log2()
+    }
+
+    /// Takes effect from the next window.
+    pub fn set_semitones(&mut self, semitones: f64) -> Result<()> {
+        let pitch = pitch_ratio(semitones);
+        self.stretcher.set_stretch(pitch)?;
+        self.resampler.set_ratio(1.0 / pitch);
+
+        Ok(())
+    }
+
+    /// Appends the frames that are ready to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.stretched.clear();
+        self.stretcher.process(input, &mut self.stretched);
+        self.resampler.process(&self.stretched, output);
+    }
+
+    /// Ends the input, appending the remaining frames to the `output`.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        self.stretched.clear();
+        self.stretcher.flush(&mut self.stretched);
+        self.resampler.process(&self.stretched, output);
+        self.resampler.flush(output);
+    }
+
+    /// Back to the start, for a new input.
+    pub fn reset(&mut self) {
+        self.stretcher.reset();
+        self.resampler.reset();
+    }
+}
+
+/// Stretches a buffer to `stretch` times its duration, keeping its pitch.
+pub fn time_stretch(
+    buffer: &AudioBuffer,
+    stretch: f64,
+    params: StretchParams,
+) -> Result<AudioBuffer> {
+    let mut stretcher = TimeStretcher::new(buffer.info, stretch, params)?;
+
+    let mut samples = Vec::with_capacity((buffer.samples.len() as f64 * stretch) as usize);
+    stretcher.process(&buffer.samples, &mut samples);
+    stretcher.flush(&mut samples);
+
+    Ok(AudioBuffer::new(buffer.info, samples))
+}
+
+/// Stretches a buffer to last `seconds`, like a line fitting the duration of its translation.
+pub fn fit_duration(
+    buffer: &AudioBuffer,
+    seconds: f64,
+    params: StretchParams,
+) -> Result<AudioBuffer> {
+    if buffer.frames() == 0 {
+        return Err(Error::Custom("Can't fit an empty buffer!".to_string()));
+    }
+
+    let frames = (seconds * buffer.info.sample_rate as f64).round();
+    let mut result = time_stretch(buffer, frames / buffer.frames() as f64, params)?;
+    result
+        .samples
+        .resize(frames.max(0.0) as usize * buffer.channels(), 0.0);
+
+    Ok(result)
+}
+
+/// Shifts the pitch of a buffer by `semitones`, keeping its duration.
+pub fn pitch_shift(
+    buffer: &AudioBuffer,
+    semitones: f64,
+    params: StretchParams,
+) -> Result<AudioBuffer> {
+    let mut shifter = PitchShifter::new(buffer.info, semitones, params)?;
+
+    let mut samples = Vec::with_capacity(buffer.samples.len());
+    shifter.process(&buffer.samples, &mut samples);
+    shifter.flush(&mut samples);
+    samples.resize(buffer.samples.len(), 0.0);
+
+    Ok(AudioBuffer::new(buffer.info, samples))
+}
+
+#[inline(always)]
+fn pitch_ratio(semitones: f64) -> f64 {
+    2f64.powf(semitones / 12.0)
+}
+
+fn check_stretch(stretch: f64) -> Result<()> {
+    if !stretch.is_finite() || stretch <= 0.0 {
+        return Err(Error::Custom(format!(
+            "Stretch must be positive, got {stretch}!"
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn sine(channels: u16, frames: usize, frequency: f64) -> AudioBuffer {
+        let info = AudioInfo {
+            channels,
+            sample_rate: RATE,
+            ..Default::default()
+        };
+        let samples = (0..frames)
+            .flat_map(|i| {
+                let value = (std::f64::consts::TAU * frequency * i as f64 / RATE as f64).sin();
+                vec![(value * 0.5) as f32; channels as usize]
+            })
+            .collect();
+
+        AudioBuffer::new(info, samples)
+    }
+
+    #[test]
+    fn stretched_length() {
+        for frames in [1, 100, 4_801, 48_000] {
+            let buffer = sine(2, frames, 440.0);
+
+            for stretch in [0.5, 0.8, 1.0, 1.25, 2.0, 3.3] {
+                let stretched = time_stretch(&buffer, stretch, StretchParams::default()).unwrap();
+                assert_eq!(
+                    stretched.frames(),
+                    (frames as f64 * stretch).round() as usize,
+                    "{frames} frames stretched by {stretch}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn streamed_length() {
+        let buffer = sine(1, 30_000, 440.0);
+        let mut stretcher = TimeStretcher::new(buffer.info, 1.5, StretchParams::default()).unwrap();
+
+        let mut output = Vec::new();
+        for chunk in buffer.samples.chunks(777) {
+            stretcher.process(chunk, &mut output);
+        }
+        stretcher.flush(&mut output);
+
+        assert_eq!(output.len(), 45_000);
+    }
+
+    #[test]
+    fn fitted_and_shifted_lengths() {
+        let buffer = sine(2, 10_000, 440.0);
+
+        let fitted = fit_duration(&buffer, 0.3, StretchParams::default()).unwrap();
+        assert_eq!(fitted.frames(), 14_400);
+
+        let shifted = pitch_shift(&buffer, 7.0, StretchParams::default()).unwrap();
+        assert_eq!(shifted.frames(), buffer.frames());
+    }
+
+    #[test]
+    fn keeps_the_pitch() {
+        let buffer = sine(1, RATE as usize, 1000.0);
+        let stretched = time_stretch(&buffer, 1.5, StretchParams::default()).unwrap();
+
+        // Away from the ends, the sine crosses zero twice a period.
+        let middle = &stretched.samples[RATE as usize / 4..RATE as usize * 5 / 4];
+        let crossings = middle
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        assert!((1990..=2010).contains(&crossings), "{crossings} crossings");
+    }
+}
@@ -0,0 +1,157 @@
+//! Removal of the leading and trailing silence, keeping some of it around the sound.
+
+use super::super::{
+    AudioInfo, Result, analysis::silence::SilenceParams, buffer::AudioBuffer, error::Error,
+};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimParams {
+    /// What counts as silence, the channels are always linked.
+    pub silence: SilenceParams,
+    /// Silence kept before the first sound, in seconds.
+    pub pre_roll: f64,
+    /// Silence kept after the last sound, in seconds.
+    pub post_roll: f64,
+}
+impl Default for TrimParams {
+    fn default() -> Self {
+        Self {
+            silence: SilenceParams::default(),
+            pre_roll: 0.05,
+            post_roll: 0.1,
+        }
+    }
+}
+
+/// Input frames that were kept, from `start` up to `end`, not included.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TrimReport {
+    pub start: usize,
+    pub end: usize,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    /// Frames of the input.
+    pub frames: usize,
+}
+
+/// Trims interleaved samples as they come. Only the silence is held back, never the sound,
+/// so the memory used is the pre-roll plus the current silence.
+#[derive(Debug, Clone)]
+pub struct SilenceTrimmer {
+    channels: usize,
+    sample_rate: u32,
+    threshold: f32,
+    min_frames: usize,
+    pre_roll_frames: usize,
+    post_roll_frames: usize,
+
+    /// Interleaved silent frames not given yet, before or after the sound.
+    held: VecDeque<f32>,
+    /// Input frame of the first held one.
+    held_start: usize,
+    /// Input frame of the first frame given, once sound was found.
+    start: Option<usize>,
+    frame: usize,
+    end: usize,
+}
+impl SilenceTrimmer {
+    pub fn new(info: AudioInfo, params: TrimParams) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Trimming needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        let to_frames = |seconds: f64| (seconds.max(0.0) * info.sample_rate as f64).round();
+
+        Ok(Self {
+            channels: info.channels as usize,
+            sample_rate: info.sample_rate,
+            threshold: params.silence.threshold_amplitude(),
+            min_frames: params.silence.min_frames(info.sample_rate),
+            pre_roll_frames: to_frames(params.pre_roll) as usize,
+            post_roll_frames: to_frames(params.post_roll) as usize,
+            held: VecDeque::new(),
+            held_start: 0,
+            start: None,
+            frame: 0,
+            end: 0,
+        })
+    }
+
+    /// Appends the frames known to be kept to the `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        for frame in input.chunks_exact(self.channels) {
+            let silent = frame.iter().all(|sample| sample.abs() < self.threshold);
+
+            if silent {
+                self.held.extend(frame);
+
+                // Before the sound only the pre-roll is needed, once the silence is long enough.
+                let held_frames = self.held.len() / self.channels;
+                if self.start.is_none()
+                    && self.frame + 1 >= self.min_frames
+                    && held_frames > self.pre_roll_frames
+                {
+                    let dropped = held_frames - self.pre_roll_frames;
+                    self.held.drain(..dropped * self.channels);
+                    self.held_start += dropped;
+                }
+            } else {
+                // A silence shorter than the minimum, or the pre-roll, is kept.
+                self.start.get_or_insert(self.held_start);
+                output.extend(self.held.drain(..));
+                output.extend_from_slice(frame);
+                self.held_start = self.frame + 1;
+                self.end = self.frame + 1;
+            }
+
+            self.frame += 1;
+        }
+    }
+
+    /// Appends the post-roll, or all the trailing silence if it is shorter than the minimum.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        if self.start.is_some() {
+            let held_frames = self.held.len() / self.channels;
+            let kept = if held_frames < self.min_frames {
+                held_frames
+            } else {
+                held_frames.min(self.post_roll_frames)
+            };
+
+            output.extend(self.held.drain(..kept * self.channels));
+            self.end += kept;
+        }
+
+        self.held.clear();
+        self.held_start = self.frame;
+    }
+
+    /// What was kept so far, final after [`SilenceTrimmer::flush`].
+    pub fn report(&self) -> TrimReport {
+        let start = self.start.unwrap_or(self.frame);
+        let end = self.end.max(start);
+        let rate = self.sample_rate as f64;
+
+        TrimReport {
+            start,
+            end,
+            start_seconds: start as f64 / rate,
+            end_seconds: end as f64 / rate,
+            frames: self.frame,
+        }
+    }
+}
+
+/// Trims the whole buffer, a silent one ends up empty.
+pub fn trim_silence(buffer: &AudioBuffer, params: TrimParams) -> Result<(AudioBuffer, TrimReport)> {
+    let mut trimmer = SilenceTrimmer::new(buffer.info, params)?;
+
+    let mut samples = Vec::new();
+    trimmer.process(&buffer.samples, &mut samples);
+    trimmer.flush(&mut samples);
+
+    Ok((AudioBuffer::new(buffer.info, samples), trimmer.report()))
+}
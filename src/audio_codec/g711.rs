@@ -0,0 +1,71 @@
+//! G.711 μ-law and A-law companding, from and to 16 bit samples.
+
+const SIGN_BIT: u8 = 0x80;
+const QUANT_MASK: u8 = 0x0f;
+const SEG_MASK: u8 = 0x70;
+const SEG_SHIFT: u8 = 4;
+
+const MULAW_BIAS: i32 = 0x84;
+const MULAW_CLIP: i32 = 32635;
+
+/// Highest value of every A-law segment, for 13 bit samples.
+const ALAW_SEG_END: [i32; 8] = [0x1f, 0x3f, 0x7f, 0xff, 0x1ff, 0x3ff, 0x7ff, 0xfff];
+
+pub fn mulaw_to_linear(value: u8) -> i16 {
+    let value = !value;
+    let t =
+        ((((value & QUANT_MASK) as i32) << 3) + MULAW_BIAS) << ((value & SEG_MASK) >> SEG_SHIFT);
+
+    (if value & SIGN_BIT != 0 {
+        MULAW_BIAS - t
+    } else {
+        t - MULAW_BIAS
+    }) as i16
+}
+
+pub fn linear_to_mulaw(value: i16) -> u8 {
+    let sign = if value < 0 { SIGN_BIT } else { 0 };
+    let pcm = (value as i32).abs().min(MULAW_CLIP) + MULAW_BIAS;
+
+    // pcm >= MULAW_BIAS, so the shifted value is never 0.
+    let segment = (31 - (pcm >> 7).leading_zeros()).min(7) as u8;
+    let mantissa = ((pcm >> (segment + 3)) & QUANT_MASK as i32) as u8;
+
+    !(sign | (segment << SEG_SHIFT) | mantissa)
+}
+
+pub fn alaw_to_linear(value: u8) -> i16 {
+    let value = value ^ 0x55;
+    let segment = (value & SEG_MASK) >> SEG_SHIFT;
+
+    let mut t = ((value & QUANT_MASK) as i32) << 4;
+    match segment {
+        0 => t += 8,
+        1 => t += 0x108,
+        _ => t = (t + 0x108) << (segment - 1),
+    }
+
+    (if value & SIGN_BIT != 0 { t } else { -t }) as i16
+}
+
+pub fn linear_to_alaw(value: i16) -> u8 {
+    let mut pcm = value as i32 >> 3;
+    let mask = if pcm >= 0 {
+        0xd5
+    } else {
+        pcm = -pcm - 1;
+        0x55
+    };
+
+    let Some(segment) = ALAW_SEG_END.iter().position(|end| pcm <= *end) else {
+        return 0x7f ^ mask;
+    };
+
+    let mantissa = if segment < 2 {
+        pcm >> 1
+    } else {
+        pcm >> segment
+    };
+
+    (((segment as u8) << SEG_SHIFT) | (mantissa as u8 & QUANT_MASK)) ^ mask
+}
@@ -0,0 +1,385 @@
+//! Synthetic signals, decoded like any file.
+//!
+//! ```ignore
+//! let info = AudioInfo { channels: 2, sample_rate: 48_000, bits_per_sample: 24, sample_type: Some(SampleType::INT) };
+//! let sweep = LgGenerator::new(info, Signal::Sweep { start: 20.0, end: 20_000.0, mode: SweepMode::Logarithmic }, 10.0)?
+//!     .with_amplitude_db(-6.0);
+//!
+//! LgPipeline::from_decoder(sweep).run(&mut LgWavEncoder::new("sweep.wav", info)?)?;
+//! ```
+
+use super::{
+    AudioInfo, Result,
+    decoder::LgDecoder,
+    dsp::db_to_gain,
+    error::Error,
+    sample::{Sample, SampleType},
+};
+use std::f64::consts::TAU;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SweepMode {
+    Linear,
+    /// Same time for every octave.
+    #[default]
+    Logarithmic,
+}
+
+/// Frequencies in Hz. The square and saw are band-limited with PolyBLEP.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    Sine {
+        frequency: f64,
+    },
+    /// `duty` is the part of the period that is high.
+    Square {
+        frequency: f64,
+        duty: f64,
+    },
+    Saw {
+        frequency: f64,
+    },
+    Triangle {
+        frequency: f64,
+    },
+    /// From `start` to `end` over the whole length.
+    Sweep {
+        start: f64,
+        end: f64,
+        mode: SweepMode,
+    },
+    /// Same `seed`, same noise.
+    WhiteNoise {
+        seed: u64,
+    },
+    /// -3 dB per octave.
+    PinkNoise {
+        seed: u64,
+    },
+    /// -6 dB per octave.
+    BrownNoise {
+        seed: u64,
+    },
+    /// A single sample at full amplitude, repeated every `period` seconds if given.
+    Impulse {
+        period: Option<f64>,
+    },
+    Silence,
+}
+
+/// Generates a signal in every channel, converted to the format of its `info`.
+#[derive(Debug, Clone)]
+pub struct LgGenerator {
+    info: AudioInfo,
+    signal: Signal,
+    amplitude: f32,
+    frames: usize,
+
+    frame: usize,
+    channel: usize,
+    value: f32,
+    /// Phase in periods, for the periodic signals.
+    phase: f64,
+    random: Random,
+    /// State of the pink and brown noise filters.
+    filter: [f32; 7],
+}
+impl LgGenerator {
+    /// Lasts `seconds`, at full amplitude.
+    pub fn new(info: AudioInfo, signal: Signal, seconds: f64) -> Result<Self> {
+        let frames = (seconds.max(0.0) * info.sample_rate as f64).round() as usize;
+        Self::with_frames(info, signal, frames)
+    }
+
+    pub fn with_frames(info: AudioInfo, signal: Signal, frames: usize) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Generator needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        let seed = match signal {
+            Signal::WhiteNoise { seed }
+            | Signal::PinkNoise { seed }
+            | Signal::BrownNoise { seed } => seed,
+            _ => 0,
+        };
+
+        Ok(Self {
+            info,
+            signal,
+            amplitude: 1.0,
+            frames,
+            frame: 0,
+            channel: 0,
+            value: 0.0,
+            phase: 0.0,
+            random: Random::new(seed),
+            filter: [0.0; 7],
+        })
+    }
+
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    pub fn with_amplitude_db(self, db: f32) -> Self {
+        self.with_amplitude(db_to_gain(db as f64) as f32)
+    }
+
+    #[inline(always)]
+    pub fn signal(&self) -> Signal {
+        self.signal
+    }
+
+    /// Frames in total.
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+impl LgGenerator {
+    /// Value of the current frame, in `-1.0..=1.0`.
+    fn next_value(&mut self) -> f32 {
+        let rate = self.info.sample_rate.max(1) as f64;
+        let time = self.frame as f64 / rate;
+
+        let value = match self.signal {
+            Signal::Sine { frequency } => {
+                let value = (self.phase * TAU).sin();
+                self.advance(frequency / rate);
+                value
+            }
+            Signal::Square { frequency, duty } => {
+                let (increment, duty) = (frequency / rate, duty.clamp(0.0, 1.0));
+                let high = if self.phase < duty { 1.0 } else { -1.0 };
+                let value = high + poly_blep(self.phase, increment)
+                    - poly_blep((self.phase - duty).rem_euclid(1.0), increment);
+                self.advance(increment);
+                value
+            }
+            Signal::Saw { frequency } => {
+                let increment = frequency / rate;
+                let value = 2.0 * self.phase - 1.0 - poly_blep(self.phase, increment);
+                self.advance(increment);
+                value
+            }
+            Signal::Triangle { frequency } => {
+                let value = 1.0 - 4.0 * (self.phase - 0.5).abs();
+                self.advance(frequency / rate);
+                value
+            }
+            Signal::Sweep { start, end, mode } => {
+                let length = self.frames as f64 / rate;
+                let cycles = match mode {
+                    _ if length <= 0.0 => 0.0,
+                    SweepMode::Linear => {
+                        start * time + (end - start) * time * time / (2.0 * length)
+                    }
+                    SweepMode::Logarithmic if start > 0.0 && end > 0.0 && start != end => {
+                        let k = (end / start).ln() / length;
+                        start * ((k * time).exp() - 1.0) / k
+                    }
+                    SweepMode::Logarithmic => start * time,
+                };
+                (cycles.fract() * TAU).sin()
+            }
+            Signal::WhiteNoise { .. } => self.white() as f64,
+            Signal::PinkNoise { .. } => {
+                // Paul Kellet's filter.
+                let white = self.white();
+                let b = &mut self.filter;
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.96900 * b[2] + white * 0.153852;
+                b[3] = 0.86650 * b[3] + white * 0.3104856;
+                b[4] = 0.55000 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.016898;
+                let pink = b[..6].iter().sum::<f32>() + b[6] + white * 0.5362;
+                b[6] = white * 0.115926;
+                (pink * 0.11).clamp(-1.0, 1.0) as f64
+            }
+            Signal::BrownNoise { .. } => {
+                let white = self.white();
+                self.filter[0] = (self.filter[0] + 0.02 * white) / 1.02;
+                (self.filter[0] * 3.5).clamp(-1.0, 1.0) as f64
+            }
+            Signal::Impulse { period } => {
+                let at = match period {
+                    Some(period) if period > 0.0 => {
+                        let period = (period * rate).round().max(1.0) as usize;
+                        self.frame.is_multiple_of(period)
+                    }
+                    _ => self.frame == 0,
+                };
+                if at { 1.0 } else { 0.0 }
+            }
+            Signal::Silence => 0.0,
+        };
+
+        value as f32 * self.amplitude
+    }
+
+    #[inline(always)]
+    fn advance(&mut self, increment: f64) {
+        self.phase = (self.phase + increment).rem_euclid(1.0);
+    }
+
+    #[inline(always)]
+    fn white(&mut self) -> f32 {
+        self.random.next_f32() * 2.0 - 1.0
+    }
+}
+impl LgDecoder for LgGenerator {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn samples<S: Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+        let channels = self.info.channels as usize;
+
+        std::iter::from_fn(move || {
+            if self.frame >= self.frames {
+                return None;
+            }
+
+            if self.channel == 0 {
+                self.value = self.next_value();
+            }
+            let value = self.value;
+
+            self.channel += 1;
+            if self.channel == channels {
+                self.channel = 0;
+                self.frame += 1;
+            }
+
+            Some(S::from_f32(value, sample_type, bits_per_sample))
+        })
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.frames / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.frames * self.info.channels as usize
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+}
+
+/// Smooths the step of a band-limited waveform at phase 0, `increment` is the phase per frame.
+fn poly_blep(phase: f64, increment: f64) -> f64 {
+    if increment <= 0.0 {
+        return 0.0;
+    }
+
+    if phase < increment {
+        let t = phase / increment;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - increment {
+        let t = (phase - 1.0) / increment;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Seeded xorshift64*, the same seed gives the same numbers.
+#[derive(Debug, Clone)]
+pub(crate) struct Random(u64);
+impl Random {
+    pub(crate) fn new(seed: u64) -> Self {
+        // Scrambled by splitmix64 so close seeds start far apart, never 0.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0.0..1.0`.
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOISES: [fn(u64) -> Signal; 3] = [
+        |seed| Signal::WhiteNoise { seed },
+        |seed| Signal::PinkNoise { seed },
+        |seed| Signal::BrownNoise { seed },
+    ];
+
+    fn generate(signal: Signal) -> Vec<f32> {
+        let info = AudioInfo {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 32,
+            sample_type: Some(SampleType::FLOAT),
+        };
+
+        LgGenerator::with_frames(info, signal, 48_000)
+            .unwrap()
+            .samples()
+            .collect()
+    }
+
+    /// Correlation of the frames with the next ones, higher for darker noise.
+    fn next_frame_correlation(samples: &[f32]) -> f64 {
+        let left: Vec<f64> = samples.iter().step_by(2).map(|x| *x as f64).collect();
+        let mean = left.iter().sum::<f64>() / left.len() as f64;
+        let variance: f64 = left.iter().map(|x| (x - mean).powi(2)).sum();
+        let covariance: f64 = left
+            .windows(2)
+            .map(|pair| (pair[0] - mean) * (pair[1] - mean))
+            .sum();
+
+        covariance / variance
+    }
+
+    #[test]
+    fn noise_is_seeded() {
+        for noise in NOISES {
+            let samples = generate(noise(1));
+
+            assert_eq!(samples.len(), 48_000 * 2);
+            assert_eq!(samples, generate(noise(1)));
+            assert_ne!(samples, generate(noise(2)));
+
+            assert!(samples.iter().all(|x| (-1.0..=1.0).contains(x)));
+            assert!(samples.iter().any(|x| *x != 0.0));
+            // Every channel plays the same noise.
+            assert!(samples.chunks_exact(2).all(|frame| frame[0] == frame[1]));
+        }
+    }
+
+    #[test]
+    fn noise_colors() {
+        let [white, pink, brown] = NOISES.map(|noise| next_frame_correlation(&generate(noise(7))));
+
+        assert!(white.abs() < 0.05, "{white}");
+        assert!(pink > 0.5 && pink < brown, "{pink}");
+        assert!(brown > 0.95, "{brown}");
+    }
+}
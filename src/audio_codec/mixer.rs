@@ -0,0 +1,477 @@
+//! Offline mixing of several sources into one format, a block at a time.
+//!
+//! ```ignore
+//! let info = AudioInfo { channels: 2, sample_rate: 48_000, bits_per_sample: 16, sample_type: Some(SampleType::INT) };
+//! let mut encoder = LgWavEncoder::new("ambience.wav", info)?;
+//!
+//! let mut mixer = LgMixer::new(info)?.with_limiter(LimiterParams::default());
+//! mixer.add_track(MixTrack::reopening_decoder(|| LgWavDecoder::new("wind.wav"))?.with_loops(4))?;
+//! mixer.add_track(MixTrack::from_decoder(LgWavDecoder::new("birds.wav")?).with_offset(2.5).with_pan(-0.5))?;
+//! mixer.run(&mut encoder)?;
+//! ```
+
+use super::{
+    AudioInfo, Result,
+    decoder::LgDecoder,
+    dsp::{ChannelLayout, Limiter, LimiterParams, ResampleQuality, db_to_gain},
+    error::Error,
+    pipeline::{
+        DEFAULT_BLOCK_FRAMES, LgDecoderSource, LgPipeline, LgSink, LgSource, RemixStage,
+        ResampleStage,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Offset {
+    Frames(usize),
+    Seconds(f64),
+}
+
+/// Opens a source again, for every loop of a track.
+type Reopen<'a> = Box<dyn FnMut() -> Result<Box<dyn LgSource + 'a>> + 'a>;
+
+/// A source placed in the mix.
+pub struct MixTrack<'a> {
+    source: Box<dyn LgSource + 'a>,
+    reopen: Option<Reopen<'a>>,
+    offset: Offset,
+    gain: f32,
+    pan: f32,
+    loops: u32,
+}
+impl<'a> MixTrack<'a> {
+    pub fn new(source: impl LgSource + 'a) -> Self {
+        Self {
+            source: Box::new(source),
+            reopen: None,
+            offset: Offset::Frames(0),
+            gain: 1.0,
+            pan: 0.0,
+            loops: 1,
+        }
+    }
+
+    #[inline(always)]
+    pub fn from_decoder(decoder: impl LgDecoder + 'a) -> Self {
+        Self::new(LgDecoderSource::new(decoder))
+    }
+
+    /// Opens the source with `open`, then again for every loop, so it is read from the start
+    /// instead of kept in memory.
+    pub fn reopening<S: LgSource + 'a>(mut open: impl FnMut() -> Result<S> + 'a) -> Result<Self> {
+        let mut result = Self::new(open()?);
+        result.reopen = Some(Box::new(move || {
+            Ok(Box::new(open()?) as Box<dyn LgSource + 'a>)
+        }));
+
+        Ok(result)
+    }
+
+    /// Like [`MixTrack::reopening`], opening a decoder.
+    #[inline(always)]
+    pub fn reopening_decoder<D: LgDecoder + 'a>(
+        mut open: impl FnMut() -> Result<D> + 'a,
+    ) -> Result<Self> {
+        Self::reopening(move || open().map(LgDecoderSource::new))
+    }
+
+    /// Start in the mix, in seconds.
+    pub fn with_offset(mut self, seconds: f64) -> Self {
+        self.offset = Offset::Seconds(seconds);
+        self
+    }
+
+    /// Start in the mix, in frames of the mix.
+    pub fn with_offset_frames(mut self, frames: usize) -> Self {
+        self.offset = Offset::Frames(frames);
+        self
+    }
+
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    pub fn with_gain_db(self, db: f32) -> Self {
+        self.with_gain(db_to_gain(db as f64) as f32)
+    }
+
+    /// From -1.0, left, to 1.0, right. Only the front left and right speakers of the mix are
+    /// balanced, the center keeps both at their gain.
+    pub fn with_pan(mut self, pan: f32) -> Self {
+        self.pan = pan.clamp(-1.0, 1.0);
+        self
+    }
+
+    /// Times the track plays back to back, more than once needs a [`MixTrack::reopening`] track.
+    pub fn with_loops(mut self, loops: u32) -> Self {
+        self.loops = loops.max(1);
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MixReport {
+    pub frames: usize,
+    /// Highest sample given, in dBFS.
+    pub peak: f64,
+    /// Samples given over full scale.
+    pub clipped: usize,
+}
+
+/// Sums tracks, converted to its format, applying the master gain and the optional limiter.
+pub struct LgMixer<'a> {
+    info: AudioInfo,
+    tracks: Vec<TrackState<'a>>,
+    master_gain: f32,
+    limiter: Option<Limiter>,
+    block_frames: usize,
+
+    /// Frame of the next block.
+    frame: usize,
+    ended: bool,
+    mix: Vec<f32>,
+    given: usize,
+    peak: f32,
+    clipped: usize,
+}
+impl<'a> LgMixer<'a> {
+    pub fn new(info: AudioInfo) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Mixing needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            info,
+            tracks: Vec::new(),
+            master_gain: 1.0,
+            limiter: None,
+            block_frames: DEFAULT_BLOCK_FRAMES,
+            frame: 0,
+            ended: false,
+            mix: Vec::new(),
+            given: 0,
+            peak: 0.0,
+            clipped: 0,
+        })
+    }
+
+    /// Gain of the sum, in dB, negative to leave headroom for the tracks adding up.
+    pub fn with_master_gain(mut self, db: f32) -> Self {
+        self.master_gain = db_to_gain(db as f64) as f32;
+        self
+    }
+
+    /// Limits the sum after the master gain.
+    pub fn with_limiter(mut self, params: LimiterParams) -> Self {
+        self.limiter = Some(Limiter::new(
+            self.info.channels as usize,
+            self.info.sample_rate,
+            params,
+        ));
+        self
+    }
+
+    /// Frames mixed at a time.
+    pub fn with_block_frames(mut self, block_frames: usize) -> Self {
+        self.block_frames = block_frames.max(1);
+        self
+    }
+
+    /// Converts the track to the format of the mix, resampling and remixing it if needed.
+    pub fn add_track(&mut self, track: MixTrack<'a>) -> Result<()> {
+        if track.loops > 1 && track.reopen.is_none() {
+            return Err(Error::Custom(
+                "Only MixTrack::reopening tracks can loop!".to_string(),
+            ));
+        }
+
+        let input = track.source.info();
+        let source = convert(track.source, self.info)?;
+
+        let mut gains = vec![0.0; self.info.channels as usize];
+        ChannelLayout::default_for(self.info.channels).pan_gains(track.gain, track.pan, &mut gains);
+
+        self.tracks.push(TrackState {
+            source,
+            reopen: track.reopen,
+            input,
+            output: self.info,
+            start: match track.offset {
+                Offset::Frames(frames) => frames,
+                Offset::Seconds(seconds) => {
+                    (seconds.max(0.0) * self.info.sample_rate as f64).round() as usize
+                }
+            },
+            gains,
+            loops: track.loops,
+            looped: 0,
+            pending: Vec::new(),
+            ended: false,
+        });
+
+        Ok(())
+    }
+
+    /// Mixes everything into the sink.
+    pub fn run(&mut self, sink: &mut impl LgSink) -> Result<MixReport> {
+        sink.prepare(self.info)?;
+
+        let mut block = Vec::new();
+        loop {
+            block.clear();
+            if self.read_block(self.block_frames, &mut block)? == 0 {
+                return Ok(self.report());
+            }
+
+            sink.write_block(&block)?;
+        }
+    }
+
+    /// What was given so far.
+    pub fn report(&self) -> MixReport {
+        MixReport {
+            frames: self.given,
+            peak: 20.0 * (self.peak as f64).log10(),
+            clipped: self.clipped,
+        }
+    }
+}
+impl LgMixer<'_> {
+    /// Sums a block, returning how many frames it has, 0 once every track ended.
+    fn mix_block(&mut self, frames: usize) -> Result<usize> {
+        let channels = self.info.channels as usize;
+        self.mix.clear();
+        self.mix.resize(frames * channels, 0.0);
+
+        let mut mixed = 0;
+        let mut playing = false;
+        for track in &mut self.tracks {
+            let skip = track.start.saturating_sub(self.frame);
+            if skip >= frames {
+                playing = true;
+                continue;
+            }
+
+            let read = track.read(frames - skip, channels)?;
+            track.add_to(&mut self.mix, skip, read, channels);
+            mixed = mixed.max(skip + read);
+            playing |= !track.ended;
+        }
+
+        // Silence between tracks is still mixed.
+        let mixed = if playing { frames } else { mixed };
+        self.mix.truncate(mixed * channels);
+        self.frame += mixed;
+
+        Ok(mixed)
+    }
+}
+impl LgSource for LgMixer<'_> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn read_block(&mut self, frames: usize, block: &mut Vec<f32>) -> Result<usize> {
+        let channels = self.info.channels as usize;
+        let start = block.len();
+
+        while !self.ended && block.len() == start {
+            let mixed = self.mix_block(frames)?;
+            self.ended = mixed == 0;
+
+            for sample in &mut self.mix {
+                *sample *= self.master_gain;
+            }
+            match &mut self.limiter {
+                Some(limiter) => {
+                    limiter.process(&self.mix, block);
+                    if self.ended {
+                        limiter.flush(block);
+                    }
+                }
+                None => block.extend_from_slice(&self.mix),
+            }
+        }
+
+        for sample in &block[start..] {
+            self.peak = self.peak.max(sample.abs());
+            self.clipped += (sample.abs() > 1.0) as usize;
+        }
+
+        let given = (block.len() - start) / channels;
+        self.given += given;
+
+        Ok(given)
+    }
+}
+
+/// Pipeline converting a source to the format of the mix.
+type Converted<'a> = LgPipeline<Box<dyn LgSource + 'a>>;
+
+fn convert<'a>(source: Box<dyn LgSource + 'a>, info: AudioInfo) -> Result<Converted<'a>> {
+    let input = source.info();
+    let mut result = LgPipeline::new(source);
+    if input.sample_rate != info.sample_rate {
+        result = result.stage(ResampleStage::new(
+            info.sample_rate,
+            ResampleQuality::default(),
+        ))?;
+    }
+    if input.channels != info.channels {
+        result = result.stage(RemixStage::layouts(
+            ChannelLayout::default_for(input.channels),
+            ChannelLayout::default_for(info.channels),
+        )?)?;
+    }
+
+    Ok(result)
+}
+
+struct TrackState<'a> {
+    source: Converted<'a>,
+    reopen: Option<Reopen<'a>>,
+    /// Format of the source, and of the mix.
+    input: AudioInfo,
+    output: AudioInfo,
+    start: usize,
+    /// Of every channel, with the pan.
+    gains: Vec<f32>,
+    /// Plays left, counting the current one.
+    loops: u32,
+    /// Frames read in the current play.
+    looped: usize,
+    /// Samples read but not mixed yet.
+    pending: Vec<f32>,
+    ended: bool,
+}
+impl TrackState<'_> {
+    /// Fills the pending samples with up to `frames` frames, returning how many there are.
+    fn read(&mut self, frames: usize, channels: usize) -> Result<usize> {
+        let wanted = frames * channels;
+
+        while self.pending.len() < wanted && !self.ended {
+            let read = self.source.read_block(frames, &mut self.pending)?;
+            self.looped += read;
+
+            if read == 0 {
+                self.next_loop()?;
+            }
+        }
+
+        Ok(self.pending.len().min(wanted) / channels)
+    }
+
+    /// Adds `frames` pending frames to the mix, from the frame `at`.
+    fn add_to(&mut self, mix: &mut [f32], at: usize, frames: usize, channels: usize) {
+        let mixed = frames * channels;
+        for (i, sample) in self.pending.drain(..mixed).enumerate() {
+            mix[at * channels + i] += sample * self.gains[i % channels];
+        }
+    }
+
+    /// Opens the source again if more plays are left, an empty one ends at once.
+    fn next_loop(&mut self) -> Result<()> {
+        self.loops = self.loops.saturating_sub(1);
+        let reopen = match &mut self.reopen {
+            Some(reopen) if self.loops > 0 && self.looped > 0 => reopen,
+            _ => {
+                self.ended = true;
+                return Ok(());
+            }
+        };
+
+        let source = reopen()?;
+        let info = source.info();
+        if info.channels != self.input.channels || info.sample_rate != self.input.sample_rate {
+            return Err(Error::WrongFmtInfo(format!(
+                "The track was {} channels at {} Hz, it reopened as {} channels at {} Hz!",
+                self.input.channels, self.input.sample_rate, info.channels, info.sample_rate
+            )));
+        }
+
+        self.source = convert(source, self.output)?;
+        self.looped = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{buffer::AudioBuffer, pipeline::LgBufferSource, sample::SampleType};
+    use super::*;
+    use std::cell::Cell;
+
+    fn info(channels: u16, sample_rate: u32) -> AudioInfo {
+        AudioInfo {
+            channels,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_type: Some(SampleType::FLOAT),
+        }
+    }
+
+    #[test]
+    fn loops_reopen_the_source() {
+        let buffer = AudioBuffer::new(info(2, 1000), (0..200).map(|i| i as f32 / 200.0).collect());
+        let opened = Cell::new(0);
+
+        let mut mixer = LgMixer::new(info(2, 1000)).unwrap().with_block_frames(64);
+        let track = MixTrack::reopening(|| {
+            opened.set(opened.get() + 1);
+            Ok(LgBufferSource::new(&buffer))
+        })
+        .unwrap()
+        .with_loops(3)
+        .with_offset_frames(10);
+        mixer.add_track(track).unwrap();
+
+        let mut mixed = AudioBuffer::new(info(2, 1000), Vec::new());
+        let report = mixer.run(&mut mixed).unwrap();
+
+        assert_eq!(opened.get(), 3);
+        assert_eq!(report.frames, 10 + 300);
+        assert!(mixed.samples[..20].iter().all(|x| *x == 0.0));
+        assert_eq!(mixed.samples[20..], buffer.samples.repeat(3));
+    }
+
+    #[test]
+    fn loops_need_reopening() {
+        let buffer = AudioBuffer::new(info(1, 1000), vec![0.5; 10]);
+        let mut mixer = LgMixer::new(info(1, 1000)).unwrap();
+
+        assert!(
+            mixer
+                .add_track(MixTrack::new(LgBufferSource::new(&buffer)).with_loops(2))
+                .is_err()
+        );
+        assert!(
+            mixer
+                .add_track(MixTrack::new(LgBufferSource::new(&buffer)))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn empty_source_stops_looping() {
+        let buffer = AudioBuffer::new(info(1, 1000), Vec::new());
+        let opened = Cell::new(0);
+
+        let mut mixer = LgMixer::new(info(1, 1000)).unwrap();
+        let track = MixTrack::reopening(|| {
+            opened.set(opened.get() + 1);
+            Ok(LgBufferSource::new(&buffer))
+        })
+        .unwrap()
+        .with_loops(u32::MAX);
+        mixer.add_track(track).unwrap();
+
+        let mut mixed = AudioBuffer::new(info(1, 1000), Vec::new());
+        assert_eq!(mixer.run(&mut mixed).unwrap().frames, 0);
+        assert_eq!(opened.get(), 1);
+    }
+}
@@ -1,7 +1,24 @@
+pub mod analysis;
+pub mod au;
+pub mod buffer;
+pub mod caf;
 pub mod decoder;
+pub mod dsp;
 pub mod encoder;
 pub mod error;
+pub mod g711;
+pub mod generator;
+pub mod mixer;
+pub mod mp3;
+pub mod pipeline;
+pub mod playback;
+pub mod qoa;
+pub mod raw;
 pub mod sample;
+pub mod sfxr;
+pub mod stream;
+pub mod tracker;
+pub mod w64;
 pub mod wav;
 
 pub type Result<T> = std::result::Result<T, error::Error>;
@@ -0,0 +1,56 @@
+/// MSB first bit reader, reading past the end gives zeros.
+pub(super) struct BitReader<'b> {
+    buf: &'b [u8],
+    /// Position in bits.
+    pub(super) pos: usize,
+    /// Size in bits.
+    pub(super) limit: usize,
+}
+impl<'b> BitReader<'b> {
+    pub(super) fn new(buf: &'b [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            limit: buf.len() * 8,
+        }
+    }
+
+    /// Reads `n` (up to 32) bits. Gives 0 and marks the reader as overflowed when there are not enough.
+    #[inline]
+    pub(super) fn get_bits(&mut self, n: u32) -> u32 {
+        if self.pos + n as usize > self.limit {
+            self.pos += n as usize;
+            return 0;
+        }
+
+        let value = self.peek(n);
+        self.pos += n as usize;
+
+        value
+    }
+
+    /// Next `n` (up to 32) bits without moving.
+    #[inline]
+    pub(super) fn peek(&self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+
+        let byte = self.pos / 8;
+        let cache = (0..5).fold(0u64, |cache, i| {
+            (cache << 8) | *self.buf.get(byte + i).unwrap_or(&0) as u64
+        });
+
+        ((cache << (24 + self.pos % 8)) >> (64 - n)) as u32
+    }
+
+    #[inline(always)]
+    pub(super) fn skip(&mut self, n: u32) {
+        self.pos += n as usize;
+    }
+
+    #[inline(always)]
+    pub(super) fn overflowed(&self) -> bool {
+        self.pos > self.limit
+    }
+}
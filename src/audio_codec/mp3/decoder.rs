@@ -0,0 +1,82 @@
+use super::super::{AudioInfo, Result, decoder::LgDecoder};
+use super::{Id3Tag, LgMp3SampleIter, mp3_info, reader::LgMp3Reader};
+use std::{fmt, fs, io, path};
+
+pub struct LgMp3Decoder<R: io::Read> {
+    info: AudioInfo,
+    sample_len: usize,
+
+    reader: LgMp3Reader<R>,
+}
+impl<R: io::Read> fmt::Debug for LgMp3Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LgMp3Decoder")
+            .field("info", &self.info)
+            .field("sample_len", &self.sample_len)
+            .field("id3", &self.reader.id3)
+            .finish()
+    }
+}
+impl LgMp3Decoder<io::BufReader<fs::File>> {
+    /// Without a Xing/Info or VBRI tag the file is scanned once, so [`LgDecoder::len`] is always known.
+    pub fn new(path: impl AsRef<path::Path>) -> Result<Self> {
+        let mut result = Self::from_reader(io::BufReader::new(fs::File::open(path)?))?;
+
+        if !result.is_len_known() {
+            result.sample_len = result.reader.count_samples_and_rewind()?;
+        }
+
+        Ok(result)
+    }
+}
+impl<R: io::Read> LgMp3Decoder<R> {
+    /// Without a Xing/Info or VBRI tag the length is unknown, so [`LgDecoder::len`] is 0.
+    pub fn from_reader(reader: R) -> Result<Self> {
+        // Already syncs to the first frame, so the format is known.
+        let reader = LgMp3Reader::new(reader)?;
+
+        Ok(Self {
+            info: mp3_info(reader.channels, reader.sample_rate),
+            sample_len: reader.total_samples.unwrap_or(0),
+            reader,
+        })
+    }
+
+    /// The first ID3v2 tag of the file.
+    #[inline(always)]
+    pub fn id3(&self) -> Option<&Id3Tag> {
+        self.reader.id3.as_ref()
+    }
+
+    /// False for files without a Xing/Info or VBRI tag opened with [`LgMp3Decoder::from_reader`].
+    #[inline(always)]
+    pub fn is_len_known(&self) -> bool {
+        self.reader.total_samples.is_some() || self.sample_len != 0
+    }
+}
+impl<R: io::Read> LgDecoder for LgMp3Decoder<R> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn samples<S: super::Sample>(&mut self) -> impl Iterator<Item = S> {
+        LgMp3SampleIter::new(&mut self.reader)
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.sample_len / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.sample_len
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_len_known() && self.sample_len == 0
+    }
+}
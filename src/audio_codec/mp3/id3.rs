@@ -0,0 +1,261 @@
+/// Size of the ID3v2 header (and footer).
+pub(super) const ID3V2_HEADER_SIZE: usize = 10;
+
+const FLAG_UNSYNCHRONISATION: u8 = 0x80;
+const FLAG_EXTENDED_HEADER: u8 = 0x40;
+const FLAG_FOOTER: u8 = 0x10;
+
+/// A single ID3v2 frame, the data is kept as stored (after removing the unsynchronisation).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Id3Frame {
+    /// `TIT2`, `TPE1`, ... v2.2 tags have 3 characters ids (`TT2`, `TP1`, ...).
+    pub id: String,
+    pub data: Vec<u8>,
+}
+impl Id3Frame {
+    /// Decodes text frames (ids starting with `T`), multiple values are separated by `/`.
+    pub fn text(&self) -> Option<String> {
+        if !self.id.starts_with('T') || self.data.is_empty() {
+            return None;
+        }
+
+        let text = decode_text(self.data[0], &self.data[1..])?;
+        let values = text
+            .split('\0')
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>();
+
+        Some(values.join("/"))
+    }
+}
+
+/// ID3v2 tag found at the start of a file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Id3Tag {
+    /// Major and revision, (4, 0) for ID3v2.4.0.
+    pub version: (u8, u8),
+    pub frames: Vec<Id3Frame>,
+}
+impl Id3Tag {
+    /// First frame with the `id`.
+    pub fn get(&self, id: &str) -> Option<&Id3Frame> {
+        self.frames.iter().find(|frame| frame.id == id)
+    }
+
+    /// Text of the first frame with any of the `ids`.
+    fn text_of(&self, ids: &[&str]) -> Option<String> {
+        ids.iter().find_map(|id| self.get(id)?.text())
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.text_of(&["TIT2", "TT2"])
+    }
+
+    pub fn artist(&self) -> Option<String> {
+        self.text_of(&["TPE1", "TP1"])
+    }
+
+    pub fn album(&self) -> Option<String> {
+        self.text_of(&["TALB", "TAL"])
+    }
+
+    pub fn year(&self) -> Option<String> {
+        self.text_of(&["TDRC", "TYER", "TYE"])
+    }
+
+    pub fn track(&self) -> Option<String> {
+        self.text_of(&["TRCK", "TRK"])
+    }
+
+    pub fn genre(&self) -> Option<String> {
+        self.text_of(&["TCON", "TCO"])
+    }
+}
+
+#[inline(always)]
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |size, b| (size << 7) | (*b & 0x7f) as usize)
+}
+
+/// Size of the whole tag (header, footer included) if `header` starts one.
+pub(super) fn tag_size(header: &[u8]) -> Option<usize> {
+    if header.len() < ID3V2_HEADER_SIZE || &header[..3] != b"ID3" {
+        return None;
+    }
+
+    if header[5] & 0x0f != 0 || header[6..10].iter().any(|b| b & 0x80 != 0) {
+        return None;
+    }
+
+    let footer = if header[5] & FLAG_FOOTER != 0 {
+        ID3V2_HEADER_SIZE
+    } else {
+        0
+    };
+
+    Some(ID3V2_HEADER_SIZE + syncsafe(&header[6..10]) + footer)
+}
+
+/// Removes the `0x00` inserted after every `0xff`.
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+
+    for (i, b) in data.iter().enumerate() {
+        if *b == 0 && i > 0 && data[i - 1] == 0xff {
+            continue;
+        }
+
+        result.push(*b);
+    }
+
+    result
+}
+
+/// Parses the whole tag, as returned by [`tag_size`]. Broken frames end the parsing.
+pub(super) fn parse(tag: &[u8]) -> Id3Tag {
+    let major = tag[3];
+    let flags = tag[5];
+    let end = (ID3V2_HEADER_SIZE + syncsafe(&tag[6..10])).min(tag.len());
+
+    let mut result = Id3Tag {
+        version: (major, tag[4]),
+        frames: Vec::new(),
+    };
+
+    // Before v2.4 the unsynchronisation is applied to the whole tag.
+    let body = if major < 4 && flags & FLAG_UNSYNCHRONISATION != 0 {
+        remove_unsynchronisation(&tag[ID3V2_HEADER_SIZE..end])
+    } else {
+        tag[ID3V2_HEADER_SIZE..end].to_vec()
+    };
+
+    let mut pos = 0;
+    if major >= 3 && flags & FLAG_EXTENDED_HEADER != 0 && body.len() >= 4 {
+        pos = if major == 3 {
+            // Doesn't include its own size.
+            4 + u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as usize
+        } else {
+            syncsafe(&body[..4])
+        };
+    }
+
+    let (id_len, header_len) = if major == 2 { (3, 6) } else { (4, 10) };
+
+    while pos + header_len <= body.len() {
+        let header = &body[pos..pos + header_len];
+
+        // Padding.
+        if header[0] == 0 {
+            break;
+        }
+
+        let Ok(id) = std::str::from_utf8(&header[..id_len]) else {
+            break;
+        };
+        if !id
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            break;
+        }
+
+        let size = match major {
+            2 => (header[3] as usize) << 16 | (header[4] as usize) << 8 | header[5] as usize,
+            3 => u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize,
+            _ => syncsafe(&header[4..8]),
+        };
+
+        let start = pos + header_len;
+        if start + size > body.len() {
+            break;
+        }
+
+        let mut data = body[start..start + size].to_vec();
+
+        if major >= 3 {
+            let format = header[9];
+
+            // Extra bytes before the data: decompressed size, encryption method, group id.
+            let extra = if major == 3 {
+                (format & 0x80 != 0) as usize * 4
+                    + (format & 0x40 != 0) as usize
+                    + (format & 0x20 != 0) as usize
+            } else {
+                (format & 0x40 != 0) as usize + (format & 0x01 != 0) as usize * 4
+            };
+            data.drain(..extra.min(data.len()));
+
+            if major == 4 && (format & 0x02 != 0 || flags & FLAG_UNSYNCHRONISATION != 0) {
+                data = remove_unsynchronisation(&data);
+            }
+        }
+
+        result.frames.push(Id3Frame {
+            id: id.to_string(),
+            data,
+        });
+
+        pos = start + size;
+    }
+
+    result
+}
+
+fn decode_utf16(data: &[u8], big_endian: bool) -> Option<String> {
+    let units = data
+        .chunks_exact(2)
+        .map(|c| {
+            if big_endian {
+                u16::from_be_bytes([c[0], c[1]])
+            } else {
+                u16::from_le_bytes([c[0], c[1]])
+            }
+        })
+        .collect::<Vec<_>>();
+
+    String::from_utf16(&units).ok()
+}
+
+fn decode_text(encoding: u8, data: &[u8]) -> Option<String> {
+    match encoding {
+        // ISO-8859-1 maps directly to the first 256 code points.
+        0 => Some(data.iter().map(|b| *b as char).collect()),
+        // UTF-16 with BOM, every value of the frame may have its own.
+        1 => {
+            let mut result = String::new();
+            let mut big_endian = false;
+
+            for value in data
+                .chunks_exact(2)
+                .collect::<Vec<_>>()
+                .split(|c| c == &[0, 0])
+            {
+                let mut bytes = value.concat();
+                match bytes.get(..2) {
+                    Some([0xfe, 0xff]) => {
+                        big_endian = true;
+                        bytes.drain(..2);
+                    }
+                    Some([0xff, 0xfe]) => {
+                        big_endian = false;
+                        bytes.drain(..2);
+                    }
+                    _ => (),
+                }
+
+                if !result.is_empty() {
+                    result.push('\0');
+                }
+                result.push_str(&decode_utf16(&bytes, big_endian)?);
+            }
+
+            Some(result)
+        }
+        2 => decode_utf16(data, true),
+        3 => String::from_utf8(data.to_vec()).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,236 @@
+use super::bits::BitReader;
+use super::{FrameHeader, MODE_JOINT_STEREO, MODE_MONO};
+
+/// Bit allocation and scalefactors of a Layer I/II frame.
+pub(super) struct ScaleInfo {
+    pub(super) scf: [f32; 3 * 64],
+    total_bands: usize,
+    stereo_bands: usize,
+    bitalloc: [u8; 64],
+    scfcod: [u8; 64],
+}
+impl Default for ScaleInfo {
+    fn default() -> Self {
+        Self {
+            scf: [0.0; 3 * 64],
+            total_bands: 0,
+            stereo_bands: 0,
+            bitalloc: [0; 64],
+            scfcod: [0; 64],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SubbandAlloc {
+    tab_offset: usize,
+    code_tab_width: u32,
+    band_count: usize,
+}
+const fn alloc(tab_offset: usize, code_tab_width: u32, band_count: usize) -> SubbandAlloc {
+    SubbandAlloc {
+        tab_offset,
+        code_tab_width,
+        band_count,
+    }
+}
+
+fn subband_alloc_table(hdr: &FrameHeader, sci: &mut ScaleInfo) -> &'static [SubbandAlloc] {
+    const ALLOC_L1: [SubbandAlloc; 1] = [alloc(76, 4, 32)];
+    const ALLOC_L2M2: [SubbandAlloc; 3] = [alloc(60, 4, 4), alloc(44, 3, 7), alloc(44, 2, 19)];
+    const ALLOC_L2M1: [SubbandAlloc; 4] = [
+        alloc(0, 4, 3),
+        alloc(16, 4, 8),
+        alloc(32, 3, 12),
+        alloc(40, 2, 7),
+    ];
+    const ALLOC_L2M1_LOWRATE: [SubbandAlloc; 2] = [alloc(44, 4, 2), alloc(44, 3, 10)];
+
+    let mode = hdr.stereo_mode();
+    let stereo_bands = match mode {
+        MODE_MONO => 0,
+        MODE_JOINT_STEREO => ((hdr.stereo_mode_ext() as usize) << 2) + 4,
+        _ => 32,
+    };
+
+    let (table, nbands): (&'static [SubbandAlloc], usize) = if hdr.is_layer1() {
+        (&ALLOC_L1, 32)
+    } else if !hdr.is_mpeg1() {
+        (&ALLOC_L2M2, 30)
+    } else {
+        let sample_rate_idx = hdr.sample_rate_index();
+        let mut kbps = hdr.bitrate_kbps() >> (mode != MODE_MONO) as u32;
+        if kbps == 0 {
+            // Free format.
+            kbps = 192;
+        }
+
+        if kbps < 56 {
+            (
+                &ALLOC_L2M1_LOWRATE,
+                if sample_rate_idx == 2 { 12 } else { 8 },
+            )
+        } else if kbps >= 96 && sample_rate_idx != 1 {
+            (&ALLOC_L2M1, 30)
+        } else {
+            (&ALLOC_L2M1, 27)
+        }
+    };
+
+    sci.total_bands = nbands;
+    sci.stereo_bands = stereo_bands.min(nbands);
+
+    table
+}
+
+fn read_scalefactors(bs: &mut BitReader, sci: &mut ScaleInfo, bands: usize) {
+    const fn dq(x: f32) -> [f32; 3] {
+        [9.536_743e-7 / x, 7.569_318e-7 / x, 6.007_772e-7 / x]
+    }
+    const DEQ: [[f32; 3]; 18] = [
+        dq(3.0),
+        dq(7.0),
+        dq(15.0),
+        dq(31.0),
+        dq(63.0),
+        dq(127.0),
+        dq(255.0),
+        dq(511.0),
+        dq(1023.0),
+        dq(2047.0),
+        dq(4095.0),
+        dq(8191.0),
+        dq(16383.0),
+        dq(32767.0),
+        dq(65535.0),
+        dq(3.0),
+        dq(5.0),
+        dq(9.0),
+    ];
+
+    let mut scf = 0;
+    for i in 0..bands {
+        let mut s = 0.0;
+        let ba = sci.bitalloc[i] as usize;
+        let mask = if ba != 0 {
+            4 + ((19 >> sci.scfcod[i]) & 3)
+        } else {
+            0
+        };
+
+        for m in [4, 2, 1] {
+            if mask & m != 0 {
+                let b = bs.get_bits(6) as usize;
+                let deq = ba * 3 - 6 + b % 3;
+                s = DEQ[deq / 3][deq % 3] * ((1 << 21) >> (b / 3)) as f32;
+            }
+
+            sci.scf[scf] = s;
+            scf += 1;
+        }
+    }
+}
+
+pub(super) fn read_scale_info(hdr: &FrameHeader, bs: &mut BitReader, sci: &mut ScaleInfo) {
+    const BITALLOC_CODE_TAB: [u8; 92] = [
+        0, 17, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, //
+        0, 17, 18, 3, 19, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 16, //
+        0, 17, 18, 3, 19, 4, 5, 16, //
+        0, 17, 18, 16, //
+        0, 17, 18, 19, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, //
+        0, 17, 18, 3, 19, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, //
+        0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+    ];
+
+    let mut subband_alloc = subband_alloc_table(hdr, sci).iter();
+    let mut k = 0;
+    let mut ba_bits = 0;
+    let mut ba_code_tab: &[u8] = &BITALLOC_CODE_TAB;
+
+    for i in 0..sci.total_bands {
+        if i == k
+            && let Some(alloc) = subband_alloc.next()
+        {
+            k += alloc.band_count;
+            ba_bits = alloc.code_tab_width;
+            ba_code_tab = &BITALLOC_CODE_TAB[alloc.tab_offset..];
+        }
+
+        let mut ba = ba_code_tab[bs.get_bits(ba_bits) as usize];
+        sci.bitalloc[2 * i] = ba;
+        if i < sci.stereo_bands {
+            ba = ba_code_tab[bs.get_bits(ba_bits) as usize];
+        }
+        sci.bitalloc[2 * i + 1] = if sci.stereo_bands != 0 { ba } else { 0 };
+    }
+
+    for i in 0..2 * sci.total_bands {
+        sci.scfcod[i] = if sci.bitalloc[i] == 0 {
+            6
+        } else if hdr.is_layer1() {
+            2
+        } else {
+            bs.get_bits(2) as u8
+        };
+    }
+
+    read_scalefactors(bs, sci, sci.total_bands * 2);
+
+    for i in sci.stereo_bands..sci.total_bands {
+        sci.bitalloc[2 * i + 1] = 0;
+    }
+}
+
+/// Reads 4 groups of samples of every band, returns how many time slots were filled.
+pub(super) fn dequantize_granule(
+    grbuf: &mut [f32],
+    bs: &mut BitReader,
+    sci: &ScaleInfo,
+    group_size: usize,
+) -> usize {
+    for j in 0..4 {
+        for i in 0..2 * sci.total_bands {
+            // Alternates between the left and right channel of the same band.
+            let dst = group_size * j + (i / 2) * 18 + (i % 2) * 576;
+            let ba = sci.bitalloc[i] as u32;
+
+            if ba != 0 {
+                if ba < 17 {
+                    let half = (1 << (ba - 1)) - 1;
+
+                    for k in 0..group_size {
+                        grbuf[dst + k] = (bs.get_bits(ba) as i32 - half) as f32;
+                    }
+                } else {
+                    // Grouped samples: 3, 5 or 9 levels.
+                    let modulo = (2 << (ba - 17)) + 1;
+                    let mut code = bs.get_bits(modulo + 2 - (modulo >> 3));
+
+                    for k in 0..group_size {
+                        grbuf[dst + k] = (code % modulo) as f32 - (modulo / 2) as f32;
+                        code /= modulo;
+                    }
+                }
+            }
+        }
+    }
+
+    group_size * 4
+}
+
+/// Scales the 12 time slots of every band, copying the bands shared by both channels.
+pub(super) fn apply_scf_384(sci: &ScaleInfo, scf_offset: usize, grbuf: &mut [f32]) {
+    let stereo = sci.stereo_bands * 18;
+    let total = sci.total_bands * 18;
+    grbuf.copy_within(stereo..total, 576 + stereo);
+
+    for i in 0..sci.total_bands {
+        let scf = &sci.scf[scf_offset + i * 6..];
+        let dst = i * 18;
+
+        for k in 0..12 {
+            grbuf[dst + k] *= scf[0];
+            grbuf[dst + k + 576] *= scf[3];
+        }
+    }
+}
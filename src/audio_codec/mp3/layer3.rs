@@ -0,0 +1,976 @@
+use super::bits::BitReader;
+use super::tables::*;
+use super::{FrameHeader, SHORT_BLOCK_TYPE, STOP_BLOCK_TYPE};
+
+// Scalefactors are kept one step below the output scale, so the synthesis lands on 16 bit.
+const BITS_DEQUANTIZER_OUT: i32 = -1;
+const MAX_SCF: i32 = 255 + BITS_DEQUANTIZER_OUT * 4 - 210;
+const MAX_SCFI: i32 = (MAX_SCF + 3) & !3;
+
+/// Side information of one granule of one channel.
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct GranuleInfo {
+    /// Index of the scalefactor band table in use.
+    sfb_table: SfbTable,
+    pub(super) part_23_length: u16,
+    big_values: u16,
+    scalefac_compress: u16,
+    global_gain: u8,
+    block_type: u8,
+    mixed_block_flag: u8,
+    n_long_sfb: u8,
+    n_short_sfb: u8,
+    table_select: [u8; 3],
+    region_count: [u8; 3],
+    subblock_gain: [u8; 3],
+    preflag: u8,
+    scalefac_scale: u8,
+    count1_table: u8,
+    scfsi: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum SfbTable {
+    #[default]
+    Long,
+    Short,
+    Mixed,
+}
+
+impl GranuleInfo {
+    /// Scalefactor band widths, 0 terminated.
+    #[inline(always)]
+    fn sfb(&self, sr_idx: usize) -> &'static [u8] {
+        match self.sfb_table {
+            SfbTable::Long => &SCF_LONG[sr_idx],
+            SfbTable::Short => &SCF_SHORT[sr_idx],
+            SfbTable::Mixed => &SCF_MIXED[sr_idx],
+        }
+    }
+}
+
+/// Index of the scalefactor band tables for the header sample rate.
+#[inline(always)]
+fn sfb_rate_index(hdr: &FrameHeader) -> usize {
+    let sr_idx = hdr.my_sample_rate_index() as usize;
+
+    sr_idx - (sr_idx != 0) as usize
+}
+
+/// Returns `main_data_begin` or `None` if the side info is corrupted.
+pub(super) fn read_side_info(
+    bs: &mut BitReader,
+    gr: &mut [GranuleInfo; 4],
+    hdr: &FrameHeader,
+) -> Option<usize> {
+    let mut scfsi = 0u32;
+    let mut part_23_sum = 0usize;
+    let mut gr_count = if hdr.is_mono() { 1 } else { 2 };
+
+    let main_data_begin = if hdr.is_mpeg1() {
+        gr_count *= 2;
+        let main_data_begin = bs.get_bits(9);
+        scfsi = bs.get_bits(7 + gr_count as u32);
+
+        main_data_begin
+    } else {
+        bs.get_bits(8 + gr_count as u32) >> gr_count
+    };
+
+    for gr in gr.iter_mut().take(gr_count) {
+        if hdr.is_mono() {
+            scfsi <<= 4;
+        }
+
+        gr.part_23_length = bs.get_bits(12) as u16;
+        part_23_sum += gr.part_23_length as usize;
+        gr.big_values = bs.get_bits(9) as u16;
+        if gr.big_values > 288 {
+            return None;
+        }
+
+        gr.global_gain = bs.get_bits(8) as u8;
+        gr.scalefac_compress = bs.get_bits(if hdr.is_mpeg1() { 4 } else { 9 }) as u16;
+        gr.sfb_table = SfbTable::Long;
+        gr.n_long_sfb = 22;
+        gr.n_short_sfb = 0;
+
+        let tables;
+        if bs.get_bits(1) != 0 {
+            gr.block_type = bs.get_bits(2) as u8;
+            if gr.block_type == 0 {
+                return None;
+            }
+
+            gr.mixed_block_flag = bs.get_bits(1) as u8;
+            gr.region_count[0] = 7;
+            gr.region_count[1] = 255;
+
+            if gr.block_type == SHORT_BLOCK_TYPE {
+                scfsi &= 0x0f0f;
+
+                if gr.mixed_block_flag == 0 {
+                    gr.region_count[0] = 8;
+                    gr.sfb_table = SfbTable::Short;
+                    gr.n_long_sfb = 0;
+                    gr.n_short_sfb = 39;
+                } else {
+                    gr.sfb_table = SfbTable::Mixed;
+                    gr.n_long_sfb = if hdr.is_mpeg1() { 8 } else { 6 };
+                    gr.n_short_sfb = 30;
+                }
+            }
+
+            tables = bs.get_bits(10) << 5;
+            for subblock_gain in gr.subblock_gain.iter_mut() {
+                *subblock_gain = bs.get_bits(3) as u8;
+            }
+        } else {
+            gr.block_type = 0;
+            gr.mixed_block_flag = 0;
+            tables = bs.get_bits(15);
+            gr.region_count[0] = bs.get_bits(4) as u8;
+            gr.region_count[1] = bs.get_bits(3) as u8;
+            gr.region_count[2] = 255;
+        }
+
+        gr.table_select[0] = (tables >> 10) as u8;
+        gr.table_select[1] = ((tables >> 5) & 31) as u8;
+        gr.table_select[2] = (tables & 31) as u8;
+        gr.preflag = if hdr.is_mpeg1() {
+            bs.get_bits(1) as u8
+        } else {
+            (gr.scalefac_compress >= 500) as u8
+        };
+        gr.scalefac_scale = bs.get_bits(1) as u8;
+        gr.count1_table = bs.get_bits(1) as u8;
+        gr.scfsi = ((scfsi >> 12) & 15) as u8;
+        scfsi <<= 4;
+    }
+
+    if part_23_sum + bs.pos > bs.limit + main_data_begin as usize * 8 {
+        return None;
+    }
+
+    Some(main_data_begin as usize)
+}
+
+fn read_scalefactors(
+    scf: &mut [u8],
+    ist_pos: &mut [u8],
+    scf_size: &[u8; 4],
+    scf_count: &[u8],
+    bs: &mut BitReader,
+    mut scfsi: i32,
+) {
+    let mut offset = 0;
+
+    for i in 0..4 {
+        let count = scf_count[i] as usize;
+        if count == 0 {
+            break;
+        }
+
+        let range = offset..offset + count;
+        if scfsi & 8 != 0 {
+            // Shared with the previous granule.
+            scf[range.clone()].copy_from_slice(&ist_pos[range]);
+        } else {
+            let bits = scf_size[i] as u32;
+
+            if bits == 0 {
+                scf[range.clone()].fill(0);
+                ist_pos[range].fill(0);
+            } else {
+                let max_scf = if scfsi < 0 { (1 << bits) - 1 } else { -1 };
+
+                for k in range {
+                    let s = bs.get_bits(bits) as i32;
+                    ist_pos[k] = if s == max_scf { 255 } else { s as u8 };
+                    scf[k] = s as u8;
+                }
+            }
+        }
+
+        offset += count;
+        scfsi *= 2;
+    }
+
+    scf[offset..offset + 3].fill(0);
+}
+
+/// `y * 2^(-exp_q2 / 4)`
+fn ldexp_q2(mut y: f32, mut exp_q2: i32) -> f32 {
+    const EXPFRAC: [f32; 4] = [9.313226e-10, 7.831_458e-10, 6.585_445e-10, 5.537_677e-10];
+
+    loop {
+        let e = exp_q2.min(30 * 4);
+        y *= EXPFRAC[(e & 3) as usize] * ((1 << 30) >> (e >> 2)) as f32;
+        exp_q2 -= e;
+
+        if exp_q2 <= 0 {
+            break;
+        }
+    }
+
+    y
+}
+
+fn decode_scalefactors(
+    hdr: &FrameHeader,
+    ist_pos: &mut [u8],
+    bs: &mut BitReader,
+    gr: &GranuleInfo,
+    scf: &mut [f32],
+    ch: usize,
+) {
+    let mut partition_offset = 0;
+    let partitions =
+        &SCF_PARTITIONS[(gr.n_short_sfb != 0) as usize + (gr.n_long_sfb == 0) as usize];
+    let mut scf_size = [0u8; 4];
+    let mut iscf = [0u8; 42];
+    let scf_shift = gr.scalefac_scale as i32 + 1;
+    let mut scfsi = gr.scfsi as i32;
+
+    if hdr.is_mpeg1() {
+        const SCFC_DECODE: [u8; 16] = [0, 1, 2, 3, 12, 5, 6, 7, 9, 10, 11, 13, 14, 15, 18, 19];
+
+        let part = SCFC_DECODE[gr.scalefac_compress as usize];
+        scf_size[0] = part >> 2;
+        scf_size[1] = part >> 2;
+        scf_size[2] = part & 3;
+        scf_size[3] = part & 3;
+    } else {
+        const MOD: [u8; 6 * 4] = [
+            5, 5, 4, 4, 5, 5, 4, 1, 4, 3, 1, 1, 5, 6, 6, 1, 4, 4, 4, 1, 4, 3, 1, 1,
+        ];
+
+        let ist = (hdr.is_i_stereo() && ch != 0) as usize;
+        let mut sfc = (gr.scalefac_compress >> ist) as i32;
+        let mut k = ist * 3 * 4;
+
+        while sfc >= 0 {
+            let mut modprod = 1;
+            for i in (0..4).rev() {
+                scf_size[i] = (sfc / modprod % MOD[k + i] as i32) as u8;
+                modprod *= MOD[k + i] as i32;
+            }
+
+            sfc -= modprod;
+            k += 4;
+        }
+
+        partition_offset = k;
+        scfsi = -16;
+    }
+
+    read_scalefactors(
+        &mut iscf,
+        ist_pos,
+        &scf_size,
+        &partitions[partition_offset..],
+        bs,
+        scfsi,
+    );
+
+    let n_long = gr.n_long_sfb as usize;
+    if gr.n_short_sfb != 0 {
+        let sh = 3 - scf_shift;
+
+        for i in (0..gr.n_short_sfb as usize).step_by(3) {
+            for w in 0..3 {
+                iscf[n_long + i + w] = iscf[n_long + i + w].wrapping_add(gr.subblock_gain[w] << sh);
+            }
+        }
+    } else if gr.preflag != 0 {
+        const PREAMP: [u8; 10] = [1, 1, 1, 1, 2, 2, 3, 3, 3, 2];
+
+        for (i, preamp) in PREAMP.iter().enumerate() {
+            iscf[11 + i] = iscf[11 + i].wrapping_add(*preamp);
+        }
+    }
+
+    let gain_exp = gr.global_gain as i32 + BITS_DEQUANTIZER_OUT * 4
+        - 210
+        - if hdr.is_ms_stereo() { 2 } else { 0 };
+    let gain = ldexp_q2((1 << (MAX_SCFI / 4)) as f32, MAX_SCFI - gain_exp);
+
+    for i in 0..(gr.n_long_sfb + gr.n_short_sfb) as usize {
+        scf[i] = ldexp_q2(gain, (iscf[i] as i32) << scf_shift);
+    }
+}
+
+fn pow_43(mut x: i32) -> f32 {
+    if x < 129 {
+        return POW43[16 + x as usize];
+    }
+
+    let mut mult = 256.0;
+    if x < 1024 {
+        mult = 16.0;
+        x <<= 3;
+    }
+
+    let sign = (2 * x) & 64;
+    let frac = ((x & 63) - sign) as f32 / ((x & !63) + sign) as f32;
+
+    POW43[16 + ((x + sign) >> 6) as usize]
+        * (1.0 + frac * ((4.0 / 3.0) + frac * (2.0 / 9.0)))
+        * mult
+}
+
+fn huffman(
+    dst: &mut [f32],
+    bs: &mut BitReader,
+    gr: &GranuleInfo,
+    sfb: &[u8],
+    scf: &[f32],
+    layer3gr_limit: usize,
+) {
+    let mut one = 0.0f32;
+    let mut ireg = 0;
+    let mut big_val_cnt = gr.big_values as i32;
+    let mut sfb_i = 0;
+    let mut scf_i = 0;
+    let mut d = 0;
+
+    while big_val_cnt > 0 {
+        let tab_num = gr.table_select[ireg] as usize;
+        let mut sfb_cnt = gr.region_count[ireg] as i32;
+        ireg += 1;
+        let codebook = &HUFFMAN_TABS[HUFFMAN_TAB_INDEX[tab_num]..];
+        let linbits = LINBITS[tab_num];
+
+        loop {
+            let np = sfb[sfb_i] as i32 / 2;
+            sfb_i += 1;
+            let pairs_to_decode = big_val_cnt.min(np);
+            one = scf[scf_i];
+            scf_i += 1;
+
+            for _ in 0..pairs_to_decode {
+                let mut w = 5;
+                let mut leaf = codebook[bs.peek(w) as usize] as i32;
+                while leaf < 0 {
+                    bs.skip(w);
+                    w = (leaf & 7) as u32;
+                    leaf = codebook[(bs.peek(w) as i32 - (leaf >> 3)) as usize] as i32;
+                }
+                bs.skip((leaf >> 8) as u32);
+
+                for _ in 0..2 {
+                    let mut lsb = leaf & 0x0f;
+
+                    if lsb == 15 && linbits != 0 {
+                        lsb += bs.get_bits(linbits) as i32;
+                        let negative = bs.peek(1) != 0;
+                        dst[d] = one * pow_43(lsb) * if negative { -1.0 } else { 1.0 };
+                    } else {
+                        let negative = bs.peek(1) != 0;
+                        dst[d] = POW43[(16 + lsb - 16 * negative as i32) as usize] * one;
+                    }
+
+                    bs.skip((lsb != 0) as u32);
+                    d += 1;
+                    leaf >>= 4;
+                }
+            }
+
+            big_val_cnt -= np;
+            sfb_cnt -= 1;
+            if big_val_cnt <= 0 || sfb_cnt < 0 || np == 0 {
+                break;
+            }
+        }
+    }
+
+    let codebook_count1: &[u8] = if gr.count1_table != 0 {
+        &HUFFMAN_TAB33
+    } else {
+        &HUFFMAN_TAB32
+    };
+    let mut np = 1 - big_val_cnt;
+
+    'count1: while d + 4 <= dst.len() {
+        let mut leaf = codebook_count1[bs.peek(4) as usize] as u32;
+        if leaf & 8 == 0 {
+            let n = leaf & 3;
+            leaf =
+                codebook_count1[((leaf >> 3) + (bs.peek(4 + n) & ((1 << n) - 1))) as usize] as u32;
+        }
+        bs.skip(leaf & 7);
+
+        if bs.pos > layer3gr_limit {
+            break;
+        }
+
+        for s in 0..4 {
+            if s % 2 == 0 {
+                // Reload the scalefactor every pair.
+                np -= 1;
+                if np == 0 {
+                    np = sfb[sfb_i] as i32 / 2;
+                    sfb_i += 1;
+                    if np == 0 {
+                        break 'count1;
+                    }
+                    one = scf[scf_i];
+                    scf_i += 1;
+                }
+            }
+
+            if leaf & (128 >> s) != 0 {
+                dst[d + s] = if bs.peek(1) != 0 { -one } else { one };
+                bs.skip(1);
+            }
+        }
+
+        d += 4;
+    }
+
+    bs.pos = layer3gr_limit;
+}
+
+fn midside_stereo(left: &mut [f32], right: &mut [f32]) {
+    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+        let (a, b) = (*l, *r);
+        *l = a + b;
+        *r = a - b;
+    }
+}
+
+fn intensity_stereo_band(left: &mut [f32], right: &mut [f32], kl: f32, kr: f32) {
+    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+        *r = *l * kr;
+        *l *= kl;
+    }
+}
+
+/// Last non zero band of the right channel, for each short window.
+fn stereo_top_band(right: &[f32], sfb: &[u8], nbands: usize) -> [i32; 3] {
+    let mut max_band = [-1; 3];
+    let mut offset = 0;
+
+    for (i, width) in sfb.iter().take(nbands).enumerate() {
+        let width = *width as usize;
+
+        if right[offset..offset + width].iter().any(|v| *v != 0.0) {
+            max_band[i % 3] = i as i32;
+        }
+
+        offset += width;
+    }
+
+    max_band
+}
+
+fn stereo_process(
+    grbuf: &mut [f32],
+    ist_pos: &[u8],
+    sfb: &[u8],
+    hdr: &FrameHeader,
+    max_band: &[i32; 3],
+    mpeg2_sh: i32,
+) {
+    const PAN: [f32; 7 * 2] = [
+        0.0,
+        1.0,
+        0.211_324_87,
+        0.7886751,
+        0.366_025_4,
+        0.633_974_6,
+        0.5,
+        0.5,
+        0.633_974_6,
+        0.366_025_4,
+        0.7886751,
+        0.211_324_87,
+        1.0,
+        0.0,
+    ];
+
+    let max_pos = if hdr.is_mpeg1() { 7 } else { 64 };
+    let (left, right) = grbuf.split_at_mut(576);
+    let mut offset = 0;
+
+    for (i, width) in sfb.iter().take_while(|w| **w != 0).enumerate() {
+        let band = offset..offset + *width as usize;
+        let ipos = ist_pos[i] as u32;
+
+        if i as i32 > max_band[i % 3] && ipos < max_pos {
+            let s = if hdr.is_ms_stereo_ext() {
+                std::f32::consts::SQRT_2
+            } else {
+                1.0
+            };
+
+            let (kl, kr) = if hdr.is_mpeg1() {
+                (PAN[2 * ipos as usize], PAN[2 * ipos as usize + 1])
+            } else {
+                let k = ldexp_q2(1.0, (((ipos + 1) >> 1) as i32) << mpeg2_sh);
+                if ipos & 1 != 0 { (k, 1.0) } else { (1.0, k) }
+            };
+
+            intensity_stereo_band(&mut left[band.clone()], &mut right[band], kl * s, kr * s);
+        } else if hdr.is_ms_stereo_ext() {
+            midside_stereo(&mut left[band.clone()], &mut right[band]);
+        }
+
+        offset += *width as usize;
+    }
+}
+
+fn intensity_stereo(
+    grbuf: &mut [f32],
+    ist_pos: &mut [u8],
+    gr: &[GranuleInfo],
+    hdr: &FrameHeader,
+    sr_idx: usize,
+) {
+    let sfb = gr[0].sfb(sr_idx);
+    let n_sfb = (gr[0].n_long_sfb + gr[0].n_short_sfb) as usize;
+    let max_blocks = if gr[0].n_short_sfb != 0 { 3 } else { 1 };
+
+    let mut max_band = stereo_top_band(&grbuf[576..], sfb, n_sfb);
+    if gr[0].n_long_sfb != 0 {
+        let max = max_band[0].max(max_band[1]).max(max_band[2]);
+        max_band = [max; 3];
+    }
+
+    for (i, max_band) in max_band.iter().enumerate().take(max_blocks) {
+        let default_pos = if hdr.is_mpeg1() { 3 } else { 0 };
+        let itop = n_sfb - max_blocks + i;
+        let prev = itop - max_blocks;
+
+        ist_pos[itop] = if *max_band >= prev as i32 {
+            default_pos
+        } else {
+            ist_pos[prev]
+        };
+    }
+
+    stereo_process(
+        grbuf,
+        ist_pos,
+        sfb,
+        hdr,
+        &max_band,
+        (gr[1].scalefac_compress & 1) as i32,
+    );
+}
+
+/// Interleaves the 3 short windows of every band.
+fn reorder(grbuf: &mut [f32], scratch: &mut [f32], sfb: &[u8]) {
+    let mut src = 0;
+    let mut dst = 0;
+
+    for widths in sfb.chunks(3) {
+        let len = widths[0] as usize;
+        if len == 0 {
+            break;
+        }
+
+        for i in 0..len {
+            scratch[dst] = grbuf[src + i];
+            scratch[dst + 1] = grbuf[src + i + len];
+            scratch[dst + 2] = grbuf[src + i + 2 * len];
+            dst += 3;
+        }
+
+        src += 3 * len;
+    }
+
+    grbuf[..dst].copy_from_slice(&scratch[..dst]);
+}
+
+fn antialias(grbuf: &mut [f32], nbands: i32) {
+    const AA: [[f32; 8]; 2] = [
+        [
+            0.857_492_9,
+            0.881_742,
+            0.949_628_65,
+            0.983_314_6,
+            0.995_517_8,
+            0.999_160_6,
+            0.999_899_2,
+            0.999_993_2,
+        ],
+        [
+            0.514_495_76,
+            0.471_731_97,
+            0.313_377_45,
+            0.181_913_2,
+            0.094_574_19,
+            0.040_965_58,
+            0.014_198_56,
+            0.003_699_97,
+        ],
+    ];
+
+    for band in 0..nbands.max(0) as usize {
+        let base = band * 18;
+
+        for i in 0..8 {
+            let u = grbuf[base + 18 + i];
+            let d = grbuf[base + 17 - i];
+            grbuf[base + 18 + i] = u * AA[0][i] - d * AA[1][i];
+            grbuf[base + 17 - i] = u * AA[1][i] + d * AA[0][i];
+        }
+    }
+}
+
+fn dct3_9(y: &mut [f32; 9]) {
+    let (mut s0, mut s2, mut s4, mut s6, mut s8) = (y[0], y[2], y[4], y[6], y[8]);
+    let mut t0 = s0 + s6 * 0.5;
+    s0 -= s6;
+    let mut t4 = (s4 + s2) * 0.939_692_6;
+    let mut t2 = (s8 + s2) * 0.766_044_4;
+    s6 = (s4 - s8) * 0.173_648_18;
+    s4 += s8 - s2;
+
+    s2 = s0 - s4 * 0.5;
+    y[4] = s4 + s0;
+    s8 = t0 - t2 + s6;
+    s0 = t0 - t4 + t2;
+    s4 = t0 + t4 - s6;
+
+    let (mut s1, mut s3, mut s5, mut s7) = (y[1], y[3], y[5], y[7]);
+
+    s3 *= 0.866_025_4;
+    t0 = (s5 + s1) * 0.9848078;
+    t4 = (s5 - s7) * 0.342_020_14;
+    t2 = (s1 + s7) * 0.642_787_6;
+    s1 = (s1 - s5 - s7) * 0.866_025_4;
+
+    s5 = t0 - s3 - t2;
+    s7 = t4 - s3 - t0;
+    s3 = t4 + s3 - t2;
+
+    y[0] = s4 - s7;
+    y[1] = s2 + s1;
+    y[2] = s0 - s3;
+    y[3] = s8 + s5;
+    y[5] = s8 - s5;
+    y[6] = s0 + s3;
+    y[7] = s2 - s1;
+    y[8] = s4 + s7;
+}
+
+fn imdct36(grbuf: &mut [f32], overlap: &mut [f32], window: &[f32; 18], nbands: usize) {
+    const TWID9: [f32; 18] = [
+        0.737_277_3,
+        0.793_353_3,
+        0.843_391_4,
+        0.887_010_8,
+        0.923_879_5,
+        0.953_716_93,
+        0.976_296,
+        0.991_444_9,
+        0.999_048_2,
+        0.675_590_2,
+        0.608_761_4,
+        0.537_299_6,
+        0.461_748_6,
+        0.382_683_43,
+        0.300_705_8,
+        0.216_439_6,
+        0.130_526_19,
+        0.043_619_38,
+    ];
+
+    for j in 0..nbands {
+        let grbuf = &mut grbuf[j * 18..j * 18 + 18];
+        let overlap = &mut overlap[j * 9..j * 9 + 9];
+        let mut co = [0.0f32; 9];
+        let mut si = [0.0f32; 9];
+
+        co[0] = -grbuf[0];
+        si[0] = grbuf[17];
+        for i in 0..4 {
+            si[8 - 2 * i] = grbuf[4 * i + 1] - grbuf[4 * i + 2];
+            co[1 + 2 * i] = grbuf[4 * i + 1] + grbuf[4 * i + 2];
+            si[7 - 2 * i] = grbuf[4 * i + 4] - grbuf[4 * i + 3];
+            co[2 + 2 * i] = -(grbuf[4 * i + 3] + grbuf[4 * i + 4]);
+        }
+
+        dct3_9(&mut co);
+        dct3_9(&mut si);
+
+        si[1] = -si[1];
+        si[3] = -si[3];
+        si[5] = -si[5];
+        si[7] = -si[7];
+
+        for i in 0..9 {
+            let ovl = overlap[i];
+            let sum = co[i] * TWID9[9 + i] + si[i] * TWID9[i];
+            overlap[i] = co[i] * TWID9[i] - si[i] * TWID9[9 + i];
+            grbuf[i] = ovl * window[i] - sum * window[9 + i];
+            grbuf[17 - i] = ovl * window[9 + i] + sum * window[i];
+        }
+    }
+}
+
+fn idct3(x0: f32, x1: f32, x2: f32) -> [f32; 3] {
+    let m1 = x1 * 0.866_025_4;
+    let a1 = x0 - x2 * 0.5;
+
+    [a1 + m1, x0 + x2, a1 - m1]
+}
+
+/// `x` is read with a stride of 3 (one short window).
+fn imdct12(x: &[f32], dst: &mut [f32], overlap: &mut [f32]) {
+    const TWID3: [f32; 6] = [
+        0.793_353_3,
+        0.923_879_5,
+        0.991_444_9,
+        0.608_761_4,
+        0.382_683_43,
+        0.130_526_19,
+    ];
+
+    let co = idct3(-x[0], x[6] + x[3], x[12] + x[9]);
+    let mut si = idct3(x[15], x[12] - x[9], x[6] - x[3]);
+    si[1] = -si[1];
+
+    for i in 0..3 {
+        let ovl = overlap[i];
+        let sum = co[i] * TWID3[3 + i] + si[i] * TWID3[i];
+        overlap[i] = co[i] * TWID3[i] - si[i] * TWID3[3 + i];
+        dst[i] = ovl * TWID3[2 - i] - sum * TWID3[5 - i];
+        dst[5 - i] = ovl * TWID3[5 - i] + sum * TWID3[2 - i];
+    }
+}
+
+fn imdct_short(grbuf: &mut [f32], overlap: &mut [f32], nbands: usize) {
+    for band in 0..nbands {
+        let grbuf = &mut grbuf[band * 18..band * 18 + 18];
+        let overlap = &mut overlap[band * 9..band * 9 + 9];
+        let mut tmp = [0.0f32; 18];
+        tmp.copy_from_slice(grbuf);
+
+        grbuf[..6].copy_from_slice(&overlap[..6]);
+
+        let (first, last) = overlap.split_at_mut(6);
+        imdct12(&tmp, &mut grbuf[6..12], last);
+        imdct12(&tmp[1..], &mut grbuf[12..18], last);
+        imdct12(&tmp[2..], first, last);
+    }
+}
+
+fn change_sign(grbuf: &mut [f32]) {
+    for band in (1..32).step_by(2) {
+        for i in (1..18).step_by(2) {
+            grbuf[band * 18 + i] = -grbuf[band * 18 + i];
+        }
+    }
+}
+
+fn imdct_granule(grbuf: &mut [f32], overlap: &mut [f32], block_type: u8, n_long_bands: usize) {
+    const MDCT_WINDOW: [[f32; 18]; 2] = [
+        [
+            0.999_048_2,
+            0.991_444_9,
+            0.976_296,
+            0.953_716_93,
+            0.923_879_5,
+            0.887_010_8,
+            0.843_391_4,
+            0.793_353_3,
+            0.737_277_3,
+            0.043_619_38,
+            0.130_526_19,
+            0.216_439_6,
+            0.300_705_8,
+            0.382_683_43,
+            0.461_748_6,
+            0.537_299_6,
+            0.608_761_4,
+            0.675_590_2,
+        ],
+        [
+            1.0,
+            1.0,
+            1.0,
+            1.0,
+            1.0,
+            1.0,
+            0.991_444_9,
+            0.923_879_5,
+            0.793_353_3,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.130_526_19,
+            0.382_683_43,
+            0.608_761_4,
+        ],
+    ];
+
+    if n_long_bands > 0 {
+        imdct36(grbuf, overlap, &MDCT_WINDOW[0], n_long_bands);
+    }
+
+    let grbuf = &mut grbuf[18 * n_long_bands..];
+    let overlap = &mut overlap[9 * n_long_bands..];
+
+    if block_type == SHORT_BLOCK_TYPE {
+        imdct_short(grbuf, overlap, 32 - n_long_bands);
+    } else {
+        imdct36(
+            grbuf,
+            overlap,
+            &MDCT_WINDOW[(block_type == STOP_BLOCK_TYPE) as usize],
+            32 - n_long_bands,
+        );
+    }
+}
+
+/// Decoder state that lives across frames.
+pub(super) struct Layer3State {
+    pub(super) mdct_overlap: [[f32; 9 * 32]; 2],
+    /// Main data kept for the next frames.
+    pub(super) reservoir: Vec<u8>,
+}
+impl Default for Layer3State {
+    fn default() -> Self {
+        Self {
+            mdct_overlap: [[0.0; 9 * 32]; 2],
+            reservoir: Vec::with_capacity(super::MAX_BITRESERVOIR_BYTES),
+        }
+    }
+}
+
+/// Frame scratch memory.
+pub(super) struct Layer3Scratch {
+    pub(super) gr_info: [GranuleInfo; 4],
+    pub(super) maindata: Vec<u8>,
+    /// One granule, left channel then right channel.
+    pub(super) grbuf: [f32; 576 * 2],
+    scf: [f32; 40],
+    reorder: [f32; 576],
+    ist_pos: [[u8; 42]; 2],
+}
+impl Default for Layer3Scratch {
+    fn default() -> Self {
+        Self {
+            gr_info: [GranuleInfo::default(); 4],
+            maindata: Vec::with_capacity(
+                super::MAX_BITRESERVOIR_BYTES + super::MAX_L3_FRAME_PAYLOAD_BYTES,
+            ),
+            grbuf: [0.0; 576 * 2],
+            scf: [0.0; 40],
+            reorder: [0.0; 576],
+            ist_pos: [[0; 42]; 2],
+        }
+    }
+}
+
+/// Joins the reservoir with the frame main data.
+/// Returns false if the reservoir doesn't have all the bytes needed (like after a seek).
+pub(super) fn restore_reservoir(
+    state: &Layer3State,
+    scratch: &mut Layer3Scratch,
+    frame_main_data: &[u8],
+    main_data_begin: usize,
+) -> bool {
+    let reserv = state.reservoir.len();
+    let bytes_have = reserv.min(main_data_begin);
+
+    scratch.maindata.clear();
+    scratch
+        .maindata
+        .extend_from_slice(&state.reservoir[reserv - bytes_have..]);
+    scratch.maindata.extend_from_slice(frame_main_data);
+
+    reserv >= main_data_begin
+}
+
+/// Keeps what was not used of the main data for the next frames.
+pub(super) fn save_reservoir(state: &mut Layer3State, maindata: &[u8], bit_pos: usize) {
+    let mut pos = bit_pos.div_ceil(8).min(maindata.len());
+    let mut remains = maindata.len() - pos;
+
+    if remains > super::MAX_BITRESERVOIR_BYTES {
+        pos += remains - super::MAX_BITRESERVOIR_BYTES;
+        remains = super::MAX_BITRESERVOIR_BYTES;
+    }
+
+    state.reservoir.clear();
+    state
+        .reservoir
+        .extend_from_slice(&maindata[pos..pos + remains]);
+}
+
+/// Decodes one granule of every channel into `scratch.grbuf`, ready for the synthesis.
+pub(super) fn decode_granule(
+    hdr: &FrameHeader,
+    state: &mut Layer3State,
+    scratch: &mut Layer3Scratch,
+    bs: &mut BitReader,
+    granule: usize,
+    nch: usize,
+) {
+    let sr_idx = sfb_rate_index(hdr);
+    let gr_info = &scratch.gr_info[granule * nch..granule * nch + nch];
+
+    scratch.grbuf.fill(0.0);
+
+    for (ch, gr) in gr_info.iter().enumerate() {
+        let layer3gr_limit = bs.pos + gr.part_23_length as usize;
+
+        decode_scalefactors(hdr, &mut scratch.ist_pos[ch], bs, gr, &mut scratch.scf, ch);
+        huffman(
+            &mut scratch.grbuf[576 * ch..576 * ch + 576],
+            bs,
+            gr,
+            gr.sfb(sr_idx),
+            &scratch.scf,
+            layer3gr_limit,
+        );
+    }
+
+    // The mode extension bits of mono frames are meaningless.
+    if nch == 2 {
+        if hdr.is_i_stereo() {
+            intensity_stereo(
+                &mut scratch.grbuf,
+                &mut scratch.ist_pos[1],
+                gr_info,
+                hdr,
+                sr_idx,
+            );
+        } else if hdr.is_ms_stereo() {
+            let (left, right) = scratch.grbuf.split_at_mut(576);
+            midside_stereo(left, right);
+        }
+    }
+
+    for (ch, gr) in gr_info.iter().enumerate() {
+        let grbuf = &mut scratch.grbuf[576 * ch..576 * ch + 576];
+        let mut aa_bands = 31;
+        let n_long_bands = (if gr.mixed_block_flag != 0 { 2 } else { 0 })
+            << (hdr.my_sample_rate_index() == 2) as usize;
+
+        if gr.n_short_sfb != 0 {
+            aa_bands = n_long_bands as i32 - 1;
+            reorder(
+                &mut grbuf[n_long_bands * 18..],
+                &mut scratch.reorder,
+                &gr.sfb(sr_idx)[gr.n_long_sfb as usize..],
+            );
+        }
+
+        antialias(grbuf, aa_bands);
+        imdct_granule(
+            grbuf,
+            &mut state.mdct_overlap[ch],
+            gr.block_type,
+            n_long_bands,
+        );
+        change_sign(grbuf);
+    }
+}
@@ -0,0 +1,268 @@
+use super::{
+    AudioInfo,
+    sample::{Sample, SampleType},
+};
+use std::io;
+use std::marker::PhantomData;
+
+pub mod decoder;
+pub mod id3;
+pub mod reader;
+
+mod bits;
+mod layer12;
+mod layer3;
+mod synth;
+mod tables;
+#[cfg(test)]
+mod tests;
+
+pub use decoder::LgMp3Decoder;
+pub use id3::{Id3Frame, Id3Tag};
+
+use reader::LgMp3Reader;
+
+// ------------------------- LAYOUT --------------------------
+const HDR_SIZE: usize = 4;
+/// Bigger than what the specification allows, some encoders go over it.
+const MAX_FREE_FORMAT_FRAME_SIZE: usize = 2304;
+/// Consecutive frames that must match for a sync to be trusted.
+const MAX_FRAME_SYNC_MATCHES: usize = 10;
+const MAX_L3_FRAME_PAYLOAD_BYTES: usize = MAX_FREE_FORMAT_FRAME_SIZE;
+const MAX_BITRESERVOIR_BYTES: usize = 511;
+const MAX_SAMPLES_PER_FRAME: usize = 1152 * 2;
+
+const SHORT_BLOCK_TYPE: u8 = 2;
+const STOP_BLOCK_TYPE: u8 = 3;
+const MODE_MONO: u8 = 3;
+const MODE_JOINT_STEREO: u8 = 1;
+
+/// Decoded audio is float, so it keeps the full precision of the synthesis.
+#[inline(always)]
+fn mp3_info(channels: u16, sample_rate: u32) -> AudioInfo {
+    AudioInfo {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_type: Some(SampleType::FLOAT),
+    }
+}
+
+// ------------------------- HEADER --------------------------
+
+/// The 4 bytes at the start of every MPEG audio frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(super) struct FrameHeader([u8; HDR_SIZE]);
+impl FrameHeader {
+    #[inline(always)]
+    fn from_slice(bytes: &[u8]) -> Self {
+        Self([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    #[inline(always)]
+    fn is_valid(&self) -> bool {
+        let h = &self.0;
+
+        h[0] == 0xff
+            && ((h[1] & 0xf0) == 0xf0 || (h[1] & 0xfe) == 0xe2)
+            && self.layer_bits() != 0
+            && self.bitrate_index() != 15
+            && self.sample_rate_index() != 3
+    }
+
+    /// Whether `other` can be the next frame of the same stream.
+    #[inline(always)]
+    fn matches(&self, other: &Self) -> bool {
+        other.is_valid()
+            && ((self.0[1] ^ other.0[1]) & 0xfe) == 0
+            && ((self.0[2] ^ other.0[2]) & 0x0c) == 0
+            && self.is_free_format() == other.is_free_format()
+    }
+
+    #[inline(always)]
+    fn is_mono(&self) -> bool {
+        (self.0[3] & 0xc0) == 0xc0
+    }
+
+    #[inline(always)]
+    fn is_ms_stereo(&self) -> bool {
+        (self.0[3] & 0xe0) == 0x60
+    }
+
+    #[inline(always)]
+    fn is_free_format(&self) -> bool {
+        (self.0[2] & 0xf0) == 0
+    }
+
+    #[inline(always)]
+    fn is_crc(&self) -> bool {
+        (self.0[1] & 1) == 0
+    }
+
+    #[inline(always)]
+    fn has_padding(&self) -> bool {
+        (self.0[2] & 0x2) != 0
+    }
+
+    #[inline(always)]
+    fn is_mpeg1(&self) -> bool {
+        (self.0[1] & 0x8) != 0
+    }
+
+    #[inline(always)]
+    fn is_not_mpeg25(&self) -> bool {
+        (self.0[1] & 0x10) != 0
+    }
+
+    #[inline(always)]
+    fn is_i_stereo(&self) -> bool {
+        (self.0[3] & 0x10) != 0
+    }
+
+    #[inline(always)]
+    fn is_ms_stereo_ext(&self) -> bool {
+        (self.0[3] & 0x20) != 0
+    }
+
+    #[inline(always)]
+    fn stereo_mode(&self) -> u8 {
+        (self.0[3] >> 6) & 3
+    }
+
+    #[inline(always)]
+    fn stereo_mode_ext(&self) -> u8 {
+        (self.0[3] >> 4) & 3
+    }
+
+    /// 3 for Layer I, 1 for Layer III.
+    #[inline(always)]
+    fn layer_bits(&self) -> u8 {
+        (self.0[1] >> 1) & 3
+    }
+
+    #[inline(always)]
+    fn layer(&self) -> u8 {
+        4 - self.layer_bits()
+    }
+
+    #[inline(always)]
+    fn bitrate_index(&self) -> u8 {
+        self.0[2] >> 4
+    }
+
+    #[inline(always)]
+    fn sample_rate_index(&self) -> u8 {
+        (self.0[2] >> 2) & 3
+    }
+
+    /// Sample rate index over the 9 rates of MPEG-2.5, MPEG-2 and MPEG-1.
+    #[inline(always)]
+    fn my_sample_rate_index(&self) -> u8 {
+        self.sample_rate_index() + (((self.0[1] >> 3) & 1) + ((self.0[1] >> 4) & 1)) * 3
+    }
+
+    #[inline(always)]
+    fn is_frame_576(&self) -> bool {
+        (self.0[1] & 14) == 2
+    }
+
+    #[inline(always)]
+    fn is_layer1(&self) -> bool {
+        (self.0[1] & 6) == 6
+    }
+
+    #[inline(always)]
+    fn channels(&self) -> u16 {
+        if self.is_mono() { 1 } else { 2 }
+    }
+
+    fn bitrate_kbps(&self) -> u32 {
+        const HALFRATE: [[[u8; 15]; 3]; 2] = [
+            [
+                [0, 4, 8, 12, 16, 20, 24, 28, 32, 40, 48, 56, 64, 72, 80],
+                [0, 4, 8, 12, 16, 20, 24, 28, 32, 40, 48, 56, 64, 72, 80],
+                [0, 16, 24, 28, 32, 40, 48, 56, 64, 72, 80, 88, 96, 112, 128],
+            ],
+            [
+                [0, 16, 20, 24, 28, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160],
+                [
+                    0, 16, 24, 28, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192,
+                ],
+                [
+                    0, 16, 32, 48, 64, 80, 96, 112, 128, 144, 160, 176, 192, 208, 224,
+                ],
+            ],
+        ];
+
+        2 * HALFRATE[self.is_mpeg1() as usize][self.layer_bits() as usize - 1]
+            [self.bitrate_index() as usize] as u32
+    }
+
+    fn sample_rate(&self) -> u32 {
+        const HZ: [u32; 3] = [44100, 48000, 32000];
+
+        HZ[self.sample_rate_index() as usize]
+            >> (!self.is_mpeg1() as u32)
+            >> (!self.is_not_mpeg25() as u32)
+    }
+
+    /// Samples per channel.
+    #[inline(always)]
+    fn frame_samples(&self) -> usize {
+        if self.is_layer1() {
+            384
+        } else {
+            1152 >> (self.is_frame_576() as usize)
+        }
+    }
+
+    /// Free format frames don't know their size, it must be found by syncing.
+    fn frame_bytes(&self, free_format_size: usize) -> usize {
+        let mut frame_bytes =
+            self.frame_samples() * self.bitrate_kbps() as usize * 125 / self.sample_rate() as usize;
+
+        if self.is_layer1() {
+            // Slot align.
+            frame_bytes &= !3;
+        }
+
+        if frame_bytes == 0 {
+            free_format_size
+        } else {
+            frame_bytes
+        }
+    }
+
+    #[inline(always)]
+    fn padding(&self) -> usize {
+        match (self.has_padding(), self.is_layer1()) {
+            (false, _) => 0,
+            (true, true) => 4,
+            (true, false) => 1,
+        }
+    }
+}
+
+// ------------------------- SAMPLE --------------------------
+
+pub struct LgMp3SampleIter<'si, R: io::Read, S: Sample> {
+    reader: &'si mut LgMp3Reader<R>,
+    _phantom: PhantomData<S>,
+}
+impl<'si, R: io::Read, S: Sample> LgMp3SampleIter<'si, R, S> {
+    fn new(reader: &'si mut LgMp3Reader<R>) -> Self {
+        Self {
+            reader,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<R: io::Read, S: Sample> Iterator for LgMp3SampleIter<'_, R, S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.reader.next_sample().ok()??;
+
+        Some(S::from_f32(sample, SampleType::FLOAT, 32))
+    }
+}
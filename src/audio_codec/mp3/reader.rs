@@ -0,0 +1,613 @@
+use super::super::error::Error;
+use super::bits::BitReader;
+use super::id3::{self, Id3Tag};
+use super::layer3::{self, Layer3Scratch, Layer3State};
+use super::layer12::{self, ScaleInfo};
+use super::synth::{QMF_STATE_LEN, SYNTH_LINS_LEN, synth_granule};
+use super::{
+    FrameHeader, HDR_SIZE, MAX_FRAME_SYNC_MATCHES, MAX_FREE_FORMAT_FRAME_SIZE,
+    MAX_SAMPLES_PER_FRAME,
+};
+use std::io;
+
+/// Bytes kept ahead of the current frame, enough to sync over 10 frames in the worst case.
+const BUF_SIZE: usize = 16 * 1024;
+/// Bigger than any frame, bytes kept when a sync fails to retry once more data arrives.
+const MAX_FRAME_LOOKAHEAD: usize = 4096;
+
+const XING_FRAMES_FLAG: u8 = 1;
+const XING_BYTES_FLAG: u8 = 2;
+const XING_TOC_FLAG: u8 = 4;
+const XING_VBR_SCALE_FLAG: u8 = 8;
+/// Delay of the decoder, LAME stores only the encoder delay.
+const DECODER_DELAY: usize = 528 + 1;
+/// VBRI tags are always 32 bytes after the header.
+const VBRI_OFFSET: usize = HDR_SIZE + 32;
+
+/// Whether `data[i..]` holds `HDR_SIZE` bytes of a header matching `hdr`.
+#[inline(always)]
+fn matches_at(hdr: &FrameHeader, data: &[u8], i: usize) -> bool {
+    i + HDR_SIZE <= data.len() && hdr.matches(&FrameHeader::from_slice(&data[i..]))
+}
+
+/// Checks that the frames following `data` are from the same stream.
+fn match_frame(data: &[u8], frame_bytes: usize) -> bool {
+    let hdr = FrameHeader::from_slice(data);
+    let mut i = 0;
+
+    for nmatch in 0..MAX_FRAME_SYNC_MATCHES {
+        let current = FrameHeader::from_slice(&data[i..]);
+        i += current.frame_bytes(frame_bytes) + current.padding();
+
+        if i + HDR_SIZE > data.len() {
+            return nmatch > 0;
+        }
+
+        if !hdr.matches(&FrameHeader::from_slice(&data[i..])) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Returns the offset and size of the first frame, the size is 0 when none was found.
+/// Free format frames get their size from the distance to the next frame.
+fn find_frame(data: &[u8], free_format_bytes: &mut usize) -> (usize, usize) {
+    for i in 0..data.len().saturating_sub(HDR_SIZE) {
+        let mp3 = &data[i..];
+        let hdr = FrameHeader::from_slice(mp3);
+        if !hdr.is_valid() {
+            continue;
+        }
+
+        let mut frame_bytes = hdr.frame_bytes(*free_format_bytes);
+        let mut frame_and_padding = frame_bytes + hdr.padding();
+
+        let mut k = HDR_SIZE;
+        while frame_bytes == 0
+            && k < MAX_FREE_FORMAT_FRAME_SIZE
+            && i + 2 * k + HDR_SIZE < data.len()
+        {
+            if matches_at(&hdr, mp3, k) {
+                let fb = k - hdr.padding();
+                let next_fb = fb + FrameHeader::from_slice(&mp3[k..]).padding();
+
+                if matches_at(&hdr, mp3, k + next_fb) {
+                    frame_and_padding = k;
+                    frame_bytes = fb;
+                    *free_format_bytes = fb;
+                }
+            }
+
+            k += 1;
+        }
+
+        if (frame_bytes != 0
+            && i + frame_and_padding <= data.len()
+            && match_frame(mp3, frame_bytes))
+            || (i == 0 && frame_and_padding == data.len())
+        {
+            return (i, frame_and_padding);
+        }
+
+        *free_format_bytes = 0;
+    }
+
+    (data.len(), 0)
+}
+
+/// Length of the ID3v1 and APEv2 tags at the end of `data`.
+fn trailing_tags_size(data: &[u8]) -> usize {
+    let mut size = data.len();
+
+    if size > 128 && &data[size - 128..size - 125] == b"TAG" {
+        size -= 128;
+
+        if size > 227 && &data[size - 227..size - 223] == b"TAG+" {
+            size -= 227;
+        }
+    }
+
+    if size > 32 && &data[size - 32..size - 24] == b"APETAGEX" {
+        size -= 32;
+    }
+
+    data.len() - size
+}
+
+/// Gapless info from the first frame.
+struct VbrTag {
+    /// Audio frames, not counting the tag frame.
+    frames: usize,
+    /// Samples per channel to skip at the start.
+    delay: usize,
+    /// Samples per channel to skip at the end.
+    padding: usize,
+}
+
+/// Reads a Xing/Info (with the LAME extension) or a VBRI tag.
+fn vbr_tag(frame: &[u8]) -> Option<VbrTag> {
+    let hdr = FrameHeader::from_slice(frame);
+    if hdr.layer() != 3 {
+        return None;
+    }
+
+    if frame.len() >= VBRI_OFFSET + 18 && &frame[VBRI_OFFSET..VBRI_OFFSET + 4] == b"VBRI" {
+        let frames = &frame[VBRI_OFFSET + 14..VBRI_OFFSET + 18];
+
+        return Some(VbrTag {
+            frames: u32::from_be_bytes([frames[0], frames[1], frames[2], frames[3]]) as usize,
+            delay: 0,
+            padding: 0,
+        });
+    }
+
+    let mut bs = BitReader::new(&frame[HDR_SIZE..]);
+    if hdr.is_crc() {
+        bs.skip(16);
+    }
+
+    let mut gr_info = Default::default();
+    layer3::read_side_info(&mut bs, &mut gr_info, &hdr)?;
+
+    let mut tag = HDR_SIZE + bs.pos / 8;
+    if tag + 12 > frame.len() || !matches!(&frame[tag..tag + 4], b"Xing" | b"Info") {
+        return None;
+    }
+
+    let flags = frame[tag + 7];
+    if flags & XING_FRAMES_FLAG == 0 {
+        return None;
+    }
+
+    tag += 8;
+    let frames = u32::from_be_bytes([frame[tag], frame[tag + 1], frame[tag + 2], frame[tag + 3]]);
+    tag += 4;
+
+    if flags & XING_BYTES_FLAG != 0 {
+        tag += 4;
+    }
+    if flags & XING_TOC_FLAG != 0 {
+        tag += 100;
+    }
+    if flags & XING_VBR_SCALE_FLAG != 0 {
+        tag += 4;
+    }
+
+    let mut result = VbrTag {
+        frames: frames as usize,
+        delay: 0,
+        padding: 0,
+    };
+
+    // LAME (or Lavc, ...) extension.
+    if frame.get(tag).is_some_and(|b| *b != 0) {
+        tag += 21;
+        if tag + 14 >= frame.len() {
+            return None;
+        }
+
+        let delay = ((frame[tag] as usize) << 4) | (frame[tag + 1] as usize >> 4);
+        let padding = ((frame[tag + 1] as usize & 0xf) << 8) | frame[tag + 2] as usize;
+
+        result.delay = delay + DECODER_DELAY;
+        result.padding = padding.saturating_sub(DECODER_DELAY);
+    }
+
+    Some(result)
+}
+
+/// State of the frame decoding, kept apart from the input buffer.
+struct FrameDecoder {
+    layer3: Layer3State,
+    scratch: Box<Layer3Scratch>,
+    scale_info: Box<ScaleInfo>,
+    qmf_state: [f32; QMF_STATE_LEN],
+    lins: Box<[f32; SYNTH_LINS_LEN]>,
+    /// Interleaved samples of the last frame, in the frame channels.
+    pcm: Vec<f32>,
+}
+impl FrameDecoder {
+    fn new() -> Self {
+        Self {
+            layer3: Layer3State::default(),
+            scratch: Box::default(),
+            scale_info: Box::default(),
+            qmf_state: [0.0; QMF_STATE_LEN],
+            lins: Box::new([0.0; SYNTH_LINS_LEN]),
+            pcm: vec![0.0; MAX_SAMPLES_PER_FRAME],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.layer3 = Layer3State::default();
+        self.qmf_state.fill(0.0);
+    }
+
+    /// Decodes into `pcm`, frames that can't be decoded give silence.
+    /// Returns false if the frame is corrupted.
+    fn decode(&mut self, frame: &[u8]) -> bool {
+        let hdr = FrameHeader::from_slice(frame);
+        let nch = hdr.channels() as usize;
+        let len = hdr.frame_samples() * nch;
+
+        let mut bs = BitReader::new(&frame[HDR_SIZE..]);
+        if hdr.is_crc() {
+            bs.skip(16);
+        }
+
+        let decoded = if hdr.layer() == 3 {
+            self.decode_layer3(&hdr, frame, &mut bs, nch)
+        } else {
+            self.decode_layer12(&hdr, &mut bs, nch).then_some(true)
+        };
+
+        if decoded != Some(true) {
+            self.pcm[..len].fill(0.0);
+        }
+
+        decoded.is_some()
+    }
+
+    /// `None` if the frame is corrupted, `Some(false)` if the bit reservoir misses data.
+    fn decode_layer3(
+        &mut self,
+        hdr: &FrameHeader,
+        frame: &[u8],
+        bs: &mut BitReader,
+        nch: usize,
+    ) -> Option<bool> {
+        let main_data_begin = layer3::read_side_info(bs, &mut self.scratch.gr_info, hdr)?;
+        if bs.overflowed() {
+            return None;
+        }
+
+        let main_data = &frame[HDR_SIZE + bs.pos / 8..];
+        let success =
+            layer3::restore_reservoir(&self.layer3, &mut self.scratch, main_data, main_data_begin);
+
+        let maindata = std::mem::take(&mut self.scratch.maindata);
+        let mut bs = BitReader::new(&maindata);
+
+        if success {
+            let granules = if hdr.is_mpeg1() { 2 } else { 1 };
+
+            for granule in 0..granules {
+                layer3::decode_granule(
+                    hdr,
+                    &mut self.layer3,
+                    &mut self.scratch,
+                    &mut bs,
+                    granule,
+                    nch,
+                );
+                synth_granule(
+                    &mut self.qmf_state,
+                    &mut self.scratch.grbuf,
+                    18,
+                    nch,
+                    &mut self.pcm[granule * 576 * nch..],
+                    &mut self.lins,
+                );
+            }
+        }
+
+        layer3::save_reservoir(&mut self.layer3, &maindata, bs.pos);
+        self.scratch.maindata = maindata;
+
+        Some(success)
+    }
+
+    fn decode_layer12(&mut self, hdr: &FrameHeader, bs: &mut BitReader, nch: usize) -> bool {
+        let sci = &mut self.scale_info;
+        let grbuf = &mut self.scratch.grbuf;
+        // 4 samples per band for Layer I, 12 for Layer II.
+        let group_size = hdr.layer() as usize | 1;
+
+        layer12::read_scale_info(hdr, bs, sci);
+        grbuf.fill(0.0);
+
+        let mut i = 0;
+        let mut out = 0;
+        for granule in 0..3 {
+            i += layer12::dequantize_granule(&mut grbuf[i..], bs, sci, group_size);
+
+            if i == 12 {
+                i = 0;
+                layer12::apply_scf_384(sci, granule, grbuf);
+                synth_granule(
+                    &mut self.qmf_state,
+                    grbuf,
+                    12,
+                    nch,
+                    &mut self.pcm[out..],
+                    &mut self.lins,
+                );
+                grbuf.fill(0.0);
+                out += 384 * nch;
+            }
+
+            if bs.overflowed() {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+pub struct LgMp3Reader<R: io::Read> {
+    pub(super) reader: R,
+    pub(super) channels: u16,
+    pub(super) sample_rate: u32,
+    pub(super) id3: Option<Id3Tag>,
+    /// Interleaved samples after the gapless trimming, known from a Xing/Info or VBRI tag.
+    pub(super) total_samples: Option<usize>,
+
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+
+    /// Header of the last frame, the next one must match it or the stream is synced again.
+    header: Option<FrameHeader>,
+    free_format_bytes: usize,
+    decoder: FrameDecoder,
+
+    /// Interleaved samples of the last frame, in `channels`.
+    pcm: Vec<f32>,
+    cursor: usize,
+    /// Interleaved samples still to skip from the encoder and decoder delay.
+    to_skip: usize,
+    /// Interleaved samples still to output, if known.
+    remaining: Option<usize>,
+}
+impl<R: io::Read> LgMp3Reader<R> {
+    /// Reads the ID3v2 tags and syncs to the first frame, so the format is known.
+    pub(super) fn new(reader: R) -> Result<Self, Error> {
+        let mut result = Self {
+            reader,
+            channels: 0,
+            sample_rate: 0,
+            id3: None,
+            total_samples: None,
+            buf: Vec::with_capacity(BUF_SIZE * 2),
+            pos: 0,
+            eof: false,
+            header: None,
+            free_format_bytes: 0,
+            decoder: FrameDecoder::new(),
+            pcm: Vec::with_capacity(MAX_SAMPLES_PER_FRAME),
+            cursor: 0,
+            to_skip: 0,
+            remaining: None,
+        };
+
+        result.read_id3v2()?;
+
+        let frame_size = result.next_frame()?.ok_or(Error::WrongHeader)?;
+        let frame = &result.buf[result.pos..result.pos + frame_size];
+        let hdr = FrameHeader::from_slice(frame);
+
+        result.channels = hdr.channels();
+        result.sample_rate = hdr.sample_rate();
+
+        if let Some(tag) = vbr_tag(frame) {
+            let channels = result.channels as usize;
+            let total = (tag.frames * hdr.frame_samples()).saturating_sub(tag.delay + tag.padding);
+
+            result.to_skip = tag.delay * channels;
+            result.total_samples = Some(total * channels);
+            result.remaining = result.total_samples;
+
+            // The tag frame has no audio.
+            result.pos += frame_size;
+        }
+
+        Ok(result)
+    }
+
+    /// Next decoded sample, `None` once all the frames were read.
+    pub(super) fn next_sample(&mut self) -> Result<Option<f32>, Error> {
+        while self.cursor >= self.pcm.len() {
+            if self.remaining == Some(0) || !self.decode_frame()? {
+                return Ok(None);
+            }
+        }
+
+        self.cursor += 1;
+
+        Ok(Some(self.pcm[self.cursor - 1]))
+    }
+
+    /// Counts the interleaved samples left by syncing every frame, without decoding.
+    pub(super) fn count_samples(&mut self) -> Result<usize, Error> {
+        let mut samples = 0;
+
+        while let Some(frame_size) = self.next_frame()? {
+            let hdr = FrameHeader::from_slice(&self.buf[self.pos..]);
+            self.header = Some(hdr);
+            self.pos += frame_size;
+
+            samples += hdr.frame_samples() * self.channels as usize;
+        }
+
+        Ok(samples)
+    }
+}
+impl<R: io::Read + io::Seek> LgMp3Reader<R> {
+    /// Like [`LgMp3Reader::count_samples`], then goes back to where it was, so the length of
+    /// a file without a Xing/Info or VBRI tag is known with a single scan.
+    pub(super) fn count_samples_and_rewind(&mut self) -> Result<usize, Error> {
+        let position = self.reader.stream_position()?;
+        let buffered = self.buf[self.pos..].to_vec();
+        let (eof, header, free_format_bytes) = (self.eof, self.header, self.free_format_bytes);
+
+        let samples = self.count_samples()?;
+
+        self.reader.seek(io::SeekFrom::Start(position))?;
+        self.buf.clear();
+        self.buf.extend_from_slice(&buffered);
+        self.pos = 0;
+        self.eof = eof;
+        self.header = header;
+        self.free_format_bytes = free_format_bytes;
+
+        Ok(samples)
+    }
+}
+impl<R: io::Read> LgMp3Reader<R> {
+    /// Reads until `size` bytes are buffered or the stream ends.
+    fn fill_to(&mut self, size: usize) -> Result<(), Error> {
+        if self.eof || self.buf.len() - self.pos >= size {
+            return Ok(());
+        }
+
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+
+        let mut len = self.buf.len();
+        self.buf.resize(size.max(BUF_SIZE), 0);
+
+        while len < size {
+            match self.reader.read(&mut self.buf[len..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        self.buf.truncate(len);
+
+        if self.eof {
+            let tags = trailing_tags_size(&self.buf);
+            self.buf.truncate(len - tags);
+        }
+
+        Ok(())
+    }
+
+    /// Parses every ID3v2 tag at the start, only the first one is kept.
+    fn read_id3v2(&mut self) -> Result<(), Error> {
+        loop {
+            self.fill_to(id3::ID3V2_HEADER_SIZE)?;
+
+            let Some(size) = id3::tag_size(&self.buf[self.pos..]) else {
+                return Ok(());
+            };
+
+            self.fill_to(size)?;
+
+            let end = (self.pos + size).min(self.buf.len());
+            if self.id3.is_none() {
+                self.id3 = Some(id3::parse(&self.buf[self.pos..end]));
+            }
+
+            self.pos = end;
+        }
+    }
+
+    /// Forgets everything about the previous frames, like after a corrupted one.
+    fn reset(&mut self) {
+        self.header = None;
+        self.free_format_bytes = 0;
+        self.decoder.reset();
+    }
+
+    /// Syncs to the next frame, which starts at `pos`. Returns its size or `None` at the end.
+    fn next_frame(&mut self) -> Result<Option<usize>, Error> {
+        loop {
+            self.fill_to(BUF_SIZE)?;
+
+            let data = &self.buf[self.pos..];
+            if data.len() <= HDR_SIZE {
+                self.pos = self.buf.len();
+                return Ok(None);
+            }
+
+            // Fast path, the next frame of the same stream.
+            if let Some(prev) = self.header {
+                let hdr = FrameHeader::from_slice(data);
+
+                if prev.matches(&hdr) {
+                    let frame_size = hdr.frame_bytes(self.free_format_bytes) + hdr.padding();
+
+                    if frame_size == data.len() || matches_at(&hdr, data, frame_size) {
+                        return Ok(Some(frame_size));
+                    }
+                }
+            }
+
+            self.reset();
+
+            let data = &self.buf[self.pos..];
+            let (offset, frame_size) = find_frame(data, &mut self.free_format_bytes);
+            if frame_size != 0 && offset + frame_size <= data.len() {
+                self.pos += offset;
+                return Ok(Some(frame_size));
+            }
+
+            if self.eof {
+                self.pos = self.buf.len();
+                return Ok(None);
+            }
+
+            // Keeps what may be the start of a frame until more data arrives.
+            self.pos += if frame_size != 0 {
+                offset
+            } else {
+                data.len().saturating_sub(MAX_FRAME_LOOKAHEAD)
+            };
+        }
+    }
+
+    /// Returns false when there are no frames left.
+    fn decode_frame(&mut self) -> Result<bool, Error> {
+        let Some(frame_size) = self.next_frame()? else {
+            return Ok(false);
+        };
+
+        let frame = &self.buf[self.pos..self.pos + frame_size];
+        let hdr = FrameHeader::from_slice(frame);
+
+        if self.decoder.decode(frame) {
+            self.header = Some(hdr);
+        } else {
+            // Like after a seek, the next frame is synced again.
+            self.header = None;
+        }
+        self.pos += frame_size;
+
+        let frame_channels = hdr.channels() as usize;
+        let frame_pcm = &self.decoder.pcm[..hdr.frame_samples() * frame_channels];
+
+        // Streams may change between mono and stereo, the first frame decides.
+        self.pcm.clear();
+        match (frame_channels, self.channels) {
+            (1, 2) => self
+                .pcm
+                .extend(frame_pcm.iter().flat_map(|sample| [*sample, *sample])),
+            (2, 1) => self
+                .pcm
+                .extend(frame_pcm.chunks_exact(2).map(|lr| (lr[0] + lr[1]) * 0.5)),
+            _ => self.pcm.extend_from_slice(frame_pcm),
+        }
+
+        // Gapless trimming.
+        let skip = self.to_skip.min(self.pcm.len());
+        self.to_skip -= skip;
+        self.cursor = skip;
+
+        if let Some(remaining) = self.remaining.as_mut() {
+            let len = (self.pcm.len() - skip).min(*remaining);
+            self.pcm.truncate(skip + len);
+            *remaining -= len;
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,256 @@
+use super::tables::SYNTH_WINDOW;
+
+/// Size of the polyphase filter history.
+pub(super) const QMF_STATE_LEN: usize = 15 * 64;
+/// Working memory of one granule synthesis.
+pub(super) const SYNTH_LINS_LEN: usize = (18 + 15) * 64;
+
+/// The synthesis output is 16 bit scaled.
+#[inline(always)]
+fn scale_pcm(sample: f32) -> f32 {
+    sample * (1.0 / 32768.0)
+}
+
+/// In place DCT-II of the 32 subbands, for each of the `n` time slots.
+fn dct_ii(grbuf: &mut [f32], n: usize) {
+    const SEC: [f32; 24] = [
+        10.190_008,
+        0.500_603,
+        0.502_419_3,
+        3.407_608_5,
+        0.505_470_9,
+        0.522_498_6,
+        2.057_781,
+        0.515_447_3,
+        0.566_944,
+        1.484_164_6,
+        0.531_042_6,
+        0.646_821_8,
+        1.169_439_9,
+        0.553_103_9,
+        0.788_154_6,
+        0.972_568_2,
+        0.582_935,
+        1.060_677_6,
+        0.839_349_6,
+        0.622_504_1,
+        1.722_447_2,
+        0.744_536_3,
+        0.674_808_3,
+        5.101_148_6,
+    ];
+
+    for k in 0..n {
+        let mut t = [[0.0f32; 8]; 4];
+
+        for i in 0..8 {
+            let x0 = grbuf[k + i * 18];
+            let x1 = grbuf[k + (15 - i) * 18];
+            let x2 = grbuf[k + (16 + i) * 18];
+            let x3 = grbuf[k + (31 - i) * 18];
+            let t0 = x0 + x3;
+            let t1 = x1 + x2;
+            let t2 = (x1 - x2) * SEC[3 * i];
+            let t3 = (x0 - x3) * SEC[3 * i + 1];
+
+            t[0][i] = t0 + t1;
+            t[1][i] = (t0 - t1) * SEC[3 * i + 2];
+            t[2][i] = t3 + t2;
+            t[3][i] = (t3 - t2) * SEC[3 * i + 2];
+        }
+
+        for x in t.iter_mut() {
+            let [
+                mut x0,
+                mut x1,
+                mut x2,
+                mut x3,
+                mut x4,
+                mut x5,
+                mut x6,
+                mut x7,
+            ] = *x;
+
+            let mut xt = x0 - x7;
+            x0 += x7;
+            x7 = x1 - x6;
+            x1 += x6;
+            x6 = x2 - x5;
+            x2 += x5;
+            x5 = x3 - x4;
+            x3 += x4;
+            x4 = x0 - x3;
+            x0 += x3;
+            x3 = x1 - x2;
+            x1 += x2;
+            x[0] = x0 + x1;
+            x[4] = (x0 - x1) * 0.707_106_77;
+            x5 += x6;
+            x6 = (x6 + x7) * 0.707_106_77;
+            x7 += xt;
+            x3 = (x3 + x4) * 0.707_106_77;
+            // Rotate by PI/8.
+            x5 -= x7 * 0.198_912_37;
+            x7 += x5 * 0.382_683_43;
+            x5 -= x7 * 0.198_912_37;
+            x0 = xt - x6;
+            xt += x6;
+            x[1] = (xt + x7) * 0.509_795_6;
+            x[2] = (x4 + x3) * 0.541_196_1;
+            x[3] = (x0 - x5) * 0.601_344_9;
+            x[5] = (x0 + x5) * 0.899_976_2;
+            x[6] = (x4 - x3) * 1.306_563;
+            x[7] = (xt - x7) * 2.562_915_6;
+        }
+
+        let mut y = k;
+        for i in 0..7 {
+            grbuf[y] = t[0][i];
+            grbuf[y + 18] = t[2][i] + t[3][i] + t[3][i + 1];
+            grbuf[y + 2 * 18] = t[1][i] + t[1][i + 1];
+            grbuf[y + 3 * 18] = t[2][i + 1] + t[3][i] + t[3][i + 1];
+            y += 4 * 18;
+        }
+        grbuf[y] = t[0][7];
+        grbuf[y + 18] = t[2][7] + t[3][7];
+        grbuf[y + 2 * 18] = t[1][7];
+        grbuf[y + 3 * 18] = t[3][7];
+    }
+}
+
+/// The 2 samples of a time slot pair that only need half the window.
+fn synth_pair(pcm: &mut [f32], dst: usize, nch: usize, lins: &[f32], z: usize) {
+    let zv = |i: usize| lins[z + i];
+
+    let mut a = (zv(14 * 64) - zv(0)) * 29.0;
+    a += (zv(64) + zv(13 * 64)) * 213.0;
+    a += (zv(12 * 64) - zv(2 * 64)) * 459.0;
+    a += (zv(3 * 64) + zv(11 * 64)) * 2037.0;
+    a += (zv(10 * 64) - zv(4 * 64)) * 5153.0;
+    a += (zv(5 * 64) + zv(9 * 64)) * 6574.0;
+    a += (zv(8 * 64) - zv(6 * 64)) * 37489.0;
+    a += zv(7 * 64) * 75038.0;
+    pcm[dst] = scale_pcm(a);
+
+    let z = z + 2;
+    let zv = |i: usize| lins[z + i];
+
+    let mut a = zv(14 * 64) * 104.0;
+    a += zv(12 * 64) * 1567.0;
+    a += zv(10 * 64) * 9727.0;
+    a += zv(8 * 64) * 64019.0;
+    a += zv(6 * 64) * -9975.0;
+    a += zv(4 * 64) * -45.0;
+    a += zv(2 * 64) * 146.0;
+    a += zv(0) * -5.0;
+    pcm[dst + 16 * nch] = scale_pcm(a);
+}
+
+/// Synthesizes 2 time slots (64 samples per channel) starting at `x` in `grbuf`.
+fn synth(
+    grbuf: &[f32],
+    x: usize,
+    pcm: &mut [f32],
+    dstl: usize,
+    nch: usize,
+    lins: &mut [f32],
+    lins_base: usize,
+) {
+    let xl = |i: usize| grbuf[x + i];
+    let xr = |i: usize| grbuf[x + 576 * (nch - 1) + i];
+    let dstr = dstl + (nch - 1);
+    let zlin = lins_base + 15 * 64;
+
+    lins[zlin + 4 * 15] = xl(18 * 16);
+    lins[zlin + 4 * 15 + 1] = xr(18 * 16);
+    lins[zlin + 4 * 15 + 2] = xl(0);
+    lins[zlin + 4 * 15 + 3] = xr(0);
+
+    lins[zlin + 4 * 31] = xl(1 + 18 * 16);
+    lins[zlin + 4 * 31 + 1] = xr(1 + 18 * 16);
+    lins[zlin + 4 * 31 + 2] = xl(1);
+    lins[zlin + 4 * 31 + 3] = xr(1);
+
+    synth_pair(pcm, dstr, nch, lins, lins_base + 4 * 15 + 1);
+    synth_pair(pcm, dstr + 32 * nch, nch, lins, lins_base + 4 * 15 + 64 + 1);
+    synth_pair(pcm, dstl, nch, lins, lins_base + 4 * 15);
+    synth_pair(pcm, dstl + 32 * nch, nch, lins, lins_base + 4 * 15 + 64);
+
+    for i in (0..15).rev() {
+        lins[zlin + 4 * i] = xl(18 * (31 - i));
+        lins[zlin + 4 * i + 1] = xr(18 * (31 - i));
+        lins[zlin + 4 * i + 2] = xl(1 + 18 * (31 - i));
+        lins[zlin + 4 * i + 3] = xr(1 + 18 * (31 - i));
+        lins[zlin + 4 * (i + 16)] = xl(1 + 18 * (1 + i));
+        lins[zlin + 4 * (i + 16) + 1] = xr(1 + 18 * (1 + i));
+        lins[zlin + 4 * i - 64 + 2] = xl(18 * (1 + i));
+        lins[zlin + 4 * i - 64 + 3] = xr(18 * (1 + i));
+
+        let mut a = [0.0f32; 4];
+        let mut b = [0.0f32; 4];
+
+        for k in 0..8 {
+            let w0 = SYNTH_WINDOW[(14 - i) * 16 + 2 * k];
+            let w1 = SYNTH_WINDOW[(14 - i) * 16 + 2 * k + 1];
+            let vz = zlin + 4 * i - k * 64;
+            let vy = zlin + 4 * i - (15 - k) * 64;
+
+            for j in 0..4 {
+                let (z, y) = (lins[vz + j], lins[vy + j]);
+                let bj = z * w1 + y * w0;
+                let aj = if k % 2 == 0 {
+                    z * w0 - y * w1
+                } else {
+                    y * w1 - z * w0
+                };
+
+                if k == 0 {
+                    a[j] = aj;
+                    b[j] = bj;
+                } else {
+                    a[j] += aj;
+                    b[j] += bj;
+                }
+            }
+        }
+
+        pcm[dstr + (15 - i) * nch] = scale_pcm(a[1]);
+        pcm[dstr + (17 + i) * nch] = scale_pcm(b[1]);
+        pcm[dstl + (15 - i) * nch] = scale_pcm(a[0]);
+        pcm[dstl + (17 + i) * nch] = scale_pcm(b[0]);
+        pcm[dstr + (47 - i) * nch] = scale_pcm(a[3]);
+        pcm[dstr + (49 + i) * nch] = scale_pcm(b[3]);
+        pcm[dstl + (47 - i) * nch] = scale_pcm(a[2]);
+        pcm[dstl + (49 + i) * nch] = scale_pcm(b[2]);
+    }
+}
+
+/// Turns `nbands` time slots of the 32 subbands of every channel into interleaved pcm.
+pub(super) fn synth_granule(
+    qmf_state: &mut [f32; QMF_STATE_LEN],
+    grbuf: &mut [f32],
+    nbands: usize,
+    nch: usize,
+    pcm: &mut [f32],
+    lins: &mut [f32; SYNTH_LINS_LEN],
+) {
+    for ch in 0..nch {
+        dct_ii(&mut grbuf[576 * ch..], nbands);
+    }
+
+    lins[..QMF_STATE_LEN].copy_from_slice(qmf_state);
+
+    for i in (0..nbands).step_by(2) {
+        synth(grbuf, i, pcm, 32 * nch * i, nch, lins, i * 64);
+    }
+
+    let history = &lins[nbands * 64..nbands * 64 + QMF_STATE_LEN];
+    if nch == 1 {
+        // Only the left half of the history is kept in mono, like the reference decoder.
+        for i in (0..QMF_STATE_LEN).step_by(2) {
+            qmf_state[i] = history[i];
+        }
+    } else {
+        qmf_state.copy_from_slice(history);
+    }
+}
@@ -0,0 +1,92 @@
+//! Reference files in `tests/data/mp3`, the first 10 frames of the `music.mp3` example of rodio,
+//! decoded to 16 bits `.wav` by minimp3. `gapless.mp3` keeps the Xing tag and its LAME extension,
+//! with 576 frames of encoder delay and 984 of padding, `no_tag.mp3` has only the audio frames.
+
+use super::super::{decoder::LgDecoder, wav::LgWavDecoder};
+use super::LgMp3Decoder;
+use std::{fs, io, path};
+
+/// Encoder delay of the LAME extension, plus the delay of the decoder.
+const DELAY: usize = 576 + 529;
+const FRAMES: usize = 10;
+
+fn data(name: &str) -> path::PathBuf {
+    path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/mp3")
+        .join(name)
+}
+
+fn reference(name: &str) -> Vec<f32> {
+    LgWavDecoder::new(data(&format!("{name}.wav")))
+        .unwrap()
+        .samples()
+        .collect()
+}
+
+/// Decodes like the reference, up to its rounding to 16 bits.
+fn check_reference(name: &str) {
+    let expected = reference(name);
+    let mut decoder = LgMp3Decoder::new(data(&format!("{name}.mp3"))).unwrap();
+
+    assert_eq!(decoder.info().channels, 2);
+    assert_eq!(decoder.info().sample_rate, 44_100);
+    assert!(decoder.is_len_known());
+    assert_eq!(decoder.len(), expected.len());
+
+    let decoded: Vec<f32> = decoder.samples().collect();
+    assert_eq!(decoded.len(), expected.len());
+    for (a, b) in decoded.iter().zip(&expected) {
+        assert!((a - b).abs() <= 1.0 / 32768.0, "{a} != {b}");
+    }
+}
+
+#[test]
+fn decodes_reference_gapless() {
+    check_reference("gapless");
+
+    let decoder = LgMp3Decoder::new(data("gapless.mp3")).unwrap();
+    let title = decoder.id3().and_then(|id3| id3.title());
+    assert_eq!(title.as_deref(), Some("Corelli Trio Sonata 11, m1"));
+}
+
+#[test]
+fn decodes_reference_without_tag() {
+    check_reference("no_tag");
+
+    // Every sample of every frame.
+    let decoder = LgMp3Decoder::new(data("no_tag.mp3")).unwrap();
+    assert_eq!(decoder.len(), FRAMES * 1152 * 2);
+}
+
+#[test]
+fn gapless_trims_delay_and_padding() {
+    let mut gapless = LgMp3Decoder::new(data("gapless.mp3")).unwrap();
+    let mut untrimmed = LgMp3Decoder::new(data("no_tag.mp3")).unwrap();
+
+    let frames = FRAMES * 1152 - DELAY - (984 - 529);
+    assert_eq!(gapless.len(), frames * 2);
+
+    let trimmed: Vec<f32> = gapless.samples().collect();
+    let untrimmed: Vec<f32> = untrimmed.samples().collect();
+    assert_eq!(trimmed, untrimmed[DELAY * 2..(DELAY + frames) * 2]);
+}
+
+#[test]
+fn len_unknown_from_reader_without_tag() {
+    let bytes = fs::read(data("no_tag.mp3")).unwrap();
+    let mut decoder = LgMp3Decoder::from_reader(io::Cursor::new(bytes)).unwrap();
+
+    assert!(!decoder.is_len_known());
+    assert_eq!(decoder.len(), 0);
+    assert!(!decoder.is_empty());
+    assert_eq!(decoder.samples::<f32>().count(), FRAMES * 1152 * 2);
+}
+
+#[test]
+fn len_known_from_reader_with_tag() {
+    let bytes = fs::read(data("gapless.mp3")).unwrap();
+    let mut decoder = LgMp3Decoder::from_reader(bytes.as_slice()).unwrap();
+
+    assert!(decoder.is_len_known());
+    assert_eq!(decoder.samples::<f32>().count(), decoder.len());
+}
@@ -0,0 +1,1006 @@
+//! Block processing from a source, through stages, to a sink.
+//!
+//! ```ignore
+//! let decoder = LgWavDecoder::new("in.wav")?;
+//! let info = AudioInfo { sample_rate: 48_000, channels: 2, ..decoder.info() };
+//! let mut encoder = LgWavEncoder::new("out.wav", info)?;
+//!
+//! LgPipeline::from_decoder(decoder)
+//!     .stage(ResampleStage::new(48_000, ResampleQuality::HIGH))?
+//!     .stage(RemixStage::layouts(ChannelLayout::surround_5_1(), ChannelLayout::stereo())?)?
+//!     .run(&mut encoder)?;
+//! ```
+
+use super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::LgDecoder,
+    dsp::{
+        Attenuation, ChannelLayout, ConvolutionParams, Convolver, Doppler, Dynamics,
+        DynamicsParams, Emitter, Fade, Fader, FilterChain, FilterSpec, HrtfSet, ImpulseResponse,
+        Limiter, LimiterParams, Listener, PitchShifter, RemixMatrix, ResampleQuality, Resampler,
+        SilenceTrimmer, Spatializer, StretchParams, TimeStretcher, TrimParams, TrimReport,
+        db_to_gain,
+    },
+    encoder::LgEncoder,
+    error::Error,
+    sample::{Sample, SampleType},
+};
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_BLOCK_FRAMES: usize = 1024;
+
+/// Gives blocks of interleaved samples normalized to `-1.0..=1.0`.
+pub trait LgSource {
+    fn info(&self) -> AudioInfo;
+
+    /// Appends up to `frames` frames to the `block`, returning how many.
+    /// 0 means there is nothing left. Sources with stages can give more frames than asked.
+    fn read_block(&mut self, frames: usize, block: &mut Vec<f32>) -> Result<usize>;
+}
+
+/// Processing between a source and a sink.
+pub trait LgStage {
+    /// Checks the format of the input, returning the format of the output.
+    /// Called once, before any block.
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo>;
+
+    /// Appends the processed `input` to the `output`, the amount of frames can change.
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()>;
+
+    /// The input ended, appends anything held back to the `output`.
+    fn flush(&mut self, _output: &mut Vec<f32>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Takes the blocks at the end of a pipeline.
+pub trait LgSink {
+    /// Checks the format of the blocks, called before the first one.
+    fn prepare(&mut self, info: AudioInfo) -> Result<()>;
+
+    fn write_block(&mut self, block: &[f32]) -> Result<()>;
+}
+
+// ------------------------- PIPELINE --------------------------
+
+pub struct LgPipeline<S: LgSource> {
+    source: S,
+    stages: Vec<Box<dyn LgStage>>,
+    /// Output format of the source and of every stage.
+    infos: Vec<AudioInfo>,
+    block_frames: usize,
+
+    /// Input and output of every stage.
+    buffers: Vec<Vec<f32>>,
+    ended: bool,
+}
+impl<D: LgDecoder> LgPipeline<LgDecoderSource<D>> {
+    #[inline(always)]
+    pub fn from_decoder(decoder: D) -> Self {
+        Self::new(LgDecoderSource::new(decoder))
+    }
+}
+impl<S: LgSource> LgPipeline<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            infos: vec![source.info()],
+            source,
+            stages: Vec::new(),
+            block_frames: DEFAULT_BLOCK_FRAMES,
+            buffers: vec![Vec::new()],
+            ended: false,
+        }
+    }
+
+    /// Frames read from the source at a time.
+    pub fn with_block_frames(mut self, block_frames: usize) -> Self {
+        self.block_frames = block_frames.max(1);
+        self
+    }
+
+    /// Adds a stage after the others, failing if it doesn't take their output format.
+    pub fn stage(mut self, mut stage: impl LgStage + 'static) -> Result<Self> {
+        let info = stage.prepare(self.info())?;
+
+        self.infos.push(info);
+        self.stages.push(Box::new(stage));
+        self.buffers.push(Vec::new());
+
+        Ok(self)
+    }
+
+    /// Format after every stage.
+    #[inline(always)]
+    pub fn infos(&self) -> &[AudioInfo] {
+        &self.infos
+    }
+
+    /// Processes everything, returning the frames given to the sink.
+    pub fn run(&mut self, sink: &mut impl LgSink) -> Result<usize> {
+        sink.prepare(self.info())?;
+
+        let mut block = Vec::new();
+        let mut frames = 0;
+        loop {
+            block.clear();
+            let read = self.read_block(self.block_frames, &mut block)?;
+            if read == 0 {
+                return Ok(frames);
+            }
+
+            sink.write_block(&block)?;
+            frames += read;
+        }
+    }
+
+    /// Processes everything into memory.
+    pub fn collect(&mut self) -> Result<AudioBuffer> {
+        let mut buffer = AudioBuffer::new(self.info(), Vec::new());
+        self.run(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    #[inline(always)]
+    pub fn into_source(self) -> S {
+        self.source
+    }
+}
+impl<S: LgSource> LgSource for LgPipeline<S> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        *self.infos.last().unwrap()
+    }
+
+    fn read_block(&mut self, frames: usize, block: &mut Vec<f32>) -> Result<usize> {
+        let channels = self.info().channels.max(1) as usize;
+
+        while !self.ended {
+            self.buffers[0].clear();
+            self.ended = self.source.read_block(frames, &mut self.buffers[0])? == 0;
+
+            for (i, stage) in self.stages.iter_mut().enumerate() {
+                let (inputs, outputs) = self.buffers.split_at_mut(i + 1);
+                let output = &mut outputs[0];
+                output.clear();
+
+                stage.process(&inputs[i], output)?;
+                if self.ended {
+                    stage.flush(output)?;
+                }
+            }
+
+            let output = self.buffers.last().unwrap();
+            if !output.is_empty() {
+                block.extend_from_slice(output);
+                return Ok(output.len() / channels);
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+// ------------------------- SOURCES --------------------------
+
+impl<S: LgSource + ?Sized> LgSource for Box<S> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        (**self).info()
+    }
+
+    #[inline(always)]
+    fn read_block(&mut self, frames: usize, block: &mut Vec<f32>) -> Result<usize> {
+        (**self).read_block(frames, block)
+    }
+}
+
+/// Reads any decoder, as normalized `f32` samples.
+pub struct LgDecoderSource<D: LgDecoder> {
+    decoder: D,
+}
+impl<D: LgDecoder> LgDecoderSource<D> {
+    #[inline(always)]
+    pub fn new(decoder: D) -> Self {
+        Self { decoder }
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+impl<D: LgDecoder> LgSource for LgDecoderSource<D> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.decoder.info()
+    }
+
+    fn read_block(&mut self, frames: usize, block: &mut Vec<f32>) -> Result<usize> {
+        let channels = self.decoder.info().channels.max(1) as usize;
+        let start = block.len();
+        block.extend(self.decoder.samples::<f32>().take(frames * channels));
+
+        Ok((block.len() - start).div_ceil(channels))
+    }
+}
+
+/// Reads an [`AudioBuffer`] from the start.
+pub struct LgBufferSource<'a> {
+    buffer: &'a AudioBuffer,
+    cursor: usize,
+}
+impl<'a> LgBufferSource<'a> {
+    #[inline(always)]
+    pub fn new(buffer: &'a AudioBuffer) -> Self {
+        Self { buffer, cursor: 0 }
+    }
+}
+impl LgSource for LgBufferSource<'_> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.buffer.info
+    }
+
+    fn read_block(&mut self, frames: usize, block: &mut Vec<f32>) -> Result<usize> {
+        let channels = self.buffer.channels().max(1);
+        let end = (self.cursor + frames * channels).min(self.buffer.samples.len());
+        block.extend_from_slice(&self.buffer.samples[self.cursor..end]);
+
+        let read = end - self.cursor;
+        self.cursor = end;
+
+        Ok(read.div_ceil(channels))
+    }
+}
+
+// ------------------------- SINKS --------------------------
+
+/// Needs the channels and sample rate of the encoder, the sample format is converted by it.
+impl<E: LgEncoder> LgSink for E {
+    fn prepare(&mut self, info: AudioInfo) -> Result<()> {
+        let encoder_info = self.info();
+        if encoder_info.channels != info.channels || encoder_info.sample_rate != info.sample_rate {
+            return Err(Error::WrongFmtInfo(format!(
+                "Encoder takes {} channels at {} Hz, got {} channels at {} Hz!",
+                encoder_info.channels, encoder_info.sample_rate, info.channels, info.sample_rate
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn write_block(&mut self, block: &[f32]) -> Result<()> {
+        for sample in block {
+            self.encode_sample(*sample)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Appends the blocks, taking the format of the pipeline if empty.
+impl LgSink for AudioBuffer {
+    fn prepare(&mut self, info: AudioInfo) -> Result<()> {
+        if self.is_empty() {
+            self.info = info;
+        } else if self.info.channels != info.channels || self.info.sample_rate != info.sample_rate {
+            return Err(Error::WrongFmtInfo(
+                "Can't append blocks with other channels or sample rate!".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn write_block(&mut self, block: &[f32]) -> Result<()> {
+        self.samples.extend_from_slice(block);
+        Ok(())
+    }
+}
+
+// ------------------------- STAGES --------------------------
+
+/// Multiplies every sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainStage {
+    pub gain: f32,
+}
+impl GainStage {
+    #[inline(always)]
+    pub fn new(gain: f32) -> Self {
+        Self { gain }
+    }
+
+    #[inline(always)]
+    pub fn db(db: f32) -> Self {
+        Self::new(db_to_gain(db as f64) as f32)
+    }
+}
+impl LgStage for GainStage {
+    #[inline(always)]
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        output.extend(input.iter().map(|sample| sample * self.gain));
+        Ok(())
+    }
+}
+
+/// Changes the sample rate, see [`Resampler`].
+#[derive(Debug, Clone)]
+pub struct ResampleStage {
+    sample_rate: u32,
+    quality: ResampleQuality,
+    resampler: Option<Resampler>,
+}
+impl ResampleStage {
+    pub fn new(sample_rate: u32, quality: ResampleQuality) -> Self {
+        Self {
+            sample_rate,
+            quality,
+            resampler: None,
+        }
+    }
+}
+impl LgStage for ResampleStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        if info.sample_rate == 0 || self.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Can't resample from or to a sample rate of 0!".to_string(),
+            ));
+        }
+
+        self.resampler = Some(Resampler::from_rates(
+            info.channels as usize,
+            info.sample_rate,
+            self.sample_rate,
+            self.quality,
+        ));
+
+        Ok(AudioInfo {
+            sample_rate: self.sample_rate,
+            ..info
+        })
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(resampler) = &mut self.resampler {
+            resampler.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(resampler) = &mut self.resampler {
+            resampler.flush(output);
+        }
+
+        Ok(())
+    }
+}
+
+/// Changes the channels, see [`RemixMatrix`].
+#[derive(Debug, Clone)]
+pub struct RemixStage {
+    matrix: RemixMatrix,
+}
+impl RemixStage {
+    #[inline(always)]
+    pub fn new(matrix: RemixMatrix) -> Self {
+        Self { matrix }
+    }
+
+    #[inline(always)]
+    pub fn layouts(input: ChannelLayout, output: ChannelLayout) -> Result<Self> {
+        Ok(Self::new(RemixMatrix::new(&input, &output)?))
+    }
+}
+impl LgStage for RemixStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        if info.channels as usize != self.matrix.input_channels() {
+            return Err(Error::WrongFmtInfo(format!(
+                "Remix matrix takes {} channels, got {}!",
+                self.matrix.input_channels(),
+                info.channels
+            )));
+        }
+
+        Ok(AudioInfo {
+            channels: self.matrix.output_channels() as u16,
+            ..info
+        })
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        self.matrix.apply_interleaved(input, output);
+        Ok(())
+    }
+}
+
+/// Changes the sample format, integers are quantized to their bit depth and clipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvertStage {
+    pub sample_type: SampleType,
+    pub bits_per_sample: u16,
+}
+impl ConvertStage {
+    #[inline(always)]
+    pub fn new(sample_type: SampleType, bits_per_sample: u16) -> Self {
+        Self {
+            sample_type,
+            bits_per_sample,
+        }
+    }
+}
+impl LgStage for ConvertStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        match (self.sample_type, self.bits_per_sample) {
+            (SampleType::INT, 8 | 16 | 24 | 32) | (SampleType::FLOAT, 32 | 64) => Ok(AudioInfo {
+                bits_per_sample: self.bits_per_sample,
+                sample_type: Some(self.sample_type),
+                ..info
+            }),
+
+            (sample_type, bits_per_sample) => Err(Error::WrongFmtInfo(format!(
+                "{sample_type:?} with {bits_per_sample} bits per sample is not supported!"
+            ))),
+        }
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        match self.sample_type {
+            SampleType::INT => output.extend(input.iter().map(|sample| {
+                i32::from_f32(*sample, SampleType::INT, self.bits_per_sample)
+                    .to_f32(SampleType::INT, self.bits_per_sample)
+            })),
+            SampleType::FLOAT => output.extend_from_slice(input),
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps a range of frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimStage {
+    range: TrimRange,
+    channels: usize,
+    /// Frames to skip and to keep, known once prepared.
+    skip: usize,
+    keep: Option<usize>,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TrimRange {
+    Frames(usize, Option<usize>),
+    Seconds(f64, Option<f64>),
+}
+impl TrimStage {
+    /// Skips `start` frames, keeping `len` frames after it or everything if `None`.
+    pub fn frames(start: usize, len: Option<usize>) -> Self {
+        Self::with_range(TrimRange::Frames(start, len))
+    }
+
+    /// Like [`TrimStage::frames`], in seconds.
+    pub fn seconds(start: f64, duration: Option<f64>) -> Self {
+        Self::with_range(TrimRange::Seconds(start, duration))
+    }
+}
+impl TrimStage {
+    fn with_range(range: TrimRange) -> Self {
+        Self {
+            range,
+            channels: 1,
+            skip: 0,
+            keep: None,
+        }
+    }
+}
+impl LgStage for TrimStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        let to_frames =
+            |seconds: f64| (seconds.max(0.0) * info.sample_rate as f64).round() as usize;
+
+        (self.skip, self.keep) = match self.range {
+            TrimRange::Frames(start, len) => (start, len),
+            TrimRange::Seconds(start, duration) => (to_frames(start), duration.map(to_frames)),
+        };
+        self.channels = info.channels.max(1) as usize;
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        let frames = input.len() / self.channels;
+        let skipped = self.skip.min(frames);
+        self.skip -= skipped;
+
+        let kept = match &mut self.keep {
+            Some(keep) => {
+                let kept = (*keep).min(frames - skipped);
+                *keep -= kept;
+                kept
+            }
+            None => frames - skipped,
+        };
+
+        output.extend_from_slice(&input[skipped * self.channels..(skipped + kept) * self.channels]);
+
+        Ok(())
+    }
+}
+
+/// Keeps the true peak under a ceiling, see [`Limiter`].
+#[derive(Debug, Clone)]
+pub struct LimiterStage {
+    params: LimiterParams,
+    limiter: Option<Limiter>,
+}
+impl LimiterStage {
+    pub fn new(params: LimiterParams) -> Self {
+        Self {
+            params,
+            limiter: None,
+        }
+    }
+}
+impl LgStage for LimiterStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        self.limiter = Some(Limiter::new(
+            info.channels as usize,
+            info.sample_rate,
+            self.params,
+        ));
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(limiter) = &mut self.limiter {
+            limiter.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(limiter) = &mut self.limiter {
+            limiter.flush(output);
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the leading and trailing silence, see [`SilenceTrimmer`].
+///
+/// The stage is boxed into the pipeline, so its [`TrimReport`] is read through
+/// [`SilenceTrimStage::report_handle`], taken before the stage is added.
+#[derive(Debug, Clone)]
+pub struct SilenceTrimStage {
+    params: TrimParams,
+    trimmer: Option<SilenceTrimmer>,
+    /// Shared by the clones of the stage.
+    report: Arc<Mutex<Option<TrimReport>>>,
+}
+impl SilenceTrimStage {
+    pub fn new(params: TrimParams) -> Self {
+        Self {
+            params,
+            trimmer: None,
+            report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Holds the report once the stage is flushed, `None` before that.
+    pub fn report_handle(&self) -> Arc<Mutex<Option<TrimReport>>> {
+        Arc::clone(&self.report)
+    }
+
+    /// The report once the stage is flushed.
+    pub fn report(&self) -> Option<TrimReport> {
+        *self
+            .report
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn set_report(&self, report: Option<TrimReport>) {
+        *self
+            .report
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = report;
+    }
+}
+impl LgStage for SilenceTrimStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        self.trimmer = Some(SilenceTrimmer::new(info, self.params)?);
+        self.set_report(None);
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(trimmer) = &mut self.trimmer {
+            trimmer.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(trimmer) = &mut self.trimmer {
+            trimmer.flush(output);
+
+            let report = trimmer.report();
+            self.set_report(Some(report));
+        }
+
+        Ok(())
+    }
+}
+
+/// Filters every channel, see [`FilterChain`].
+#[derive(Debug, Clone)]
+pub struct FilterStage {
+    specs: Vec<FilterSpec>,
+    chain: Option<FilterChain>,
+}
+impl FilterStage {
+    pub fn new(specs: &[FilterSpec]) -> Self {
+        Self {
+            specs: specs.to_vec(),
+            chain: None,
+        }
+    }
+}
+impl LgStage for FilterStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        self.chain = Some(FilterChain::new(
+            info.channels as usize,
+            info.sample_rate,
+            &self.specs,
+        )?);
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        let start = output.len();
+        output.extend_from_slice(input);
+        if let Some(chain) = &mut self.chain {
+            chain.process(&mut output[start..]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Compresses, expands or gates, see [`Dynamics`].
+pub struct DynamicsStage {
+    params: DynamicsParams,
+    sidechain_filter: Vec<FilterSpec>,
+    sidechain: Option<Box<dyn LgSource>>,
+    dynamics: Option<Dynamics>,
+
+    channels: usize,
+    /// Samples read from the sidechain but not heard yet.
+    key: Vec<f32>,
+}
+impl DynamicsStage {
+    pub fn new(params: DynamicsParams) -> Self {
+        Self {
+            params,
+            sidechain_filter: Vec::new(),
+            sidechain: None,
+            dynamics: None,
+            channels: 0,
+            key: Vec::new(),
+        }
+    }
+
+    /// Filters what the detector hears, see [`Dynamics::set_sidechain_filter`].
+    pub fn with_sidechain_filter(mut self, specs: &[FilterSpec]) -> Self {
+        self.sidechain_filter = specs.to_vec();
+        self
+    }
+
+    /// Hears the `source` instead of the input, frame by frame, like ducking music under a
+    /// voice. It needs the sample rate of the input, any channels, and is silent once it ends.
+    pub fn with_sidechain(mut self, source: impl LgSource + 'static) -> Self {
+        self.sidechain = Some(Box::new(source));
+        self
+    }
+}
+impl LgStage for DynamicsStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        if let Some(sidechain) = &self.sidechain {
+            let key = sidechain.info();
+            if key.channels == 0 || key.sample_rate != info.sample_rate {
+                return Err(Error::WrongFmtInfo(format!(
+                    "Dynamics sidechain needs channels and the sample_rate {} of the input!",
+                    info.sample_rate
+                )));
+            }
+        }
+
+        let mut dynamics = Dynamics::new(info, self.params)?;
+        dynamics.set_sidechain_filter(&self.sidechain_filter)?;
+        self.dynamics = Some(dynamics);
+        self.channels = info.channels as usize;
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        let Some(dynamics) = &mut self.dynamics else {
+            return Ok(());
+        };
+        let Some(sidechain) = &mut self.sidechain else {
+            dynamics.process(input, output);
+            return Ok(());
+        };
+
+        let key_channels = sidechain.info().channels as usize;
+        let wanted = input.len() / self.channels * key_channels;
+        while self.key.len() < wanted {
+            let frames = (wanted - self.key.len()).div_ceil(key_channels);
+            if sidechain.read_block(frames, &mut self.key)? == 0 {
+                break;
+            }
+        }
+
+        dynamics.process_sidechain(input, &self.key, key_channels, output);
+        self.key.drain(..wanted.min(self.key.len()));
+
+        Ok(())
+    }
+}
+
+/// Fades in the start and out the end, see [`Fader`].
+#[derive(Debug, Clone)]
+pub struct FadeStage {
+    fade_in: Option<Fade>,
+    fade_out: Option<Fade>,
+    fader: Option<Fader>,
+}
+impl FadeStage {
+    pub fn new(fade_in: Option<Fade>, fade_out: Option<Fade>) -> Self {
+        Self {
+            fade_in,
+            fade_out,
+            fader: None,
+        }
+    }
+}
+impl LgStage for FadeStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        self.fader = Some(Fader::new(info, self.fade_in, self.fade_out)?);
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(fader) = &mut self.fader {
+            fader.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(fader) = &mut self.fader {
+            fader.flush(output);
+        }
+
+        Ok(())
+    }
+}
+
+/// Spatializes a mono input to the `layout`, from a fixed listener and emitter.
+pub struct SpatialStage {
+    layout: ChannelLayout,
+    listener: Listener,
+    emitter: Emitter,
+    attenuation: Attenuation,
+    doppler: Option<Doppler>,
+    hrtf: Option<Arc<HrtfSet>>,
+    spatializer: Option<Spatializer>,
+}
+impl SpatialStage {
+    pub fn new(layout: ChannelLayout, listener: Listener, emitter: Emitter) -> Self {
+        Self {
+            layout,
+            listener,
+            emitter,
+            attenuation: Attenuation::default(),
+            doppler: None,
+            hrtf: None,
+            spatializer: None,
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    pub fn with_doppler(mut self, doppler: Doppler) -> Self {
+        self.doppler = Some(doppler);
+        self
+    }
+
+    pub fn with_hrtf(mut self, hrtf: Arc<HrtfSet>) -> Self {
+        self.hrtf = Some(hrtf);
+        self
+    }
+}
+impl LgStage for SpatialStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        if info.channels != 1 {
+            return Err(Error::WrongFmtInfo(format!(
+                "Only mono sounds can be spatialized, got {} channels!",
+                info.channels
+            )));
+        }
+
+        let mut spatializer = Spatializer::new(self.layout.clone(), info.sample_rate)?
+            .with_attenuation(self.attenuation);
+        if let Some(doppler) = self.doppler {
+            spatializer = spatializer.with_doppler(doppler);
+        }
+        if let Some(hrtf) = &self.hrtf {
+            spatializer = spatializer.with_hrtf(Arc::clone(hrtf))?;
+        }
+        spatializer.set_listener(self.listener);
+        spatializer.set_emitter(self.emitter);
+        self.spatializer = Some(spatializer);
+
+        Ok(AudioInfo {
+            channels: self.layout.channels() as u16,
+            ..info
+        })
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(spatializer) = &mut self.spatializer {
+            spatializer.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(spatializer) = &mut self.spatializer {
+            spatializer.flush(output);
+        }
+
+        Ok(())
+    }
+}
+
+/// Convolves with an impulse response, like a reverb, giving its tail on flush.
+pub struct ConvolutionStage {
+    response: Arc<ImpulseResponse>,
+    params: ConvolutionParams,
+    convolver: Option<Convolver>,
+}
+impl ConvolutionStage {
+    pub fn new(response: Arc<ImpulseResponse>, params: ConvolutionParams) -> Self {
+        Self {
+            response,
+            params,
+            convolver: None,
+        }
+    }
+}
+impl LgStage for ConvolutionStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        let convolver = Convolver::new(info, &self.response, self.params)?;
+        let channels = convolver.output_channels() as u16;
+        self.convolver = Some(convolver);
+
+        Ok(AudioInfo { channels, ..info })
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(convolver) = &mut self.convolver {
+            convolver.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(convolver) = &mut self.convolver {
+            convolver.flush(output);
+        }
+
+        Ok(())
+    }
+}
+
+/// Changes the duration, keeping the pitch.
+pub struct TimeStretchStage {
+    stretch: f64,
+    params: StretchParams,
+    stretcher: Option<TimeStretcher>,
+}
+impl TimeStretchStage {
+    /// `stretch` is the output duration over the input duration.
+    pub fn new(stretch: f64, params: StretchParams) -> Self {
+        Self {
+            stretch,
+            params,
+            stretcher: None,
+        }
+    }
+}
+impl LgStage for TimeStretchStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        self.stretcher = Some(TimeStretcher::new(info, self.stretch, self.params)?);
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(stretcher) = &mut self.stretcher {
+            stretcher.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(stretcher) = &mut self.stretcher {
+            stretcher.flush(output);
+        }
+
+        Ok(())
+    }
+}
+
+/// Changes the pitch, keeping the duration.
+pub struct PitchShiftStage {
+    semitones: f64,
+    params: StretchParams,
+    shifter: Option<PitchShifter>,
+}
+impl PitchShiftStage {
+    pub fn new(semitones: f64, params: StretchParams) -> Self {
+        Self {
+            semitones,
+            params,
+            shifter: None,
+        }
+    }
+}
+impl LgStage for PitchShiftStage {
+    fn prepare(&mut self, info: AudioInfo) -> Result<AudioInfo> {
+        self.shifter = Some(PitchShifter::new(info, self.semitones, self.params)?);
+
+        Ok(info)
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        if let Some(shifter) = &mut self.shifter {
+            shifter.process(input, output);
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if let Some(shifter) = &mut self.shifter {
+            shifter.flush(output);
+        }
+
+        Ok(())
+    }
+}
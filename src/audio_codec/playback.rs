@@ -0,0 +1,577 @@
+//! Voices for a game, mixed into the output buffer of an audio callback given by the host.
+//!
+//! ```ignore
+//! let clip = Arc::new(AudioBuffer::from_decoder(&mut LgWavDecoder::new("step.wav")?));
+//! let mut voices = LgVoiceManager::new(2, 48_000, 32)?;
+//!
+//! let id = voices.play_clip(&clip, PlayParams { pitch: 1.2, ..Default::default() })?;
+//! // In the audio callback:
+//! voices.render(output)?;
+//! ```
+
+use super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::LgDecoder,
+    dsp::{ChannelLayout, FadeLength, RemixMatrix, ResampleQuality, Resampler},
+    error::Error,
+    pipeline::{DEFAULT_BLOCK_FRAMES, LgDecoderSource, LgSource},
+    sample::SampleType,
+};
+use std::sync::Arc;
+
+/// Fade out of stolen voices, to avoid a click.
+const STEAL_FADE_SECONDS: f64 = 0.005;
+/// Range of the pitch, so the buffers of a voice can be allocated when it starts.
+const MIN_PITCH: f32 = 0.25;
+const MAX_PITCH: f32 = 4.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Looping {
+    #[default]
+    Off,
+    Whole,
+    /// From `start` up to `end` not included, in frames of the clip.
+    Points {
+        start: usize,
+        end: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayParams {
+    pub volume: f32,
+    /// From -1.0, left, to 1.0, right, on the front left and right speakers.
+    pub pan: f32,
+    /// Speed of the playback, 2.0 is an octave up, from 0.25 to 4.0.
+    pub pitch: f32,
+    /// Only clips can loop.
+    pub looping: Looping,
+    pub fade_in: Option<FadeLength>,
+    /// When every voice is taken, the lowest priority one is stolen for a higher or equal one.
+    pub priority: i32,
+}
+impl Default for PlayParams {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pan: 0.0,
+            pitch: 1.0,
+            looping: Looping::Off,
+            fade_in: None,
+            priority: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+/// Plays voices, rendering them into interleaved buffers of its format.
+pub struct LgVoiceManager {
+    info: AudioInfo,
+    layout: ChannelLayout,
+    max_voices: usize,
+    max_block_frames: usize,
+    quality: ResampleQuality,
+    volume: f32,
+
+    voices: Vec<Voice>,
+    next_id: u64,
+}
+impl LgVoiceManager {
+    /// At most `max_voices` play at once, voices being stolen fade out on top of them.
+    pub fn new(channels: u16, sample_rate: u32, max_voices: usize) -> Result<Self> {
+        if channels == 0 || sample_rate == 0 || max_voices == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Voices need a sample_rate, channels and max_voices > 0!".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            info: AudioInfo {
+                channels,
+                sample_rate,
+                bits_per_sample: 32,
+                sample_type: Some(SampleType::FLOAT),
+            },
+            layout: ChannelLayout::default_for(channels),
+            max_voices,
+            max_block_frames: DEFAULT_BLOCK_FRAMES,
+            quality: ResampleQuality::LOW,
+            volume: 1.0,
+            voices: Vec::with_capacity(max_voices * 2),
+            next_id: 0,
+        })
+    }
+
+    /// Resampling of the voices started after, [`ResampleQuality::LOW`] by default.
+    pub fn with_quality(mut self, quality: ResampleQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Frames rendered at once, [`DEFAULT_BLOCK_FRAMES`] by default.
+    /// Voices allocate their buffers for it when they start, so rendering doesn't allocate,
+    /// longer outputs are rendered a block at a time.
+    pub fn with_max_block_frames(mut self, frames: usize) -> Self {
+        self.max_block_frames = frames.max(1);
+        self
+    }
+
+    #[inline(always)]
+    pub fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// Voices playing, including the ones fading out.
+    #[inline(always)]
+    pub fn voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Plays a shared clip, `None` if every voice has a higher priority.
+    pub fn play_clip(
+        &mut self,
+        clip: &Arc<AudioBuffer>,
+        params: PlayParams,
+    ) -> Result<Option<VoiceId>> {
+        let frames = clip.frames();
+        let (start, end) = match params.looping {
+            Looping::Off | Looping::Whole => (0, frames),
+            Looping::Points { start, end } => (start, end),
+        };
+        if params.looping != Looping::Off && (start >= end || end > frames) {
+            return Err(Error::Custom(format!(
+                "Loop {start}..{end} is not in a clip of {frames} frames!"
+            )));
+        }
+
+        let source = VoiceSource::Clip {
+            clip: Arc::clone(clip),
+            position: 0,
+            loop_points: (params.looping != Looping::Off).then_some((start, end)),
+        };
+        self.start(source, clip.info, params)
+    }
+
+    /// Plays a decoder as it is decoded, `None` if every voice has a higher priority.
+    /// Decoding happens in [`LgVoiceManager::render`], so it should be fast, like of memory.
+    pub fn play_decoder(
+        &mut self,
+        decoder: impl LgDecoder + Send + 'static,
+        params: PlayParams,
+    ) -> Result<Option<VoiceId>> {
+        if params.looping != Looping::Off {
+            return Err(Error::Custom(
+                "Only clips can loop, decoders can't go back!".to_string(),
+            ));
+        }
+
+        let info = decoder.info();
+        let source = VoiceSource::Decoder(Box::new(LgDecoderSource::new(decoder)));
+        self.start(source, info, params)
+    }
+
+    /// Fades the voice out if given a length, false if it already ended.
+    pub fn stop(&mut self, id: VoiceId, fade: Option<FadeLength>) -> bool {
+        let rate = self.info.sample_rate;
+        self.voice(id)
+            .map(|voice| voice.stop(fade.map_or(0, |fade| fade.frames(rate))))
+            .is_some()
+    }
+
+    pub fn stop_all(&mut self, fade: Option<FadeLength>) {
+        let frames = fade.map_or(0, |fade| fade.frames(self.info.sample_rate));
+        for voice in &mut self.voices {
+            voice.stop(frames);
+        }
+    }
+
+    /// False if the voice already ended, changes are smoothed over the next render.
+    pub fn set_voice_volume(&mut self, id: VoiceId, volume: f32) -> bool {
+        let Some(voice) = find_voice(&mut self.voices, id) else {
+            return false;
+        };
+
+        voice.set_gains(&self.layout, volume, voice.pan);
+        true
+    }
+
+    pub fn set_voice_pan(&mut self, id: VoiceId, pan: f32) -> bool {
+        let Some(voice) = find_voice(&mut self.voices, id) else {
+            return false;
+        };
+
+        voice.set_gains(&self.layout, voice.volume, pan);
+        true
+    }
+
+    pub fn set_voice_pitch(&mut self, id: VoiceId, pitch: f32) -> bool {
+        let rate = self.info.sample_rate;
+        self.voice(id)
+            .map(|voice| voice.set_pitch(pitch, rate))
+            .is_some()
+    }
+
+    #[inline(always)]
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voices
+            .iter()
+            .any(|voice| voice.id == id && !voice.ended)
+    }
+
+    /// Overwrites the interleaved `output` with the next frames of every voice.
+    pub fn render(&mut self, output: &mut [f32]) -> Result<()> {
+        output.fill(0.0);
+        let channels = self.info.channels as usize;
+        let frames = output.len() / channels;
+
+        for block in output[..frames * channels].chunks_mut(self.max_block_frames * channels) {
+            for voice in &mut self.voices {
+                voice.render(block, channels, self.volume)?;
+            }
+        }
+        self.voices.retain(|voice| !voice.ended);
+
+        Ok(())
+    }
+}
+impl LgVoiceManager {
+    #[inline(always)]
+    fn voice(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        find_voice(&mut self.voices, id)
+    }
+
+    fn start(
+        &mut self,
+        source: VoiceSource,
+        info: AudioInfo,
+        params: PlayParams,
+    ) -> Result<Option<VoiceId>> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Can't play a sound without a sample_rate or channels!".to_string(),
+            ));
+        }
+        let matrix = RemixMatrix::new(&ChannelLayout::default_for(info.channels), &self.layout)?;
+
+        // Voices fading out after being stopped don't count.
+        let active = self.voices.iter().filter(|voice| !voice.stopping).count();
+        if active >= self.max_voices {
+            let Some(victim) = self
+                .voices
+                .iter_mut()
+                .filter(|voice| !voice.stopping)
+                .min_by_key(|voice| (voice.priority, voice.id.0))
+            else {
+                return Ok(None);
+            };
+            if victim.priority > params.priority {
+                return Ok(None);
+            }
+
+            victim.stop((STEAL_FADE_SECONDS * self.info.sample_rate as f64).round() as usize);
+        }
+
+        // Too many voices fading out, the oldest stop at once.
+        while self.voices.len() >= self.max_voices * 2 {
+            let oldest = self
+                .voices
+                .iter()
+                .enumerate()
+                .filter(|(_, voice)| voice.stopping)
+                .min_by_key(|(_, voice)| voice.id.0)
+                .map_or(0, |(i, _)| i);
+            self.voices.remove(oldest);
+        }
+
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+
+        let mut voice = Voice {
+            id,
+            priority: params.priority,
+            source,
+            channels: info.channels as usize,
+            source_rate: info.sample_rate,
+            resampler: Resampler::new(info.channels as usize, 1.0, self.quality),
+            matrix,
+            volume: params.volume,
+            pan: params.pan,
+            gains: vec![0.0; self.layout.channels()],
+            target_gains: vec![0.0; self.layout.channels()],
+            envelope: 0.0,
+            envelope_step: 0.0,
+            stopping: false,
+            input: Vec::new(),
+            resampled: Vec::new(),
+            cursor: 0,
+            mixed: vec![0.0; self.layout.channels()],
+            source_ended: false,
+            ended: false,
+        };
+        voice.reserve(self.max_block_frames, self.info.sample_rate);
+        voice.set_pitch(params.pitch, self.info.sample_rate);
+        voice.set_gains(&self.layout, params.volume, params.pan);
+        voice.gains.copy_from_slice(&voice.target_gains);
+        match params
+            .fade_in
+            .map(|fade| fade.frames(self.info.sample_rate))
+        {
+            Some(frames) if frames > 0 => voice.envelope_step = 1.0 / frames as f32,
+            _ => voice.envelope = 1.0,
+        }
+
+        self.voices.push(voice);
+        Ok(Some(id))
+    }
+}
+
+fn find_voice(voices: &mut [Voice], id: VoiceId) -> Option<&mut Voice> {
+    voices
+        .iter_mut()
+        .find(|voice| voice.id == id && !voice.ended)
+}
+
+enum VoiceSource {
+    Clip {
+        clip: Arc<AudioBuffer>,
+        position: usize,
+        loop_points: Option<(usize, usize)>,
+    },
+    Decoder(Box<dyn LgSource + Send>),
+}
+impl VoiceSource {
+    /// Appends up to `frames` frames, 0 once it ended.
+    fn read(&mut self, frames: usize, channels: usize, output: &mut Vec<f32>) -> Result<usize> {
+        match self {
+            Self::Clip {
+                clip,
+                position,
+                loop_points,
+            } => {
+                let end = loop_points.map_or(clip.frames(), |(_, end)| end);
+                if *position >= end {
+                    match loop_points {
+                        Some((start, _)) => *position = *start,
+                        None => return Ok(0),
+                    }
+                }
+
+                let read = frames.min(end - *position);
+                output.extend_from_slice(
+                    &clip.samples[*position * channels..(*position + read) * channels],
+                );
+                *position += read;
+
+                Ok(read)
+            }
+            Self::Decoder(source) => source.read_block(frames, output),
+        }
+    }
+}
+
+struct Voice {
+    id: VoiceId,
+    priority: i32,
+    source: VoiceSource,
+    channels: usize,
+    source_rate: u32,
+    resampler: Resampler,
+    matrix: RemixMatrix,
+
+    volume: f32,
+    pan: f32,
+    /// Of every output channel, going to the targets over a render.
+    gains: Vec<f32>,
+    target_gains: Vec<f32>,
+    /// Fade in and out, with its change every frame.
+    envelope: f32,
+    envelope_step: f32,
+    stopping: bool,
+
+    input: Vec<f32>,
+    /// Frames ready, from the cursor.
+    resampled: Vec<f32>,
+    cursor: usize,
+    mixed: Vec<f32>,
+    source_ended: bool,
+    ended: bool,
+}
+impl Voice {
+    fn stop(&mut self, frames: usize) {
+        if self.stopping && self.envelope_step < 0.0 {
+            return;
+        }
+
+        self.stopping = true;
+        if frames == 0 || self.envelope <= 0.0 {
+            self.ended = true;
+        } else {
+            self.envelope_step = -self.envelope / frames as f32;
+        }
+    }
+
+    /// Allocates the buffers for renders of up to `frames` frames, at any pitch.
+    fn reserve(&mut self, frames: usize, output_rate: u32) {
+        let ratio = output_rate as f64 / self.source_rate as f64;
+        let (lowest, highest) = (ratio / MAX_PITCH as f64, ratio / MIN_PITCH as f64);
+
+        // A fill reads at most a frame more than needed, rounded up.
+        let read = (frames as f64 / lowest).ceil() as usize + 2;
+        let held = self.resampler.reserve(read, lowest);
+        self.input = Vec::with_capacity(read * self.channels);
+
+        // The frames left are fewer than needed, the resampler adds them and the held ones.
+        let resampled = frames + ((held + 2) as f64 * highest).ceil() as usize + 2;
+        self.resampled = Vec::with_capacity(resampled * self.channels);
+    }
+
+    fn set_pitch(&mut self, pitch: f32, output_rate: u32) {
+        let pitch = pitch.clamp(MIN_PITCH, MAX_PITCH) as f64;
+        self.resampler
+            .set_ratio(output_rate as f64 / (self.source_rate as f64 * pitch));
+    }
+
+    fn set_gains(&mut self, layout: &ChannelLayout, volume: f32, pan: f32) {
+        self.volume = volume;
+        self.pan = pan.clamp(-1.0, 1.0);
+        layout.pan_gains(volume, self.pan, &mut self.target_gains);
+    }
+
+    /// Adds the voice to the interleaved `output`.
+    fn render(&mut self, output: &mut [f32], channels: usize, volume: f32) -> Result<()> {
+        if self.ended {
+            return Ok(());
+        }
+
+        let frames = output.len() / channels;
+        self.fill(frames)?;
+
+        let ready = (self.resampled.len() - self.cursor) / self.channels;
+        let rendered = ready.min(frames);
+        let ramp = 1.0 / frames.max(1) as f32;
+
+        for (i, frame) in output.chunks_exact_mut(channels).take(rendered).enumerate() {
+            let input = &self.resampled[self.cursor + i * self.channels..][..self.channels];
+            self.matrix.apply(input, &mut self.mixed);
+
+            self.envelope = (self.envelope + self.envelope_step).clamp(0.0, 1.0);
+            let t = (i + 1) as f32 * ramp;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let gain =
+                    self.gains[channel] + (self.target_gains[channel] - self.gains[channel]) * t;
+                *sample += self.mixed[channel] * gain * self.envelope * volume;
+            }
+
+            if self.stopping && self.envelope <= 0.0 {
+                self.ended = true;
+                break;
+            }
+        }
+        self.gains.copy_from_slice(&self.target_gains);
+        self.cursor += rendered * self.channels;
+
+        if rendered < frames {
+            self.ended = true;
+        }
+
+        Ok(())
+    }
+
+    /// Resamples until `frames` frames are ready or the source ended.
+    fn fill(&mut self, frames: usize) -> Result<()> {
+        self.resampled.drain(..self.cursor);
+        self.cursor = 0;
+
+        while self.resampled.len() / self.channels < frames && !self.source_ended {
+            let needed = frames - self.resampled.len() / self.channels;
+            let wanted = ((needed as f64 / self.resampler.ratio()).ceil() as usize + 1)
+                .min(self.input.capacity() / self.channels);
+            self.input.clear();
+
+            if self.source.read(wanted, self.channels, &mut self.input)? == 0 {
+                self.resampler.flush(&mut self.resampled);
+                self.source_ended = true;
+            } else {
+                self.resampler.process(&self.input, &mut self.resampled);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(channels: u16, sample_rate: u32, frames: usize) -> Arc<AudioBuffer> {
+        let samples = (0..frames * channels as usize)
+            .map(|i| ((i / channels as usize) as f32 * 0.05).sin() * 0.5)
+            .collect();
+
+        Arc::new(AudioBuffer::new(
+            AudioInfo {
+                channels,
+                sample_rate,
+                bits_per_sample: 32,
+                sample_type: Some(SampleType::FLOAT),
+            },
+            samples,
+        ))
+    }
+
+    fn buffers(voices: &LgVoiceManager) -> Vec<(*const f32, usize)> {
+        voices
+            .voices
+            .iter()
+            .flat_map(|voice| [&voice.input, &voice.resampled, &voice.mixed, &voice.gains])
+            .map(|buffer| (buffer.as_ptr(), buffer.capacity()))
+            .collect()
+    }
+
+    #[test]
+    fn render_keeps_the_buffers() {
+        let mut voices = LgVoiceManager::new(2, 48_000, 4)
+            .unwrap()
+            .with_max_block_frames(256)
+            .with_quality(ResampleQuality::HIGH);
+
+        let looping = PlayParams {
+            looping: Looping::Whole,
+            ..Default::default()
+        };
+        let fast = voices
+            .play_clip(&sine(2, 96_000, 3000), looping)
+            .unwrap()
+            .unwrap();
+        let slow = voices
+            .play_clip(&sine(1, 8_000, 500), looping)
+            .unwrap()
+            .unwrap();
+        let once = voices
+            .play_clip(&sine(1, 22_050, 20_000), PlayParams::default())
+            .unwrap()
+            .unwrap();
+
+        let started = buffers(&voices);
+        let mut output = vec![0.0; 1000 * 2];
+        for pitch in [100.0, 4.0, 1.0, 0.25, 0.0, 3.0] {
+            voices.set_voice_pitch(fast, pitch);
+            voices.set_voice_pitch(slow, pitch.recip());
+            voices.set_voice_pitch(once, pitch);
+            voices.set_voice_pan(once, -0.5);
+
+            voices.render(&mut output).unwrap();
+            assert!(output.iter().any(|sample| *sample != 0.0));
+            assert_eq!(buffers(&voices), started);
+        }
+    }
+}
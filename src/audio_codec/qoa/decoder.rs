@@ -0,0 +1,73 @@
+use super::super::{AudioInfo, Result, decoder::LgDecoder};
+use super::{LgQoaSampleIter, qoa_info, reader::LgQoaReader};
+use std::{fmt, fs, io, path};
+
+pub struct LgQoaDecoder<R: io::Read> {
+    info: AudioInfo,
+    sample_len: usize,
+
+    reader: LgQoaReader<R>,
+}
+impl<R: io::Read> fmt::Debug for LgQoaDecoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LgQoaDecoder")
+            .field("info", &self.info)
+            .field("sample_len", &self.sample_len)
+            .finish()
+    }
+}
+impl LgQoaDecoder<io::BufReader<fs::File>> {
+    pub fn new(path: impl AsRef<path::Path>) -> Result<Self> {
+        let file = fs::File::open(path)?;
+
+        Self::from_reader(io::BufReader::new(file))
+    }
+}
+impl<R: io::Read> LgQoaDecoder<R> {
+    pub fn from_reader(reader: R) -> Result<Self> {
+        // Already reads the first frame header, so the format is known.
+        let (reader, samples) = LgQoaReader::new(reader)?;
+
+        Ok(Self {
+            info: qoa_info(reader.channels, reader.sample_rate),
+            sample_len: samples as usize * reader.channels as usize,
+            reader,
+        })
+    }
+
+    /// Streamed files don't store their length, so [`LgDecoder::len`] is 0 for them.
+    #[inline(always)]
+    pub fn is_streaming(&self) -> bool {
+        self.sample_len == 0
+    }
+}
+impl<R: io::Read> LgDecoder for LgQoaDecoder<R> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn samples<S: super::Sample>(&mut self) -> impl Iterator<Item = S> {
+        LgQoaSampleIter::new(&mut self.reader)
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        // Files without frames have no format.
+        self.sample_len
+            .checked_div(self.info.channels as usize * self.info.sample_rate as usize)
+            .unwrap_or(0)
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.sample_len
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        // The length of streamed files is unknown, only the frames left tell.
+        self.is_streaming() && self.reader.is_ended()
+    }
+}
@@ -0,0 +1,73 @@
+use super::writer::LgQoaWriter;
+use super::{
+    super::{
+        Result,
+        encoder::LgEncoder,
+        sample::{Sample, SampleType},
+    },
+    AudioInfo, qoa_info,
+};
+use std::{fs, io, path};
+
+/// Only `channels` and `sample_rate` are used from the [`AudioInfo`], QOA is always 16 bit.
+pub struct LgQoaEncoder<W: io::Write + io::Seek> {
+    pub(super) info: AudioInfo,
+    writer: LgQoaWriter<W>,
+}
+impl LgQoaEncoder<io::BufWriter<fs::File>> {
+    pub fn new(path: impl AsRef<path::Path>, info: AudioInfo) -> Result<Self> {
+        Self::create(path, info, false)
+    }
+
+    /// The file header will not contain the number of samples, like when it is sent through a stream.
+    pub fn new_streaming(path: impl AsRef<path::Path>, info: AudioInfo) -> Result<Self> {
+        Self::create(path, info, true)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.finish()
+    }
+
+    fn create(path: impl AsRef<path::Path>, info: AudioInfo, streaming: bool) -> Result<Self> {
+        let file = fs::File::create(path)?;
+        let writer = LgQoaWriter::new(io::BufWriter::new(file), &info, streaming)?;
+
+        Ok(Self {
+            info: qoa_info(info.channels, info.sample_rate),
+            writer,
+        })
+    }
+}
+impl<W: io::Write + io::Seek> LgEncoder for LgQoaEncoder<W> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn encode_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        let value = i32::from_f32(sample.to_f32(SampleType::INT, 16), SampleType::INT, 16);
+
+        self.writer.write_sample(value as i16)
+    }
+
+    /// Samples still waiting for a full frame are counted.
+    #[inline(always)]
+    fn encoded_samples(&self) -> usize {
+        self.writer.samples_written * self.info.channels as usize + self.writer.pending_samples()
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.len() / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.encoded_samples()
+    }
+}
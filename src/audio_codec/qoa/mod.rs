@@ -0,0 +1,160 @@
+use super::{
+    AudioInfo,
+    sample::{Sample, SampleType},
+};
+use std::io;
+use std::marker::PhantomData;
+
+pub mod decoder;
+pub mod encoder;
+pub mod reader;
+pub mod writer;
+
+#[cfg(test)]
+mod tests;
+
+pub use decoder::LgQoaDecoder;
+pub use encoder::LgQoaEncoder;
+
+use reader::LgQoaReader;
+
+// ------------------------- LAYOUT --------------------------
+const QOA_MAGIC: &[u8; 4] = b"qoaf";
+const QOA_MAX_CHANNELS: u16 = 8;
+
+const QOA_SLICE_LEN: usize = 20;
+const QOA_SLICES_PER_FRAME: usize = 256;
+const QOA_FRAME_LEN: usize = QOA_SLICES_PER_FRAME * QOA_SLICE_LEN;
+const QOA_LMS_LEN: usize = 4;
+
+/// Size in bytes of a frame with `slices` slices per channel.
+#[inline(always)]
+const fn frame_size(channels: usize, slices: usize) -> usize {
+    8 + QOA_LMS_LEN * 4 * channels + 8 * slices * channels
+}
+
+/// QOA is always decoded to (and encoded from) 16 bit signed integers.
+#[inline(always)]
+fn qoa_info(channels: u16, sample_rate: u32) -> AudioInfo {
+    AudioInfo {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_type: Some(SampleType::INT),
+    }
+}
+
+// ------------------------- TABLES --------------------------
+const QOA_QUANT_TAB: [usize; 17] = [
+    7, 7, 7, 5, 5, 3, 3, 1, // -8..-1
+    0, // 0
+    0, 2, 2, 4, 4, 6, 6, 6, // 1..8
+];
+
+const QOA_SCALEFACTOR_TAB: [i32; 16] = [
+    1, 7, 21, 45, 84, 138, 211, 304, 421, 562, 731, 928, 1157, 1419, 1715, 2048,
+];
+
+/// `((1 << 16) + QOA_SCALEFACTOR_TAB[s] - 1) / QOA_SCALEFACTOR_TAB[s]`
+const QOA_RECIPROCAL_TAB: [i32; 16] = [
+    65536, 9363, 3121, 1457, 781, 475, 311, 216, 156, 117, 90, 71, 57, 47, 39, 32,
+];
+
+/// `round(QOA_SCALEFACTOR_TAB[s] * [0.75, -0.75, 2.5, -2.5, 4.5, -4.5, 7, -7])`
+const QOA_DEQUANT_TAB: [[i32; 8]; 16] = [
+    [1, -1, 3, -3, 5, -5, 7, -7],
+    [5, -5, 18, -18, 32, -32, 49, -49],
+    [16, -16, 53, -53, 95, -95, 147, -147],
+    [34, -34, 113, -113, 203, -203, 315, -315],
+    [63, -63, 210, -210, 378, -378, 588, -588],
+    [104, -104, 345, -345, 621, -621, 966, -966],
+    [158, -158, 528, -528, 950, -950, 1477, -1477],
+    [228, -228, 760, -760, 1368, -1368, 2128, -2128],
+    [316, -316, 1053, -1053, 1895, -1895, 2947, -2947],
+    [422, -422, 1405, -1405, 2529, -2529, 3934, -3934],
+    [548, -548, 1828, -1828, 3290, -3290, 5117, -5117],
+    [696, -696, 2320, -2320, 4176, -4176, 6496, -6496],
+    [868, -868, 2893, -2893, 5207, -5207, 8099, -8099],
+    [1064, -1064, 3548, -3548, 6386, -6386, 9933, -9933],
+    [1286, -1286, 4288, -4288, 7718, -7718, 12005, -12005],
+    [1536, -1536, 5120, -5120, 9216, -9216, 14336, -14336],
+];
+
+// ------------------------- LMS --------------------------
+
+/// Sign-sign least mean squares predictor, one per channel.
+/// Arithmetic wraps like the reference implementation, so corrupted files can't panic.
+#[derive(Debug, Default, Clone, Copy)]
+struct QoaLms {
+    history: [i32; QOA_LMS_LEN],
+    weights: [i32; QOA_LMS_LEN],
+}
+impl QoaLms {
+    /// Initial state used by the encoder.
+    fn new() -> Self {
+        Self {
+            history: [0; QOA_LMS_LEN],
+            weights: [0, 0, -(1 << 13), 1 << 14],
+        }
+    }
+
+    #[inline(always)]
+    fn predict(&self) -> i32 {
+        let prediction = self
+            .history
+            .iter()
+            .zip(self.weights.iter())
+            .fold(0i32, |acc, (h, w)| acc.wrapping_add(h.wrapping_mul(*w)));
+
+        prediction >> 13
+    }
+
+    #[inline(always)]
+    fn update(&mut self, sample: i32, residual: i32) {
+        let delta = residual >> 4;
+        for (h, w) in self.history.iter().zip(self.weights.iter_mut()) {
+            *w = w.wrapping_add(if *h < 0 { -delta } else { delta });
+        }
+
+        self.history.copy_within(1.., 0);
+        self.history[QOA_LMS_LEN - 1] = sample;
+    }
+}
+
+#[inline(always)]
+fn clamp_s16(value: i32) -> i32 {
+    value.clamp(i16::MIN as i32, i16::MAX as i32)
+}
+
+/// Divides `value` by the scalefactor, rounding away from zero.
+#[inline(always)]
+fn qoa_div(value: i32, scalefactor: usize) -> i32 {
+    let reciprocal = QOA_RECIPROCAL_TAB[scalefactor] as i64;
+    let n = ((value as i64 * reciprocal + (1 << 15)) >> 16) as i32;
+
+    n + (value.signum() - n.signum())
+}
+
+// ------------------------- SAMPLE --------------------------
+
+pub struct LgQoaSampleIter<'si, R: io::Read, S: Sample> {
+    reader: &'si mut LgQoaReader<R>,
+    _phantom: PhantomData<S>,
+}
+impl<'si, R: io::Read, S: Sample> LgQoaSampleIter<'si, R, S> {
+    fn new(reader: &'si mut LgQoaReader<R>) -> Self {
+        Self {
+            reader,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<R: io::Read, S: Sample> Iterator for LgQoaSampleIter<'_, R, S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.reader.next_sample().ok()??;
+
+        Some(S::from_f32(sample as f32 / 32768.0, SampleType::INT, 16))
+    }
+}
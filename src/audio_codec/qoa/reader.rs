@@ -0,0 +1,175 @@
+use super::super::error::Error;
+use super::{
+    QOA_DEQUANT_TAB, QOA_LMS_LEN, QOA_MAGIC, QOA_SLICE_LEN, QoaLms, clamp_s16, frame_size,
+};
+use crate::reader::LgReader;
+use std::io;
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct QoaFrameHeader {
+    pub(super) channels: u16,
+    pub(super) sample_rate: u32,
+    /// Samples per channel in this frame.
+    pub(super) samples: usize,
+    /// Frame size in bytes, including this header.
+    pub(super) size: usize,
+}
+
+pub struct LgQoaReader<R: io::Read> {
+    pub(super) reader: R,
+    pub(super) channels: u16,
+    pub(super) sample_rate: u32,
+    lms: Vec<QoaLms>,
+    /// Interleaved samples of the last decoded frame.
+    frame: Vec<i16>,
+    cursor: usize,
+    /// Read ahead, so the format is known with the file header and the end with the last frame.
+    next_header: Option<QoaFrameHeader>,
+}
+impl<R: io::Read> LgQoaReader<R> {
+    /// Reads the file header and the first frame header.
+    /// Returns the reader and the samples per channel (0 in streaming mode).
+    /// Files written from no samples have no frames, their channels and sample_rate are 0.
+    pub(super) fn new(mut reader: R) -> Result<(Self, u32), Error> {
+        if QOA_MAGIC != &reader.read_next_bytes()? {
+            return Err(Error::WrongHeader);
+        }
+
+        let samples = reader.read_be_u32()?;
+
+        let mut result = Self {
+            reader,
+            channels: 0,
+            sample_rate: 0,
+            lms: Vec::new(),
+            frame: Vec::new(),
+            cursor: 0,
+            next_header: None,
+        };
+
+        let Some(header) = result.read_frame_header()? else {
+            // Only an empty file can end right after its header.
+            return match samples {
+                0 => Ok((result, samples)),
+                _ => Err(Error::WrongHeader),
+            };
+        };
+        if header.channels == 0 || header.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "QOA channels and sample_rate must be > 0!".to_string(),
+            ));
+        }
+
+        result.channels = header.channels;
+        result.sample_rate = header.sample_rate;
+        result.lms = vec![QoaLms::default(); header.channels as usize];
+        result.next_header = Some(header);
+
+        Ok((result, samples))
+    }
+
+    /// Next decoded sample, `None` once all the frames were read.
+    pub(super) fn next_sample(&mut self) -> Result<Option<i16>, Error> {
+        while self.cursor >= self.frame.len() {
+            if !self.decode_frame()? {
+                return Ok(None);
+            }
+        }
+
+        self.cursor += 1;
+
+        Ok(Some(self.frame[self.cursor - 1]))
+    }
+
+    /// No samples left to read.
+    #[inline(always)]
+    pub(super) fn is_ended(&self) -> bool {
+        self.cursor >= self.frame.len() && self.next_header.is_none()
+    }
+}
+impl<R: io::Read> LgQoaReader<R> {
+    /// `None` when the end of the stream is reached.
+    fn read_frame_header(&mut self) -> Result<Option<QoaFrameHeader>, Error> {
+        let header = match self.reader.read_be_u64() {
+            Ok(header) => header,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(QoaFrameHeader {
+            channels: ((header >> 56) & 0xff) as u16,
+            sample_rate: ((header >> 32) & 0xffffff) as u32,
+            samples: ((header >> 16) & 0xffff) as usize,
+            size: (header & 0xffff) as usize,
+        }))
+    }
+
+    /// Returns false when there are no frames left.
+    fn decode_frame(&mut self) -> Result<bool, Error> {
+        let Some(header) = self.next_header.take() else {
+            return Ok(false);
+        };
+
+        if header.channels != self.channels || header.sample_rate != self.sample_rate {
+            return Err(Error::WrongFmtInfo(
+                "All QOA frames must have the same channels and sample_rate!".to_string(),
+            ));
+        }
+
+        let channels = self.channels as usize;
+        let slices = header.samples.div_ceil(QOA_SLICE_LEN);
+        let used_size = frame_size(channels, slices);
+
+        if header.size < used_size {
+            return Err(Error::WrongFmtInfo(
+                "QOA frame is too small for its samples!".to_string(),
+            ));
+        }
+
+        for lms in self.lms.iter_mut() {
+            let mut history = self.reader.read_be_u64()?;
+            let mut weights = self.reader.read_be_u64()?;
+
+            for i in 0..QOA_LMS_LEN {
+                lms.history[i] = (history >> 48) as i16 as i32;
+                lms.weights[i] = (weights >> 48) as i16 as i32;
+                history <<= 16;
+                weights <<= 16;
+            }
+        }
+
+        self.frame.clear();
+        self.frame.resize(header.samples * channels, 0);
+        self.cursor = 0;
+
+        for sample_index in (0..header.samples).step_by(QOA_SLICE_LEN) {
+            let slice_end = (sample_index + QOA_SLICE_LEN).min(header.samples);
+
+            for (c, lms) in self.lms.iter_mut().enumerate() {
+                let mut slice = self.reader.read_be_u64()?;
+                let scalefactor = ((slice >> 60) & 0xf) as usize;
+                slice <<= 4;
+
+                for si in sample_index..slice_end {
+                    let predicted = lms.predict();
+                    let quantized = ((slice >> 61) & 0x7) as usize;
+                    let dequantized = QOA_DEQUANT_TAB[scalefactor][quantized];
+                    let reconstructed = clamp_s16(predicted.wrapping_add(dequantized));
+
+                    self.frame[si * channels + c] = reconstructed as i16;
+                    slice <<= 3;
+
+                    lms.update(reconstructed, dequantized);
+                }
+            }
+        }
+
+        // Frames may declare more slices than needed.
+        let mut _skip_bytes = vec![0u8; header.size - used_size];
+        self.reader.read_into(&mut _skip_bytes)?;
+
+        self.next_header = self.read_frame_header()?;
+
+        Ok(true)
+    }
+}
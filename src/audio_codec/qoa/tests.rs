@@ -0,0 +1,108 @@
+//! Reference files in `tests/data/qoa`, encoded and decoded to `.wav` by a C port of the
+//! reference `qoa.h`. The mono one spans 2 frames, the stereo one ends with a partial slice.
+
+use super::super::{AudioInfo, decoder::LgDecoder, wav::LgWavDecoder};
+use super::{LgQoaDecoder, qoa_info, writer::LgQoaWriter};
+use std::{io, path};
+
+fn data(name: &str) -> path::PathBuf {
+    path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/qoa")
+        .join(name)
+}
+
+/// Samples and format of a reference `.wav`.
+fn reference(name: &str) -> (AudioInfo, Vec<i32>) {
+    let mut wav = LgWavDecoder::new(data(&format!("{name}.wav"))).unwrap();
+
+    (wav.info(), wav.samples().collect())
+}
+
+/// Encodes `samples` to memory, the way [`super::LgQoaEncoder`] does to files.
+fn encode(info: &AudioInfo, samples: &[i32], streaming: bool) -> Vec<u8> {
+    let mut bytes = io::Cursor::new(Vec::new());
+    let mut writer = LgQoaWriter::new(&mut bytes, info, streaming).unwrap();
+    for sample in samples {
+        writer.write_sample(*sample as i16).unwrap();
+    }
+    writer.finish().unwrap();
+    drop(writer);
+
+    bytes.into_inner()
+}
+
+fn assert_same_format(info: &AudioInfo, expected: &AudioInfo) {
+    assert_eq!(info.channels, expected.channels);
+    assert_eq!(info.sample_rate, expected.sample_rate);
+    assert_eq!(info.bits_per_sample, 16);
+    assert_eq!(info.sample_type, expected.sample_type);
+}
+
+fn check_reference(name: &str) {
+    let (info, expected) = reference(name);
+    let mut decoder = LgQoaDecoder::new(data(&format!("{name}.qoa"))).unwrap();
+
+    assert_same_format(&decoder.info(), &info);
+    assert_eq!(decoder.len(), expected.len());
+    assert!(!decoder.is_empty());
+    assert_eq!(decoder.samples::<i32>().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn decodes_reference_mono() {
+    check_reference("mono");
+}
+
+#[test]
+fn decodes_reference_stereo() {
+    check_reference("stereo");
+}
+
+#[test]
+fn round_trip() {
+    let (info, samples) = reference("stereo");
+    let bytes = encode(&info, &samples, false);
+    let mut decoder = LgQoaDecoder::from_reader(bytes.as_slice()).unwrap();
+
+    assert_same_format(&decoder.info(), &info);
+    assert_eq!(decoder.len(), samples.len());
+
+    let decoded: Vec<i32> = decoder.samples().collect();
+    assert_eq!(decoded.len(), samples.len());
+
+    // Lossy, so only close.
+    let (signal, noise) =
+        samples
+            .iter()
+            .zip(&decoded)
+            .fold((0f64, 0f64), |(signal, noise), (a, b)| {
+                let error = (*a - *b) as f64;
+                (signal + (*a as f64).powi(2), noise + error * error)
+            });
+    assert!(10.0 * (signal / noise).log10() > 30.0);
+}
+
+#[test]
+fn round_trip_streaming() {
+    let (info, samples) = reference("mono");
+    let bytes = encode(&info, &samples, true);
+    let mut decoder = LgQoaDecoder::from_reader(bytes.as_slice()).unwrap();
+
+    assert!(decoder.is_streaming());
+    assert_eq!(decoder.len(), 0);
+    assert!(!decoder.is_empty());
+    assert_eq!(decoder.samples::<i32>().count(), samples.len());
+    assert!(decoder.is_empty());
+}
+
+#[test]
+fn round_trip_empty() {
+    let info = qoa_info(2, 44_100);
+    let bytes = encode(&info, &[], false);
+    let mut decoder = LgQoaDecoder::from_reader(bytes.as_slice()).unwrap();
+
+    assert!(decoder.is_empty());
+    assert_eq!(decoder.len(), 0);
+    assert_eq!(decoder.duration(), 0);
+    assert_eq!(decoder.samples::<i32>().count(), 0);
+}
@@ -0,0 +1,221 @@
+use super::super::AudioInfo;
+use super::super::Result;
+use super::super::error::Error;
+use super::{
+    QOA_DEQUANT_TAB, QOA_FRAME_LEN, QOA_MAGIC, QOA_MAX_CHANNELS, QOA_QUANT_TAB,
+    QOA_SCALEFACTOR_TAB, QOA_SLICE_LEN, QoaLms, clamp_s16, frame_size, qoa_div,
+};
+use crate::writer::LgWriter;
+use std::io;
+
+const SAMPLES_POSITION: usize = 4;
+
+pub struct LgQoaWriter<W: io::Write + io::Seek> {
+    pub(super) writer: W,
+    /// Samples per channel encoded into frames.
+    pub(super) samples_written: usize,
+    channels: usize,
+    sample_rate: u32,
+    /// In streaming mode the file header has 0 samples and is never updated.
+    streaming: bool,
+    lms: Vec<QoaLms>,
+    /// Interleaved samples waiting for a full frame.
+    frame: Vec<i16>,
+}
+impl<W: io::Write + io::Seek> Drop for LgQoaWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+impl<W: io::Write + io::Seek> LgQoaWriter<W> {
+    pub fn new(writer: W, info: &AudioInfo, streaming: bool) -> Result<Self> {
+        if info.channels == 0 || info.channels > QOA_MAX_CHANNELS {
+            return Err(Error::WrongFmtInfo(
+                "QOA supports from 1 to 8 channels!".to_string(),
+            ));
+        }
+
+        if info.sample_rate == 0 || info.sample_rate > 0xffffff {
+            return Err(Error::WrongFmtInfo(
+                "QOA sample_rate must be in 1..=16777215!".to_string(),
+            ));
+        }
+
+        let mut result = Self {
+            writer,
+            samples_written: 0,
+            channels: info.channels as usize,
+            sample_rate: info.sample_rate,
+            streaming,
+            lms: vec![QoaLms::new(); info.channels as usize],
+            frame: Vec::with_capacity(QOA_FRAME_LEN * info.channels as usize),
+        };
+
+        result.writer.write_all(QOA_MAGIC)?;
+        // Empty for now, updated when finishing.
+        result.writer.write_be_u32(0)?;
+
+        Ok(result)
+    }
+
+    #[inline(always)]
+    pub fn write_sample(&mut self, sample: i16) -> Result<()> {
+        self.frame.push(sample);
+
+        if self.frame.len() == QOA_FRAME_LEN * self.channels {
+            self.encode_frame()?;
+        }
+
+        Ok(())
+    }
+
+    /// Samples waiting for a full frame.
+    #[inline(always)]
+    pub fn pending_samples(&self) -> usize {
+        self.frame.len()
+    }
+
+    /// Frames are only written once full, so samples still waiting for one are not flushed.
+    pub fn flush(&mut self) -> Result<()> {
+        let current_pos = self.writer.stream_position()?;
+        self.update_headers()?;
+        self.writer.flush()?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        // Incomplete channels can't be encoded.
+        self.frame
+            .truncate(self.frame.len() - self.frame.len() % self.channels);
+
+        if !self.frame.is_empty() {
+            self.encode_frame()?;
+        }
+
+        self.update_headers()?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+impl<W: io::Write + io::Seek> LgQoaWriter<W> {
+    fn update_headers(&mut self) -> Result<()> {
+        if self.streaming {
+            return Ok(());
+        }
+
+        let samples = u32::try_from(self.samples_written).map_err(|_| {
+            Error::Custom("QOA files can't have more than u32::MAX samples!".to_string())
+        })?;
+
+        let current_pos = self.writer.stream_position()?;
+        self.writer.go_to(SAMPLES_POSITION)?;
+        self.writer.write_be_u32(samples)?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+
+    fn encode_frame(&mut self) -> Result<()> {
+        let channels = self.channels;
+        let frame_len = self.frame.len() / channels;
+        let slices = frame_len.div_ceil(QOA_SLICE_LEN);
+
+        let header = (channels as u64) << 56
+            | (self.sample_rate as u64) << 32
+            | (frame_len as u64) << 16
+            | frame_size(channels, slices) as u64;
+        self.writer.write_be_u64(header)?;
+
+        for lms in &self.lms {
+            let (history, weights) = lms.history.iter().zip(lms.weights.iter()).fold(
+                (0u64, 0u64),
+                |(history, weights), (h, w)| {
+                    (
+                        (history << 16) | (*h as u16 as u64),
+                        (weights << 16) | (*w as u16 as u64),
+                    )
+                },
+            );
+
+            self.writer.write_be_u64(history)?;
+            self.writer.write_be_u64(weights)?;
+        }
+
+        // Starting every slice search at the last best scalefactor makes it much faster.
+        let mut prev_scalefactor = [0usize; QOA_MAX_CHANNELS as usize];
+
+        for sample_index in (0..frame_len).step_by(QOA_SLICE_LEN) {
+            let slice_end = (sample_index + QOA_SLICE_LEN).min(frame_len);
+
+            for (c, prev_scalefactor) in prev_scalefactor.iter_mut().take(channels).enumerate() {
+                let mut best_rank = u64::MAX;
+                let mut best_slice = 0u64;
+                let mut best_lms = self.lms[c];
+                let mut best_scalefactor = 0;
+
+                for sfi in 0..QOA_SCALEFACTOR_TAB.len() {
+                    let scalefactor = (sfi + *prev_scalefactor) % QOA_SCALEFACTOR_TAB.len();
+
+                    let mut lms = self.lms[c];
+                    let mut slice = scalefactor as u64;
+                    let mut current_rank = 0u64;
+
+                    for si in sample_index..slice_end {
+                        let sample = self.frame[si * channels + c] as i32;
+                        let predicted = lms.predict();
+
+                        let residual = sample.wrapping_sub(predicted);
+                        let scaled = qoa_div(residual, scalefactor);
+                        let clamped = scaled.clamp(-8, 8);
+                        let quantized = QOA_QUANT_TAB[(clamped + 8) as usize];
+                        let dequantized = QOA_DEQUANT_TAB[scalefactor][quantized];
+                        let reconstructed = clamp_s16(predicted.wrapping_add(dequantized));
+
+                        // Big weights cause pops and clicks, so they are penalized.
+                        let weights_penalty = (lms
+                            .weights
+                            .iter()
+                            .map(|w| *w as i64 * *w as i64)
+                            .fold(0i64, i64::saturating_add)
+                            >> 18)
+                            - 0x8ff;
+                        let weights_penalty = weights_penalty.max(0) as u64;
+
+                        let error = (sample - reconstructed) as i64;
+                        current_rank = current_rank
+                            .saturating_add((error * error) as u64)
+                            .saturating_add(weights_penalty.saturating_mul(weights_penalty));
+                        if current_rank > best_rank {
+                            break;
+                        }
+
+                        lms.update(reconstructed, dequantized);
+                        slice = (slice << 3) | quantized as u64;
+                    }
+
+                    if current_rank < best_rank {
+                        best_rank = current_rank;
+                        best_slice = slice;
+                        best_lms = lms;
+                        best_scalefactor = scalefactor;
+                    }
+                }
+
+                *prev_scalefactor = best_scalefactor;
+                self.lms[c] = best_lms;
+
+                // The last slice may be shorter, the unused bits are left as 0.
+                best_slice <<= (QOA_SLICE_LEN - (slice_end - sample_index)) * 3;
+                self.writer.write_be_u64(best_slice)?;
+            }
+        }
+
+        self.samples_written += frame_len;
+        self.frame.clear();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use super::super::{AudioInfo, Result, decoder::LgDecoder};
+use super::{LgRawSampleIter, RawFormat, check_info};
+use std::{fmt, fs, io, path};
+
+pub struct LgRawDecoder<R: io::Read> {
+    info: AudioInfo,
+    format: RawFormat,
+    /// `None` when reading from a stream.
+    sample_len: Option<usize>,
+
+    reader: R,
+}
+impl<R: io::Read> fmt::Debug for LgRawDecoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LgRawDecoder")
+            .field("info", &self.info)
+            .field("format", &self.format)
+            .field("sample_len", &self.sample_len)
+            .finish()
+    }
+}
+impl LgRawDecoder<io::BufReader<fs::File>> {
+    /// The whole file is sample data, so the length is known.
+    pub fn new(path: impl AsRef<path::Path>, info: AudioInfo, format: RawFormat) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let file_size = file.metadata()?.len() as usize;
+
+        let mut result = Self::from_reader(io::BufReader::new(file), info, format)?;
+        result.sample_len = Some(file_size / (info.bits_per_sample as usize / 8));
+
+        Ok(result)
+    }
+}
+impl<R: io::Read> LgRawDecoder<R> {
+    /// The length of a stream is unknown, so [`LgDecoder::len`] is 0.
+    pub fn from_reader(reader: R, info: AudioInfo, format: RawFormat) -> Result<Self> {
+        check_info(&info)?;
+
+        Ok(Self {
+            info,
+            format,
+            sample_len: None,
+            reader,
+        })
+    }
+
+    #[inline(always)]
+    pub fn format(&self) -> RawFormat {
+        self.format
+    }
+
+    /// False for decoders created with [`LgRawDecoder::from_reader`].
+    #[inline(always)]
+    pub fn is_len_known(&self) -> bool {
+        self.sample_len.is_some()
+    }
+}
+impl<R: io::Read> LgDecoder for LgRawDecoder<R> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn samples<S: super::Sample>(&mut self) -> impl Iterator<Item = S> {
+        LgRawSampleIter::new(&mut self.reader, &self.info, self.format)
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.len() / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.sample_len.unwrap_or(0)
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.sample_len == Some(0)
+    }
+}
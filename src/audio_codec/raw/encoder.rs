@@ -0,0 +1,91 @@
+use super::{
+    super::{
+        Result,
+        encoder::LgEncoder,
+        sample::{Sample, SampleType},
+    },
+    AudioInfo, RawFormat, check_info,
+};
+use std::{fs, io, path};
+
+/// Only writes the samples, so any [`io::Write`] can be used.
+pub struct LgRawEncoder<W: io::Write> {
+    pub(super) info: AudioInfo,
+    format: RawFormat,
+    samples_written: usize,
+    writer: W,
+}
+impl LgRawEncoder<io::BufWriter<fs::File>> {
+    pub fn new(path: impl AsRef<path::Path>, info: AudioInfo, format: RawFormat) -> Result<Self> {
+        let file = fs::File::create(path)?;
+
+        Self::from_writer(io::BufWriter::new(file), info, format)
+    }
+}
+impl<W: io::Write> LgRawEncoder<W> {
+    pub fn from_writer(writer: W, info: AudioInfo, format: RawFormat) -> Result<Self> {
+        check_info(&info)?;
+
+        Ok(Self {
+            info,
+            format,
+            samples_written: 0,
+            writer,
+        })
+    }
+
+    #[inline(always)]
+    pub fn format(&self) -> RawFormat {
+        self.format
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()
+    }
+
+    /// Flushes and gives back the writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush()?;
+
+        Ok(self.writer)
+    }
+}
+impl<W: io::Write> LgEncoder for LgRawEncoder<W> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn encode_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
+        sample.write_as(
+            &mut self.writer,
+            self.info.sample_type.unwrap_or(SampleType::INT),
+            self.info.bits_per_sample,
+            self.format.byte_order,
+            self.format.signed,
+        )?;
+        self.samples_written += 1;
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encoded_samples(&self) -> usize {
+        self.samples_written
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.len() / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.samples_written
+    }
+}
@@ -0,0 +1,88 @@
+use super::{
+    AudioInfo,
+    error::Error,
+    sample::{ByteOrder, Sample, SampleType},
+};
+use std::io;
+use std::marker::PhantomData;
+
+pub mod decoder;
+pub mod encoder;
+
+#[cfg(test)]
+mod tests;
+
+pub use decoder::LgRawDecoder;
+pub use encoder::LgRawEncoder;
+
+/// How the samples are stored, the rest comes from the [`AudioInfo`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawFormat {
+    pub byte_order: ByteOrder,
+    /// Applies to every integer size, 8 bit samples are usually unsigned.
+    pub signed: bool,
+}
+impl Default for RawFormat {
+    /// Signed little-endian, like most hardware.
+    fn default() -> Self {
+        Self {
+            byte_order: ByteOrder::LittleEndian,
+            signed: true,
+        }
+    }
+}
+
+/// Nothing in the data says the format, so it is checked before using it.
+fn check_info(info: &AudioInfo) -> Result<(), Error> {
+    if info.channels == 0 || info.sample_rate == 0 {
+        return Err(Error::WrongFmtInfo(
+            "Raw channels and sample_rate must be > 0!".to_string(),
+        ));
+    }
+
+    match (
+        info.sample_type.unwrap_or(SampleType::INT),
+        info.bits_per_sample,
+    ) {
+        (SampleType::INT, 8 | 16 | 24 | 32) | (SampleType::FLOAT, 32 | 64) => Ok(()),
+
+        (sample_type, bits_per_sample) => Err(Error::WrongFmtInfo(format!(
+            "{sample_type:?} with {bits_per_sample} bits per sample is not supported!"
+        ))),
+    }
+}
+
+// ------------------------- SAMPLE --------------------------
+
+pub struct LgRawSampleIter<'si, R: io::Read, S: Sample> {
+    sample_type: SampleType,
+    bits_per_sample: u16,
+    format: RawFormat,
+    reader: &'si mut R,
+    _phantom: PhantomData<S>,
+}
+impl<'si, R: io::Read, S: Sample> LgRawSampleIter<'si, R, S> {
+    fn new(reader: &'si mut R, info: &AudioInfo, format: RawFormat) -> Self {
+        Self {
+            sample_type: info.sample_type.unwrap_or(SampleType::INT),
+            bits_per_sample: info.bits_per_sample,
+            format,
+            reader,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<R: io::Read, S: Sample> Iterator for LgRawSampleIter<'_, R, S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        S::read_as(
+            self.reader,
+            self.sample_type,
+            self.bits_per_sample,
+            self.format.byte_order,
+            self.format.signed,
+        )
+        .ok()
+    }
+}
@@ -0,0 +1,146 @@
+use super::super::{
+    AudioInfo,
+    decoder::LgDecoder,
+    encoder::LgEncoder,
+    sample::{ByteOrder, Sample, SampleType},
+};
+use super::{LgRawDecoder, LgRawEncoder, RawFormat};
+
+const INT_FORMATS: [u16; 4] = [8, 16, 24, 32];
+
+fn info(sample_type: SampleType, bits_per_sample: u16) -> AudioInfo {
+    AudioInfo {
+        channels: 1,
+        sample_rate: 8_000,
+        bits_per_sample,
+        sample_type: Some(sample_type),
+    }
+}
+
+fn format(byte_order: ByteOrder, signed: bool) -> RawFormat {
+    RawFormat { byte_order, signed }
+}
+
+fn encode<S: Sample>(info: AudioInfo, format: RawFormat, samples: Vec<S>) -> Vec<u8> {
+    let mut encoder = LgRawEncoder::from_writer(Vec::new(), info, format).unwrap();
+    for sample in samples {
+        encoder.encode_sample(sample).unwrap();
+    }
+
+    encoder.into_inner().unwrap()
+}
+
+fn decode<S: Sample>(info: AudioInfo, format: RawFormat, bytes: &[u8]) -> Vec<S> {
+    LgRawDecoder::from_reader(bytes, info, format)
+        .unwrap()
+        .samples()
+        .collect()
+}
+
+#[test]
+fn int_round_trips() {
+    for bits in INT_FORMATS {
+        let max = ((1i64 << (bits - 1)) - 1) as i32;
+        let samples = vec![-max - 1, -max / 7, -1, 0, 1, max / 3, max];
+        let info = info(SampleType::INT, bits);
+
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            for signed in [true, false] {
+                let format = format(byte_order, signed);
+                let bytes = encode(info, format, samples.clone());
+
+                assert_eq!(bytes.len(), samples.len() * bits as usize / 8);
+                assert_eq!(decode::<i32>(info, format, &bytes), samples, "{format:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn big_endian_layout() {
+    let signed = format(ByteOrder::BigEndian, true);
+
+    let bytes = encode(info(SampleType::INT, 16), signed, vec![0x1234i32, -2]);
+    assert_eq!(bytes, [0x12, 0x34, 0xff, 0xfe]);
+
+    let bytes = encode(info(SampleType::INT, 24), signed, vec![0x123456i32]);
+    assert_eq!(bytes, [0x12, 0x34, 0x56]);
+
+    let bytes = encode(info(SampleType::INT, 32), signed, vec![0x12345678i32]);
+    assert_eq!(bytes, [0x12, 0x34, 0x56, 0x78]);
+}
+
+#[test]
+fn unsigned_is_offset_binary() {
+    let samples = vec![i16::MIN as i32, -1, 0, i16::MAX as i32];
+
+    let little = encode(
+        info(SampleType::INT, 16),
+        format(ByteOrder::LittleEndian, false),
+        samples.clone(),
+    );
+    assert_eq!(little, [0x00, 0x00, 0xff, 0x7f, 0x00, 0x80, 0xff, 0xff]);
+
+    let big = encode(
+        info(SampleType::INT, 24),
+        format(ByteOrder::BigEndian, false),
+        vec![0i32, -1],
+    );
+    assert_eq!(big, [0x80, 0x00, 0x00, 0x7f, 0xff, 0xff]);
+
+    let big = encode(
+        info(SampleType::INT, 32),
+        format(ByteOrder::BigEndian, false),
+        vec![i32::MIN, 0],
+    );
+    assert_eq!(big, [0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn signed_8_bits() {
+    let info = info(SampleType::INT, 8);
+    let samples = vec![-128i32, -1, 0, 127];
+
+    let signed = format(ByteOrder::LittleEndian, true);
+    let bytes = encode(info, signed, samples.clone());
+    assert_eq!(bytes, [0x80, 0xff, 0x00, 0x7f]);
+    assert_eq!(decode::<i32>(info, signed, &bytes), samples);
+    assert_eq!(
+        decode::<f32>(info, signed, &bytes),
+        [-1.0, -1.0 / 128.0, 0.0, 127.0 / 128.0]
+    );
+
+    // Usual 8 bits, the byte order doesn't matter.
+    let unsigned = format(ByteOrder::BigEndian, false);
+    let bytes = encode(info, unsigned, samples.clone());
+    assert_eq!(bytes, [0x00, 0x7f, 0x80, 0xff]);
+    assert_eq!(decode::<i32>(info, unsigned, &bytes), samples);
+}
+
+#[test]
+fn float_round_trips() {
+    let samples = vec![-1.0f32, -0.5, 0.0, 0.25, 1.0];
+
+    for bits in [32, 64] {
+        let info = info(SampleType::FLOAT, bits);
+
+        // Floats ignore the signedness.
+        for signed in [true, false] {
+            let little = encode(
+                info,
+                format(ByteOrder::LittleEndian, signed),
+                samples.clone(),
+            );
+            let big = encode(info, format(ByteOrder::BigEndian, signed), samples.clone());
+
+            let size = bits as usize / 8;
+            for (little, big) in little.chunks(size).zip(big.chunks(size)) {
+                assert!(little.iter().eq(big.iter().rev()));
+            }
+            assert_eq!(
+                decode::<f32>(info, format(ByteOrder::BigEndian, signed), &big),
+                samples
+            );
+        }
+    }
+}
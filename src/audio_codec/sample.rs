@@ -1,5 +1,6 @@
 use super::{Result, error::Error};
 use crate::{bytes::conversions::*, reader::LgReader, writer::LgWriter};
+use std::io;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SampleType {
@@ -7,6 +8,13 @@ pub enum SampleType {
     FLOAT,
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum ByteOrder {
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
 pub trait Sample: Sized {
     fn read(
         reader: &mut impl LgReader<Error = super::error::Error>,
@@ -20,6 +28,148 @@ pub trait Sample: Sized {
         sample_type: SampleType,
         bits_per_sample: u16,
     ) -> Result<()>;
+
+    /// Builds a sample from a normalized value (`-1.0..=1.0`), as if it was read
+    /// with the given format.
+    /// By default the value is stored with the format and read back, panicking if
+    /// [`Sample::read`] doesn't support it.
+    fn from_f32(value: f32, sample_type: SampleType, bits_per_sample: u16) -> Self {
+        let mut bytes = io::Cursor::new([0u8; 8]);
+        // Formats f32 can't store are read back from zeros.
+        let _ = value.write(&mut bytes, sample_type, bits_per_sample);
+
+        Self::read(
+            &mut SampleBytes(bytes.get_ref()),
+            sample_type,
+            bits_per_sample,
+        )
+        .expect("Sample::from_f32 needs a format supported by Sample::read!")
+    }
+
+    /// Normalizes the sample to `-1.0..=1.0`, interpreting it as stored with the given format.
+    /// By default the sample is stored with the format and read back, silent if
+    /// [`Sample::write`] doesn't support it.
+    fn to_f32(self, sample_type: SampleType, bits_per_sample: u16) -> f32 {
+        stored_sample(self, sample_type, bits_per_sample).map_or(0.0, |(_, value)| value)
+    }
+
+    /// Like [`Sample::read`], which only reads little-endian integers that are signed except for 8 bits.
+    /// `signed` applies to every integer size, floats ignore it.
+    fn read_as<E>(
+        reader: &mut impl LgReader<Error = E>,
+        sample_type: SampleType,
+        bits_per_sample: u16,
+        byte_order: ByteOrder,
+        signed: bool,
+    ) -> Result<Self>
+    where
+        Error: From<E>,
+    {
+        let size = sample_size(sample_type, bits_per_sample)?;
+        let mut bytes = [0u8; 8];
+        reader.read_into(&mut bytes[..size])?;
+
+        let bytes = &mut bytes[..size];
+        if byte_order == ByteOrder::BigEndian {
+            bytes.reverse();
+        }
+        if flip_sign(sample_type, bits_per_sample, signed) {
+            bytes[size - 1] ^= 0x80;
+        }
+
+        Self::read(&mut SampleBytes(bytes), sample_type, bits_per_sample)
+    }
+
+    /// Like [`Sample::write`], for any byte order and signedness (see [`Sample::read_as`]).
+    /// Doesn't need to seek.
+    fn write_as(
+        self,
+        writer: &mut impl io::Write,
+        sample_type: SampleType,
+        bits_per_sample: u16,
+        byte_order: ByteOrder,
+        signed: bool,
+    ) -> Result<()> {
+        let mut bytes = io::Cursor::new([0u8; 8]);
+        self.write(&mut bytes, sample_type, bits_per_sample)?;
+
+        let size = bytes.position() as usize;
+        let bytes = &mut bytes.get_mut()[..size];
+        if flip_sign(sample_type, bits_per_sample, signed) {
+            bytes[size - 1] ^= 0x80;
+        }
+        if byte_order == ByteOrder::BigEndian {
+            bytes.reverse();
+        }
+
+        Ok(writer.write_all(bytes)?)
+    }
+}
+
+/// Bytes of a sample, if the format is supported.
+fn sample_size(sample_type: SampleType, bits_per_sample: u16) -> Result<usize> {
+    match (sample_type, bits_per_sample) {
+        (SampleType::INT, 8 | 16 | 24 | 32) | (SampleType::FLOAT, 32 | 64) => {
+            Ok(bits_per_sample as usize / 8)
+        }
+
+        _ => Err(Error::Conversion(std::format!(
+            "{:?} with {} bits per sample is not supported!",
+            sample_type,
+            bits_per_sample
+        ))),
+    }
+}
+
+/// Offset binary and two's complement only differ by the top bit, so it is flipped
+/// when the signedness is not the one expected by [`Sample::read`] and [`Sample::write`].
+#[inline(always)]
+fn flip_sign(sample_type: SampleType, bits_per_sample: u16, signed: bool) -> bool {
+    sample_type == SampleType::INT && signed == (bits_per_sample == 8)
+}
+
+/// The little-endian bytes of a single sample, so [`Sample::read`] can be used on them.
+struct SampleBytes<'a>(&'a [u8]);
+impl LgReader for SampleBytes<'_> {
+    type Error = Error;
+
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<()> {
+        Ok(io::Read::read_exact(&mut self.0, buffer)?)
+    }
+
+    fn read_next_bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.read_into(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    fn skip_next_bytes<const N: usize>(&mut self) -> Result<()> {
+        self.read_next_bytes::<N>().map(|_| ())
+    }
+}
+
+/// Stores `sample` with the given format and reads it back, as `S` and normalized, for
+/// what needs both without `S` being `Copy`.
+pub(crate) fn stored_sample<S: Sample>(
+    sample: S,
+    sample_type: SampleType,
+    bits_per_sample: u16,
+) -> Result<(S, f32)> {
+    let mut bytes = io::Cursor::new([0u8; 8]);
+    sample.write(&mut bytes, sample_type, bits_per_sample)?;
+
+    let bytes = &bytes.get_ref()[..bytes.position() as usize];
+    Ok((
+        S::read(&mut SampleBytes(bytes), sample_type, bits_per_sample)?,
+        f32::read(&mut SampleBytes(bytes), sample_type, bits_per_sample)?,
+    ))
+}
+
+/// Full scale of an integer sample with `bits_per_sample` bits.
+#[inline(always)]
+fn int_scale(bits_per_sample: u16) -> f32 {
+    (1u64 << (bits_per_sample.clamp(1, 32) - 1)) as f32
 }
 
 impl Sample for i32 {
@@ -71,6 +221,25 @@ impl Sample for i32 {
 
         Ok(())
     }
+
+    #[inline]
+    fn from_f32(value: f32, sample_type: SampleType, bits_per_sample: u16) -> Self {
+        match sample_type {
+            SampleType::INT => {
+                let scale = int_scale(bits_per_sample);
+                (value * scale).round().clamp(-scale, scale - 1.0) as i32
+            }
+            SampleType::FLOAT => f32_to_i32(value),
+        }
+    }
+
+    #[inline]
+    fn to_f32(self, sample_type: SampleType, bits_per_sample: u16) -> f32 {
+        match sample_type {
+            SampleType::INT => self as f32 / int_scale(bits_per_sample),
+            SampleType::FLOAT => i32_to_f32(self),
+        }
+    }
 }
 
 impl Sample for f32 {
@@ -97,7 +266,7 @@ impl Sample for f32 {
             }
         };
 
-        Ok(i32_to_f32(int_value))
+        Ok(int_value.to_f32(sample_type, bits_per_sample))
     }
 
     fn write(
@@ -106,7 +275,7 @@ impl Sample for f32 {
         sample_type: SampleType,
         bits_per_sample: u16,
     ) -> Result<()> {
-        let int_value = f32_to_i32(self);
+        let int_value = i32::from_f32(self, sample_type, bits_per_sample);
 
         let _e = match (sample_type, bits_per_sample) {
             (SampleType::INT, 8) => writer.write_le_i8(int_value as i8),
@@ -127,4 +296,85 @@ impl Sample for f32 {
 
         Ok(())
     }
+
+    #[inline(always)]
+    fn from_f32(value: f32, _: SampleType, _: u16) -> Self {
+        value
+    }
+
+    #[inline(always)]
+    fn to_f32(self, _: SampleType, _: u16) -> f32 {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Only has what the [`Sample`] trait requires.
+    #[derive(Debug, PartialEq)]
+    struct Minimal(i32);
+    impl Sample for Minimal {
+        fn read(
+            reader: &mut impl LgReader<Error = Error>,
+            sample_type: SampleType,
+            bits_per_sample: u16,
+        ) -> Result<Self> {
+            i32::read(reader, sample_type, bits_per_sample).map(Minimal)
+        }
+
+        fn write(
+            self,
+            writer: &mut impl LgWriter<Error = io::Error>,
+            sample_type: SampleType,
+            bits_per_sample: u16,
+        ) -> Result<()> {
+            self.0.write(writer, sample_type, bits_per_sample)
+        }
+    }
+
+    const INT_FORMATS: [u16; 4] = [8, 16, 24, 32];
+
+    #[test]
+    fn int_scaling() {
+        assert_eq!(16384.to_f32(SampleType::INT, 16), 0.5);
+        assert_eq!((-128).to_f32(SampleType::INT, 8), -1.0);
+        assert_eq!(i32::from_f32(0.5, SampleType::INT, 24), 1 << 22);
+        // Full scale clips to the largest value.
+        assert_eq!(i32::from_f32(1.0, SampleType::INT, 16), i16::MAX as i32);
+        assert_eq!(i32::from_f32(-2.0, SampleType::INT, 16), i16::MIN as i32);
+
+        for bits in INT_FORMATS {
+            let value = -100.0 / int_scale(bits);
+            assert_eq!(f32::from_f32(value, SampleType::INT, bits), value);
+            assert_eq!(i32::from_f32(value, SampleType::INT, bits), -100);
+            assert_eq!((-100).to_f32(SampleType::INT, bits), value);
+        }
+    }
+
+    #[test]
+    fn default_conversions_match_i32() {
+        for bits in INT_FORMATS {
+            for value in [-1.0, -0.25, 0.0, 0.001, 0.5, 0.999] {
+                let expected = i32::from_f32(value, SampleType::INT, bits);
+                let sample = Minimal::from_f32(value, SampleType::INT, bits);
+
+                assert_eq!(sample, Minimal(expected));
+                assert_eq!(
+                    sample.to_f32(SampleType::INT, bits),
+                    expected.to_f32(SampleType::INT, bits)
+                );
+            }
+        }
+
+        let sample = Minimal::from_f32(0.5, SampleType::FLOAT, 32);
+        assert_eq!(sample, Minimal(i32::from_f32(0.5, SampleType::FLOAT, 32)));
+        assert_eq!(sample.to_f32(SampleType::FLOAT, 32), 0.5);
+    }
+
+    #[test]
+    fn default_to_f32_of_unsupported_format_is_silent() {
+        assert_eq!(Minimal(1234).to_f32(SampleType::INT, 12), 0.0);
+    }
 }
@@ -0,0 +1,836 @@
+//! Procedural sound effects, with the synth and parameters of sfxr.
+//!
+//! Parameters are saved in the `.sfs` files of sfxr, version 102, little-endian:
+//! version `i32`, wave `i32`, volume `f32`, then the parameters as `f32` in the order of
+//! [`SfxrParams`], with `filter_on` as a `u8` after `env_punch`.
+//!
+//! ```ignore
+//! let mut params = SfxrParams::preset(SfxrPreset::Laser, 7);
+//! params.mutate(8);
+//! params.save("laser.sfs")?;
+//!
+//! let info = AudioInfo { channels: 1, sample_rate: 44_100, bits_per_sample: 16, sample_type: Some(SampleType::INT) };
+//! params.export_wav("laser.wav", info)?;
+//! ```
+
+use super::{
+    AudioInfo, Result,
+    buffer::AudioBuffer,
+    decoder::LgDecoder,
+    dsp::{ResampleQuality, resample},
+    error::Error,
+    generator::Random,
+    pipeline::LgPipeline,
+    sample::{Sample, SampleType},
+    wav::LgWavEncoder,
+};
+use crate::{reader::LgReader, writer::LgWriter};
+use std::{f32::consts::TAU, fs, io, path};
+
+const SFS_VERSION: i32 = 102;
+/// Rate the synth runs at, its times are in samples of it.
+const SFXR_SAMPLE_RATE: u32 = 44_100;
+/// Gain of the synth before the volume.
+const MASTER_VOLUME: f32 = 0.05;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SfxrWave {
+    #[default]
+    Square,
+    Saw,
+    Sine,
+    Noise,
+}
+impl SfxrWave {
+    const ALL: [Self; 4] = [Self::Square, Self::Saw, Self::Sine, Self::Noise];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SfxrPreset {
+    Pickup,
+    Laser,
+    Explosion,
+    Powerup,
+    Hit,
+    Jump,
+    Blip,
+}
+
+/// Parameters of sfxr, in `0.0..=1.0` or `-1.0..=1.0` for the ramps and the ones with a sign.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SfxrParams {
+    pub wave: SfxrWave,
+    pub volume: f32,
+
+    pub base_freq: f32,
+    /// Ends the sound when the frequency slides under it.
+    pub freq_limit: f32,
+    pub freq_ramp: f32,
+    pub freq_dramp: f32,
+    /// Of the square wave.
+    pub duty: f32,
+    pub duty_ramp: f32,
+
+    pub vib_strength: f32,
+    pub vib_speed: f32,
+    /// Unused by the synth, kept for the files of sfxr.
+    pub vib_delay: f32,
+
+    pub env_attack: f32,
+    pub env_sustain: f32,
+    pub env_decay: f32,
+    /// Louder start of the sustain.
+    pub env_punch: f32,
+
+    /// Unused by the synth, kept for the files of sfxr.
+    pub filter_on: bool,
+    pub lpf_resonance: f32,
+    pub lpf_freq: f32,
+    pub lpf_ramp: f32,
+    pub hpf_freq: f32,
+    pub hpf_ramp: f32,
+
+    pub pha_offset: f32,
+    pub pha_ramp: f32,
+
+    /// Restarts the frequency and arpeggio.
+    pub repeat_speed: f32,
+    pub arp_speed: f32,
+    /// Positive goes up, negative down.
+    pub arp_mod: f32,
+}
+impl Default for SfxrParams {
+    fn default() -> Self {
+        Self {
+            wave: SfxrWave::Square,
+            volume: 0.5,
+            base_freq: 0.3,
+            freq_limit: 0.0,
+            freq_ramp: 0.0,
+            freq_dramp: 0.0,
+            duty: 0.0,
+            duty_ramp: 0.0,
+            vib_strength: 0.0,
+            vib_speed: 0.0,
+            vib_delay: 0.0,
+            env_attack: 0.0,
+            env_sustain: 0.3,
+            env_decay: 0.4,
+            env_punch: 0.0,
+            filter_on: false,
+            lpf_resonance: 0.0,
+            lpf_freq: 1.0,
+            lpf_ramp: 0.0,
+            hpf_freq: 0.0,
+            hpf_ramp: 0.0,
+            pha_offset: 0.0,
+            pha_ramp: 0.0,
+            repeat_speed: 0.0,
+            arp_speed: 0.0,
+            arp_mod: 0.0,
+        }
+    }
+}
+impl SfxrParams {
+    /// Random parameters of the kind of sound, the same `seed` gives the same ones.
+    pub fn preset(preset: SfxrPreset, seed: u64) -> Self {
+        let mut random = SfxrRandom(Random::new(seed));
+        let r = &mut random;
+        let mut p = Self::default();
+
+        match preset {
+            SfxrPreset::Pickup => {
+                p.base_freq = 0.4 + r.frnd(0.5);
+                p.env_sustain = r.frnd(0.1);
+                p.env_decay = 0.1 + r.frnd(0.4);
+                p.env_punch = 0.3 + r.frnd(0.3);
+                if r.chance() {
+                    p.arp_speed = 0.5 + r.frnd(0.2);
+                    p.arp_mod = 0.2 + r.frnd(0.4);
+                }
+            }
+            SfxrPreset::Laser => {
+                p.wave = SfxrWave::ALL[r.rnd(2)];
+                if p.wave == SfxrWave::Sine && r.chance() {
+                    p.wave = SfxrWave::ALL[r.rnd(1)];
+                }
+                p.base_freq = 0.5 + r.frnd(0.5);
+                p.freq_limit = (p.base_freq - 0.2 - r.frnd(0.6)).max(0.2);
+                p.freq_ramp = -0.15 - r.frnd(0.2);
+                if r.rnd(2) == 0 {
+                    p.base_freq = 0.3 + r.frnd(0.6);
+                    p.freq_limit = r.frnd(0.1);
+                    p.freq_ramp = -0.35 - r.frnd(0.3);
+                }
+                if r.chance() {
+                    p.duty = r.frnd(0.5);
+                    p.duty_ramp = r.frnd(0.2);
+                } else {
+                    p.duty = 0.4 + r.frnd(0.5);
+                    p.duty_ramp = -r.frnd(0.7);
+                }
+                p.env_sustain = 0.1 + r.frnd(0.2);
+                p.env_decay = r.frnd(0.4);
+                if r.chance() {
+                    p.env_punch = r.frnd(0.3);
+                }
+                if r.rnd(2) == 0 {
+                    p.pha_offset = r.frnd(0.2);
+                    p.pha_ramp = -r.frnd(0.2);
+                }
+                if r.chance() {
+                    p.hpf_freq = r.frnd(0.3);
+                }
+            }
+            SfxrPreset::Explosion => {
+                p.wave = SfxrWave::Noise;
+                if r.chance() {
+                    p.base_freq = 0.1 + r.frnd(0.4);
+                    p.freq_ramp = -0.1 + r.frnd(0.4);
+                } else {
+                    p.base_freq = 0.2 + r.frnd(0.7);
+                    p.freq_ramp = -0.2 - r.frnd(0.2);
+                }
+                p.base_freq *= p.base_freq;
+                if r.rnd(4) == 0 {
+                    p.freq_ramp = 0.0;
+                }
+                if r.rnd(2) == 0 {
+                    p.repeat_speed = 0.3 + r.frnd(0.5);
+                }
+                p.env_sustain = 0.1 + r.frnd(0.3);
+                p.env_decay = r.frnd(0.5);
+                if r.chance() {
+                    p.pha_offset = -0.3 + r.frnd(0.9);
+                    p.pha_ramp = -r.frnd(0.3);
+                }
+                p.env_punch = 0.2 + r.frnd(0.6);
+                if r.chance() {
+                    p.vib_strength = r.frnd(0.7);
+                    p.vib_speed = r.frnd(0.6);
+                }
+                if r.rnd(2) == 0 {
+                    p.arp_speed = 0.6 + r.frnd(0.3);
+                    p.arp_mod = 0.8 - r.frnd(1.6);
+                }
+            }
+            SfxrPreset::Powerup => {
+                if r.chance() {
+                    p.wave = SfxrWave::Saw;
+                } else {
+                    p.duty = r.frnd(0.6);
+                }
+                p.base_freq = 0.2 + r.frnd(0.3);
+                if r.chance() {
+                    p.freq_ramp = 0.1 + r.frnd(0.4);
+                    p.repeat_speed = 0.4 + r.frnd(0.4);
+                } else {
+                    p.freq_ramp = 0.05 + r.frnd(0.2);
+                    if r.chance() {
+                        p.vib_strength = r.frnd(0.7);
+                        p.vib_speed = r.frnd(0.6);
+                    }
+                }
+                p.env_sustain = r.frnd(0.4);
+                p.env_decay = 0.1 + r.frnd(0.4);
+            }
+            SfxrPreset::Hit => {
+                p.wave = match SfxrWave::ALL[r.rnd(2)] {
+                    SfxrWave::Sine => SfxrWave::Noise,
+                    wave => wave,
+                };
+                if p.wave == SfxrWave::Square {
+                    p.duty = r.frnd(0.6);
+                }
+                p.base_freq = 0.2 + r.frnd(0.6);
+                p.freq_ramp = -0.3 - r.frnd(0.4);
+                p.env_sustain = r.frnd(0.1);
+                p.env_decay = 0.1 + r.frnd(0.2);
+                if r.chance() {
+                    p.hpf_freq = r.frnd(0.3);
+                }
+            }
+            SfxrPreset::Jump => {
+                p.duty = r.frnd(0.6);
+                p.base_freq = 0.3 + r.frnd(0.3);
+                p.freq_ramp = 0.1 + r.frnd(0.2);
+                p.env_sustain = 0.1 + r.frnd(0.3);
+                p.env_decay = 0.1 + r.frnd(0.2);
+                if r.chance() {
+                    p.hpf_freq = r.frnd(0.3);
+                }
+                if r.chance() {
+                    p.lpf_freq = 1.0 - r.frnd(0.6);
+                }
+            }
+            SfxrPreset::Blip => {
+                p.wave = SfxrWave::ALL[r.rnd(1)];
+                if p.wave == SfxrWave::Square {
+                    p.duty = r.frnd(0.6);
+                }
+                p.base_freq = 0.2 + r.frnd(0.4);
+                p.env_sustain = 0.1 + r.frnd(0.1);
+                p.env_decay = r.frnd(0.2);
+                p.hpf_freq = 0.1;
+            }
+        }
+
+        p
+    }
+
+    /// Nudges about half of the parameters, the same `seed` gives the same changes.
+    pub fn mutate(&mut self, seed: u64) {
+        let mut random = SfxrRandom(Random::new(seed));
+
+        for (value, signed) in [
+            (&mut self.base_freq, false),
+            (&mut self.freq_ramp, true),
+            (&mut self.freq_dramp, true),
+            (&mut self.duty, false),
+            (&mut self.duty_ramp, true),
+            (&mut self.vib_strength, false),
+            (&mut self.vib_speed, false),
+            (&mut self.vib_delay, false),
+            (&mut self.env_attack, false),
+            (&mut self.env_sustain, false),
+            (&mut self.env_decay, false),
+            (&mut self.env_punch, false),
+            (&mut self.lpf_resonance, false),
+            (&mut self.lpf_freq, false),
+            (&mut self.lpf_ramp, true),
+            (&mut self.hpf_freq, false),
+            (&mut self.hpf_ramp, true),
+            (&mut self.pha_offset, true),
+            (&mut self.pha_ramp, true),
+            (&mut self.repeat_speed, false),
+            (&mut self.arp_speed, false),
+            (&mut self.arp_mod, true),
+        ] {
+            if random.chance() {
+                let min = if signed { -1.0 } else { 0.0 };
+                *value = (*value + random.frnd(0.1) - 0.05).clamp(min, 1.0);
+            }
+        }
+    }
+
+    /// Renders the sound, mono at 44.1 kHz.
+    pub fn render(&self) -> AudioBuffer {
+        let mut synth = Synth::new(self);
+        let mut samples = Vec::new();
+        while let Some(sample) = synth.next_sample() {
+            samples.push(sample);
+        }
+
+        AudioBuffer::new(
+            AudioInfo {
+                channels: 1,
+                sample_rate: SFXR_SAMPLE_RATE,
+                bits_per_sample: 32,
+                sample_type: Some(SampleType::FLOAT),
+            },
+            samples,
+        )
+    }
+
+    /// Renders the sound to a WAV file of the format of `info`.
+    pub fn export_wav(&self, path: impl AsRef<path::Path>, info: AudioInfo) -> Result<()> {
+        let mut encoder = LgWavEncoder::new(path, info)?;
+        LgPipeline::from_decoder(LgSfxrDecoder::new(self, info)?).run(&mut encoder)?;
+
+        encoder.finish()
+    }
+
+    pub fn write<W: io::Write + io::Seek>(&self, writer: &mut W) -> Result<()> {
+        writer.write_le_i32(SFS_VERSION)?;
+        writer.write_le_i32(self.wave as i32)?;
+        writer.write_le_f32(self.volume)?;
+
+        for (i, value) in self.values().into_iter().enumerate() {
+            if i == FILTER_ON_INDEX {
+                writer.write_u8(self.filter_on as u8)?;
+            }
+            writer.write_le_f32(value)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: io::Read>(reader: &mut R) -> Result<Self> {
+        let version = reader.read_le_i32()?;
+        if version != SFS_VERSION {
+            return Err(Error::Custom(format!(
+                "sfxr version {version} is not supported!"
+            )));
+        }
+
+        let wave = reader.read_le_i32()?;
+        let mut result = Self {
+            wave: *usize::try_from(wave)
+                .ok()
+                .and_then(|wave| SfxrWave::ALL.get(wave))
+                .ok_or(Error::WrongFmt)?,
+            volume: reader.read_le_f32()?,
+            ..Default::default()
+        };
+
+        let mut values = [0.0; VALUES];
+        for (i, value) in values.iter_mut().enumerate() {
+            if i == FILTER_ON_INDEX {
+                result.filter_on = reader.read_u8()? != 0;
+            }
+            *value = reader.read_le_f32()?;
+        }
+        result.set_values(values);
+
+        Ok(result)
+    }
+
+    pub fn save(&self, path: impl AsRef<path::Path>) -> Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        self.write(&mut writer)?;
+        io::Write::flush(&mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<path::Path>) -> Result<Self> {
+        Self::read(&mut io::BufReader::new(fs::File::open(path)?))
+    }
+}
+
+/// `f32` parameters after the volume, in the order of the files.
+const VALUES: usize = 23;
+/// `filter_on` is saved before this one.
+const FILTER_ON_INDEX: usize = 13;
+
+impl SfxrParams {
+    fn values(&self) -> [f32; VALUES] {
+        [
+            self.base_freq,
+            self.freq_limit,
+            self.freq_ramp,
+            self.freq_dramp,
+            self.duty,
+            self.duty_ramp,
+            self.vib_strength,
+            self.vib_speed,
+            self.vib_delay,
+            self.env_attack,
+            self.env_sustain,
+            self.env_decay,
+            self.env_punch,
+            self.lpf_resonance,
+            self.lpf_freq,
+            self.lpf_ramp,
+            self.hpf_freq,
+            self.hpf_ramp,
+            self.pha_offset,
+            self.pha_ramp,
+            self.repeat_speed,
+            self.arp_speed,
+            self.arp_mod,
+        ]
+    }
+
+    fn set_values(&mut self, values: [f32; VALUES]) {
+        [
+            self.base_freq,
+            self.freq_limit,
+            self.freq_ramp,
+            self.freq_dramp,
+            self.duty,
+            self.duty_ramp,
+            self.vib_strength,
+            self.vib_speed,
+            self.vib_delay,
+            self.env_attack,
+            self.env_sustain,
+            self.env_decay,
+            self.env_punch,
+            self.lpf_resonance,
+            self.lpf_freq,
+            self.lpf_ramp,
+            self.hpf_freq,
+            self.hpf_ramp,
+            self.pha_offset,
+            self.pha_ramp,
+            self.repeat_speed,
+            self.arp_speed,
+            self.arp_mod,
+        ] = values;
+    }
+}
+
+/// Renders an sfxr sound, converted to the format of its `info`, every channel playing it.
+#[derive(Debug, Clone)]
+pub struct LgSfxrDecoder {
+    info: AudioInfo,
+    samples: Vec<f32>,
+    cursor: usize,
+}
+impl LgSfxrDecoder {
+    /// The whole sound is rendered here, they are short.
+    pub fn new(params: &SfxrParams, info: AudioInfo) -> Result<Self> {
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "sfxr needs a sample_rate and channels > 0!".to_string(),
+            ));
+        }
+
+        let mut buffer = params.render();
+        if info.sample_rate != SFXR_SAMPLE_RATE {
+            buffer = resample(&buffer, info.sample_rate, ResampleQuality::HIGH);
+        }
+
+        Ok(Self {
+            info,
+            samples: buffer.samples,
+            cursor: 0,
+        })
+    }
+}
+impl LgDecoder for LgSfxrDecoder {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn samples<S: Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+        let channels = self.info.channels as usize;
+
+        std::iter::from_fn(move || {
+            let sample = *self.samples.get(self.cursor / channels)?;
+            self.cursor += 1;
+
+            Some(S::from_f32(sample, sample_type, bits_per_sample))
+        })
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.samples.len() / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.samples.len() * self.info.channels as usize
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// `rnd` and `frnd` of sfxr.
+struct SfxrRandom(Random);
+impl SfxrRandom {
+    /// In `0..=max`.
+    fn rnd(&mut self, max: usize) -> usize {
+        (self.0.next_u64() % (max as u64 + 1)) as usize
+    }
+
+    /// In `0.0..range`.
+    fn frnd(&mut self, range: f32) -> f32 {
+        self.0.next_f32() * range
+    }
+
+    #[inline(always)]
+    fn chance(&mut self) -> bool {
+        self.rnd(1) == 1
+    }
+}
+
+/// State of the synth of sfxr, one sample at a time, 8 times supersampled.
+struct Synth<'a> {
+    params: &'a SfxrParams,
+    random: Random,
+    playing: bool,
+
+    phase: usize,
+    period: f64,
+    max_period: f64,
+    slide: f64,
+    delta_slide: f64,
+    square_duty: f32,
+    square_slide: f32,
+    arp_mod: f64,
+    arp_time: usize,
+    arp_limit: usize,
+
+    /// Low-pass position, speed, cutoff and its ramp, damping, then the high-pass.
+    lpf: (f32, f32, f32, f32, f32),
+    hpf: (f32, f32, f32),
+    vib_phase: f32,
+    vib_speed: f32,
+    vib_amp: f32,
+
+    env_volume: f32,
+    env_stage: usize,
+    env_time: usize,
+    env_length: [usize; 3],
+
+    phaser: [f32; 1024],
+    phaser_phase: f32,
+    phaser_delta: f32,
+    phaser_position: usize,
+    noise: [f32; 32],
+    repeat_time: usize,
+    repeat_limit: usize,
+}
+impl<'a> Synth<'a> {
+    fn new(params: &'a SfxrParams) -> Self {
+        let p = params;
+        let lpf_cutoff = p.lpf_freq.powi(3) * 0.1;
+
+        let mut result = Self {
+            params,
+            random: Random::new(0),
+            playing: true,
+            phase: 0,
+            period: 0.0,
+            max_period: 0.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            square_duty: 0.0,
+            square_slide: 0.0,
+            arp_mod: 0.0,
+            arp_time: 0,
+            arp_limit: 0,
+            lpf: (
+                0.0,
+                0.0,
+                lpf_cutoff,
+                1.0 + p.lpf_ramp * 0.0001,
+                (5.0 / (1.0 + p.lpf_resonance.powi(2) * 20.0) * (0.01 + lpf_cutoff)).min(0.8),
+            ),
+            hpf: (0.0, p.hpf_freq.powi(2) * 0.1, 1.0 + p.hpf_ramp * 0.0003),
+            vib_phase: 0.0,
+            vib_speed: p.vib_speed.powi(2) * 0.01,
+            vib_amp: p.vib_strength * 0.5,
+            env_volume: 0.0,
+            env_stage: 0,
+            env_time: 0,
+            env_length: [p.env_attack, p.env_sustain, p.env_decay]
+                .map(|length| (length * length * 100_000.0) as usize),
+            phaser: [0.0; 1024],
+            phaser_phase: p.pha_offset.powi(2) * 1020.0 * p.pha_offset.signum(),
+            phaser_delta: p.pha_ramp.powi(2) * p.pha_ramp.signum(),
+            phaser_position: 0,
+            noise: [0.0; 32],
+            repeat_time: 0,
+            repeat_limit: match p.repeat_speed {
+                0.0 => 0,
+                speed => ((1.0 - speed).powi(2) * 20_000.0 + 32.0) as usize,
+            },
+        };
+        result.restart();
+        result.refill_noise();
+
+        result
+    }
+
+    /// Back to the start of the frequency, done again on every repeat.
+    fn restart(&mut self) {
+        let p = self.params;
+
+        self.period = 100.0 / (p.base_freq as f64 * p.base_freq as f64 + 0.001);
+        self.max_period = 100.0 / (p.freq_limit as f64 * p.freq_limit as f64 + 0.001);
+        self.slide = 1.0 - (p.freq_ramp as f64).powi(3) * 0.01;
+        self.delta_slide = -(p.freq_dramp as f64).powi(3) * 0.000_001;
+        self.square_duty = 0.5 - p.duty * 0.5;
+        self.square_slide = -p.duty_ramp * 0.000_05;
+        self.arp_mod = if p.arp_mod >= 0.0 {
+            1.0 - (p.arp_mod as f64).powi(2) * 0.9
+        } else {
+            1.0 + (p.arp_mod as f64).powi(2) * 10.0
+        };
+        self.arp_time = 0;
+        self.arp_limit = match p.arp_speed {
+            1.0 => 0,
+            speed => ((1.0 - speed).powi(2) * 20_000.0 + 32.0) as usize,
+        };
+    }
+
+    fn refill_noise(&mut self) {
+        for noise in &mut self.noise {
+            *noise = self.random.next_f32() * 2.0 - 1.0;
+        }
+    }
+
+    fn next_sample(&mut self) -> Option<f32> {
+        if !self.playing {
+            return None;
+        }
+        let p = self.params;
+
+        self.repeat_time += 1;
+        if self.repeat_limit != 0 && self.repeat_time >= self.repeat_limit {
+            self.repeat_time = 0;
+            self.restart();
+        }
+
+        // Frequency slide and arpeggio.
+        self.arp_time += 1;
+        if self.arp_limit != 0 && self.arp_time >= self.arp_limit {
+            self.arp_limit = 0;
+            self.period *= self.arp_mod;
+        }
+        self.slide += self.delta_slide;
+        self.period *= self.slide;
+        if self.period > self.max_period {
+            self.period = self.max_period;
+            if p.freq_limit > 0.0 {
+                self.playing = false;
+            }
+        }
+        let mut period = self.period;
+        if self.vib_amp > 0.0 {
+            self.vib_phase += self.vib_speed;
+            period *= 1.0 + (self.vib_phase.sin() * self.vib_amp) as f64;
+        }
+        let period = (period as usize).max(8);
+        self.square_duty = (self.square_duty + self.square_slide).clamp(0.0, 0.5);
+
+        // Volume envelope.
+        self.env_time += 1;
+        if self.env_time > self.env_length[self.env_stage] {
+            self.env_time = 0;
+            self.env_stage += 1;
+            if self.env_stage == 3 {
+                self.playing = false;
+                return None;
+            }
+        }
+        let progress = |stage: usize| self.env_time as f32 / self.env_length[stage].max(1) as f32;
+        self.env_volume = match self.env_stage {
+            0 => progress(0),
+            1 => 1.0 + (1.0 - progress(1)) * 2.0 * p.env_punch,
+            _ => 1.0 - progress(2),
+        };
+
+        self.phaser_phase += self.phaser_delta;
+        let phaser_offset = (self.phaser_phase.abs() as usize).min(1023);
+
+        if self.hpf.2 != 0.0 {
+            self.hpf.1 = (self.hpf.1 * self.hpf.2).clamp(0.000_01, 0.1);
+        }
+
+        let mut sum = 0.0;
+        for _ in 0..8 {
+            self.phase += 1;
+            if self.phase >= period {
+                self.phase %= period;
+                if p.wave == SfxrWave::Noise {
+                    self.refill_noise();
+                }
+            }
+
+            let position = self.phase as f32 / period as f32;
+            let mut sample = match p.wave {
+                SfxrWave::Square if position < self.square_duty => 0.5,
+                SfxrWave::Square => -0.5,
+                SfxrWave::Saw => 1.0 - position * 2.0,
+                SfxrWave::Sine => (position * TAU).sin(),
+                SfxrWave::Noise => self.noise[self.phase * 32 / period],
+            };
+
+            // Low-pass, resonant.
+            let (position, speed, cutoff, ramp, damping) = &mut self.lpf;
+            let previous = *position;
+            *cutoff = (*cutoff * *ramp).clamp(0.0, 0.1);
+            if p.lpf_freq != 1.0 {
+                *speed += (sample - *position) * *cutoff;
+                *speed -= *speed * *damping;
+            } else {
+                *position = sample;
+                *speed = 0.0;
+            }
+            *position += *speed;
+
+            // High-pass.
+            let (high, cutoff, _) = &mut self.hpf;
+            *high += *position - previous;
+            *high -= *high * *cutoff;
+            sample = *high;
+
+            // Phaser.
+            self.phaser[self.phaser_position & 1023] = sample;
+            sample += self.phaser[(self.phaser_position + 1024 - phaser_offset) & 1023];
+            self.phaser_position = (self.phaser_position + 1) & 1023;
+
+            sum += sample * self.env_volume;
+        }
+
+        let sample = sum / 8.0 * MASTER_VOLUME * 2.0 * p.volume;
+        Some(sample.clamp(-1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESETS: [SfxrPreset; 7] = [
+        SfxrPreset::Pickup,
+        SfxrPreset::Laser,
+        SfxrPreset::Explosion,
+        SfxrPreset::Powerup,
+        SfxrPreset::Hit,
+        SfxrPreset::Jump,
+        SfxrPreset::Blip,
+    ];
+
+    #[test]
+    fn presets_round_trip() {
+        for preset in PRESETS {
+            for seed in 0..8 {
+                let mut params = SfxrParams::preset(preset, seed);
+                params.filter_on = seed % 2 == 0;
+
+                let mut bytes = io::Cursor::new(Vec::new());
+                params.write(&mut bytes).unwrap();
+                let bytes = bytes.into_inner();
+
+                // The size of the files of sfxr.
+                assert_eq!(bytes.len(), 105);
+                assert_eq!(bytes[..4], SFS_VERSION.to_le_bytes());
+                assert_eq!(bytes[4..8], (params.wave as i32).to_le_bytes());
+                assert_eq!(SfxrParams::read(&mut bytes.as_slice()).unwrap(), params);
+            }
+        }
+    }
+
+    #[test]
+    fn presets_are_seeded() {
+        for preset in PRESETS {
+            assert_eq!(SfxrParams::preset(preset, 3), SfxrParams::preset(preset, 3));
+
+            let rendered = SfxrParams::preset(preset, 3).render();
+            assert!(rendered.frames() > 0);
+            assert!(rendered.samples.iter().all(|x| (-1.0..=1.0).contains(x)));
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = io::Cursor::new(Vec::new());
+        SfxrParams::default().write(&mut bytes).unwrap();
+        let bytes = bytes.into_inner();
+
+        let mut version = bytes.clone();
+        version[..4].copy_from_slice(&100i32.to_le_bytes());
+        assert!(SfxrParams::read(&mut version.as_slice()).is_err());
+
+        let mut wave = bytes.clone();
+        wave[4..8].copy_from_slice(&4i32.to_le_bytes());
+        assert!(matches!(
+            SfxrParams::read(&mut wave.as_slice()),
+            Err(Error::WrongFmt)
+        ));
+
+        assert!(SfxrParams::read(&mut &bytes[..100]).is_err());
+    }
+}
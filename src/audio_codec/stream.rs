@@ -0,0 +1,282 @@
+//! Moving samples between threads, to decode away from the audio callback.
+//!
+//! ```ignore
+//! let mut music = LgStreamingDecoder::new(LgWavDecoder::new("music.wav")?, 48_000)?;
+//! // In the audio callback, never blocks:
+//! music.read(output);
+//! if music.underruns() > 0 { /* The worker can't keep up, prefetch more. */ }
+//! ```
+
+use super::{AudioInfo, Result, decoder::LgDecoder, error::Error};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+/// Frames decoded at a time by the worker.
+const STREAM_CHUNK_FRAMES: usize = 1024;
+/// Longest the worker sleeps while the ring is full.
+const MAX_WAIT: Duration = Duration::from_millis(5);
+/// Shortest the worker sleeps while the ring is full, so a tiny prefetch doesn't spin.
+const MIN_WAIT: Duration = Duration::from_micros(100);
+
+struct Ring {
+    /// Bits of the samples, atomics so each side reads and writes without locks.
+    samples: Box<[AtomicU32]>,
+    /// Samples written and read since the start, only the producer and consumer change them.
+    written: AtomicUsize,
+    read: AtomicUsize,
+}
+impl Ring {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.written
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.read.load(Ordering::Acquire))
+    }
+}
+
+/// Wait-free ring of samples between two threads, split into its producer and consumer.
+/// `capacity` is in samples.
+pub fn ring_buffer(capacity: usize) -> (RingProducer, RingConsumer) {
+    let ring = Arc::new(Ring {
+        samples: (0..capacity.max(1)).map(|_| AtomicU32::new(0)).collect(),
+        written: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+    });
+
+    (
+        RingProducer {
+            ring: Arc::clone(&ring),
+        },
+        RingConsumer { ring },
+    )
+}
+
+/// Writing side of a [`ring_buffer`].
+pub struct RingProducer {
+    ring: Arc<Ring>,
+}
+impl RingProducer {
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.ring.samples.len()
+    }
+
+    /// Samples that can be pushed.
+    #[inline(always)]
+    pub fn free(&self) -> usize {
+        self.capacity() - self.ring.len()
+    }
+
+    /// Pushes as many `samples` as there is room for, returning how many.
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        let capacity = self.capacity();
+        let written = self.ring.written.load(Ordering::Relaxed);
+        let read = self.ring.read.load(Ordering::Acquire);
+        let pushed = samples.len().min(capacity - written.wrapping_sub(read));
+
+        for (i, sample) in samples[..pushed].iter().enumerate() {
+            self.ring.samples[written.wrapping_add(i) % capacity]
+                .store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.ring
+            .written
+            .store(written.wrapping_add(pushed), Ordering::Release);
+
+        pushed
+    }
+
+    /// True once the consumer was dropped.
+    #[inline(always)]
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.ring) == 1
+    }
+}
+
+/// Reading side of a [`ring_buffer`].
+pub struct RingConsumer {
+    ring: Arc<Ring>,
+}
+impl RingConsumer {
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.ring.samples.len()
+    }
+
+    /// Samples that can be popped.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pops as many samples as are ready into the `output`, returning how many.
+    pub fn pop(&mut self, output: &mut [f32]) -> usize {
+        let capacity = self.capacity();
+        let read = self.ring.read.load(Ordering::Relaxed);
+        let written = self.ring.written.load(Ordering::Acquire);
+        let popped = output.len().min(written.wrapping_sub(read));
+
+        for (i, sample) in output[..popped].iter_mut().enumerate() {
+            *sample = f32::from_bits(
+                self.ring.samples[read.wrapping_add(i) % capacity].load(Ordering::Relaxed),
+            );
+        }
+        self.ring
+            .read
+            .store(read.wrapping_add(popped), Ordering::Release);
+
+        popped
+    }
+
+    /// True once the producer was dropped, what is left can still be popped.
+    #[inline(always)]
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.ring) == 1
+    }
+}
+
+/// Shared between the worker and the reader.
+#[derive(Default)]
+struct StreamState {
+    stop: AtomicBool,
+    decoded: AtomicBool,
+}
+
+/// Decodes on a worker thread, prefetching into a [`ring_buffer`] the audio callback reads from.
+/// Reading never allocates, locks or waits, missing samples are silence and counted as underruns.
+pub struct LgStreamingDecoder {
+    info: AudioInfo,
+    consumer: RingConsumer,
+    state: Arc<StreamState>,
+
+    underruns: usize,
+    silent_frames: usize,
+}
+impl LgStreamingDecoder {
+    /// Keeps up to `prefetch_frames` frames decoded ahead of the reads.
+    pub fn new(
+        mut decoder: impl LgDecoder + Send + 'static,
+        prefetch_frames: usize,
+    ) -> Result<Self> {
+        let info = decoder.info();
+        if info.channels == 0 || info.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Can't stream a sound without a sample_rate or channels!".to_string(),
+            ));
+        }
+
+        let channels = info.channels as usize;
+        let (mut producer, consumer) = ring_buffer(prefetch_frames.max(1) * channels);
+        let state = Arc::new(StreamState::default());
+
+        // Sleeping a quarter of the prefetch when full leaves time to refill it.
+        let wait = Duration::from_secs_f64(prefetch_frames as f64 / info.sample_rate as f64 / 4.0)
+            .clamp(MIN_WAIT, MAX_WAIT);
+
+        let worker_state = Arc::clone(&state);
+        std::thread::Builder::new()
+            .name("lg_streaming_decoder".to_string())
+            .spawn(move || {
+                let mut chunk = Vec::with_capacity(STREAM_CHUNK_FRAMES * channels);
+                let mut pushed = 0;
+
+                while !worker_state.stop.load(Ordering::Relaxed) {
+                    if pushed == chunk.len() {
+                        chunk.clear();
+                        chunk.extend(
+                            decoder
+                                .samples::<f32>()
+                                .take(STREAM_CHUNK_FRAMES * channels),
+                        );
+                        chunk.truncate(chunk.len() - chunk.len() % channels);
+                        pushed = 0;
+
+                        if chunk.is_empty() {
+                            break;
+                        }
+                    }
+
+                    pushed += producer.push(&chunk[pushed..]);
+                    if pushed < chunk.len() {
+                        std::thread::sleep(wait);
+                    }
+                }
+
+                worker_state.decoded.store(true, Ordering::Release);
+            })?;
+
+        Ok(Self {
+            info,
+            consumer,
+            state,
+            underruns: 0,
+            silent_frames: 0,
+        })
+    }
+
+    /// Format of the decoder, read as normalized `f32` samples.
+    #[inline(always)]
+    pub fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    /// Fills the interleaved `output`, with silence after what is ready, returning the frames
+    /// read. Silence before the end of the decoder is an underrun.
+    pub fn read(&mut self, output: &mut [f32]) -> usize {
+        let channels = self.info.channels as usize;
+        let frames = output.len() / channels;
+
+        // Checked before popping, so samples pushed right before it ended aren't left behind.
+        let decoded = self.state.decoded.load(Ordering::Acquire);
+        let ready = (self.consumer.len() / channels).min(frames);
+        self.consumer.pop(&mut output[..ready * channels]);
+        output[ready * channels..].fill(0.0);
+
+        if ready < frames && !(decoded && self.consumer.is_empty()) {
+            self.underruns += 1;
+            self.silent_frames += frames - ready;
+        }
+
+        ready
+    }
+
+    /// Frames ready to be read.
+    #[inline(always)]
+    pub fn buffered(&self) -> usize {
+        self.consumer.len() / self.info.channels as usize
+    }
+
+    /// True once the decoder ended and everything was read.
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.state.decoded.load(Ordering::Acquire) && self.consumer.is_empty()
+    }
+
+    /// Reads that were missing samples.
+    #[inline(always)]
+    pub fn underruns(&self) -> usize {
+        self.underruns
+    }
+
+    /// Frames of silence given by the underruns.
+    #[inline(always)]
+    pub fn silent_frames(&self) -> usize {
+        self.silent_frames
+    }
+}
+/// Only tells the worker to stop, it is detached so dropping never waits on the audio thread.
+/// It ends once the decoding or sleeping it is doing finishes.
+impl Drop for LgStreamingDecoder {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,142 @@
+use super::super::{
+    AudioInfo, Result,
+    decoder::LgDecoder,
+    error::Error,
+    sample::{Sample, SampleType},
+};
+use super::{TrackerModule, player::Player};
+use std::{path, sync::Arc};
+
+/// How samples are read between their frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Sharp, like the hardware of the Amiga.
+    Nearest,
+    Linear,
+    #[default]
+    Cubic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackerParams {
+    pub sample_rate: u32,
+    pub interpolation: Interpolation,
+    /// 0 plays every channel in the middle, 1 as far as they are panned.
+    pub stereo_separation: f32,
+    /// Times the song plays again when it loops, it plays once with 0.
+    pub repeats: usize,
+    pub volume: f32,
+}
+impl Default for TrackerParams {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44_100,
+            interpolation: Interpolation::Cubic,
+            stereo_separation: 1.0,
+            repeats: 0,
+            volume: 1.0,
+        }
+    }
+}
+
+/// Plays a tracker module to stereo, converted to the format of its `info`.
+#[derive(Debug, Clone)]
+pub struct LgTrackerDecoder {
+    info: AudioInfo,
+    module: Arc<TrackerModule>,
+    player: Player,
+    frames: usize,
+
+    /// Frames of the last tick.
+    block: Vec<f32>,
+    cursor: usize,
+}
+impl LgTrackerDecoder {
+    pub fn new(path: impl AsRef<path::Path>, params: TrackerParams) -> Result<Self> {
+        Self::from_module(TrackerModule::load(path)?, params)
+    }
+
+    /// The song is played once without mixing, so [`LgDecoder::len`] is known.
+    pub fn from_module(module: TrackerModule, params: TrackerParams) -> Result<Self> {
+        if params.sample_rate == 0 {
+            return Err(Error::WrongFmtInfo(
+                "Modules need a sample_rate > 0 to play!".to_string(),
+            ));
+        }
+
+        let module = Arc::new(module);
+        let player = Player::new(Arc::clone(&module), &params);
+
+        let mut counter = player.clone();
+        let mut frames = 0;
+        while !counter.is_ended() {
+            frames += counter.tick(None);
+        }
+
+        Ok(Self {
+            info: AudioInfo {
+                channels: 2,
+                sample_rate: params.sample_rate,
+                bits_per_sample: 32,
+                sample_type: Some(SampleType::FLOAT),
+            },
+            module,
+            player,
+            frames,
+            block: Vec::new(),
+            cursor: 0,
+        })
+    }
+
+    #[inline(always)]
+    pub fn module(&self) -> &TrackerModule {
+        &self.module
+    }
+
+    /// Frames in total.
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+impl LgDecoder for LgTrackerDecoder {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    fn samples<S: Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+        let bits_per_sample = self.info.bits_per_sample;
+
+        std::iter::from_fn(move || {
+            while self.cursor == self.block.len() {
+                self.block.clear();
+                self.cursor = 0;
+                if self.player.tick(Some(&mut self.block)) == 0 {
+                    return None;
+                }
+            }
+
+            let sample = self.block[self.cursor];
+            self.cursor += 1;
+
+            Some(S::from_f32(sample, sample_type, bits_per_sample))
+        })
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.frames / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.frames * self.info.channels as usize
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+}
@@ -0,0 +1,386 @@
+//! Tracker modules, ProTracker MOD, Scream Tracker 3 S3M and FastTracker 2 XM, played to PCM.
+//!
+//! The formats are read into the same [`TrackerModule`], with their effects translated to
+//! [`Effect`]. The few places where the trackers differ are decided by [`TrackerModule::format`].
+//!
+//! ```ignore
+//! let decoder = LgTrackerDecoder::new("song.xm", TrackerParams::default())?;
+//! let info = decoder.info();
+//!
+//! LgPipeline::from_decoder(decoder).run(&mut LgWavEncoder::new("song.wav", info)?)?;
+//! ```
+
+use super::{Result, error::Error};
+use std::{fs, io, path};
+
+pub mod decoder;
+
+mod player;
+mod protracker;
+mod s3m;
+mod xm;
+
+#[cfg(test)]
+mod tests;
+
+pub use decoder::{Interpolation, LgTrackerDecoder, TrackerParams};
+
+// ------------------------- LAYOUT --------------------------
+/// Note played at the rate of the samples, C-4 in S3M and XM, C-2 in ProTracker.
+const BASE_NOTE: u8 = 48;
+/// Notes go from C-0 to B-9.
+const NOTES: usize = 120;
+/// Rate of the samples when they don't say, for the base note.
+const BASE_RATE: f64 = 8363.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackerFormat {
+    Mod,
+    S3m,
+    Xm,
+}
+
+/// A whole song, in any of the formats.
+#[derive(Debug, Clone)]
+pub struct TrackerModule {
+    pub format: TrackerFormat,
+    pub title: String,
+    pub channels: usize,
+    /// Patterns to play, in order.
+    pub order: Vec<usize>,
+    /// Position of the `order` the song goes back to after the end.
+    pub restart: usize,
+    pub patterns: Vec<Pattern>,
+    pub instruments: Vec<Instrument>,
+    pub samples: Vec<TrackerSample>,
+
+    /// Ticks per row.
+    pub speed: u8,
+    /// Beats per minute, a tick lasts 2.5 / `tempo` seconds.
+    pub tempo: u8,
+    /// `0..=64`.
+    pub global_volume: u8,
+    /// Linear periods of FastTracker 2, Amiga periods otherwise.
+    pub linear_periods: bool,
+    /// Of every channel at the start, `0..=255` from left to right.
+    pub panning: Vec<u8>,
+}
+impl TrackerModule {
+    /// Reads a MOD, S3M or XM file, told apart by their content.
+    pub fn load(path: impl AsRef<path::Path>) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_reader(mut reader: impl io::Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let result = if xm::is_xm(bytes) {
+            xm::read(bytes)?
+        } else if s3m::is_s3m(bytes) {
+            s3m::read(bytes)?
+        } else {
+            protracker::read(bytes)?
+        };
+
+        if result.order.is_empty() || result.channels == 0 {
+            return Err(Error::Custom("The module has nothing to play!".to_string()));
+        }
+
+        Ok(result)
+    }
+
+    /// The pattern at `position` of the order, if it exists.
+    #[inline(always)]
+    pub fn pattern_at(&self, position: usize) -> Option<&Pattern> {
+        self.patterns.get(*self.order.get(position)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub rows: usize,
+    /// `rows * channels` cells, row after row.
+    pub cells: Vec<Cell>,
+}
+impl Pattern {
+    /// Empty rows.
+    pub fn new(rows: usize, channels: usize) -> Self {
+        Self {
+            rows,
+            cells: vec![Cell::default(); rows * channels],
+        }
+    }
+
+    #[inline(always)]
+    pub fn row(&self, row: usize, channels: usize) -> &[Cell] {
+        &self.cells[row * channels..(row + 1) * channels]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub note: Note,
+    /// Starts at 1, 0 keeps the instrument of the channel.
+    pub instrument: u8,
+    pub volume: VolumeCommand,
+    pub effect: Effect,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Note {
+    #[default]
+    None,
+    /// Semitones from C-0.
+    On(u8),
+    /// Releases the instrument, which fades out.
+    Off,
+    /// Stops the sound at once.
+    Cut,
+}
+
+/// Volume column of S3M and XM, volumes in `0..=64`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeCommand {
+    #[default]
+    None,
+    Set(u8),
+    SlideDown(u8),
+    SlideUp(u8),
+    FineSlideDown(u8),
+    FineSlideUp(u8),
+    VibratoSpeed(u8),
+    Vibrato(u8),
+    /// `0..=255`.
+    SetPanning(u8),
+    PanningSlideLeft(u8),
+    PanningSlideRight(u8),
+    TonePortamento(u8),
+}
+
+/// Effects of the formats, parameters as in the files. A parameter of 0 reuses the last one
+/// for the effects that remember it in the format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    #[default]
+    None,
+    /// Semitones of the second and third ticks, in the high and low nibbles.
+    Arpeggio(u8),
+    PortamentoUp(u8),
+    PortamentoDown(u8),
+    FinePortamentoUp(u8),
+    FinePortamentoDown(u8),
+    ExtraFinePortamentoUp(u8),
+    ExtraFinePortamentoDown(u8),
+    TonePortamento(u8),
+    /// Speed and depth in the high and low nibbles, like the other oscillators.
+    Vibrato(u8),
+    FineVibrato(u8),
+    TonePortamentoVolumeSlide(u8),
+    VibratoVolumeSlide(u8),
+    Tremolo(u8),
+    /// Ticks on and off, plus one.
+    Tremor(u8),
+    /// `0..=255`.
+    SetPanning(u8),
+    PanningSlide(u8),
+    /// In steps of 256 frames.
+    SampleOffset(u8),
+    /// Up in the high nibble, down in the low one.
+    VolumeSlide(u8),
+    FineVolumeSlideUp(u8),
+    FineVolumeSlideDown(u8),
+    PositionJump(u8),
+    SetVolume(u8),
+    /// Row of the next pattern.
+    PatternBreak(u8),
+    SetSpeed(u8),
+    SetTempo(u8),
+    SetGlobalVolume(u8),
+    GlobalVolumeSlide(u8),
+    /// Marks the start of the loop with 0, plays it that many more times otherwise.
+    PatternLoop(u8),
+    /// Rows to play again without their notes.
+    PatternDelay(u8),
+    /// Volume change in the high nibble, ticks between retriggers in the low one.
+    Retrigger(u8),
+    NoteCut(u8),
+    NoteDelay(u8),
+    KeyOff(u8),
+    SetVibratoWaveform(u8),
+    SetTremoloWaveform(u8),
+    SetFinetune(u8),
+    SetEnvelopePosition(u8),
+}
+
+/// What plays for every note, with its envelopes.
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    pub name: String,
+    /// Index in [`TrackerModule::samples`] of every note, if any.
+    pub keymap: [Option<usize>; NOTES],
+    pub volume_envelope: Option<Envelope>,
+    pub panning_envelope: Option<Envelope>,
+    /// Volume lost every tick once released, out of 65536.
+    pub fadeout: u16,
+    pub vibrato: AutoVibrato,
+}
+impl Instrument {
+    /// Plays `sample` for every note, like the instruments of MOD and S3M.
+    pub fn from_sample(name: String, sample: usize) -> Self {
+        Self {
+            name,
+            keymap: [Some(sample); NOTES],
+            volume_envelope: None,
+            panning_envelope: None,
+            fadeout: 0,
+            vibrato: AutoVibrato::default(),
+        }
+    }
+}
+
+/// Values in `0..=64` at ticks from the note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    /// `(tick, value)`, ticks going up.
+    pub points: Vec<(u16, u8)>,
+    /// Point held while the note is on.
+    pub sustain: Option<usize>,
+    /// First and last points of the loop.
+    pub repeat: Option<(usize, usize)>,
+}
+impl Envelope {
+    /// Value at `tick`, linear between the points.
+    pub fn value(&self, tick: u16) -> f32 {
+        let Some(&(first_tick, first)) = self.points.first() else {
+            return 64.0;
+        };
+        if tick <= first_tick {
+            return first as f32;
+        }
+
+        for pair in self.points.windows(2) {
+            let ((start, from), (end, to)) = (pair[0], pair[1]);
+            if tick < end && end > start {
+                let t = (tick - start) as f32 / (end - start) as f32;
+                return from as f32 + (to as f32 - from as f32) * t;
+            }
+        }
+
+        self.points[self.points.len() - 1].1 as f32
+    }
+
+    /// Tick after `tick`, held at the sustain point while `held`.
+    pub fn advance(&self, tick: u16, held: bool) -> u16 {
+        if let Some(sustain) = self.sustain.and_then(|point| self.points.get(point))
+            && held
+            && tick == sustain.0
+        {
+            return tick;
+        }
+
+        let next = tick.saturating_add(1);
+        match self.repeat {
+            Some((start, end)) if end < self.points.len() && next >= self.points[end].0 => {
+                // Set past the loop, it doesn't go back.
+                if tick > self.points[end].0 {
+                    next
+                } else {
+                    self.points[start.min(end)].0
+                }
+            }
+            _ => next,
+        }
+    }
+}
+
+/// Vibrato of an instrument, on every note.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AutoVibrato {
+    pub waveform: Waveform,
+    /// Ticks to reach the full depth.
+    pub sweep: u8,
+    pub depth: u8,
+    /// Added to the position, out of 256 per period, every tick.
+    pub rate: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    RampDown,
+    RampUp,
+    Square,
+    Random,
+}
+impl Waveform {
+    /// From the low 2 bits of the effects and instruments.
+    #[inline(always)]
+    pub fn from_bits(bits: u8) -> Self {
+        [Self::Sine, Self::RampDown, Self::Square, Self::Random][bits as usize & 3]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SampleLoop {
+    #[default]
+    Off,
+    Forward,
+    PingPong,
+}
+
+/// Mono sound of an instrument.
+#[derive(Debug, Clone)]
+pub struct TrackerSample {
+    pub name: String,
+    /// Normalized to `-1.0..=1.0`.
+    pub data: Vec<f32>,
+    pub looping: SampleLoop,
+    /// In frames, the end excluded.
+    pub loop_start: usize,
+    pub loop_end: usize,
+    /// `0..=64`.
+    pub volume: u8,
+    /// `0..=255`, the panning of the channel if none.
+    pub panning: Option<u8>,
+    /// Frames per second of the base note, with the finetune.
+    pub rate: f64,
+    /// Semitones added to the notes.
+    pub relative_note: i8,
+}
+impl TrackerSample {
+    /// Makes the loop fit the data, turning it off if empty.
+    fn fit_loop(mut self) -> Self {
+        self.loop_end = self.loop_end.min(self.data.len());
+        self.loop_start = self.loop_start.min(self.loop_end);
+        if self.loop_end - self.loop_start < 2 {
+            self.looping = SampleLoop::Off;
+        }
+
+        self
+    }
+}
+
+/// Text of a fixed size field, up to its first 0.
+fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end])
+        .trim_end()
+        .to_string()
+}
+
+/// Rate of a sample tuned by `finetune` in 1/128 of a semitone, from the base rate.
+#[inline(always)]
+fn finetuned_rate(finetune: f64) -> f64 {
+    BASE_RATE * 2f64.powf(finetune / (128.0 * 12.0))
+}
+
+/// The file is shorter than its headers say.
+fn truncated(what: &str) -> Error {
+    Error::Custom(format!("The module ends in its {what}!"))
+}
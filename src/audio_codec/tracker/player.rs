@@ -0,0 +1,1010 @@
+use super::{
+    BASE_NOTE, BASE_RATE, Cell, Effect, Instrument, Interpolation, NOTES, Note, SampleLoop,
+    TrackerFormat, TrackerModule, TrackerParams, TrackerSample, VolumeCommand, Waveform,
+    finetuned_rate,
+};
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    sync::Arc,
+};
+
+/// Quarters of an Amiga period times the frequency, 1712 at the base rate.
+const AMIGA_CLOCK: f64 = 1712.0 * BASE_RATE;
+/// Linear period of the base note at the base rate, 64 per semitone.
+const LINEAR_BASE_PERIOD: f64 = 4608.0;
+/// Limits of ProTracker, B-3 and C-1, in quarters.
+const MOD_PERIODS: (f64, f64) = (113.0 * 4.0, 856.0 * 4.0);
+/// Rows of the missing patterns.
+const EMPTY_ROWS: usize = 64;
+/// Gains reach the ones of a new tick over this time, against clicks.
+const RAMP_SECONDS: f64 = 0.002;
+/// Modules that never end stop there.
+const MAX_SECONDS: f64 = 3.0 * 3600.0;
+/// Rates of Scream Tracker 3 for the finetunes, 8 being none.
+const S3M_FINETUNES: [f64; 16] = [
+    7895.0, 7941.0, 7985.0, 8046.0, 8107.0, 8169.0, 8232.0, 8280.0, 8363.0, 8413.0, 8463.0, 8529.0,
+    8581.0, 8651.0, 8723.0, 8757.0,
+];
+
+/// Plays a module tick after tick, as the trackers did.
+#[derive(Debug, Clone)]
+pub(super) struct Player {
+    module: Arc<TrackerModule>,
+    rate: f64,
+    interpolation: Interpolation,
+    separation: f32,
+    gain: f32,
+
+    speed: usize,
+    tempo: usize,
+    global_volume: i32,
+    position: usize,
+    row: usize,
+    /// Ticks played of the row.
+    tick: usize,
+    /// Times the row plays again, from a pattern delay.
+    row_delay: usize,
+    jump: Option<usize>,
+    break_row: Option<usize>,
+    loop_row: Option<usize>,
+    /// Rows played of every position, to find where the song loops.
+    visited: Vec<Vec<bool>>,
+    repeats: usize,
+    ended: bool,
+    /// Fraction of a frame the ticks carry.
+    remainder: f64,
+    frames: usize,
+
+    channels: Vec<Channel>,
+}
+impl Player {
+    pub(super) fn new(module: Arc<TrackerModule>, params: &TrackerParams) -> Self {
+        let channels = (0..module.channels)
+            .map(|channel| Channel::new(module.panning.get(channel).copied().unwrap_or(128)))
+            .collect();
+        let visited = (0..module.order.len())
+            .map(|position| vec![false; rows_at(&module, position)])
+            .collect();
+
+        Self {
+            rate: params.sample_rate as f64,
+            interpolation: params.interpolation,
+            separation: params.stereo_separation.clamp(0.0, 1.0),
+            // Loud enough for a few channels, without clipping many.
+            gain: params.volume / (module.channels as f32).sqrt().max(2.0),
+            speed: module.speed.max(1) as usize,
+            tempo: module.tempo.max(32) as usize,
+            global_volume: module.global_volume.min(64) as i32,
+            position: 0,
+            row: 0,
+            tick: 0,
+            row_delay: 0,
+            jump: None,
+            break_row: None,
+            loop_row: None,
+            visited,
+            repeats: params.repeats,
+            ended: false,
+            remainder: 0.0,
+            frames: 0,
+            channels,
+            module,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Plays a tick, appending its stereo frames to `output` if any, or only moving through the
+    /// song. Returns the frames of the tick, 0 once the song ended.
+    pub(super) fn tick(&mut self, output: Option<&mut Vec<f32>>) -> usize {
+        if self.ended {
+            return 0;
+        }
+
+        let first = self.tick < self.speed;
+        let tick = self.tick % self.speed;
+        if self.tick == 0 {
+            self.start_row();
+        }
+        for channel in 0..self.channels.len() {
+            if tick != 0 || first {
+                self.update_channel(channel, tick);
+            }
+            self.prepare_channel(channel);
+        }
+
+        let length = self.rate * 2.5 / self.tempo as f64 + self.remainder;
+        let frames = length as usize;
+        self.remainder = length - frames as f64;
+        if let Some(output) = output {
+            self.mix(frames, output);
+        }
+
+        self.frames += frames;
+        if self.frames as f64 > MAX_SECONDS * self.rate {
+            self.ended = true;
+        }
+
+        self.tick += 1;
+        if self.tick >= self.speed * (1 + self.row_delay) {
+            self.next_row();
+        }
+
+        frames
+    }
+}
+impl Player {
+    fn start_row(&mut self) {
+        let module = Arc::clone(&self.module);
+        self.visited[self.position][self.row] = true;
+
+        let cells = module
+            .pattern_at(self.position)
+            .map(|pattern| pattern.row(self.row, module.channels));
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            let cell = cells.map_or(Cell::default(), |cells| cells[i]);
+            channel.cell = Cell {
+                effect: channel.memory.resolve(cell.effect, module.format),
+                ..cell
+            };
+        }
+    }
+
+    fn next_row(&mut self) {
+        self.tick = 0;
+        self.row_delay = 0;
+
+        let (jump, break_row) = (self.jump.take(), self.break_row.take());
+        let (mut position, mut row, moved) = match (self.loop_row.take(), jump, break_row) {
+            (Some(row), ..) => (self.position, row, false),
+            (None, Some(position), row) => (position, row.unwrap_or(0), true),
+            (None, None, Some(row)) => (self.position + 1, row, true),
+            (None, None, None) if self.row + 1 < rows_at(&self.module, self.position) => {
+                (self.position, self.row + 1, false)
+            }
+            (None, None, None) => (self.position + 1, 0, true),
+        };
+        if position >= self.module.order.len() {
+            position = self.module.restart;
+        }
+        if row >= rows_at(&self.module, position) {
+            row = 0;
+        }
+
+        if moved && self.visited[position][row] {
+            if self.repeats == 0 {
+                self.ended = true;
+                return;
+            }
+
+            self.repeats -= 1;
+            self.visited.iter_mut().for_each(|rows| rows.fill(false));
+        }
+        self.position = position;
+        self.row = row;
+    }
+
+    fn update_channel(&mut self, i: usize, tick: usize) {
+        let channel = &mut self.channels[i];
+        channel.period_offset = 0.0;
+        channel.arpeggio = 0;
+        channel.volume_offset = 0;
+        channel.muted = false;
+
+        let delay = match channel.cell.effect {
+            Effect::NoteDelay(delay) => delay as usize,
+            _ => 0,
+        };
+        if tick == delay {
+            self.trigger(i);
+        }
+
+        self.volume_column(i, tick);
+        self.effect(i, tick);
+    }
+
+    /// Note, instrument and what the volume column does when they come.
+    fn trigger(&mut self, i: usize) {
+        let module = Arc::clone(&self.module);
+        let channel = &mut self.channels[i];
+        let cell = channel.cell;
+        let portamento = matches!(
+            cell.effect,
+            Effect::TonePortamento(_) | Effect::TonePortamentoVolumeSlide(_)
+        ) || matches!(cell.volume, VolumeCommand::TonePortamento(_));
+
+        if cell.instrument != 0 {
+            let instrument = cell.instrument as usize - 1;
+            channel.instrument = (instrument < module.instruments.len()).then_some(instrument);
+        }
+        let instrument = channel.instrument.map(|i| &module.instruments[i]);
+
+        match cell.note {
+            Note::On(note) if portamento && channel.playing => {
+                if let Some(sample) = channel.sample.map(|s| &module.samples[s]) {
+                    let note = transpose(note, sample);
+                    channel.target = period(&module, note as f64, channel.rate);
+                }
+            }
+            Note::On(note) => {
+                let index = instrument.and_then(|instrument| instrument.keymap[note as usize]);
+                match index.and_then(|index| Some((index, module.samples.get(index)?))) {
+                    Some((index, sample)) => {
+                        channel.sample = Some(index);
+                        channel.rate = match cell.effect {
+                            Effect::SetFinetune(finetune) => finetune_rate(&module, finetune),
+                            _ => sample.rate,
+                        };
+                        channel.period =
+                            period(&module, transpose(note, sample) as f64, channel.rate);
+                        channel.target = channel.period;
+                        channel.start(sample);
+                    }
+                    // Notes without a sample are silent.
+                    None => channel.playing = false,
+                }
+            }
+            Note::Off => channel.key_off(instrument),
+            Note::Cut => {
+                channel.playing = false;
+                channel.volume = 0;
+            }
+            Note::None => {}
+        }
+
+        // Defaults of the sample playing in FastTracker 2, of the instrument in the others.
+        let defaults = match module.format {
+            TrackerFormat::Xm => channel.sample,
+            _ => instrument.and_then(|instrument| instrument.keymap[BASE_NOTE as usize]),
+        };
+        if cell.instrument != 0
+            && let Some(sample) = defaults.and_then(|s| module.samples.get(s))
+        {
+            channel.volume = sample.volume as i32;
+            if let Some(panning) = sample.panning {
+                channel.panning = panning as i32;
+            }
+            if module.format == TrackerFormat::Xm {
+                channel.restart_envelopes();
+            }
+        }
+
+        if let (Effect::SampleOffset(offset), Note::On(_), false) =
+            (cell.effect, cell.note, portamento)
+            && let Some(sample) = channel.sample.map(|s| &module.samples[s])
+        {
+            channel.position = offset as f64 * 256.0;
+            if channel.position >= sample.data.len() as f64 {
+                match sample.looping {
+                    SampleLoop::Off => channel.playing = false,
+                    _ => channel.position = sample.loop_start as f64,
+                }
+            }
+        }
+
+        match cell.volume {
+            VolumeCommand::Set(volume) => channel.volume = volume.min(64) as i32,
+            VolumeCommand::FineSlideDown(amount) => channel.slide_volume(-(amount as i32)),
+            VolumeCommand::FineSlideUp(amount) => channel.slide_volume(amount as i32),
+            VolumeCommand::VibratoSpeed(speed) if speed != 0 => channel.vibrato.speed = speed,
+            VolumeCommand::Vibrato(depth) if depth != 0 => channel.vibrato.depth = depth,
+            VolumeCommand::SetPanning(panning) => channel.panning = panning as i32,
+            VolumeCommand::TonePortamento(speed) if speed != 0 => {
+                channel.memory.tone_portamento = speed;
+            }
+            _ => {}
+        }
+    }
+
+    /// What the volume column does on the later ticks.
+    fn volume_column(&mut self, i: usize, tick: usize) {
+        if tick == 0 {
+            return;
+        }
+
+        let channel = &mut self.channels[i];
+        match channel.cell.volume {
+            VolumeCommand::SlideDown(amount) => channel.slide_volume(-(amount as i32)),
+            VolumeCommand::SlideUp(amount) => channel.slide_volume(amount as i32),
+            VolumeCommand::Vibrato(_) => {
+                let param = (channel.vibrato.speed << 4) | channel.vibrato.depth;
+                self.vibrato(i, tick, param, 32.0);
+            }
+            VolumeCommand::PanningSlideLeft(amount) => {
+                channel.panning = (channel.panning - amount as i32).max(0);
+            }
+            VolumeCommand::PanningSlideRight(amount) => {
+                channel.panning = (channel.panning + amount as i32).min(255);
+            }
+            VolumeCommand::TonePortamento(_) => {
+                let speed = channel.memory.tone_portamento;
+                self.tone_portamento(i, speed);
+            }
+            _ => {}
+        }
+    }
+
+    fn effect(&mut self, i: usize, tick: usize) {
+        let module = Arc::clone(&self.module);
+        let channel = &mut self.channels[i];
+
+        match channel.cell.effect {
+            // Done with the note.
+            Effect::None | Effect::SetFinetune(_) | Effect::SampleOffset(_) => {}
+            Effect::Arpeggio(param) => channel.arpeggio = [0, param >> 4, param & 0x0F][tick % 3],
+            Effect::PortamentoUp(param) => self.portamento(i, tick, param, -1.0),
+            Effect::PortamentoDown(param) => self.portamento(i, tick, param, 1.0),
+            Effect::FinePortamentoUp(param) if tick == 0 => {
+                self.slide_period(i, -(param as f64) * 4.0);
+            }
+            Effect::FinePortamentoDown(param) if tick == 0 => {
+                self.slide_period(i, param as f64 * 4.0);
+            }
+            Effect::ExtraFinePortamentoUp(param) if tick == 0 => {
+                self.slide_period(i, -(param as f64));
+            }
+            Effect::ExtraFinePortamentoDown(param) if tick == 0 => {
+                self.slide_period(i, param as f64);
+            }
+            Effect::TonePortamento(speed) if tick != 0 => self.tone_portamento(i, speed),
+            Effect::Vibrato(param) => self.vibrato(i, tick, param, 32.0),
+            Effect::FineVibrato(param) => self.vibrato(i, tick, param, 128.0),
+            Effect::TonePortamentoVolumeSlide(param) => {
+                let speed = channel.memory.tone_portamento;
+                if tick != 0 {
+                    self.tone_portamento(i, speed);
+                }
+                self.volume_slide(i, tick, param);
+            }
+            Effect::VibratoVolumeSlide(param) => {
+                let vibrato = channel.memory.vibrato;
+                self.vibrato(i, tick, vibrato, 32.0);
+                self.volume_slide(i, tick, param);
+            }
+            Effect::Tremolo(param) => {
+                channel.tremolo.set(param);
+                if tick != 0 {
+                    let depth = channel.tremolo.depth as f32;
+                    channel.volume_offset = (channel.tremolo.value() * 255.0 * depth / 64.0) as i32;
+                    channel.tremolo.advance();
+                }
+            }
+            Effect::Tremor(param) => {
+                let (on, off) = ((param >> 4) as usize + 1, (param & 0x0F) as usize + 1);
+                channel.muted = channel.tremor_ticks % (on + off) >= on;
+                channel.tremor_ticks += 1;
+            }
+            Effect::SetPanning(panning) if tick == 0 => channel.panning = panning as i32,
+            Effect::PanningSlide(param) if tick != 0 => {
+                let (right, left) = ((param >> 4) as i32, (param & 0x0F) as i32);
+                let slide = if right != 0 { right } else { -left };
+                channel.panning = (channel.panning + slide).clamp(0, 255);
+            }
+            Effect::VolumeSlide(param) => self.volume_slide(i, tick, param),
+            Effect::FineVolumeSlideUp(amount) if tick == 0 => channel.slide_volume(amount as i32),
+            Effect::FineVolumeSlideDown(amount) if tick == 0 => {
+                channel.slide_volume(-(amount as i32));
+            }
+            Effect::PositionJump(position) if tick == 0 => self.jump = Some(position as usize),
+            Effect::SetVolume(volume) if tick == 0 => channel.volume = volume.min(64) as i32,
+            Effect::PatternBreak(row) if tick == 0 => self.break_row = Some(row as usize),
+            Effect::SetSpeed(speed) if tick == 0 && speed != 0 => self.speed = speed as usize,
+            Effect::SetTempo(tempo) if tick == 0 => self.tempo = tempo.max(32) as usize,
+            Effect::SetGlobalVolume(volume) if tick == 0 => {
+                self.global_volume = volume.min(64) as i32;
+            }
+            Effect::GlobalVolumeSlide(param) if tick != 0 => {
+                let (up, down) = ((param >> 4) as i32, (param & 0x0F) as i32);
+                let slide = if up != 0 { up } else { -down };
+                self.global_volume = (self.global_volume + slide).clamp(0, 64);
+            }
+            Effect::PatternLoop(count) if tick == 0 => {
+                if count == 0 {
+                    channel.loop_start = self.row;
+                } else if channel.loop_count == 0 {
+                    channel.loop_count = count;
+                    self.loop_row = Some(channel.loop_start);
+                } else {
+                    channel.loop_count -= 1;
+                    if channel.loop_count != 0 {
+                        self.loop_row = Some(channel.loop_start);
+                    }
+                }
+            }
+            Effect::PatternDelay(rows) if tick == 0 && self.row_delay == 0 => {
+                self.row_delay = rows as usize;
+            }
+            Effect::Retrigger(param) => {
+                let interval = (param & 0x0F) as usize;
+                if tick != 0
+                    && interval != 0
+                    && tick.is_multiple_of(interval)
+                    && let Some(sample) = channel.sample.map(|s| &module.samples[s])
+                {
+                    channel.start(sample);
+                    channel.volume = retrigger_volume(channel.volume, param >> 4);
+                }
+            }
+            Effect::NoteCut(at) if tick == at as usize => channel.volume = 0,
+            Effect::KeyOff(at) if tick == at as usize => {
+                let instrument = channel.instrument.map(|i| &module.instruments[i]);
+                channel.key_off(instrument);
+            }
+            Effect::SetVibratoWaveform(param) if tick == 0 => channel.vibrato.set_waveform(param),
+            Effect::SetTremoloWaveform(param) if tick == 0 => channel.tremolo.set_waveform(param),
+            Effect::SetEnvelopePosition(position) if tick == 0 => {
+                channel.volume_envelope = position as u16;
+                channel.panning_envelope = position as u16;
+            }
+            _ => {}
+        }
+    }
+
+    fn portamento(&mut self, i: usize, tick: usize, param: u8, direction: f64) {
+        // Scream Tracker 3 does the fine ones with the same effects.
+        if self.module.format == TrackerFormat::S3m && param >= 0xE0 {
+            if tick == 0 {
+                let amount = (param & 0x0F) as f64;
+                let amount = if param >= 0xF0 { amount * 4.0 } else { amount };
+                self.slide_period(i, amount * direction);
+            }
+        } else if tick != 0 {
+            self.slide_period(i, param as f64 * 4.0 * direction);
+        }
+    }
+
+    fn tone_portamento(&mut self, i: usize, speed: u8) {
+        let channel = &mut self.channels[i];
+        let speed = speed as f64 * 4.0;
+        channel.period = if channel.period < channel.target {
+            (channel.period + speed).min(channel.target)
+        } else {
+            (channel.period - speed).max(channel.target)
+        };
+    }
+
+    fn vibrato(&mut self, i: usize, tick: usize, param: u8, divisor: f32) {
+        let channel = &mut self.channels[i];
+        channel.vibrato.set(param);
+        if tick != 0 {
+            let depth = channel.vibrato.depth as f32;
+            channel.period_offset = (channel.vibrato.value() * 255.0 * depth / divisor) as f64;
+            channel.vibrato.advance();
+        }
+    }
+
+    fn volume_slide(&mut self, i: usize, tick: usize, param: u8) {
+        let channel = &mut self.channels[i];
+        let (up, down) = ((param >> 4) as i32, (param & 0x0F) as i32);
+
+        // Scream Tracker 3 does the fine ones with the same effect.
+        if self.module.format == TrackerFormat::S3m {
+            if down == 0x0F && up != 0 {
+                if tick == 0 {
+                    channel.slide_volume(up);
+                }
+                return;
+            }
+            if up == 0x0F && down != 0 {
+                if tick == 0 {
+                    channel.slide_volume(-down);
+                }
+                return;
+            }
+        }
+
+        if tick != 0 {
+            channel.slide_volume(if up != 0 { up } else { -down });
+        }
+    }
+
+    fn slide_period(&mut self, i: usize, amount: f64) {
+        let channel = &mut self.channels[i];
+        channel.period += amount;
+        channel.period = match (self.module.format, self.module.linear_periods) {
+            (TrackerFormat::Mod, _) => channel.period.clamp(MOD_PERIODS.0, MOD_PERIODS.1),
+            (_, true) => channel.period.clamp(1.0, LINEAR_BASE_PERIOD * 2.0),
+            (_, false) => channel.period.clamp(1.0, AMIGA_CLOCK),
+        };
+    }
+
+    /// Envelopes, fadeout and the gains and step of the tick.
+    fn prepare_channel(&mut self, i: usize) {
+        let module = Arc::clone(&self.module);
+        let channel = &mut self.channels[i];
+        channel.target_gains = [0.0; 2];
+        if !channel.playing || channel.sample.is_none() {
+            return;
+        }
+
+        let mut volume = (channel.volume + channel.volume_offset).clamp(0, 64) as f32 / 64.0;
+        let mut panning = channel.panning as f32;
+        let mut period = channel.period + channel.period_offset;
+
+        if let Some(instrument) = channel.instrument.map(|i| &module.instruments[i]) {
+            if let Some(envelope) = &instrument.volume_envelope {
+                volume *= envelope.value(channel.volume_envelope) / 64.0;
+                channel.volume_envelope = envelope.advance(channel.volume_envelope, channel.key_on);
+            }
+            if !channel.key_on {
+                volume *= channel.fadeout as f32 / 65536.0;
+                channel.fadeout = channel.fadeout.saturating_sub(instrument.fadeout as u32);
+            }
+            if let Some(envelope) = &instrument.panning_envelope {
+                let value = envelope.value(channel.panning_envelope) - 32.0;
+                panning += value * (128.0 - (panning - 128.0).abs()) / 32.0;
+                channel.panning_envelope =
+                    envelope.advance(channel.panning_envelope, channel.key_on);
+            }
+
+            let vibrato = instrument.vibrato;
+            if vibrato.depth != 0 && vibrato.rate != 0 {
+                let sweep = match vibrato.sweep {
+                    0 => 1.0,
+                    sweep => (channel.auto_vibrato_ticks as f32 / sweep as f32).min(1.0),
+                };
+                let phase = channel.auto_vibrato_position as f32 / 256.0;
+                period += (wave(vibrato.waveform, phase) * vibrato.depth as f32 * sweep) as f64;
+                channel.auto_vibrato_position =
+                    channel.auto_vibrato_position.wrapping_add(vibrato.rate);
+                channel.auto_vibrato_ticks += 1;
+            }
+        }
+        if channel.muted {
+            volume = 0.0;
+        }
+
+        let volume = volume * self.global_volume as f32 / 64.0 * self.gain;
+        let panning = 0.5 + (panning.clamp(0.0, 255.0) / 255.0 - 0.5) * self.separation;
+        channel.target_gains = [
+            volume * (panning * FRAC_PI_2).cos(),
+            volume * (panning * FRAC_PI_2).sin(),
+        ];
+
+        let period = if module.linear_periods {
+            period - channel.arpeggio as f64 * 64.0
+        } else {
+            period / 2f64.powf(channel.arpeggio as f64 / 12.0)
+        };
+        channel.step = frequency(&module, period) / self.rate;
+    }
+
+    fn mix(&mut self, frames: usize, output: &mut Vec<f32>) {
+        let start = output.len();
+        output.resize(start + frames * 2, 0.0);
+        let output = &mut output[start..];
+        let ramp = ((self.rate * RAMP_SECONDS) as usize).clamp(1, frames.max(1));
+
+        for channel in &mut self.channels {
+            let (from, to) = (channel.gains, channel.target_gains);
+            channel.gains = to;
+            let Some(sample) = channel.sample.map(|s| &self.module.samples[s]) else {
+                continue;
+            };
+
+            for (frame, output) in output.chunks_exact_mut(2).enumerate() {
+                if !channel.playing {
+                    break;
+                }
+
+                let t = ((frame + 1) as f32 / ramp as f32).min(1.0);
+                let value = channel.read(sample, self.interpolation);
+                output[0] += value * (from[0] + (to[0] - from[0]) * t);
+                output[1] += value * (from[1] + (to[1] - from[1]) * t);
+                channel.advance(sample);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Channel {
+    cell: Cell,
+    memory: Memory,
+
+    instrument: Option<usize>,
+    sample: Option<usize>,
+    playing: bool,
+    /// In frames of the sample, past the loop end when going back in a ping-pong loop.
+    position: f64,
+    /// Rate of the sample at the base note, changed by the finetune effects.
+    rate: f64,
+    period: f64,
+    /// Of the tone portamento.
+    target: f64,
+    volume: i32,
+    panning: i32,
+
+    /// Changes of the tick, from the effects.
+    period_offset: f64,
+    arpeggio: u8,
+    volume_offset: i32,
+    muted: bool,
+    vibrato: Oscillator,
+    tremolo: Oscillator,
+    tremor_ticks: usize,
+
+    key_on: bool,
+    /// Out of 65536, once released.
+    fadeout: u32,
+    volume_envelope: u16,
+    panning_envelope: u16,
+    auto_vibrato_position: u8,
+    auto_vibrato_ticks: u32,
+
+    loop_start: usize,
+    loop_count: u8,
+
+    gains: [f32; 2],
+    target_gains: [f32; 2],
+    /// Frames of the sample per output frame.
+    step: f64,
+}
+impl Channel {
+    fn new(panning: u8) -> Self {
+        Self {
+            cell: Cell::default(),
+            memory: Memory::default(),
+            instrument: None,
+            sample: None,
+            playing: false,
+            position: 0.0,
+            rate: BASE_RATE,
+            period: 0.0,
+            target: 0.0,
+            volume: 0,
+            panning: panning as i32,
+            period_offset: 0.0,
+            arpeggio: 0,
+            volume_offset: 0,
+            muted: false,
+            vibrato: Oscillator::default(),
+            tremolo: Oscillator::default(),
+            tremor_ticks: 0,
+            key_on: false,
+            fadeout: 65536,
+            volume_envelope: 0,
+            panning_envelope: 0,
+            auto_vibrato_position: 0,
+            auto_vibrato_ticks: 0,
+            loop_start: 0,
+            loop_count: 0,
+            gains: [0.0; 2],
+            target_gains: [0.0; 2],
+            step: 0.0,
+        }
+    }
+
+    /// Plays the sample from its start.
+    fn start(&mut self, sample: &TrackerSample) {
+        self.position = 0.0;
+        self.playing = !sample.data.is_empty();
+        self.vibrato.restart();
+        self.tremolo.restart();
+        self.tremor_ticks = 0;
+        self.restart_envelopes();
+    }
+
+    fn restart_envelopes(&mut self) {
+        self.key_on = true;
+        self.fadeout = 65536;
+        self.volume_envelope = 0;
+        self.panning_envelope = 0;
+        self.auto_vibrato_position = 0;
+        self.auto_vibrato_ticks = 0;
+    }
+
+    /// Without a volume envelope, the note stops at once.
+    fn key_off(&mut self, instrument: Option<&Instrument>) {
+        self.key_on = false;
+        if instrument.is_none_or(|instrument| instrument.volume_envelope.is_none()) {
+            self.volume = 0;
+        }
+    }
+
+    #[inline(always)]
+    fn slide_volume(&mut self, amount: i32) {
+        self.volume = (self.volume + amount).clamp(0, 64);
+    }
+
+    fn read(&self, sample: &TrackerSample, interpolation: Interpolation) -> f32 {
+        let position = match sample.looping {
+            SampleLoop::PingPong if self.position >= sample.loop_end as f64 => {
+                2.0 * sample.loop_end as f64 - self.position
+            }
+            _ => self.position,
+        };
+        let index = position.floor() as isize;
+        let t = (position - index as f64) as f32;
+
+        match interpolation {
+            Interpolation::Nearest => tap(sample, index),
+            Interpolation::Linear => {
+                let (a, b) = (tap(sample, index), tap(sample, index + 1));
+                a + (b - a) * t
+            }
+            Interpolation::Cubic => {
+                // Catmull-Rom.
+                let [p0, p1, p2, p3] = [-1, 0, 1, 2].map(|k| tap(sample, index + k));
+                let a = -0.5 * p0 + 1.5 * p1 - 1.5 * p2 + 0.5 * p3;
+                let b = p0 - 2.5 * p1 + 2.0 * p2 - 0.5 * p3;
+                let c = -0.5 * p0 + 0.5 * p2;
+                ((a * t + b) * t + c) * t + p1
+            }
+        }
+    }
+
+    fn advance(&mut self, sample: &TrackerSample) {
+        self.position += self.step;
+
+        let (start, end) = (sample.loop_start as f64, sample.loop_end as f64);
+        let length = end - start;
+        match sample.looping {
+            SampleLoop::Off if self.position >= sample.data.len() as f64 => self.playing = false,
+            SampleLoop::Forward if self.position >= end => {
+                self.position = start + (self.position - end) % length;
+            }
+            // Going back from the end while under twice the length past it.
+            SampleLoop::PingPong if self.position >= end + length => {
+                let phase = (self.position - end) % (2.0 * length);
+                self.position = if phase < length {
+                    end + phase
+                } else {
+                    start + phase - length
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parameters the effects reuse when given 0.
+#[derive(Debug, Default, Clone, Copy)]
+struct Memory {
+    portamento_up: u8,
+    portamento_down: u8,
+    fine_portamento_up: u8,
+    fine_portamento_down: u8,
+    extra_fine_portamento_up: u8,
+    extra_fine_portamento_down: u8,
+    tone_portamento: u8,
+    vibrato: u8,
+    tremolo: u8,
+    volume_slide: u8,
+    offset: u8,
+    retrigger: u8,
+    tremor: u8,
+    panning_slide: u8,
+    global_volume_slide: u8,
+    arpeggio: u8,
+}
+impl Memory {
+    /// The effect with the parameters it remembers, as its format does.
+    fn resolve(&mut self, effect: Effect, format: TrackerFormat) -> Effect {
+        let whole = |slot: &mut u8, param: u8| {
+            if param != 0 {
+                *slot = param;
+            }
+            *slot
+        };
+        let nibbles = |slot: &mut u8, param: u8| {
+            if param & 0xF0 != 0 {
+                *slot = (*slot & 0x0F) | (param & 0xF0);
+            }
+            if param & 0x0F != 0 {
+                *slot = (*slot & 0xF0) | (param & 0x0F);
+            }
+            *slot
+        };
+        // ProTracker doesn't remember its slides.
+        let slides = format != TrackerFormat::Mod;
+        let xm = format == TrackerFormat::Xm;
+
+        match effect {
+            // Scream Tracker 3 shares it between both directions.
+            Effect::PortamentoUp(param) | Effect::PortamentoDown(param)
+                if format == TrackerFormat::S3m =>
+            {
+                let param = whole(&mut self.portamento_down, param);
+                match effect {
+                    Effect::PortamentoUp(_) => Effect::PortamentoUp(param),
+                    _ => Effect::PortamentoDown(param),
+                }
+            }
+            Effect::PortamentoUp(param) if xm => {
+                Effect::PortamentoUp(whole(&mut self.portamento_up, param))
+            }
+            Effect::PortamentoDown(param) if xm => {
+                Effect::PortamentoDown(whole(&mut self.portamento_down, param))
+            }
+            Effect::FinePortamentoUp(param) if xm => {
+                Effect::FinePortamentoUp(whole(&mut self.fine_portamento_up, param))
+            }
+            Effect::FinePortamentoDown(param) if xm => {
+                Effect::FinePortamentoDown(whole(&mut self.fine_portamento_down, param))
+            }
+            Effect::ExtraFinePortamentoUp(param) => {
+                Effect::ExtraFinePortamentoUp(whole(&mut self.extra_fine_portamento_up, param))
+            }
+            Effect::ExtraFinePortamentoDown(param) => {
+                Effect::ExtraFinePortamentoDown(whole(&mut self.extra_fine_portamento_down, param))
+            }
+            Effect::TonePortamento(param) => {
+                Effect::TonePortamento(whole(&mut self.tone_portamento, param))
+            }
+            Effect::Vibrato(param) => Effect::Vibrato(nibbles(&mut self.vibrato, param)),
+            Effect::FineVibrato(param) => Effect::FineVibrato(nibbles(&mut self.vibrato, param)),
+            Effect::Tremolo(param) => Effect::Tremolo(nibbles(&mut self.tremolo, param)),
+            Effect::VolumeSlide(param) if slides => {
+                Effect::VolumeSlide(whole(&mut self.volume_slide, param))
+            }
+            Effect::TonePortamentoVolumeSlide(param) if slides => {
+                Effect::TonePortamentoVolumeSlide(whole(&mut self.volume_slide, param))
+            }
+            Effect::VibratoVolumeSlide(param) if slides => {
+                Effect::VibratoVolumeSlide(whole(&mut self.volume_slide, param))
+            }
+            Effect::SampleOffset(param) => Effect::SampleOffset(whole(&mut self.offset, param)),
+            Effect::Retrigger(param) if slides => {
+                Effect::Retrigger(nibbles(&mut self.retrigger, param))
+            }
+            Effect::Tremor(param) => Effect::Tremor(whole(&mut self.tremor, param)),
+            Effect::PanningSlide(param) => {
+                Effect::PanningSlide(whole(&mut self.panning_slide, param))
+            }
+            Effect::GlobalVolumeSlide(param) => {
+                Effect::GlobalVolumeSlide(whole(&mut self.global_volume_slide, param))
+            }
+            Effect::Arpeggio(param) if format == TrackerFormat::S3m => {
+                Effect::Arpeggio(whole(&mut self.arpeggio, param))
+            }
+            effect => effect,
+        }
+    }
+}
+
+/// Vibrato or tremolo, 64 steps per period.
+#[derive(Debug, Default, Clone, Copy)]
+struct Oscillator {
+    waveform: Waveform,
+    /// Keeps the position on new notes.
+    keep: bool,
+    position: u8,
+    speed: u8,
+    depth: u8,
+}
+impl Oscillator {
+    /// Speed and depth from the nibbles.
+    #[inline(always)]
+    fn set(&mut self, param: u8) {
+        self.speed = param >> 4;
+        self.depth = param & 0x0F;
+    }
+
+    #[inline(always)]
+    fn set_waveform(&mut self, param: u8) {
+        self.waveform = Waveform::from_bits(param);
+        self.keep = param & 4 != 0;
+    }
+
+    #[inline(always)]
+    fn restart(&mut self) {
+        if !self.keep {
+            self.position = 0;
+        }
+    }
+
+    /// In `-1.0..=1.0`.
+    #[inline(always)]
+    fn value(&self) -> f32 {
+        wave(self.waveform, self.position as f32 / 64.0)
+    }
+
+    #[inline(always)]
+    fn advance(&mut self) {
+        self.position = (self.position + self.speed) & 63;
+    }
+}
+
+/// Value of the waveform at `phase` in `0.0..1.0`, in `-1.0..=1.0`.
+fn wave(waveform: Waveform, phase: f32) -> f32 {
+    match waveform {
+        Waveform::Sine => (phase * TAU).sin(),
+        Waveform::RampDown => 1.0 - 2.0 * phase,
+        Waveform::RampUp => 2.0 * phase - 1.0,
+        Waveform::Square if phase < 0.5 => 1.0,
+        Waveform::Square => -1.0,
+        Waveform::Random => {
+            // The same for the same phase, so playing again sounds the same.
+            let hash = ((phase * 256.0) as u32).wrapping_mul(0x9E37_79B9) >> 16;
+            (hash & 0x1FF) as f32 / 255.5 - 1.0
+        }
+    }
+}
+
+/// Sample of the data at `index`, through its loop, silent outside.
+#[inline(always)]
+fn tap(sample: &TrackerSample, index: isize) -> f32 {
+    if index < 0 {
+        return 0.0;
+    }
+
+    let (start, end) = (sample.loop_start, sample.loop_end);
+    let index = match sample.looping {
+        SampleLoop::Forward if index as usize >= end => {
+            start + (index as usize - end) % (end - start)
+        }
+        SampleLoop::PingPong if index as usize >= end => {
+            let length = end - start;
+            let phase = (index as usize - end) % (2 * length);
+            if phase < length {
+                end - 1 - phase
+            } else {
+                start + phase - length
+            }
+        }
+        _ => index as usize,
+    };
+
+    sample.data.get(index).copied().unwrap_or(0.0)
+}
+
+fn rows_at(module: &TrackerModule, position: usize) -> usize {
+    module
+        .pattern_at(position)
+        .map_or(EMPTY_ROWS, |pattern| pattern.rows)
+}
+
+/// Note with the relative note of the sample.
+#[inline(always)]
+fn transpose(note: u8, sample: &TrackerSample) -> u8 {
+    (note as i32 + sample.relative_note as i32).clamp(0, NOTES as i32 - 1) as u8
+}
+
+/// Period of `note` for a sample of `rate` at the base note.
+fn period(module: &TrackerModule, note: f64, rate: f64) -> f64 {
+    let semitones = note - BASE_NOTE as f64;
+
+    if module.linear_periods {
+        LINEAR_BASE_PERIOD - semitones * 64.0 - 768.0 * (rate / BASE_RATE).log2()
+    } else {
+        AMIGA_CLOCK / (rate * 2f64.powf(semitones / 12.0))
+    }
+}
+
+fn frequency(module: &TrackerModule, period: f64) -> f64 {
+    if module.linear_periods {
+        BASE_RATE * 2f64.powf((LINEAR_BASE_PERIOD - period) / 768.0)
+    } else {
+        AMIGA_CLOCK / period.max(1.0)
+    }
+}
+
+/// Rate of a sample with the finetune of the effect.
+fn finetune_rate(module: &TrackerModule, finetune: u8) -> f64 {
+    let finetune = finetune & 0x0F;
+
+    match module.format {
+        TrackerFormat::S3m => S3M_FINETUNES[finetune as usize],
+        // Signed eighths of a semitone.
+        TrackerFormat::Mod => finetuned_rate(((finetune << 4) as i8 >> 4) as f64 * 16.0),
+        TrackerFormat::Xm => finetuned_rate((finetune as f64 - 8.0) * 16.0),
+    }
+}
+
+fn retrigger_volume(volume: i32, change: u8) -> i32 {
+    let volume = match change {
+        0x1..=0x5 => volume - (1 << (change - 1)),
+        0x6 => volume * 2 / 3,
+        0x7 => volume / 2,
+        0x9..=0xD => volume + (1 << (change - 9)),
+        0xE => volume * 3 / 2,
+        0xF => volume * 2,
+        _ => volume,
+    };
+
+    volume.clamp(0, 64)
+}
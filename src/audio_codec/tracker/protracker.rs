@@ -0,0 +1,209 @@
+use super::super::{Result, error::Error};
+use super::{
+    BASE_NOTE, Cell, Effect, Instrument, Note, Pattern, SampleLoop, TrackerFormat, TrackerModule,
+    TrackerSample, finetuned_rate, text, truncated,
+};
+use crate::reader::LgReader;
+use std::io;
+
+const ROWS: usize = 64;
+const TITLE_SIZE: usize = 20;
+const SAMPLE_HEADER_SIZE: usize = 30;
+const SIGNATURE_OFFSET: usize = 1080;
+/// Period of the base note, C-2.
+const BASE_PERIOD: f64 = 428.0;
+
+/// Big-endian, with 15 samples and 4 channels if it has no signature, like the first Soundtracker.
+pub(super) fn read(bytes: &[u8]) -> Result<TrackerModule> {
+    let signature = bytes.get(SIGNATURE_OFFSET..SIGNATURE_OFFSET + 4);
+    let (sample_count, channels) = match signature.and_then(signature_channels) {
+        Some(channels) => (31, channels),
+        None => (15, 4),
+    };
+    if bytes.len() < TITLE_SIZE + sample_count * SAMPLE_HEADER_SIZE + 130 {
+        return Err(Error::WrongHeader);
+    }
+
+    let mut reader = io::Cursor::new(bytes);
+    let mut title = [0; TITLE_SIZE];
+    reader.read_into(&mut title)?;
+
+    let mut headers = Vec::with_capacity(sample_count);
+    for _ in 0..sample_count {
+        let mut name = [0; 22];
+        reader.read_into(&mut name)?;
+        let length = reader.read_be_u16()? as usize * 2;
+        let finetune = reader.read_u8()? & 0x0F;
+        let volume = reader.read_u8()?;
+        let loop_start = reader.read_be_u16()? as usize * 2;
+        let loop_length = reader.read_be_u16()? as usize * 2;
+
+        headers.push((
+            text(&name),
+            length,
+            finetune,
+            volume,
+            loop_start,
+            loop_length,
+        ));
+    }
+
+    let song_length = reader.read_u8()? as usize;
+    let restart = reader.read_u8()? as usize;
+    let mut positions = [0; 128];
+    reader.read_into(&mut positions)?;
+    if sample_count == 31 {
+        reader.skip_next_bytes::<4>()?;
+    } else if song_length > 128
+        || positions.iter().any(|pattern| *pattern >= 128)
+        || headers.iter().any(|header| header.3 > 64)
+    {
+        // Without a signature, only a sane header tells it is a module.
+        return Err(Error::WrongHeader);
+    }
+
+    // Every position counts, even past the song.
+    let pattern_count = positions.iter().max().map_or(0, |max| *max as usize + 1);
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        let mut pattern = Pattern::new(ROWS, channels);
+        for cell in &mut pattern.cells {
+            let bytes = reader
+                .read_exact_n::<4>()
+                .map_err(|_| truncated("patterns"))?;
+            *cell = read_cell(bytes);
+        }
+        patterns.push(pattern);
+    }
+
+    let mut samples = Vec::with_capacity(sample_count);
+    let mut instruments = Vec::with_capacity(sample_count);
+    let mut data = &bytes[(reader.position() as usize).min(bytes.len())..];
+    for (i, (name, length, finetune, volume, loop_start, loop_length)) in
+        headers.into_iter().enumerate()
+    {
+        // Some files are cut short, they play what they have.
+        let (sample, rest) = data.split_at(length.min(data.len()));
+        data = rest;
+
+        let finetune = ((finetune << 4) as i8 >> 4) as f64;
+        samples.push(
+            TrackerSample {
+                name: name.clone(),
+                data: sample.iter().map(|b| *b as i8 as f32 / 128.0).collect(),
+                looping: if loop_length > 2 {
+                    SampleLoop::Forward
+                } else {
+                    SampleLoop::Off
+                },
+                loop_start,
+                loop_end: loop_start + loop_length,
+                volume: volume.min(64),
+                panning: None,
+                // Eighths of a semitone.
+                rate: finetuned_rate(finetune * 16.0),
+                relative_note: 0,
+            }
+            .fit_loop(),
+        );
+        instruments.push(Instrument::from_sample(name, i));
+    }
+
+    let order: Vec<usize> = positions[..song_length.min(128)]
+        .iter()
+        .map(|pattern| *pattern as usize)
+        .collect();
+
+    Ok(TrackerModule {
+        format: TrackerFormat::Mod,
+        title: text(&title),
+        channels,
+        restart: if restart < order.len() { restart } else { 0 },
+        order,
+        patterns,
+        instruments,
+        samples,
+        speed: 6,
+        tempo: 125,
+        global_volume: 64,
+        linear_periods: false,
+        // Left, right, right, left, like the Amiga.
+        panning: (0..channels)
+            .map(|channel| if matches!(channel % 4, 0 | 3) { 0 } else { 255 })
+            .collect(),
+    })
+}
+
+/// Effects of ProTracker, which FastTracker 2 kept.
+pub(super) fn effect(command: u8, param: u8) -> Effect {
+    let (x, y) = (param >> 4, param & 0x0F);
+
+    match command {
+        0x0 if param != 0 => Effect::Arpeggio(param),
+        0x1 => Effect::PortamentoUp(param),
+        0x2 => Effect::PortamentoDown(param),
+        0x3 => Effect::TonePortamento(param),
+        0x4 => Effect::Vibrato(param),
+        0x5 => Effect::TonePortamentoVolumeSlide(param),
+        0x6 => Effect::VibratoVolumeSlide(param),
+        0x7 => Effect::Tremolo(param),
+        0x8 => Effect::SetPanning(param),
+        0x9 => Effect::SampleOffset(param),
+        0xA => Effect::VolumeSlide(param),
+        0xB => Effect::PositionJump(param),
+        0xC => Effect::SetVolume(param),
+        // In decimal.
+        0xD => Effect::PatternBreak(x * 10 + y),
+        0xE => match x {
+            0x1 => Effect::FinePortamentoUp(y),
+            0x2 => Effect::FinePortamentoDown(y),
+            0x4 => Effect::SetVibratoWaveform(y),
+            0x5 => Effect::SetFinetune(y),
+            0x6 => Effect::PatternLoop(y),
+            0x7 => Effect::SetTremoloWaveform(y),
+            0x8 => Effect::SetPanning(y * 17),
+            0x9 => Effect::Retrigger(y),
+            0xA => Effect::FineVolumeSlideUp(y),
+            0xB => Effect::FineVolumeSlideDown(y),
+            0xC => Effect::NoteCut(y),
+            0xD => Effect::NoteDelay(y),
+            0xE => Effect::PatternDelay(y),
+            _ => Effect::None,
+        },
+        0xF if param < 0x20 => Effect::SetSpeed(param),
+        0xF => Effect::SetTempo(param),
+        _ => Effect::None,
+    }
+}
+
+fn read_cell(bytes: [u8; 4]) -> Cell {
+    let period = ((bytes[0] as u16 & 0x0F) << 8) | bytes[1] as u16;
+    let note = match period {
+        0 => Note::None,
+        period => {
+            let note = BASE_NOTE as f64 + 12.0 * (BASE_PERIOD / period as f64).log2();
+            Note::On(note.round().clamp(0.0, 119.0) as u8)
+        }
+    };
+
+    Cell {
+        note,
+        instrument: (bytes[0] & 0xF0) | (bytes[2] >> 4),
+        volume: Default::default(),
+        effect: effect(bytes[2] & 0x0F, bytes[3]),
+    }
+}
+
+fn signature_channels(signature: &[u8]) -> Option<usize> {
+    let digit = |byte: u8| byte.is_ascii_digit().then(|| (byte - b'0') as usize);
+
+    match signature {
+        b"M.K." | b"M!K!" | b"M&K!" | b"N.T." | b"FLT4" => Some(4),
+        b"FLT8" | b"CD81" | b"OKTA" | b"OCTA" => Some(8),
+        [n, b'C', b'H', b'N'] => digit(*n),
+        [b'T', b'D', b'Z', n] => digit(*n),
+        [a, b, b'C', b'H' | b'N'] => Some(digit(*a)? * 10 + digit(*b)?),
+        _ => None,
+    }
+    .filter(|channels| (1..=32).contains(channels))
+}
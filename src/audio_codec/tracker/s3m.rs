@@ -0,0 +1,283 @@
+use super::super::{Result, error::Error};
+use super::{
+    BASE_RATE, Cell, Effect, Instrument, Note, Pattern, SampleLoop, TrackerFormat, TrackerModule,
+    TrackerSample, VolumeCommand, text, truncated,
+};
+use crate::reader::LgReader;
+use std::io;
+
+const ROWS: usize = 64;
+const MAGIC: &[u8; 4] = b"SCRM";
+const MAGIC_OFFSET: usize = 0x2C;
+const CHANNEL_SETTINGS_OFFSET: u64 = 0x40;
+/// Default panning says the channel pannings follow the pointers.
+const PANNING_TABLE: u8 = 252;
+const ORDER_END: u8 = 255;
+const ORDER_SKIP: u8 = 254;
+
+#[inline(always)]
+pub(super) fn is_s3m(bytes: &[u8]) -> bool {
+    bytes.get(MAGIC_OFFSET..MAGIC_OFFSET + 4) == Some(MAGIC)
+}
+
+/// Little-endian, with its parts placed by pointers in paragraphs of 16 bytes.
+pub(super) fn read(bytes: &[u8]) -> Result<TrackerModule> {
+    let mut reader = io::Cursor::new(bytes);
+    let mut title = [0; 28];
+    reader
+        .read_into(&mut title)
+        .map_err(|_| Error::WrongHeader)?;
+
+    reader.set_position(0x20);
+    let order_count = reader.read_le_u16()? as usize;
+    let instrument_count = reader.read_le_u16()? as usize;
+    let pattern_count = reader.read_le_u16()? as usize;
+    let _flags = reader.read_le_u16()?;
+    let _version = reader.read_le_u16()?;
+    let unsigned_samples = reader.read_le_u16()? == 2;
+    reader.skip_next_bytes::<4>()?;
+    let global_volume = reader.read_u8()?;
+    let speed = reader.read_u8()?;
+    let tempo = reader.read_u8()?;
+    let stereo = reader.read_u8()? & 0x80 != 0;
+    let _ultra_click = reader.read_u8()?;
+    let default_panning = reader.read_u8()?;
+
+    reader.set_position(CHANNEL_SETTINGS_OFFSET);
+    let settings: [u8; 32] = reader.read_exact_n()?;
+    let mut positions = vec![0; order_count];
+    reader.read_into(&mut positions)?;
+    let instrument_pointers = read_pointers(&mut reader, instrument_count)?;
+    let pattern_pointers = read_pointers(&mut reader, pattern_count)?;
+    let panning_table: Option<[u8; 32]> = match default_panning {
+        PANNING_TABLE => reader.read_exact_n().ok(),
+        _ => None,
+    };
+
+    // Only the enabled channels are kept, in order.
+    let mut channel_map = [None; 32];
+    let mut panning = Vec::new();
+    for (channel, setting) in settings.iter().enumerate() {
+        if *setting >= 16 {
+            continue;
+        }
+
+        let table = panning_table
+            .map(|table| table[channel])
+            .filter(|pan| pan & 0x20 != 0);
+        panning.push(match table {
+            Some(pan) => (pan & 0x0F) * 17,
+            None if !stereo => 128,
+            None if *setting < 8 => 0x3 * 17,
+            None => 0xC * 17,
+        });
+        channel_map[channel] = Some(panning.len() - 1);
+    }
+    let channels = panning.len();
+
+    let mut samples = Vec::with_capacity(instrument_count);
+    let mut instruments = Vec::with_capacity(instrument_count);
+    for (i, pointer) in instrument_pointers.into_iter().enumerate() {
+        let sample = read_sample(bytes, pointer, unsigned_samples)?;
+        instruments.push(Instrument::from_sample(sample.name.clone(), i));
+        samples.push(sample);
+    }
+
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for pointer in pattern_pointers {
+        patterns.push(read_pattern(bytes, pointer, &channel_map, channels)?);
+    }
+
+    Ok(TrackerModule {
+        format: TrackerFormat::S3m,
+        title: text(&title),
+        channels,
+        order: positions
+            .into_iter()
+            .take_while(|pattern| *pattern != ORDER_END)
+            .filter(|pattern| *pattern != ORDER_SKIP)
+            .map(|pattern| pattern as usize)
+            .collect(),
+        restart: 0,
+        patterns,
+        instruments,
+        samples,
+        speed: if speed == 0 { 6 } else { speed },
+        tempo: if tempo < 33 { 125 } else { tempo },
+        global_volume: global_volume.min(64),
+        linear_periods: false,
+        panning,
+    })
+}
+
+fn read_pointers(reader: &mut io::Cursor<&[u8]>, count: usize) -> Result<Vec<usize>> {
+    (0..count)
+        .map(|_| Ok(reader.read_le_u16()? as usize * 16))
+        .collect()
+}
+
+/// Adlib instruments and missing ones are empty samples.
+fn read_sample(bytes: &[u8], pointer: usize, unsigned: bool) -> Result<TrackerSample> {
+    let mut result = TrackerSample {
+        name: String::new(),
+        data: Vec::new(),
+        looping: SampleLoop::Off,
+        loop_start: 0,
+        loop_end: 0,
+        volume: 0,
+        panning: None,
+        rate: BASE_RATE,
+        relative_note: 0,
+    };
+    if pointer == 0 {
+        return Ok(result);
+    }
+
+    let mut reader = io::Cursor::new(bytes);
+    reader.set_position(pointer as u64);
+    let kind = reader.read_u8().map_err(|_| truncated("instruments"))?;
+    reader.skip_next_bytes::<12>()?;
+    let segment = ((reader.read_u8()? as usize) << 16) | reader.read_le_u16()? as usize;
+    let length = reader.read_le_u32()? as usize;
+    let loop_start = reader.read_le_u32()? as usize;
+    let loop_end = reader.read_le_u32()? as usize;
+    let volume = reader.read_u8()?;
+    reader.skip_next_bytes::<2>()?;
+    let flags = reader.read_u8()?;
+    let rate = reader.read_le_u32()?;
+    reader.skip_next_bytes::<12>()?;
+    let mut name = [0; 28];
+    reader.read_into(&mut name)?;
+
+    result.name = text(&name);
+    if kind != 1 {
+        return Ok(result);
+    }
+
+    let sixteen_bits = flags & 4 != 0;
+    let size = if sixteen_bits { 2 } else { 1 };
+    let start = (segment * 16).min(bytes.len());
+    // Of stereo samples, the left channel comes first and is the one kept.
+    let data = &bytes[start..(start + length * size).min(bytes.len())];
+    result.data = match (sixteen_bits, unsigned) {
+        (false, true) => data.iter().map(|b| (*b as f32 - 128.0) / 128.0).collect(),
+        (false, false) => data.iter().map(|b| *b as i8 as f32 / 128.0).collect(),
+        (true, _) => data
+            .chunks_exact(2)
+            .map(|b| {
+                let value = u16::from_le_bytes([b[0], b[1]]);
+                if unsigned {
+                    (value as f32 - 32768.0) / 32768.0
+                } else {
+                    value as i16 as f32 / 32768.0
+                }
+            })
+            .collect(),
+    };
+
+    result.looping = if flags & 1 != 0 {
+        SampleLoop::Forward
+    } else {
+        SampleLoop::Off
+    };
+    result.loop_start = loop_start;
+    result.loop_end = loop_end;
+    result.volume = volume.min(64);
+    if rate != 0 {
+        result.rate = rate as f64;
+    }
+
+    Ok(result.fit_loop())
+}
+
+fn read_pattern(
+    bytes: &[u8],
+    pointer: usize,
+    channel_map: &[Option<usize>; 32],
+    channels: usize,
+) -> Result<Pattern> {
+    let mut result = Pattern::new(ROWS, channels);
+    if pointer == 0 {
+        return Ok(result);
+    }
+
+    let mut reader = io::Cursor::new(bytes);
+    reader.set_position(pointer as u64 + 2);
+    let mut row = 0;
+    while row < ROWS {
+        let what = reader.read_u8().map_err(|_| truncated("patterns"))?;
+        if what == 0 {
+            row += 1;
+            continue;
+        }
+
+        let mut cell = Cell::default();
+        if what & 0x20 != 0 {
+            cell.note = match reader.read_u8()? {
+                255 => Note::None,
+                254 => Note::Cut,
+                note => Note::On(((note >> 4) * 12 + (note & 0x0F).min(11)).min(119)),
+            };
+            cell.instrument = reader.read_u8()?;
+        }
+        if what & 0x40 != 0 {
+            cell.volume = match reader.read_u8()? {
+                255 => VolumeCommand::None,
+                volume => VolumeCommand::Set(volume.min(64)),
+            };
+        }
+        if what & 0x80 != 0 {
+            let command = reader.read_u8()?;
+            cell.effect = effect(command, reader.read_u8()?);
+        }
+
+        if let Some(channel) = channel_map[what as usize & 0x1F] {
+            result.cells[row * channels + channel] = cell;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Commands from A to Z, as 1 to 26.
+fn effect(command: u8, param: u8) -> Effect {
+    let (x, y) = (param >> 4, param & 0x0F);
+
+    match command.wrapping_add(b'A' - 1) {
+        b'A' if param != 0 => Effect::SetSpeed(param),
+        b'B' => Effect::PositionJump(param),
+        // In decimal.
+        b'C' => Effect::PatternBreak(x * 10 + y),
+        b'D' => Effect::VolumeSlide(param),
+        b'E' => Effect::PortamentoDown(param),
+        b'F' => Effect::PortamentoUp(param),
+        b'G' => Effect::TonePortamento(param),
+        b'H' => Effect::Vibrato(param),
+        b'I' => Effect::Tremor(param),
+        b'J' => Effect::Arpeggio(param),
+        b'K' => Effect::VibratoVolumeSlide(param),
+        b'L' => Effect::TonePortamentoVolumeSlide(param),
+        b'O' => Effect::SampleOffset(param),
+        b'Q' => Effect::Retrigger(param),
+        b'R' => Effect::Tremolo(param),
+        b'S' => match x {
+            0x2 => Effect::SetFinetune(y),
+            0x3 => Effect::SetVibratoWaveform(y),
+            0x4 => Effect::SetTremoloWaveform(y),
+            0x8 => Effect::SetPanning(y * 17),
+            0xB => Effect::PatternLoop(y),
+            0xC => Effect::NoteCut(y),
+            0xD => Effect::NoteDelay(y),
+            0xE => Effect::PatternDelay(y),
+            _ => Effect::None,
+        },
+        b'T' if param >= 0x20 => Effect::SetTempo(param),
+        b'U' => Effect::FineVibrato(param),
+        b'V' => Effect::SetGlobalVolume(param.min(64)),
+        b'W' => Effect::GlobalVolumeSlide(param),
+        // Surround is played in the middle.
+        b'X' if param == 0xA4 => Effect::SetPanning(128),
+        b'X' => Effect::SetPanning((param.min(0x80) as u16 * 255 / 0x80) as u8),
+        _ => Effect::None,
+    }
+}
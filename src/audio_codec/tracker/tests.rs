@@ -0,0 +1,155 @@
+//! Modules built in memory, with only what the tests need.
+
+use super::super::decoder::LgDecoder;
+use super::{
+    Cell, Effect, LgTrackerDecoder, Note, SampleLoop, TrackerFormat, TrackerModule, TrackerParams,
+    finetuned_rate,
+};
+
+/// ProTracker MOD of 4 channels and 2 patterns, the first sample is a square wave.
+fn minimal_mod(signature: &[u8; 4], channels: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    let mut title = [0; 20];
+    title[..7].copy_from_slice(b"minimal");
+    bytes.extend_from_slice(&title);
+
+    for sample in 0..31 {
+        let mut name = [0; 22];
+        if sample == 0 {
+            name[..6].copy_from_slice(b"square");
+            bytes.extend_from_slice(&name);
+            // 16 words, finetune -1, volume 48, looping over all of it.
+            bytes.extend_from_slice(&16u16.to_be_bytes());
+            bytes.extend_from_slice(&[0x0F, 48]);
+            bytes.extend_from_slice(&0u16.to_be_bytes());
+            bytes.extend_from_slice(&16u16.to_be_bytes());
+        } else {
+            bytes.extend_from_slice(&name);
+            bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        }
+    }
+
+    // Song of 2 positions, a restart past them.
+    bytes.extend_from_slice(&[2, 127]);
+    let mut positions = [0; 128];
+    positions[1] = 1;
+    bytes.extend_from_slice(&positions);
+    bytes.extend_from_slice(signature);
+
+    let cell = |period: u16, instrument: u8, command: u8, param: u8| {
+        [
+            (instrument & 0xF0) | (period >> 8) as u8,
+            period as u8,
+            (instrument << 4) | command,
+            param,
+        ]
+    };
+    for pattern in 0..2 {
+        for row in 0..64 {
+            for channel in 0..channels {
+                let cell = match (pattern, row, channel) {
+                    // C-2 setting the volume to 32, C-3 setting the speed.
+                    (0, 0, 0) => cell(428, 1, 0xC, 0x20),
+                    (0, 0, 1) => cell(214, 1, 0xF, 0x06),
+                    (0, 1, 0) => cell(0, 0, 0xE, 0xC3),
+                    // Ends the song on its first row.
+                    (1, 0, 3) => cell(0, 0, 0xD, 0x12),
+                    _ => [0; 4],
+                };
+                bytes.extend_from_slice(&cell);
+            }
+        }
+    }
+
+    bytes.extend_from_slice(&[0x40; 16]);
+    bytes.extend_from_slice(&[0xC0; 16]);
+
+    bytes
+}
+
+#[test]
+fn reads_minimal_mod() {
+    let module = TrackerModule::from_bytes(&minimal_mod(b"M.K.", 4)).unwrap();
+
+    assert_eq!(module.format, TrackerFormat::Mod);
+    assert_eq!(module.title, "minimal");
+    assert_eq!(module.channels, 4);
+    assert_eq!(module.order, [0, 1]);
+    assert_eq!(module.restart, 0);
+    assert_eq!((module.speed, module.tempo), (6, 125));
+    assert_eq!(module.panning, [0, 255, 255, 0]);
+
+    assert_eq!(module.patterns.len(), 2);
+    let pattern = module.pattern_at(0).unwrap();
+    assert_eq!(pattern.rows, 64);
+    assert_eq!(
+        pattern.row(0, 4)[..2],
+        [
+            Cell {
+                note: Note::On(48),
+                instrument: 1,
+                effect: Effect::SetVolume(32),
+                ..Default::default()
+            },
+            Cell {
+                note: Note::On(60),
+                instrument: 1,
+                effect: Effect::SetSpeed(6),
+                ..Default::default()
+            },
+        ]
+    );
+    assert_eq!(pattern.row(1, 4)[0].effect, Effect::NoteCut(3));
+    assert_eq!(
+        module.pattern_at(1).unwrap().row(0, 4)[3].effect,
+        Effect::PatternBreak(12)
+    );
+
+    assert_eq!(module.samples.len(), 31);
+    assert_eq!(module.instruments.len(), 31);
+    assert_eq!(module.instruments[0].keymap[48], Some(0));
+    let sample = &module.samples[0];
+    assert_eq!(sample.name, "square");
+    assert_eq!(sample.data.len(), 32);
+    assert!(sample.data[..16].iter().all(|x| *x == 0.5));
+    assert!(sample.data[16..].iter().all(|x| *x == -0.5));
+    assert_eq!(sample.looping, SampleLoop::Forward);
+    assert_eq!((sample.loop_start, sample.loop_end), (0, 32));
+    assert_eq!(sample.volume, 48);
+    assert_eq!(sample.rate, finetuned_rate(-16.0));
+    assert!(
+        module.samples[1..]
+            .iter()
+            .all(|sample| sample.data.is_empty())
+    );
+}
+
+#[test]
+fn reads_channels_of_the_signature() {
+    let module = TrackerModule::from_bytes(&minimal_mod(b"6CHN", 6)).unwrap();
+    assert_eq!(module.channels, 6);
+    assert_eq!(module.panning, [0, 255, 255, 0, 0, 255]);
+}
+
+#[test]
+fn plays_minimal_mod() {
+    let module = TrackerModule::from_bytes(&minimal_mod(b"M.K.", 4)).unwrap();
+    let mut decoder = LgTrackerDecoder::from_module(module, TrackerParams::default()).unwrap();
+
+    // 64 rows then one, of 6 ticks of 20 ms.
+    assert_eq!(decoder.frames(), 65 * 6 * 882);
+    let samples: Vec<f32> = decoder.samples().collect();
+    assert_eq!(samples.len(), decoder.frames() * 2);
+    // Sounds until cut on the 3rd tick of the second row, the other note plays on.
+    assert!(samples[..882 * 2].iter().any(|x| *x != 0.0));
+}
+
+#[test]
+fn rejects_truncated_mod() {
+    let bytes = minimal_mod(b"M.K.", 4);
+
+    // Ends in the first pattern.
+    assert!(TrackerModule::from_bytes(&bytes[..1084 + 100]).is_err());
+    assert!(TrackerModule::from_bytes(&[]).is_err());
+}
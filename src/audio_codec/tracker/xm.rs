@@ -0,0 +1,312 @@
+use super::super::{Result, error::Error};
+use super::{
+    AutoVibrato, Cell, Effect, Envelope, Instrument, NOTES, Note, Pattern, SampleLoop,
+    TrackerFormat, TrackerModule, TrackerSample, VolumeCommand, Waveform, finetuned_rate,
+    protracker, text, truncated,
+};
+use crate::reader::LgReader;
+use std::io;
+
+const MAGIC: &[u8; 17] = b"Extended Module: ";
+/// The header size is counted from here.
+const HEADER_OFFSET: u64 = 60;
+const KEY_OFF: u8 = 97;
+const ENVELOPE_POINTS: usize = 12;
+
+#[inline(always)]
+pub(super) fn is_xm(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Little-endian, every part starting with its size.
+pub(super) fn read(bytes: &[u8]) -> Result<TrackerModule> {
+    let mut reader = io::Cursor::new(bytes);
+    reader.set_position(MAGIC.len() as u64);
+    let mut title = [0; 20];
+    reader
+        .read_into(&mut title)
+        .map_err(|_| Error::WrongHeader)?;
+
+    reader.set_position(HEADER_OFFSET);
+    let header_size = reader.read_le_u32()? as u64;
+    let song_length = reader.read_le_u16()? as usize;
+    let restart = reader.read_le_u16()? as usize;
+    let channels = reader.read_le_u16()? as usize;
+    let pattern_count = reader.read_le_u16()? as usize;
+    let instrument_count = reader.read_le_u16()? as usize;
+    let flags = reader.read_le_u16()?;
+    let speed = reader.read_le_u16()?;
+    let tempo = reader.read_le_u16()?;
+    let positions: [u8; 256] = reader.read_exact_n()?;
+    if channels == 0 || channels > 64 {
+        return Err(Error::WrongFmtInfo(format!(
+            "XM modules have 1 to 64 channels, got {channels}!"
+        )));
+    }
+
+    reader.set_position(HEADER_OFFSET + header_size);
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        patterns.push(read_pattern(&mut reader, channels)?);
+    }
+
+    let mut instruments = Vec::with_capacity(instrument_count);
+    let mut samples = Vec::new();
+    for _ in 0..instrument_count {
+        instruments.push(read_instrument(&mut reader, &mut samples)?);
+    }
+
+    let order: Vec<usize> = positions[..song_length.min(256)]
+        .iter()
+        .map(|pattern| *pattern as usize)
+        .collect();
+
+    Ok(TrackerModule {
+        format: TrackerFormat::Xm,
+        title: text(&title),
+        channels,
+        restart: if restart < order.len() { restart } else { 0 },
+        order,
+        patterns,
+        instruments,
+        samples,
+        speed: speed.clamp(1, 31) as u8,
+        tempo: tempo.clamp(32, 255) as u8,
+        global_volume: 64,
+        linear_periods: flags & 1 != 0,
+        panning: vec![128; channels],
+    })
+}
+
+fn read_pattern(reader: &mut io::Cursor<&[u8]>, channels: usize) -> Result<Pattern> {
+    let start = reader.position();
+    let header_length = reader.read_le_u32().map_err(|_| truncated("patterns"))? as u64;
+    let _packing = reader.read_u8()?;
+    let rows = reader.read_le_u16()? as usize;
+    let packed_size = reader.read_le_u16()? as usize;
+    reader.set_position(start + header_length);
+
+    let mut result = Pattern::new(rows.clamp(1, 256), channels);
+    let end = reader.position() + packed_size as u64;
+    if packed_size == 0 {
+        return Ok(result);
+    }
+
+    for cell in &mut result.cells {
+        if reader.position() >= end {
+            break;
+        }
+
+        // With the high bit set, the first byte says which of the 5 follow.
+        let first = reader.read_u8()?;
+        let what = if first & 0x80 != 0 { first } else { 0x1F };
+        let mut next = |bit: u8| -> Result<u8> {
+            if what & bit == 0 {
+                return Ok(0);
+            }
+            if bit == 1 && first & 0x80 == 0 {
+                return Ok(first);
+            }
+            Ok(reader.read_u8()?)
+        };
+
+        let note = next(0x01)?;
+        let instrument = next(0x02)?;
+        let volume = next(0x04)?;
+        let command = next(0x08)?;
+        let param = next(0x10)?;
+        *cell = Cell {
+            note: match note {
+                0 => Note::None,
+                KEY_OFF => Note::Off,
+                note => Note::On((note - 1).min(NOTES as u8 - 1)),
+            },
+            instrument,
+            volume: volume_command(volume),
+            effect: effect(command, param),
+        };
+    }
+    reader.set_position(end);
+
+    Ok(result)
+}
+
+/// Appends the samples of the instrument to `samples`.
+fn read_instrument(
+    reader: &mut io::Cursor<&[u8]>,
+    samples: &mut Vec<TrackerSample>,
+) -> Result<Instrument> {
+    let start = reader.position();
+    let size = reader.read_le_u32().map_err(|_| truncated("instruments"))? as u64;
+    let mut name = [0; 22];
+    reader.read_into(&mut name)?;
+    let _kind = reader.read_u8()?;
+    let sample_count = reader.read_le_u16()? as usize;
+
+    let mut result = Instrument {
+        name: text(&name),
+        keymap: [None; NOTES],
+        volume_envelope: None,
+        panning_envelope: None,
+        fadeout: 0,
+        vibrato: AutoVibrato::default(),
+    };
+    if sample_count == 0 {
+        reader.set_position(start + size);
+        return Ok(result);
+    }
+
+    let sample_header_size = reader.read_le_u32()? as u64;
+    let mut keymap = [0; 96];
+    reader.read_into(&mut keymap)?;
+    let mut envelope_points = [[(0, 0); ENVELOPE_POINTS]; 2];
+    for points in &mut envelope_points {
+        for point in points.iter_mut() {
+            *point = (reader.read_le_u16()?, reader.read_le_u16()?.min(64) as u8);
+        }
+    }
+    let counts: [u8; 2] = reader.read_exact_n()?;
+    let volume_points: [u8; 3] = reader.read_exact_n()?;
+    let panning_points: [u8; 3] = reader.read_exact_n()?;
+    let kinds: [u8; 2] = reader.read_exact_n()?;
+    let vibrato: [u8; 4] = reader.read_exact_n()?;
+    result.fadeout = reader.read_le_u16()?;
+    reader.set_position(start + size);
+
+    let envelope = |i: usize, [sustain, loop_start, loop_end]: [u8; 3]| {
+        let count = (counts[i] as usize).min(ENVELOPE_POINTS);
+        (kinds[i] & 1 != 0 && count > 0).then(|| Envelope {
+            points: envelope_points[i][..count].to_vec(),
+            sustain: (kinds[i] & 2 != 0).then_some(sustain as usize),
+            repeat: (kinds[i] & 4 != 0).then_some((loop_start as usize, loop_end as usize)),
+        })
+    };
+    result.volume_envelope = envelope(0, volume_points);
+    result.panning_envelope = envelope(1, panning_points);
+    result.vibrato = AutoVibrato {
+        waveform: match vibrato[0] & 3 {
+            0 => Waveform::Sine,
+            1 => Waveform::Square,
+            2 => Waveform::RampDown,
+            _ => Waveform::RampUp,
+        },
+        sweep: vibrato[1],
+        depth: vibrato[2],
+        rate: vibrato[3],
+    };
+
+    let first = samples.len();
+    for (note, sample) in keymap.iter().enumerate() {
+        if (*sample as usize) < sample_count {
+            result.keymap[note] = Some(first + *sample as usize);
+        }
+    }
+
+    let mut headers = Vec::with_capacity(sample_count);
+    for _ in 0..sample_count {
+        let header_start = reader.position();
+        let length = reader.read_le_u32().map_err(|_| truncated("samples"))? as usize;
+        let loop_start = reader.read_le_u32()? as usize;
+        let loop_length = reader.read_le_u32()? as usize;
+        let volume = reader.read_u8()?;
+        let finetune = reader.read_u8()? as i8;
+        let kind = reader.read_u8()?;
+        let panning = reader.read_u8()?;
+        let relative_note = reader.read_u8()? as i8;
+        let _reserved = reader.read_u8()?;
+        let mut name = [0; 22];
+        reader.read_into(&mut name)?;
+        reader.set_position(header_start + sample_header_size);
+
+        let sample = TrackerSample {
+            name: text(&name),
+            data: Vec::new(),
+            looping: match kind & 3 {
+                1 => SampleLoop::Forward,
+                2 => SampleLoop::PingPong,
+                _ => SampleLoop::Off,
+            },
+            loop_start,
+            loop_end: loop_start + loop_length,
+            volume: volume.min(64),
+            panning: Some(panning),
+            rate: finetuned_rate(finetune as f64),
+            relative_note,
+        };
+        headers.push((sample, length, kind & 0x10 != 0));
+    }
+
+    // The data of every sample follows the headers, as deltas.
+    let bytes = *reader.get_ref();
+    for (mut sample, length, sixteen_bits) in headers {
+        let position = (reader.position() as usize).min(bytes.len());
+        let data = &bytes[position..(position + length).min(bytes.len())];
+        reader.set_position((position + length) as u64);
+
+        sample.data = if sixteen_bits {
+            // Lengths are in bytes.
+            sample.loop_start /= 2;
+            sample.loop_end /= 2;
+
+            let mut value = 0i16;
+            data.chunks_exact(2)
+                .map(|b| {
+                    value = value.wrapping_add(i16::from_le_bytes([b[0], b[1]]));
+                    value as f32 / 32768.0
+                })
+                .collect()
+        } else {
+            let mut value = 0i8;
+            data.iter()
+                .map(|b| {
+                    value = value.wrapping_add(*b as i8);
+                    value as f32 / 128.0
+                })
+                .collect()
+        };
+        samples.push(sample.fit_loop());
+    }
+
+    Ok(result)
+}
+
+fn volume_command(volume: u8) -> VolumeCommand {
+    let param = volume & 0x0F;
+
+    match volume >> 4 {
+        0x1..=0x4 => VolumeCommand::Set(volume - 0x10),
+        0x5 if volume == 0x50 => VolumeCommand::Set(64),
+        0x6 => VolumeCommand::SlideDown(param),
+        0x7 => VolumeCommand::SlideUp(param),
+        0x8 => VolumeCommand::FineSlideDown(param),
+        0x9 => VolumeCommand::FineSlideUp(param),
+        0xA => VolumeCommand::VibratoSpeed(param),
+        0xB => VolumeCommand::Vibrato(param),
+        0xC => VolumeCommand::SetPanning(param * 17),
+        0xD => VolumeCommand::PanningSlideLeft(param),
+        0xE => VolumeCommand::PanningSlideRight(param),
+        0xF => VolumeCommand::TonePortamento(param << 4),
+        _ => VolumeCommand::None,
+    }
+}
+
+/// Effects of ProTracker, then the letters FastTracker 2 added.
+fn effect(command: u8, param: u8) -> Effect {
+    if command < 0x10 {
+        return protracker::effect(command, param);
+    }
+
+    let (x, y) = (param >> 4, param & 0x0F);
+    match command.wrapping_sub(10).wrapping_add(b'A') {
+        b'G' => Effect::SetGlobalVolume(param.min(64)),
+        b'H' => Effect::GlobalVolumeSlide(param),
+        b'K' => Effect::KeyOff(param),
+        b'L' => Effect::SetEnvelopePosition(param),
+        b'P' => Effect::PanningSlide(param),
+        b'R' => Effect::Retrigger(param),
+        b'T' => Effect::Tremor(param),
+        b'X' if x == 1 => Effect::ExtraFinePortamentoUp(y),
+        b'X' if x == 2 => Effect::ExtraFinePortamentoDown(y),
+        _ => Effect::None,
+    }
+}
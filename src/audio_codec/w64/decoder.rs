@@ -0,0 +1,91 @@
+use super::super::{
+    AudioInfo, Result, decoder::LgDecoder, error::Error, sample::SampleType, wav::LgWavSampleIter,
+};
+use super::{W64Chunks, reader::LgW64Reader};
+use std::{fmt, fs, io, path};
+
+pub struct LgW64Decoder<R: io::Read> {
+    info: AudioInfo,
+    sample_len: usize,
+
+    reader: LgW64Reader<R>,
+}
+impl<R: io::Read> fmt::Debug for LgW64Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LgW64Decoder")
+            .field("info", &self.info)
+            .field("sample_len", &self.sample_len)
+            .finish()
+    }
+}
+impl LgW64Decoder<io::BufReader<fs::File>> {
+    pub fn new(path: impl AsRef<path::Path>) -> Result<Self> {
+        let file = fs::File::open(path)?;
+
+        Self::from_reader(io::BufReader::new(file))
+    }
+}
+impl<R: io::Read> LgW64Decoder<R> {
+    pub fn from_reader(reader: R) -> Result<Self> {
+        // Already checks the header.
+        let mut reader = LgW64Reader::new(reader)?;
+
+        // Just in case the fmt chunk is not present.
+        let mut info = Err(Error::WrongFmt);
+        let sample_len;
+
+        loop {
+            match reader.read_next_chunk()? {
+                W64Chunks::Fmt(w64_info) => info = Ok(w64_info),
+                W64Chunks::Data(d_len) => {
+                    match &info {
+                        Ok(info) => {
+                            sample_len = (d_len / (info.bits_per_sample as u64 / 8)) as usize
+                        }
+                        Err(_) => return Err(Error::WrongFmt),
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        Ok(Self {
+            info: info?,
+            sample_len,
+            reader,
+        })
+    }
+}
+impl<R: io::Read> LgDecoder for LgW64Decoder<R> {
+    #[inline(always)]
+    fn info(&self) -> AudioInfo {
+        self.info
+    }
+
+    #[inline(always)]
+    fn samples<S: super::Sample>(&mut self) -> impl Iterator<Item = S> {
+        let sample_type = self.info.sample_type.unwrap_or(SampleType::INT);
+
+        LgWavSampleIter::new(
+            &mut self.reader.reader,
+            sample_type,
+            self.info.bits_per_sample,
+        )
+    }
+
+    #[inline(always)]
+    fn duration(&self) -> usize {
+        self.sample_len / self.info.channels as usize / self.info.sample_rate as usize
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.sample_len
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.sample_len == 0
+    }
+}
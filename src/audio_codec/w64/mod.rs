@@ -0,0 +1,45 @@
+use super::{AudioInfo, sample::Sample};
+
+pub mod decoder;
+pub mod encoder;
+pub mod reader;
+pub mod writer;
+
+#[cfg(test)]
+mod tests;
+
+pub use decoder::LgW64Decoder;
+pub use encoder::LgW64Encoder;
+
+// ------------------------- GUIDS --------------------------
+const W64_GUID_RIFF: [u8; 16] = [
+    0x72, 0x69, 0x66, 0x66, 0x2e, 0x91, 0xcf, 0x11, 0xa5, 0xd6, 0x28, 0xdb, 0x04, 0xc1, 0x00, 0x00,
+];
+const W64_GUID_WAVE: [u8; 16] = [
+    0x77, 0x61, 0x76, 0x65, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+const W64_GUID_FMT: [u8; 16] = [
+    0x66, 0x6d, 0x74, 0x20, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+const W64_GUID_DATA: [u8; 16] = [
+    0x64, 0x61, 0x74, 0x61, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+
+// ------------------------- LAYOUT --------------------------
+/// GUID + u64 size, the chunk sizes include it.
+const W64_CHUNK_HEADER_SIZE: u64 = 24;
+/// riff chunk header + wave GUID.
+const W64_HEADER_SIZE: u64 = W64_CHUNK_HEADER_SIZE + 16;
+
+/// Chunks are aligned to 8 bytes.
+#[inline(always)]
+const fn padding(size: u64) -> u64 {
+    size.next_multiple_of(8) - size
+}
+
+// ------------------------- CHUNKS --------------------------
+pub(super) enum W64Chunks {
+    Fmt(AudioInfo),
+    /// Data size in bytes.
+    Data(u64),
+}
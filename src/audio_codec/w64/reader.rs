@@ -0,0 +1,78 @@
+use super::super::error::Error;
+use super::super::wav::reader::LgWavReader;
+use super::{
+    W64_CHUNK_HEADER_SIZE, W64_GUID_DATA, W64_GUID_FMT, W64_GUID_RIFF, W64_GUID_WAVE,
+    W64_HEADER_SIZE, W64Chunks, padding,
+};
+use crate::reader::LgReader;
+use std::io;
+
+/// Wave64 only changes the chunk headers, so the WAV reader is used for the fmt chunk and the samples.
+pub struct LgW64Reader<R: io::Read> {
+    pub(super) reader: LgWavReader<R>,
+}
+impl<R: io::Read> LgW64Reader<R> {
+    pub(super) fn new(mut reader: R) -> Result<Self, Error> {
+        if W64_GUID_RIFF != reader.read_next_bytes()? {
+            return Err(Error::WrongHeader);
+        }
+
+        let riff_size = reader.read_le_u64()?;
+
+        if W64_GUID_WAVE != reader.read_next_bytes()? || riff_size < W64_HEADER_SIZE {
+            return Err(Error::WrongHeader);
+        }
+
+        Ok(Self {
+            reader: LgWavReader::from_raw(reader, (riff_size - W64_HEADER_SIZE) as usize),
+        })
+    }
+
+    /// Unknown chunks are skipped.
+    pub(super) fn read_next_chunk(&mut self) -> Result<W64Chunks, Error> {
+        loop {
+            let guid: [u8; 16] = self.reader.read_next_bytes()?;
+            let ck_size = self.reader.read_le_u64()?;
+
+            if ck_size < W64_CHUNK_HEADER_SIZE {
+                return Err(Error::WrongFmtInfo(
+                    "Wave64 chunks must have ck_size >= 24!".to_string(),
+                ));
+            }
+            let data_size = ck_size - W64_CHUNK_HEADER_SIZE;
+
+            match guid {
+                W64_GUID_FMT => {
+                    let start = self.reader.cursor();
+                    let info = self.reader.read_fmt(data_size as usize)?;
+                    let read = (self.reader.cursor() - start) as u64;
+
+                    self.skip(data_size.saturating_sub(read) + padding(ck_size))?;
+
+                    return Ok(W64Chunks::Fmt(info));
+                }
+
+                W64_GUID_DATA => {
+                    // Chunks after the data are never read.
+                    self.reader.limit(data_size as usize);
+
+                    return Ok(W64Chunks::Data(data_size));
+                }
+
+                _ => self.skip(data_size + padding(ck_size))?,
+            }
+        }
+    }
+
+    fn skip(&mut self, mut bytes: u64) -> Result<(), Error> {
+        let mut buffer = [0u8; 512];
+
+        while bytes > 0 {
+            let len = bytes.min(buffer.len() as u64) as usize;
+            self.reader.read_into(&mut buffer[..len])?;
+            bytes -= len as u64;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use super::super::{AudioInfo, decoder::LgDecoder, sample::SampleType};
+use super::{
+    LgW64Decoder, W64_CHUNK_HEADER_SIZE, W64_GUID_DATA, W64_GUID_RIFF, writer::LgW64Writer,
+};
+use std::io;
+
+fn info(channels: u16, sample_type: SampleType, bits_per_sample: u16) -> AudioInfo {
+    AudioInfo {
+        channels,
+        sample_rate: 48_000,
+        bits_per_sample,
+        sample_type: Some(sample_type),
+    }
+}
+
+fn encode(info: &AudioInfo, samples: &[i32]) -> Vec<u8> {
+    let mut bytes = io::Cursor::new(Vec::new());
+    let mut writer = LgW64Writer::new(&mut bytes, info).unwrap();
+    for sample in samples {
+        writer
+            .write_sample(*sample, info.sample_type.unwrap(), info.bits_per_sample)
+            .unwrap();
+    }
+    writer.finish().unwrap();
+    drop(writer);
+
+    bytes.into_inner()
+}
+
+fn le_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+/// Checks the riff and data sizes, returning where the samples start.
+fn check_sizes(bytes: &[u8], data_bytes: usize) -> usize {
+    assert_eq!(bytes[..16], W64_GUID_RIFF);
+    assert_eq!(le_u64(bytes, 16), bytes.len() as u64);
+
+    let data = bytes
+        .windows(16)
+        .position(|guid| guid == W64_GUID_DATA)
+        .unwrap();
+    // Chunks start aligned.
+    assert_eq!(data % 8, 0);
+    assert_eq!(
+        le_u64(bytes, data + 16),
+        W64_CHUNK_HEADER_SIZE + data_bytes as u64
+    );
+    assert_eq!(bytes.len(), data + 24 + data_bytes);
+
+    data + 24
+}
+
+fn check_round_trip(info: &AudioInfo, samples: &[i32]) {
+    let bytes = encode(info, samples);
+    check_sizes(&bytes, samples.len() * info.bits_per_sample as usize / 8);
+
+    let mut decoder = LgW64Decoder::from_reader(bytes.as_slice()).unwrap();
+    assert_eq!(decoder.info().channels, info.channels);
+    assert_eq!(decoder.info().sample_rate, info.sample_rate);
+    assert_eq!(decoder.info().bits_per_sample, info.bits_per_sample);
+    assert_eq!(decoder.info().sample_type, info.sample_type);
+    assert_eq!(decoder.len(), samples.len());
+    assert_eq!(decoder.samples::<i32>().collect::<Vec<_>>(), samples);
+}
+
+#[test]
+fn round_trip_16_bits() {
+    let samples: Vec<i32> = (0..2000).map(|i| (i * 37 % 65_536) - 32_768).collect();
+    check_round_trip(&info(2, SampleType::INT, 16), &samples);
+}
+
+#[test]
+fn round_trip_24_bits_unaligned() {
+    // 3 bytes of data, the data chunk doesn't end aligned.
+    check_round_trip(&info(1, SampleType::INT, 24), &[-8_388_608]);
+    check_round_trip(&info(1, SampleType::INT, 24), &[1, -2, 8_388_607, 0, -5]);
+}
+
+#[test]
+fn round_trip_float() {
+    let samples: Vec<i32> = (-8..8).map(|i| i << 27).collect();
+    check_round_trip(&info(2, SampleType::FLOAT, 32), &samples);
+}
+
+#[test]
+fn round_trip_empty() {
+    let info = info(2, SampleType::INT, 16);
+    let bytes = encode(&info, &[]);
+    let start = check_sizes(&bytes, 0);
+    assert_eq!(start, bytes.len());
+
+    let mut decoder = LgW64Decoder::from_reader(bytes.as_slice()).unwrap();
+    assert!(decoder.is_empty());
+    assert_eq!(decoder.samples::<i32>().count(), 0);
+}
@@ -0,0 +1,92 @@
+use super::super::Result;
+use super::super::sample::{Sample, SampleType};
+use super::super::wav::writer::{fmt_ck_size, write_fmt_data};
+use super::{
+    AudioInfo, W64_CHUNK_HEADER_SIZE, W64_GUID_DATA, W64_GUID_FMT, W64_GUID_RIFF, W64_GUID_WAVE,
+    W64_HEADER_SIZE, padding,
+};
+use crate::writer::LgWriter;
+use std::io;
+
+const RIFF_CK_SIZE_POSITION: usize = 16;
+
+pub struct LgW64Writer<W: io::Write + io::Seek> {
+    pub(super) writer: W,
+    pub(super) data_bytes_written: u64,
+    /// Position of the data chunk GUID.
+    data_ck_position: u64,
+}
+impl<W: io::Write + io::Seek> Drop for LgW64Writer<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+impl<W: io::Write + io::Seek> LgW64Writer<W> {
+    pub fn new(mut writer: W, info: &AudioInfo) -> Result<Self> {
+        writer.write_all(&W64_GUID_RIFF)?;
+        // Empty for now. (ck_size) - position 16.
+        writer.write_le_u64(0)?;
+        writer.write_all(&W64_GUID_WAVE)?;
+
+        // The fmt chunk is the same as in WAV.
+        let fmt_ck_size = W64_CHUNK_HEADER_SIZE + fmt_ck_size(info) as u64;
+        writer.write_all(&W64_GUID_FMT)?;
+        writer.write_le_u64(fmt_ck_size)?;
+        write_fmt_data(&mut writer, info)?;
+        writer.write_all(&vec![0; padding(fmt_ck_size) as usize])?;
+
+        writer.write_all(&W64_GUID_DATA)?;
+        writer.write_le_u64(W64_CHUNK_HEADER_SIZE)?;
+
+        Ok(Self {
+            writer,
+            data_bytes_written: 0,
+            data_ck_position: W64_HEADER_SIZE + fmt_ck_size + padding(fmt_ck_size),
+        })
+    }
+
+    #[inline(always)]
+    pub fn write_sample<S: Sample>(
+        &mut self,
+        sample: S,
+        sample_type: SampleType,
+        bits_per_sample: u16,
+    ) -> Result<()> {
+        sample.write(&mut self.writer, sample_type, bits_per_sample)?;
+        self.data_bytes_written += bits_per_sample as u64 / 8;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        let current_pos = self.writer.stream_position()?;
+        self.update_headers()?;
+        self.writer.flush()?;
+        self.writer.go_to(current_pos as usize)?;
+
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.update_headers()?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+impl<W: io::Write + io::Seek> LgW64Writer<W> {
+    fn update_headers(&mut self) -> Result<()> {
+        let data_ck_size = W64_CHUNK_HEADER_SIZE + self.data_bytes_written;
+
+        // riff ck_size, the whole file.
+        self.writer.go_to(RIFF_CK_SIZE_POSITION)?;
+        self.writer
+            .write_le_u64(self.data_ck_position + data_ck_size)?;
+
+        // data ck_size.
+        self.writer.go_to(self.data_ck_position as usize + 16)?;
+        self.writer.write_le_u64(data_ck_size)?;
+
+        Ok(())
+    }
+}
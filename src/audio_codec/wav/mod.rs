@@ -81,7 +81,7 @@ impl<'si, R, S: Sample> LgWavSampleIter<'si, R, S>
 where
     R: LgReader,
 {
-    fn new(reader: &'si mut R, sample_type: SampleType, bits_per_sample: u16) -> Self {
+    pub(crate) fn new(reader: &'si mut R, sample_type: SampleType, bits_per_sample: u16) -> Self {
         Self {
             sample_type,
             bits_per_sample,
@@ -121,8 +121,7 @@ impl<R: io::Read> LgWavReader<R> {
                 // Some files will have metadata in them after the data chunk.
                 // We don't want that to be marked as a sample, so we make sure we only read the rest of the data.
                 let data_ck_size = self.read_le_u32()?;
-                self.max_size = data_ck_size as usize;
-                self.cursor = 0;
+                self.limit(data_ck_size as usize);
 
                 WavChunks::Data(data_ck_size)
             }
@@ -137,7 +136,22 @@ impl<R: io::Read> LgWavReader<R> {
 
     pub(super) fn read_fmt_chunk(&mut self) -> Result<AudioInfo, Error> {
         let ck_size = self.read_le_u32()? as usize;
+        let info = self.read_fmt(ck_size)?;
 
+        // 4 bytes for the ck_id.
+        // 4 bytes for the ck_size.
+        let bytes_to_skip = (ck_size + 8) - self.cursor;
+        self.cursor += bytes_to_skip;
+
+        // 4 bytes for the ck_id.
+        // 4 bytes for the ck_size.
+        assert_eq!(self.cursor, 8 + ck_size);
+
+        Ok(info)
+    }
+
+    /// Reads the data of a fmt chunk with `ck_size` bytes, also used by other RIFF based formats.
+    pub(crate) fn read_fmt(&mut self, ck_size: usize) -> Result<AudioInfo, Error> {
         if !(16..=40).contains(&ck_size) {
             return Err(Error::WrongFmt);
         }
@@ -180,15 +194,6 @@ impl<R: io::Read> LgWavReader<R> {
             _ => return Err(Error::WrongFmt),
         };
 
-        // 4 bytes for the ck_id.
-        // 4 bytes for the ck_size.
-        let bytes_to_skip = (ck_size + 8) - self.cursor;
-        self.cursor += bytes_to_skip;
-
-        // 4 bytes for the ck_id.
-        // 4 bytes for the ck_size.
-        assert_eq!(self.cursor, 8 + ck_size);
-
         Ok(info)
     }
 
@@ -286,6 +291,27 @@ impl<R: io::Read> LgWavReader<R> {
     }
 }
 impl<R: io::Read> LgWavReader<R> {
+    /// Wraps a reader positioned inside a RIFF based file, with `max_size` bytes left to read.
+    pub(crate) fn from_raw(reader: R, max_size: usize) -> Self {
+        Self {
+            reader,
+            max_size,
+            cursor: 0,
+        }
+    }
+
+    /// Only the next `size` bytes can be read.
+    pub(crate) fn limit(&mut self, size: usize) {
+        self.max_size = size;
+        self.cursor = 0;
+    }
+
+    /// Bytes read since the last [`LgWavReader::limit`].
+    #[inline(always)]
+    pub(crate) fn cursor(&self) -> usize {
+        self.cursor
+    }
+
     fn move_cursor(&mut self, n: usize) -> Result<(), Error> {
         if self.cursor + n > self.max_size + 1 {
             return Err(Error::Io(io::Error::new::<String>(
@@ -77,119 +77,125 @@ impl<W: io::Write + io::Seek> LgWavWriter<W> {
     fn write_fmt_chunk(&mut self, info: &AudioInfo) -> Result<()> {
         self.writer.write_all(b"fmt ")?;
 
-        match info.sample_type {
-            Some(SampleType::INT) | None => {
-                if info.channels > 2 {
-                    self.write_check_pcm_ex_fmt(info)
-                } else {
-                    self.write_check_pcm_fmt(info)
-                }
-            }
-
-            Some(SampleType::FLOAT) => self.write_check_ieee_float_fmt(info),
-        }
-    }
+        let ck_size = fmt_ck_size(info);
 
-    fn write_check_pcm_fmt(&mut self, info: &AudioInfo) -> Result<()> {
         // Header + fmt header + fmt data + data tag.
-        self.data_ck_size_position = 12 + 8 + 16 + 4;
-
-        // ck_size of 16.
-        self.writer.write_le_u32(16)?;
+        self.data_ck_size_position = 12 + 8 + ck_size as usize + 4;
 
-        // fmt_tag.
-        self.writer.write_le_u16(WAVE_FORMAT_PCM)?;
+        self.writer.write_le_u32(ck_size)?;
 
-        self.write_fmt(info)
+        write_fmt_data(&mut self.writer, info)
     }
 
-    fn write_check_pcm_ex_fmt(&mut self, info: &AudioInfo) -> Result<()> {
-        // Header + fmt header + fmt data + data tag.
-        self.data_ck_size_position = 12 + 8 + 40 + 4;
-
-        // ck_size of 40.
-        self.writer.write_le_u32(40)?;
+    fn update_headers(&mut self) -> Result<()> {
+        let file_size = self.data_bytes_written + self.data_ck_size_position as u32 - 4;
 
-        // fmt_tag.
-        self.writer.write_le_u16(WAVE_FORMAT_EXTENSIBLE)?;
+        // RIFF ck_size.
+        self.writer.go_to(RIFF_CK_SIZE_POSITION)?;
+        self.writer.write_le_u32(file_size)?;
 
-        self.write_fmt(info)?;
+        // Data ck_size.
+        self.writer.go_to(self.data_ck_size_position)?;
+        self.writer.write_le_u32(self.data_bytes_written)?;
 
-        // cb_size.
-        self.writer.write_le_u16(22)?;
+        Ok(())
+    }
+}
 
-        // valid_bits_per_sample.
-        self.writer.write_le_u16(info.bits_per_sample)?;
+/// Size of the fmt chunk data written for `info`.
+pub(crate) fn fmt_ck_size(info: &AudioInfo) -> u32 {
+    match info.sample_type {
+        Some(SampleType::INT) | None => {
+            if info.channels > 2 {
+                40
+            } else {
+                16
+            }
+        }
 
-        // channel_mask.
-        let channels = if info.channels > 18 {
-            18
-        } else {
-            info.channels
-        };
-        self.writer.write_le_u32(channels as u32)?;
+        Some(SampleType::FLOAT) => 18,
+    }
+}
 
-        // sub_format.
-        self.writer.write_all(&[
-            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38,
-            0x9b, 0x71,
-        ])?;
+/// Writes the data of the fmt chunk (without ck_id and ck_size), also used by other RIFF based formats.
+pub(crate) fn write_fmt_data<W: io::Write + io::Seek>(
+    writer: &mut W,
+    info: &AudioInfo,
+) -> Result<()> {
+    match info.sample_type {
+        Some(SampleType::INT) | None => {
+            if info.channels > 2 {
+                write_pcm_ex_fmt(writer, info)
+            } else {
+                // fmt_tag.
+                writer.write_le_u16(WAVE_FORMAT_PCM)?;
+
+                write_fmt(writer, info)
+            }
+        }
 
-        Ok(())
+        Some(SampleType::FLOAT) => write_ieee_float_fmt(writer, info),
     }
+}
 
-    fn write_check_ieee_float_fmt(&mut self, info: &AudioInfo) -> Result<()> {
-        // Header + fmt header + fmt data + data tag.
-        self.data_ck_size_position = 12 + 8 + 18 + 4;
+fn write_pcm_ex_fmt<W: io::Write + io::Seek>(writer: &mut W, info: &AudioInfo) -> Result<()> {
+    // fmt_tag.
+    writer.write_le_u16(WAVE_FORMAT_EXTENSIBLE)?;
 
-        // ck_size of 18.
-        self.writer.write_le_u32(18)?;
+    write_fmt(writer, info)?;
 
-        // fmt_tag.
-        self.writer.write_le_u16(WAVE_FORMAT_IEEE_FLOAT)?;
+    // cb_size.
+    writer.write_le_u16(22)?;
 
-        self.write_fmt(info)?;
+    // valid_bits_per_sample.
+    writer.write_le_u16(info.bits_per_sample)?;
 
-        // cb_size.
-        self.writer.write_le_u16(0)?;
+    // channel_mask.
+    let channels = if info.channels > 18 {
+        18
+    } else {
+        info.channels
+    };
+    writer.write_le_u32(channels as u32)?;
 
-        Ok(())
-    }
+    // sub_format.
+    writer.write_all(&[
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b,
+        0x71,
+    ])?;
 
-    fn write_fmt(&mut self, info: &AudioInfo) -> Result<()> {
-        // n_channels.
-        self.writer.write_le_u16(info.channels)?;
+    Ok(())
+}
 
-        // samples_per_sec.
-        self.writer.write_le_u32(info.sample_rate)?;
+fn write_ieee_float_fmt<W: io::Write + io::Seek>(writer: &mut W, info: &AudioInfo) -> Result<()> {
+    // fmt_tag.
+    writer.write_le_u16(WAVE_FORMAT_IEEE_FLOAT)?;
 
-        // avg_bytes_per_sec.
-        let bytes_per_sec =
-            info.sample_rate * (info.bits_per_sample / 8) as u32 * info.channels as u32;
+    write_fmt(writer, info)?;
 
-        self.writer.write_le_u32(bytes_per_sec)?;
+    // cb_size.
+    writer.write_le_u16(0)?;
 
-        // block_align.
-        self.writer
-            .write_le_u16((bytes_per_sec / info.sample_rate) as u16)?;
+    Ok(())
+}
 
-        // bits_per_sample.
-        self.writer.write_le_u16(info.bits_per_sample)?;
+fn write_fmt<W: io::Write + io::Seek>(writer: &mut W, info: &AudioInfo) -> Result<()> {
+    // n_channels.
+    writer.write_le_u16(info.channels)?;
 
-        Ok(())
-    }
+    // samples_per_sec.
+    writer.write_le_u32(info.sample_rate)?;
 
-    fn update_headers(&mut self) -> Result<()> {
-        let file_size = self.data_bytes_written + self.data_ck_size_position as u32 - 4;
+    // avg_bytes_per_sec.
+    let bytes_per_sec = info.sample_rate * (info.bits_per_sample / 8) as u32 * info.channels as u32;
 
-        // RIFF ck_size.
-        self.writer.go_to(RIFF_CK_SIZE_POSITION)?;
-        self.writer.write_le_u32(file_size)?;
+    writer.write_le_u32(bytes_per_sec)?;
 
-        // Data ck_size.
-        self.writer.go_to(self.data_ck_size_position)?;
-        self.writer.write_le_u32(self.data_bytes_written)?;
+    // block_align.
+    writer.write_le_u16((bytes_per_sec / info.sample_rate) as u16)?;
 
-        Ok(())
-    }
+    // bits_per_sample.
+    writer.write_le_u16(info.bits_per_sample)?;
+
+    Ok(())
 }
@@ -32,6 +32,11 @@ pub trait LgReader {
         Ok(u32::from_le_bytes(self.read_exact_n()?))
     }
 
+    #[inline]
+    fn read_le_u64(&mut self) -> Result<u64, Self::Error> {
+        Ok(u64::from_le_bytes(self.read_exact_n()?))
+    }
+
     #[inline]
     fn read_le_i8(&mut self) -> Result<i8, Self::Error> {
         Ok(crate::bytes::conversions::u8_to_i8(
@@ -81,6 +86,11 @@ pub trait LgReader {
         Ok(u32::from_be_bytes(self.read_exact_n()?))
     }
 
+    #[inline]
+    fn read_be_u64(&mut self) -> Result<u64, Self::Error> {
+        Ok(u64::from_be_bytes(self.read_exact_n()?))
+    }
+
     #[inline]
     fn read_be_i16(&mut self) -> Result<i16, Self::Error> {
         Ok(i16::from_be_bytes(self.read_exact_n()?))
@@ -22,6 +22,40 @@ pub trait LgWriter {
     fn write_le_f32(&mut self, data: f32) -> Result<(), Self::Error>;
 
     fn write_le_f64(&mut self, data: f64) -> Result<(), Self::Error>;
+
+    /// Writes every byte of `data`.
+    #[inline]
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        for byte in data {
+            self.write_u8(*byte)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn write_le_u64(&mut self, data: u64) -> Result<(), Self::Error> {
+        self.write_bytes(&data.to_le_bytes())
+    }
+
+    #[inline]
+    fn write_be_u16(&mut self, data: u16) -> Result<usize, Self::Error> {
+        self.write_bytes(&data.to_be_bytes())?;
+
+        Ok(2)
+    }
+
+    #[inline]
+    fn write_be_u32(&mut self, data: u32) -> Result<usize, Self::Error> {
+        self.write_bytes(&data.to_be_bytes())?;
+
+        Ok(4)
+    }
+
+    #[inline]
+    fn write_be_u64(&mut self, data: u64) -> Result<(), Self::Error> {
+        self.write_bytes(&data.to_be_bytes())
+    }
 }
 impl<W: io::Write + io::Seek> LgWriter for W {
     type Error = std::io::Error;
@@ -68,4 +102,8 @@ impl<W: io::Write + io::Seek> LgWriter for W {
     fn write_le_f64(&mut self, data: f64) -> Result<(), Self::Error> {
         self.write_all(&data.to_le_bytes())
     }
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.write_all(data)
+    }
 }